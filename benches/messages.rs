@@ -0,0 +1,237 @@
+//! Reading and writing large messages as `Value` trees and as raw JSON text
+//!
+//! Run with `cargo bench --bench messages`. Each group compares the `Value` path, which is
+//! how every message used to be handled and how the server still reads requests, with the
+//! raw path the client and the transports take now. `serve_tool_call` times the server's
+//! side of a large tool call, from the frame as received to the response as sent.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mcpr::schema::common::BlobResourceContents;
+use mcpr::schema::json_rpc::{JSONRPCMessage, JSONRPCRequest, JSONRPCResponse, RequestId};
+use mcpr::schema::raw::{RawMessage, RawResponse};
+use mcpr::server::middleware::McpRequest;
+use mcpr::server::{Server, ServerConfig};
+use mcpr::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::hint::black_box;
+use std::io;
+use tower::ServiceExt;
+
+#[derive(Serialize, Deserialize)]
+struct Row {
+    id: u64,
+    name: String,
+    tags: Vec<String>,
+    score: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Report {
+    rows: Vec<Row>,
+}
+
+/// A report of about 5 MB as JSON
+fn report() -> Report {
+    let rows = (0..50_000)
+        .map(|id| Row {
+            id,
+            name: format!("row number {}", id),
+            tags: vec!["alpha".to_string(), "beta".to_string()],
+            score: id as f64 / 7.0,
+        })
+        .collect();
+    Report { rows }
+}
+
+fn read_response(c: &mut Criterion) {
+    let frame = serde_json::to_string(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "result": report(),
+    }))
+    .unwrap();
+
+    let mut group = c.benchmark_group("read_response");
+    group.throughput(Throughput::Bytes(frame.len() as u64));
+    group.bench_function("value", |b| {
+        b.iter(|| {
+            let JSONRPCMessage::Response(response) = serde_json::from_str(&frame).unwrap() else {
+                unreachable!()
+            };
+            let report: Report = serde_json::from_value(response.result).unwrap();
+            black_box(report)
+        })
+    });
+    group.bench_function("raw", |b| {
+        b.iter(|| {
+            let RawMessage::Response(response) = RawMessage::parse(&frame).unwrap() else {
+                unreachable!()
+            };
+            black_box(response.result_as::<Report>().unwrap())
+        })
+    });
+    group.finish();
+}
+
+fn read_request(c: &mut Criterion) {
+    let frame = serde_json::to_string(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": { "name": "import", "arguments": report() },
+    }))
+    .unwrap();
+
+    // Reading the envelope first does not pay off when the params end up a `Value`
+    // anyway, which is why `recv` still parses messages in one go
+    let mut group = c.benchmark_group("read_request");
+    group.throughput(Throughput::Bytes(frame.len() as u64));
+    group.bench_function("value", |b| {
+        b.iter(|| black_box(serde_json::from_str::<JSONRPCMessage>(&frame).unwrap()))
+    });
+    group.bench_function("raw", |b| {
+        b.iter(|| {
+            let message = RawMessage::parse(&frame).unwrap();
+            black_box(JSONRPCMessage::try_from(message).unwrap())
+        })
+    });
+    group.finish();
+}
+
+fn write_response(c: &mut Criterion) {
+    let report = report();
+
+    let mut group = c.benchmark_group("write_response");
+    group.bench_function("value", |b| {
+        b.iter(|| {
+            let result = serde_json::to_value(&report).unwrap();
+            let response = JSONRPCResponse::new(RequestId::Number(1), result);
+            black_box(serde_json::to_string(&JSONRPCMessage::Response(response)).unwrap())
+        })
+    });
+    group.bench_function("raw", |b| {
+        b.iter(|| {
+            let result = serde_json::value::to_raw_value(&report).unwrap();
+            let response = RawResponse::new(RequestId::Number(1), result);
+            black_box(serde_json::to_string(&RawMessage::Response(response)).unwrap())
+        })
+    });
+    group.finish();
+
+    // Passing a request on, as a proxy does
+    let frame = serde_json::to_string(&JSONRPCMessage::Request(JSONRPCRequest::new(
+        RequestId::Number(1),
+        "tools/call".to_string(),
+        Some(json!({ "name": "import", "arguments": report })),
+    )))
+    .unwrap();
+    let mut group = c.benchmark_group("forward_request");
+    group.throughput(Throughput::Bytes(frame.len() as u64));
+    group.bench_function("value", |b| {
+        b.iter(|| {
+            let message: JSONRPCMessage = serde_json::from_str(&frame).unwrap();
+            black_box(serde_json::to_string(&message).unwrap())
+        })
+    });
+    group.bench_function("raw", |b| {
+        b.iter(|| {
+            let message = RawMessage::parse(&frame).unwrap();
+            black_box(serde_json::to_string(&message).unwrap())
+        })
+    });
+    group.finish();
+}
+
+fn serve_tool_call(c: &mut Criterion) {
+    let frame = serde_json::to_string(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": { "name": "import", "arguments": report() },
+    }))
+    .unwrap();
+
+    let config = ServerConfig::new().with_tool(Tool {
+        name: "import".to_string(),
+        ..Default::default()
+    });
+    let mut server = Server::new(config);
+    server
+        .register_tool_handler("import", |params: Value| async move {
+            Ok(json!({ "imported": params["rows"].as_array().map_or(0, Vec::len) }))
+        })
+        .unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("serve_tool_call");
+    group.throughput(Throughput::Bytes(frame.len() as u64));
+    group.bench_function("value", |b| {
+        b.iter(|| {
+            let JSONRPCMessage::Request(request) = serde_json::from_str(&frame).unwrap() else {
+                unreachable!()
+            };
+            let response = runtime
+                .block_on(server.service().oneshot(McpRequest::new(request)))
+                .unwrap();
+            black_box(serde_json::to_string(&response).unwrap())
+        })
+    });
+    group.finish();
+}
+
+fn blobs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("blob");
+    for size in [64 * 1024, 8 * 1024 * 1024] {
+        let bytes: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+        let blob = BlobResourceContents::from_reader("file:///blob", None, &bytes[..]).unwrap();
+        group.throughput(Throughput::Bytes(size as u64));
+
+        // Encoding from a reader either buffers all of it first or encodes as it goes
+        group.bench_with_input(
+            BenchmarkId::new("encode_whole", size),
+            &bytes,
+            |b, bytes| {
+                b.iter(|| {
+                    let mut buffered = Vec::new();
+                    io::Read::read_to_end(&mut &bytes[..], &mut buffered).unwrap();
+                    black_box(STANDARD.encode(&buffered))
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("encode_streamed", size),
+            &bytes,
+            |b, bytes| {
+                b.iter(|| {
+                    BlobResourceContents::from_reader("file:///blob", None, &bytes[..]).unwrap()
+                })
+            },
+        );
+
+        group.bench_with_input(BenchmarkId::new("decode_whole", size), &blob, |b, blob| {
+            b.iter(|| {
+                let bytes = STANDARD.decode(&blob.blob).unwrap();
+                io::Write::write_all(&mut io::sink(), &bytes).unwrap();
+            })
+        });
+        group.bench_with_input(
+            BenchmarkId::new("decode_streamed", size),
+            &blob,
+            |b, blob| b.iter(|| blob.write_to(io::sink()).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = read_response, read_request, write_response, serve_tool_call, blobs
+}
+criterion_main!(benches);
@@ -1,12 +1,11 @@
 use log::info;
 use mcpr::{
     error::MCPError,
-    schema::common::{Tool, ToolInputSchema},
     server::{Server, ServerConfig},
     transport::stdio::StdioTransport,
+    Schema, Tool,
 };
 use serde_json::{json, Value};
-use std::collections::HashMap;
 
 #[tokio::main]
 async fn main() -> Result<(), MCPError> {
@@ -22,42 +21,20 @@ async fn main() -> Result<(), MCPError> {
     let echo_tool = Tool {
         name: "echo".to_string(),
         description: Some("Echoes back the input".to_string()),
-        input_schema: ToolInputSchema {
-            r#type: "object".to_string(),
-            properties: Some(
-                [(
-                    "message".to_string(),
-                    json!({
-                        "type": "string",
-                        "description": "The message to echo"
-                    }),
-                )]
-                .into_iter()
-                .collect::<HashMap<_, _>>(),
-            ),
-            required: Some(vec!["message".to_string()]),
-        },
+        input_schema: Schema::object()
+            .prop("message", Schema::string().desc("The message to echo"))
+            .required(["message"]),
+        ..Default::default()
     };
 
     // Create a hello tool
     let hello_tool = Tool {
         name: "hello".to_string(),
         description: Some("Says hello to someone".to_string()),
-        input_schema: ToolInputSchema {
-            r#type: "object".to_string(),
-            properties: Some(
-                [(
-                    "name".to_string(),
-                    json!({
-                        "type": "string",
-                        "description": "The name to greet"
-                    }),
-                )]
-                .into_iter()
-                .collect::<HashMap<_, _>>(),
-            ),
-            required: Some(vec!["name".to_string()]),
-        },
+        input_schema: Schema::object()
+            .prop("name", Schema::string().desc("The name to greet"))
+            .required(["name"]),
+        ..Default::default()
     };
 
     // Configure the server
@@ -236,6 +236,7 @@ impl GitHubTool for ReadmeQueryTool {
                 required: Some(required),
             },
         }
+        ..Default::default()
     }
 
     fn handle(&self, params: Value) -> Result<Value> {
@@ -105,6 +105,7 @@ impl GitHubTool for RepoSearchTool {
                 required: Some(required),
             },
         }
+        ..Default::default()
     }
 
     fn handle(&self, params: Value) -> Result<Value> {
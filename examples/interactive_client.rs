@@ -84,7 +84,7 @@ async fn main() -> Result<(), MCPError> {
             match reader.read_line(&mut buffer).await {
                 Ok(0) => break, // EOF
                 Ok(_) => {
-                    if let Err(_) = input_tx.send(buffer.trim().to_string()).await {
+                    if input_tx.send(buffer.trim().to_string()).await.is_err() {
                         break;
                     }
                 }
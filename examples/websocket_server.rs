@@ -1,12 +1,12 @@
 use log::info;
 use mcpr::{
     error::MCPError,
-    schema::common::{Tool, ToolInputSchema},
     server::{Server, ServerConfig},
     transport::websocket::WebSocketTransport,
+    Schema, Tool,
 };
 use serde_json::json;
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 use tokio::sync::Notify;
 
 #[tokio::main]
@@ -23,21 +23,10 @@ async fn main() -> Result<(), MCPError> {
     let echo_tool = Tool {
         name: "echo".to_string(),
         description: Some("Echoes back the input".to_string()),
-        input_schema: ToolInputSchema {
-            r#type: "object".to_string(),
-            properties: Some(
-                [(
-                    "message".to_string(),
-                    json!({
-                        "type": "string",
-                        "description": "The message to echo"
-                    }),
-                )]
-                .into_iter()
-                .collect::<HashMap<_, _>>(),
-            ),
-            required: Some(vec!["message".to_string()]),
-        },
+        input_schema: Schema::object()
+            .prop("message", Schema::string().desc("The message to echo"))
+            .required(["message"]),
+        ..Default::default()
     };
 
     // Configure the server
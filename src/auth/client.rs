@@ -0,0 +1,681 @@
+//! OAuth 2.1 client for remote MCP servers
+//!
+//! A typical confidential client discovers the authorization server and asks for tokens
+//! with its own credentials:
+//!
+//! ```rust,no_run
+//! use mcpr::auth::client::{AuthorizationServerMetadata, OAuthClient};
+//! use mcpr::transport::{options::ClientTransportOptions, sse::SSETransport};
+//! use std::sync::Arc;
+//!
+//! # async fn example() -> Result<(), mcpr::error::MCPError> {
+//! let metadata = AuthorizationServerMetadata::discover("https://mcp.example.com/mcp").await?;
+//! let oauth = OAuthClient::new(metadata, "my-client").with_client_secret("s3cret");
+//! let options = ClientTransportOptions::new().with_oauth(Arc::new(oauth));
+//! let transport = SSETransport::new("https://mcp.example.com/mcp").with_client_options(options);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Interactive clients register themselves with
+//! [`register_client`](AuthorizationServerMetadata::register_client), send the user to
+//! the URL of an [`AuthorizationRequest`], and pass the redirect they receive back to
+//! [`exchange_code`](OAuthClient::exchange_code).
+
+use crate::error::MCPError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use log::debug;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use url::Url;
+
+/// Tokens this close to expiring are refreshed before use
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// Where an authorization server publishes its metadata (RFC 8414)
+const METADATA_PATH: &str = "/.well-known/oauth-authorization-server";
+
+/// Authorization server metadata (RFC 8414)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthorizationServerMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registration_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub introspection_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes_supported: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response_types_supported: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub grant_types_supported: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub code_challenge_methods_supported: Vec<String>,
+}
+
+impl AuthorizationServerMetadata {
+    /// The default endpoints the MCP specification prescribes for servers that publish no
+    /// metadata: `/authorize`, `/token` and `/register` at the origin of `server_url`
+    pub fn defaults_for(server_url: &str) -> Result<Self, MCPError> {
+        let origin = origin(server_url)?;
+        Ok(Self {
+            issuer: origin.clone(),
+            authorization_endpoint: format!("{}/authorize", origin),
+            token_endpoint: format!("{}/token", origin),
+            registration_endpoint: Some(format!("{}/register", origin)),
+            introspection_endpoint: None,
+            scopes_supported: Vec::new(),
+            response_types_supported: vec!["code".to_string()],
+            grant_types_supported: Vec::new(),
+            code_challenge_methods_supported: vec!["S256".to_string()],
+        })
+    }
+
+    /// Fetch the metadata of the authorization server for the MCP server at `server_url`
+    ///
+    /// Falls back to [`defaults_for`](Self::defaults_for) if the server publishes none.
+    pub async fn discover(server_url: &str) -> Result<Self, MCPError> {
+        Self::discover_with(&reqwest::Client::new(), server_url).await
+    }
+
+    /// [`discover`](Self::discover) using the given HTTP client
+    pub async fn discover_with(http: &reqwest::Client, server_url: &str) -> Result<Self, MCPError> {
+        let url = format!("{}{}", origin(server_url)?, METADATA_PATH);
+        debug!("Fetching authorization server metadata from {}", url);
+        let response = http
+            .get(&url)
+            .header(
+                "MCP-Protocol-Version",
+                crate::constants::LATEST_PROTOCOL_VERSION,
+            )
+            .send()
+            .await
+            .map_err(|e| MCPError::Authorization(format!("Failed to fetch {}: {}", url, e)))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            debug!("No authorization server metadata, using the default endpoints");
+            return Self::defaults_for(server_url);
+        }
+        if !response.status().is_success() {
+            return Err(MCPError::Authorization(format!(
+                "Failed to fetch {}: HTTP {}",
+                url,
+                response.status()
+            )));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| MCPError::Authorization(format!("Invalid metadata at {}: {}", url, e)))
+    }
+
+    /// Register a client dynamically (RFC 7591)
+    pub async fn register_client(
+        &self,
+        request: &ClientRegistrationRequest,
+    ) -> Result<ClientRegistration, MCPError> {
+        let endpoint = self.registration_endpoint.as_deref().ok_or_else(|| {
+            MCPError::Authorization(
+                "The authorization server does not support dynamic client registration".to_string(),
+            )
+        })?;
+        let response = reqwest::Client::new()
+            .post(endpoint)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| MCPError::Authorization(format!("Failed to register client: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(error_response(response, "Client registration").await);
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| MCPError::Authorization(format!("Invalid registration response: {}", e)))
+    }
+}
+
+/// Client metadata sent to the registration endpoint (RFC 7591)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClientRegistrationRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redirect_uris: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub grant_types: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub response_types: Vec<String>,
+    /// `none` for public clients, which have no secret
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_endpoint_auth_method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+impl ClientRegistrationRequest {
+    /// A public client using the authorization code flow with the given redirect URI
+    pub fn public(client_name: &str, redirect_uri: &str) -> Self {
+        Self {
+            client_name: Some(client_name.to_string()),
+            redirect_uris: vec![redirect_uri.to_string()],
+            grant_types: vec![
+                "authorization_code".to_string(),
+                "refresh_token".to_string(),
+            ],
+            response_types: vec!["code".to_string()],
+            token_endpoint_auth_method: Some("none".to_string()),
+            scope: None,
+        }
+    }
+}
+
+/// The credentials issued by the registration endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClientRegistration {
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+}
+
+/// A successful response from the token endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_in: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// A pending authorization code flow
+///
+/// Send the user to [`url`](Self::url), then hand the redirect to
+/// [`OAuthClient::exchange_code`].
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    /// Where the user authorizes the client
+    pub url: Url,
+    /// The value the redirect must carry back
+    pub state: String,
+    code_verifier: String,
+}
+
+/// The token in use, with enough to replace it
+struct CachedToken {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_at: Option<Instant>,
+}
+
+impl CachedToken {
+    fn from_response(response: TokenResponse) -> Self {
+        Self {
+            expires_at: response
+                .expires_in
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+        }
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| Instant::now() + EXPIRY_MARGIN < expires_at)
+    }
+}
+
+/// Obtains, caches and refreshes access tokens for one client
+///
+/// [`access_token`](Self::access_token) returns the cached token while it is fresh and
+/// otherwise refreshes it. Confidential clients (those with a secret) fall back to the
+/// client credentials flow; public clients need the authorization code flow first.
+pub struct OAuthClient {
+    http: reqwest::Client,
+    metadata: AuthorizationServerMetadata,
+    client_id: String,
+    client_secret: Option<String>,
+    scopes: Vec<String>,
+    redirect_uri: Option<String>,
+    resource: Option<String>,
+    tokens: Mutex<Option<CachedToken>>,
+}
+
+impl OAuthClient {
+    /// Create a client with the given ID
+    pub fn new(metadata: AuthorizationServerMetadata, client_id: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            metadata,
+            client_id: client_id.to_string(),
+            client_secret: None,
+            scopes: Vec::new(),
+            redirect_uri: None,
+            resource: None,
+            tokens: Mutex::new(None),
+        }
+    }
+
+    /// Create a client from the credentials of a dynamic registration
+    pub fn from_registration(
+        metadata: AuthorizationServerMetadata,
+        registration: &ClientRegistration,
+    ) -> Self {
+        let client = Self::new(metadata, &registration.client_id);
+        match &registration.client_secret {
+            Some(secret) => client.with_client_secret(secret),
+            None => client,
+        }
+    }
+
+    /// Authenticate to the token endpoint with a secret, making this a confidential client
+    pub fn with_client_secret(mut self, secret: &str) -> Self {
+        self.client_secret = Some(secret.to_string());
+        self
+    }
+
+    /// Request these scopes
+    pub fn with_scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = scopes.iter().map(|scope| scope.to_string()).collect();
+        self
+    }
+
+    /// Set the redirect URI of the authorization code flow
+    pub fn with_redirect_uri(mut self, redirect_uri: &str) -> Self {
+        self.redirect_uri = Some(redirect_uri.to_string());
+        self
+    }
+
+    /// Ask for tokens bound to one MCP server (RFC 8707 resource indicator)
+    pub fn with_resource(mut self, resource: &str) -> Self {
+        self.resource = Some(resource.to_string());
+        self
+    }
+
+    /// Use the given HTTP client to talk to the authorization server
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// The authorization server this client talks to
+    pub fn metadata(&self) -> &AuthorizationServerMetadata {
+        &self.metadata
+    }
+
+    /// Start the authorization code flow with PKCE
+    pub fn authorization_request(&self) -> Result<AuthorizationRequest, MCPError> {
+        let redirect_uri = self.redirect_uri.as_deref().ok_or_else(|| {
+            MCPError::Authorization("The authorization code flow needs a redirect URI".to_string())
+        })?;
+        let methods = &self.metadata.code_challenge_methods_supported;
+        if !methods.is_empty() && !methods.iter().any(|method| method == "S256") {
+            return Err(MCPError::Authorization(
+                "The authorization server does not support PKCE with S256".to_string(),
+            ));
+        }
+
+        let code_verifier = random_token(32);
+        let state = random_token(16);
+        let mut url = Url::parse(&self.metadata.authorization_endpoint).map_err(|e| {
+            MCPError::Authorization(format!("Invalid authorization endpoint: {}", e))
+        })?;
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("response_type", "code")
+                .append_pair("client_id", &self.client_id)
+                .append_pair("redirect_uri", redirect_uri)
+                .append_pair("code_challenge", &code_challenge(&code_verifier))
+                .append_pair("code_challenge_method", "S256")
+                .append_pair("state", &state);
+            if !self.scopes.is_empty() {
+                query.append_pair("scope", &self.scopes.join(" "));
+            }
+            if let Some(resource) = &self.resource {
+                query.append_pair("resource", resource);
+            }
+        }
+        Ok(AuthorizationRequest {
+            url,
+            state,
+            code_verifier,
+        })
+    }
+
+    /// Finish the authorization code flow with the URL the user was redirected to
+    pub async fn exchange_code(
+        &self,
+        request: &AuthorizationRequest,
+        redirect_url: &str,
+    ) -> Result<TokenResponse, MCPError> {
+        let redirect = Url::parse(redirect_url)
+            .map_err(|e| MCPError::Authorization(format!("Invalid redirect URL: {}", e)))?;
+        let params: HashMap<_, _> = redirect.query_pairs().into_owned().collect();
+        if let Some(error) = params.get("error") {
+            return Err(MCPError::Authorization(format!(
+                "Authorization denied: {}",
+                describe_error(error, params.get("error_description"))
+            )));
+        }
+        if params.get("state") != Some(&request.state) {
+            return Err(MCPError::Authorization(
+                "The redirect does not belong to this authorization request".to_string(),
+            ));
+        }
+        let code = params.get("code").ok_or_else(|| {
+            MCPError::Authorization("The redirect carries no authorization code".to_string())
+        })?;
+
+        let redirect_uri = self.redirect_uri.clone().unwrap_or_default();
+        self.request_token(vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code.clone()),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", request.code_verifier.clone()),
+        ])
+        .await
+    }
+
+    /// Get a token with the client's own credentials
+    pub async fn client_credentials(&self) -> Result<TokenResponse, MCPError> {
+        let mut params = vec![("grant_type", "client_credentials".to_string())];
+        if !self.scopes.is_empty() {
+            params.push(("scope", self.scopes.join(" ")));
+        }
+        self.request_token(params).await
+    }
+
+    /// Replace the cached token with the refresh token's successor
+    pub async fn refresh(&self) -> Result<TokenResponse, MCPError> {
+        let refresh_token = self
+            .tokens
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|token| token.refresh_token.clone())
+            .ok_or_else(|| MCPError::Authorization("No refresh token".to_string()))?;
+        self.request_token(vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token),
+        ])
+        .await
+    }
+
+    /// Seed the cache, e.g. with tokens persisted by an earlier run
+    pub async fn set_tokens(&self, tokens: TokenResponse) {
+        *self.tokens.lock().await = Some(CachedToken::from_response(tokens));
+    }
+
+    /// Forget the cached token, e.g. after the server rejected it
+    pub async fn clear_tokens(&self) {
+        *self.tokens.lock().await = None;
+    }
+
+    /// A current access token, refreshing or requesting one as needed
+    pub async fn access_token(&self) -> Result<String, MCPError> {
+        // Held throughout so that concurrent callers share one refresh
+        let mut tokens = self.tokens.lock().await;
+        if let Some(token) = tokens.as_ref().filter(|token| token.is_fresh()) {
+            return Ok(token.access_token.clone());
+        }
+
+        let refresh_token = tokens
+            .as_ref()
+            .and_then(|token| token.refresh_token.clone());
+        if let Some(refresh_token) = refresh_token {
+            debug!("Refreshing access token");
+            match self
+                .token_endpoint(vec![
+                    ("grant_type", "refresh_token".to_string()),
+                    ("refresh_token", refresh_token),
+                ])
+                .await
+            {
+                Ok(response) => return Ok(store(&mut tokens, response)),
+                Err(e) => debug!("Refreshing the access token failed: {}", e),
+            }
+        }
+
+        if self.client_secret.is_none() {
+            *tokens = None;
+            return Err(MCPError::Authorization(
+                "No valid access token; the user has to authorize this client".to_string(),
+            ));
+        }
+        debug!("Requesting access token with client credentials");
+        let mut params = vec![("grant_type", "client_credentials".to_string())];
+        if !self.scopes.is_empty() {
+            params.push(("scope", self.scopes.join(" ")));
+        }
+        let response = self.token_endpoint(params).await?;
+        Ok(store(&mut tokens, response))
+    }
+
+    /// Call the token endpoint and cache the result
+    async fn request_token(&self, params: Vec<(&str, String)>) -> Result<TokenResponse, MCPError> {
+        let response = self.token_endpoint(params).await?;
+        store(&mut *self.tokens.lock().await, response.clone());
+        Ok(response)
+    }
+
+    /// Call the token endpoint
+    async fn token_endpoint(
+        &self,
+        mut params: Vec<(&str, String)>,
+    ) -> Result<TokenResponse, MCPError> {
+        if let Some(resource) = &self.resource {
+            params.push(("resource", resource.clone()));
+        }
+        let mut request = self.http.post(&self.metadata.token_endpoint);
+        match &self.client_secret {
+            Some(secret) => request = request.basic_auth(&self.client_id, Some(secret)),
+            None => params.push(("client_id", self.client_id.clone())),
+        }
+
+        let response = request
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| MCPError::Authorization(format!("Token request failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(error_response(response, "Token request").await);
+        }
+        let token: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| MCPError::Authorization(format!("Invalid token response: {}", e)))?;
+        if !token.token_type.eq_ignore_ascii_case("bearer") {
+            return Err(MCPError::Authorization(format!(
+                "Unsupported token type {}",
+                token.token_type
+            )));
+        }
+        Ok(token)
+    }
+}
+
+/// Cache a token response and return its access token
+fn store(tokens: &mut Option<CachedToken>, response: TokenResponse) -> String {
+    let mut token = CachedToken::from_response(response);
+    // Servers may keep the refresh token and omit it from the response
+    if token.refresh_token.is_none() {
+        token.refresh_token = tokens.take().and_then(|old| old.refresh_token);
+    }
+    let access_token = token.access_token.clone();
+    *tokens = Some(token);
+    access_token
+}
+
+/// The scheme, host and port of a URL, which is where the authorization endpoints live
+fn origin(server_url: &str) -> Result<String, MCPError> {
+    let url = Url::parse(server_url)
+        .map_err(|e| MCPError::Authorization(format!("Invalid server URL: {}", e)))?;
+    Ok(url.origin().ascii_serialization())
+}
+
+/// A URL-safe random string made of `bytes` random bytes
+fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+/// The S256 code challenge for a PKCE code verifier (RFC 7636)
+fn code_challenge(code_verifier: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, code_verifier.as_bytes());
+    URL_SAFE_NO_PAD.encode(digest)
+}
+
+fn describe_error(error: &str, description: Option<&String>) -> String {
+    match description {
+        Some(description) => format!("{} ({})", error, description),
+        None => error.to_string(),
+    }
+}
+
+/// Turn an OAuth error response into an error
+async fn error_response(response: reqwest::Response, what: &str) -> MCPError {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        error: String,
+        error_description: Option<String>,
+    }
+
+    let status = response.status();
+    match response.json::<ErrorBody>().await {
+        Ok(body) => MCPError::Authorization(format!(
+            "{} failed: {}",
+            what,
+            describe_error(&body.error, body.error_description.as_ref())
+        )),
+        Err(_) => MCPError::Authorization(format!("{} failed: HTTP {}", what, status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_server::TestAuthServer;
+
+    /// Follow the authorization URL and return where the server redirects the user
+    async fn authorize(request: &AuthorizationRequest) -> String {
+        let http = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let response = http.get(request.url.clone()).send().await.unwrap();
+        assert!(response.status().is_redirection());
+        response.headers()["location"].to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_code_challenge_matches_rfc_7636() {
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        assert_eq!(random_token(32).len(), 43);
+    }
+
+    #[tokio::test]
+    async fn test_discovery_falls_back_to_default_endpoints() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move { axum::serve(listener, axum::Router::new()).await });
+
+        let metadata = AuthorizationServerMetadata::discover(&format!("http://{}/mcp", addr))
+            .await
+            .unwrap();
+        assert_eq!(metadata.token_endpoint, format!("http://{}/token", addr));
+        assert_eq!(
+            metadata.registration_endpoint,
+            Some(format!("http://{}/register", addr))
+        );
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_registration_and_authorization_code_flow() {
+        let auth = TestAuthServer::start(3600).await;
+        let metadata = AuthorizationServerMetadata::discover(&format!("{}/mcp", auth.url))
+            .await
+            .unwrap();
+        assert_eq!(metadata.issuer, auth.url);
+
+        let redirect_uri = "http://127.0.0.1:9/callback";
+        let registration = metadata
+            .register_client(&ClientRegistrationRequest::public("test", redirect_uri))
+            .await
+            .unwrap();
+        assert!(registration.client_secret.is_none());
+        let client =
+            OAuthClient::from_registration(metadata, &registration).with_redirect_uri(redirect_uri);
+
+        // Without a token and without a secret, the user has to authorize first
+        assert!(matches!(
+            client.access_token().await,
+            Err(MCPError::Authorization(_))
+        ));
+
+        let request = client.authorization_request().unwrap();
+        let redirect = authorize(&request).await;
+        assert!(redirect.starts_with(redirect_uri));
+
+        // A redirect from another flow is refused
+        let other = client.authorization_request().unwrap();
+        assert!(client.exchange_code(&other, &redirect).await.is_err());
+
+        let tokens = client.exchange_code(&request, &redirect).await.unwrap();
+        assert_eq!(client.access_token().await.unwrap(), tokens.access_token);
+    }
+
+    #[tokio::test]
+    async fn test_expiring_tokens_are_refreshed() {
+        // Tokens that expire within the safety margin are refreshed on every use
+        let auth = TestAuthServer::start(1).await;
+        auth.add_client("agent", "secret");
+        let metadata = AuthorizationServerMetadata::discover(&auth.url)
+            .await
+            .unwrap();
+        let client = OAuthClient::new(metadata, "agent").with_client_secret("secret");
+
+        let first = client.client_credentials().await.unwrap();
+        let second = client.access_token().await.unwrap();
+        assert_ne!(first.access_token, second);
+        assert_eq!(auth.grants("client_credentials"), 1);
+        assert_eq!(auth.grants("refresh_token"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials_are_cached() {
+        let auth = TestAuthServer::start(3600).await;
+        auth.add_client("agent", "secret");
+        let metadata = AuthorizationServerMetadata::discover(&auth.url)
+            .await
+            .unwrap();
+        let client = OAuthClient::new(metadata.clone(), "agent")
+            .with_client_secret("secret")
+            .with_scopes(&["tools"]);
+
+        let token = client.access_token().await.unwrap();
+        assert_eq!(client.access_token().await.unwrap(), token);
+        assert_eq!(auth.grants("client_credentials"), 1);
+
+        let wrong = OAuthClient::new(metadata, "agent").with_client_secret("wrong");
+        match wrong.access_token().await {
+            Err(MCPError::Authorization(e)) => assert!(e.contains("invalid_client"), "{}", e),
+            other => panic!("Expected an authorization error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! OAuth 2.1 authorization for the HTTP-based transports
+//!
+//! Remote MCP servers protect their endpoints with OAuth 2.1 bearer tokens. The
+//! [`client`] module obtains tokens: it discovers the authorization server's metadata,
+//! registers clients dynamically, runs the authorization code flow with PKCE or the client
+//! credentials flow, and caches and refreshes tokens. Hand an
+//! [`OAuthClient`](client::OAuthClient) to
+//! [`ClientTransportOptions::with_oauth`](crate::transport::options::ClientTransportOptions::with_oauth)
+//! and every request carries a current token.
+//!
+//! The [`server`] module checks tokens: [`BearerAuth`](server::BearerAuth) wraps an axum
+//! router, answers requests without a valid token with `401` and a `WWW-Authenticate`
+//! challenge, and passes the token's [`TokenInfo`](server::TokenInfo) on to the handlers.
+//!
+//! Independently of the transport, [`Server`](crate::server::Server) can authenticate each
+//! session with an [`Authenticator`](session::Authenticator) from the [`session`] module,
+//! which names the [`Principal`](session::Principal) behind it, and restrict the tools
+//! that principal sees with a [`Policy`](policy::Policy).
+
+pub mod client;
+pub mod policy;
+pub mod server;
+pub mod session;
+
+/// A minimal authorization server for tests
+#[cfg(test)]
+pub(crate) mod test_server {
+    use axum::extract::{Query, State};
+    use axum::http::{header, HeaderMap, StatusCode};
+    use axum::response::{IntoResponse, Redirect, Response};
+    use axum::routing::{get, post};
+    use axum::{Form, Json, Router};
+    use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+    use base64::Engine;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tokio::task::JoinHandle;
+
+    #[derive(Default)]
+    pub(crate) struct AuthState {
+        base_url: String,
+        // Registered clients and their secrets, if confidential
+        clients: HashMap<String, Option<String>>,
+        // Authorization code -> (client ID, code challenge, redirect URI)
+        codes: HashMap<String, (String, String, String)>,
+        // Access token -> (client ID, scope, resource)
+        access_tokens: HashMap<String, (String, String, Option<String>)>,
+        // Refresh token -> (client ID, scope, resource)
+        refresh_tokens: HashMap<String, (String, String, Option<String>)>,
+        // Lifetime of issued access tokens in seconds
+        expires_in: u64,
+        next_id: u64,
+        /// Number of requests to the token endpoint, by grant type
+        pub(crate) grants: HashMap<String, usize>,
+    }
+
+    impl AuthState {
+        fn next(&mut self, prefix: &str) -> String {
+            self.next_id += 1;
+            format!("{}-{}", prefix, self.next_id)
+        }
+
+        fn issue(&mut self, client_id: &str, scope: &str, resource: Option<&String>) -> Value {
+            let access_token = self.next("access");
+            let refresh_token = self.next("refresh");
+            let grant = (client_id.to_string(), scope.to_string(), resource.cloned());
+            self.access_tokens
+                .insert(access_token.clone(), grant.clone());
+            self.refresh_tokens.insert(refresh_token.clone(), grant);
+            json!({
+                "access_token": access_token,
+                "token_type": "Bearer",
+                "expires_in": self.expires_in,
+                "refresh_token": refresh_token,
+                "scope": scope,
+            })
+        }
+    }
+
+    /// A running authorization server
+    pub(crate) struct TestAuthServer {
+        pub(crate) url: String,
+        pub(crate) state: Arc<Mutex<AuthState>>,
+        task: JoinHandle<()>,
+    }
+
+    impl Drop for TestAuthServer {
+        fn drop(&mut self) {
+            self.task.abort();
+        }
+    }
+
+    impl TestAuthServer {
+        /// Start a server issuing access tokens that live for `expires_in` seconds
+        pub(crate) async fn start(expires_in: u64) -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let state = Arc::new(Mutex::new(AuthState {
+                base_url: url.clone(),
+                expires_in,
+                ..AuthState::default()
+            }));
+            let app = Router::new()
+                .route("/.well-known/oauth-authorization-server", get(metadata))
+                .route("/register", post(register))
+                .route("/authorize", get(authorize))
+                .route("/token", post(token))
+                .route("/introspect", post(introspect))
+                .with_state(Arc::clone(&state));
+            let task = tokio::spawn(async move {
+                axum::serve(listener, app).await.unwrap();
+            });
+            Self { url, state, task }
+        }
+
+        /// Register a confidential client directly
+        pub(crate) fn add_client(&self, client_id: &str, secret: &str) {
+            self.state
+                .lock()
+                .unwrap()
+                .clients
+                .insert(client_id.to_string(), Some(secret.to_string()));
+        }
+
+        pub(crate) fn grants(&self, grant_type: &str) -> usize {
+            let state = self.state.lock().unwrap();
+            state.grants.get(grant_type).copied().unwrap_or(0)
+        }
+    }
+
+    type Shared = State<Arc<Mutex<AuthState>>>;
+
+    async fn metadata(State(state): Shared) -> Json<Value> {
+        let base = state.lock().unwrap().base_url.clone();
+        Json(json!({
+            "issuer": base,
+            "authorization_endpoint": format!("{}/authorize", base),
+            "token_endpoint": format!("{}/token", base),
+            "registration_endpoint": format!("{}/register", base),
+            "introspection_endpoint": format!("{}/introspect", base),
+            "response_types_supported": ["code"],
+            "grant_types_supported": ["authorization_code", "client_credentials", "refresh_token"],
+            "code_challenge_methods_supported": ["S256"],
+        }))
+    }
+
+    async fn register(State(state): Shared, Json(request): Json<Value>) -> Response {
+        let mut state = state.lock().unwrap();
+        let client_id = state.next("client");
+        let public = request["token_endpoint_auth_method"] == "none";
+        let secret = (!public).then(|| state.next("secret"));
+        state.clients.insert(client_id.clone(), secret.clone());
+        let mut body = request;
+        body["client_id"] = json!(client_id);
+        if let Some(secret) = secret {
+            body["client_secret"] = json!(secret);
+        }
+        (StatusCode::CREATED, Json(body)).into_response()
+    }
+
+    async fn authorize(
+        State(state): Shared,
+        Query(params): Query<HashMap<String, String>>,
+    ) -> Response {
+        let mut state = state.lock().unwrap();
+        if params.get("code_challenge_method").map(String::as_str) != Some("S256") {
+            return (StatusCode::BAD_REQUEST, "PKCE with S256 is required").into_response();
+        }
+        let code = state.next("code");
+        state.codes.insert(
+            code.clone(),
+            (
+                params["client_id"].clone(),
+                params["code_challenge"].clone(),
+                params["redirect_uri"].clone(),
+            ),
+        );
+        Redirect::to(&format!(
+            "{}?code={}&state={}",
+            params["redirect_uri"], code, params["state"]
+        ))
+        .into_response()
+    }
+
+    fn oauth_error(status: StatusCode, error: &str) -> Response {
+        (status, Json(json!({ "error": error }))).into_response()
+    }
+
+    /// The client ID, if the client authenticated correctly
+    fn authenticate(
+        state: &AuthState,
+        headers: &HeaderMap,
+        form: &HashMap<String, String>,
+    ) -> Option<String> {
+        let (client_id, secret) = match headers.get(header::AUTHORIZATION) {
+            Some(value) => {
+                let encoded = value.to_str().ok()?.strip_prefix("Basic ")?;
+                let decoded = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+                let (id, secret) = decoded.split_once(':')?;
+                (id.to_string(), Some(secret.to_string()))
+            }
+            None => (form.get("client_id")?.clone(), None),
+        };
+        (state.clients.get(&client_id)? == &secret).then_some(client_id)
+    }
+
+    async fn token(
+        State(state): Shared,
+        headers: HeaderMap,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Response {
+        let mut state = state.lock().unwrap();
+        let grant_type = form.get("grant_type").cloned().unwrap_or_default();
+        *state.grants.entry(grant_type.clone()).or_default() += 1;
+        let Some(client_id) = authenticate(&state, &headers, &form) else {
+            return oauth_error(StatusCode::UNAUTHORIZED, "invalid_client");
+        };
+
+        let resource = form.get("resource");
+        let body = match grant_type.as_str() {
+            "client_credentials" => {
+                let scope = form.get("scope").cloned().unwrap_or_default();
+                state.issue(&client_id, &scope, resource)
+            }
+            "authorization_code" => {
+                let Some((code_client, challenge, redirect_uri)) =
+                    state.codes.remove(&form["code"])
+                else {
+                    return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant");
+                };
+                let verifier = form.get("code_verifier").cloned().unwrap_or_default();
+                let digest = ring::digest::digest(&ring::digest::SHA256, verifier.as_bytes());
+                if code_client != client_id
+                    || URL_SAFE_NO_PAD.encode(digest) != challenge
+                    || form.get("redirect_uri") != Some(&redirect_uri)
+                {
+                    return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant");
+                }
+                state.issue(&client_id, "", resource)
+            }
+            "refresh_token" => match state.refresh_tokens.remove(&form["refresh_token"]) {
+                Some((owner, scope, resource)) if owner == client_id => {
+                    state.issue(&client_id, &scope, resource.as_ref())
+                }
+                _ => return oauth_error(StatusCode::BAD_REQUEST, "invalid_grant"),
+            },
+            _ => return oauth_error(StatusCode::BAD_REQUEST, "unsupported_grant_type"),
+        };
+        Json(body).into_response()
+    }
+
+    async fn introspect(
+        State(state): Shared,
+        headers: HeaderMap,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Response {
+        let state = state.lock().unwrap();
+        if authenticate(&state, &headers, &form).is_none() {
+            return oauth_error(StatusCode::UNAUTHORIZED, "invalid_client");
+        }
+        let body = match state.access_tokens.get(&form["token"]) {
+            Some((client_id, scope, resource)) => {
+                let mut body = json!({
+                    "active": true,
+                    "sub": format!("user-of-{}", client_id),
+                    "client_id": client_id,
+                    "scope": scope,
+                });
+                if let Some(resource) = resource {
+                    body["aud"] = json!(resource);
+                }
+                body
+            }
+            None => json!({ "active": false }),
+        };
+        Json(body).into_response()
+    }
+}
@@ -0,0 +1,167 @@
+//! Deciding what each principal may use
+//!
+//! A server with a [`Policy`] asks it about every tool. Tools the policy denies are left
+//! out of `tools/list`, and calls to them fail as if the tool did not exist.
+//!
+//! The server does not serve resources or prompts itself; layers that do can ask the
+//! policy about them through
+//! [`SessionContext::allows_resource`](crate::server::middleware::SessionContext::allows_resource)
+//! and [`allows_prompt`](crate::server::middleware::SessionContext::allows_prompt).
+
+use crate::auth::session::Principal;
+use std::collections::HashMap;
+
+/// Decides which tools, resources and prompts a session may see and use
+pub trait Policy: Send + Sync {
+    /// Whether `principal` may use the tool; `principal` is `None` for sessions the server
+    /// did not authenticate
+    fn allow_tool(&self, principal: Option<&Principal>, tool: &str) -> bool;
+
+    /// Whether `principal` may list and read the resource at `uri`
+    ///
+    /// Allows every resource unless overridden.
+    fn allow_resource(&self, _principal: Option<&Principal>, _uri: &str) -> bool {
+        true
+    }
+
+    /// Whether `principal` may list and get the prompt
+    ///
+    /// Allows every prompt unless overridden.
+    fn allow_prompt(&self, _principal: Option<&Principal>, _prompt: &str) -> bool {
+        true
+    }
+}
+
+impl<F> Policy for F
+where
+    F: Fn(Option<&Principal>, &str) -> bool + Send + Sync,
+{
+    fn allow_tool(&self, principal: Option<&Principal>, tool: &str) -> bool {
+        self(principal, tool)
+    }
+}
+
+/// Role-based access to tools, resources and prompts
+///
+/// Tools listed with [`require_role`](Self::require_role), resources listed with
+/// [`require_role_for_resource`](Self::require_role_for_resource) and prompts listed with
+/// [`require_role_for_prompt`](Self::require_role_for_prompt) are available to principals
+/// with one of the given roles. Everything else is available to everyone, or to nobody
+/// after [`deny_by_default`](Self::deny_by_default).
+#[derive(Debug, Clone, Default)]
+pub struct RolePolicy {
+    tools: HashMap<String, Vec<String>>,
+    resources: HashMap<String, Vec<String>>,
+    prompts: HashMap<String, Vec<String>>,
+    deny_by_default: bool,
+}
+
+impl RolePolicy {
+    /// Create a policy that allows everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `tool` available only to principals with one of `roles`
+    pub fn require_role(mut self, tool: &str, roles: &[&str]) -> Self {
+        self.tools.insert(tool.to_string(), to_strings(roles));
+        self
+    }
+
+    /// Make the resource at `uri` available only to principals with one of `roles`
+    pub fn require_role_for_resource(mut self, uri: &str, roles: &[&str]) -> Self {
+        self.resources.insert(uri.to_string(), to_strings(roles));
+        self
+    }
+
+    /// Make `prompt` available only to principals with one of `roles`
+    pub fn require_role_for_prompt(mut self, prompt: &str, roles: &[&str]) -> Self {
+        self.prompts.insert(prompt.to_string(), to_strings(roles));
+        self
+    }
+
+    /// Hide tools, resources and prompts that have no role requirement
+    pub fn deny_by_default(mut self) -> Self {
+        self.deny_by_default = true;
+        self
+    }
+
+    /// Whether `principal` has one of the roles `table` requires for `name`
+    fn allow(
+        &self,
+        table: &HashMap<String, Vec<String>>,
+        principal: Option<&Principal>,
+        name: &str,
+    ) -> bool {
+        match table.get(name) {
+            Some(roles) => principal.is_some_and(|p| roles.iter().any(|role| p.has_role(role))),
+            None => !self.deny_by_default,
+        }
+    }
+}
+
+fn to_strings(roles: &[&str]) -> Vec<String> {
+    roles.iter().map(|role| role.to_string()).collect()
+}
+
+impl Policy for RolePolicy {
+    fn allow_tool(&self, principal: Option<&Principal>, tool: &str) -> bool {
+        self.allow(&self.tools, principal, tool)
+    }
+
+    fn allow_resource(&self, principal: Option<&Principal>, uri: &str) -> bool {
+        self.allow(&self.resources, principal, uri)
+    }
+
+    fn allow_prompt(&self, principal: Option<&Principal>, prompt: &str) -> bool {
+        self.allow(&self.prompts, principal, prompt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_policy() {
+        let admin = Principal::new("root").with_roles(&["admin"]);
+        let user = Principal::new("alice");
+        let policy = RolePolicy::new().require_role("delete", &["admin"]);
+
+        assert!(policy.allow_tool(Some(&admin), "delete"));
+        assert!(!policy.allow_tool(Some(&user), "delete"));
+        assert!(!policy.allow_tool(None, "delete"));
+        assert!(policy.allow_tool(Some(&user), "read"));
+        assert!(!policy.deny_by_default().allow_tool(Some(&admin), "read"));
+    }
+
+    #[test]
+    fn test_role_policy_for_resources_and_prompts() {
+        let admin = Principal::new("root").with_roles(&["admin"]);
+        let user = Principal::new("alice");
+        let policy = RolePolicy::new()
+            .require_role_for_resource("file:///etc/shadow", &["admin"])
+            .require_role_for_prompt("incident", &["admin"]);
+
+        assert!(policy.allow_resource(Some(&admin), "file:///etc/shadow"));
+        assert!(!policy.allow_resource(Some(&user), "file:///etc/shadow"));
+        assert!(policy.allow_resource(None, "file:///README"));
+        assert!(policy.allow_prompt(Some(&admin), "incident"));
+        assert!(!policy.allow_prompt(None, "incident"));
+        assert!(policy.allow_prompt(Some(&user), "summary"));
+        // Tool requirements do not leak into the other tables
+        assert!(policy.allow_tool(Some(&user), "incident"));
+
+        let closed = policy.deny_by_default();
+        assert!(!closed.allow_resource(Some(&admin), "file:///README"));
+        assert!(!closed.allow_prompt(Some(&admin), "summary"));
+    }
+
+    #[test]
+    fn test_closures_allow_resources_and_prompts() {
+        let policy = |_: Option<&Principal>, tool: &str| tool == "read";
+        assert!(!policy.allow_tool(None, "write"));
+        assert!(policy.allow_resource(None, "file:///anything"));
+        assert!(policy.allow_prompt(None, "anything"));
+    }
+}
@@ -0,0 +1,453 @@
+//! Bearer token checks for MCP servers
+//!
+//! [`BearerAuth`] wraps the routes of an HTTP transport. Requests without a token, or with
+//! one the [`TokenValidator`] rejects, get `401 Unauthorized` with a `WWW-Authenticate`
+//! challenge (RFC 6750), as do tokens issued for another server when an audience is set;
+//! tokens lacking a required scope get `403 Forbidden`. Accepted requests carry the
+//! token's [`TokenInfo`] as a request extension.
+
+use crate::error::MCPError;
+use async_trait::async_trait;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use log::{debug, error};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// What a valid token says about its holder
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenInfo {
+    /// The user or service the token was issued to
+    pub subject: Option<String>,
+    /// The OAuth client that obtained the token
+    pub client_id: Option<String>,
+    /// The scopes granted
+    pub scopes: Vec<String>,
+    /// The resource servers the token is meant for
+    pub audience: Vec<String>,
+}
+
+/// Decides whether a bearer token is valid
+#[async_trait]
+pub trait TokenValidator: Send + Sync {
+    /// The token's details if it is valid, `None` if it is not
+    ///
+    /// Errors mean validity could not be determined, e.g. because the authorization
+    /// server was unreachable.
+    async fn validate(&self, token: &str) -> Result<Option<TokenInfo>, MCPError>;
+}
+
+/// A fixed set of tokens, for tests and simple deployments
+#[derive(Debug, Clone, Default)]
+pub struct StaticTokens {
+    tokens: HashMap<String, TokenInfo>,
+}
+
+impl StaticTokens {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept `token`, describing its holder with `info`
+    pub fn with_token(mut self, token: &str, info: TokenInfo) -> Self {
+        self.tokens.insert(token.to_string(), info);
+        self
+    }
+}
+
+#[async_trait]
+impl TokenValidator for StaticTokens {
+    async fn validate(&self, token: &str) -> Result<Option<TokenInfo>, MCPError> {
+        Ok(self.tokens.get(token).cloned())
+    }
+}
+
+/// Asks the authorization server about each token (RFC 7662 token introspection)
+pub struct IntrospectionValidator {
+    http: reqwest::Client,
+    endpoint: String,
+    client_id: String,
+    client_secret: String,
+}
+
+impl IntrospectionValidator {
+    /// Use the introspection endpoint, authenticating as the given client
+    pub fn new(endpoint: &str, client_id: &str, client_secret: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            endpoint: endpoint.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl TokenValidator for IntrospectionValidator {
+    async fn validate(&self, token: &str) -> Result<Option<TokenInfo>, MCPError> {
+        #[derive(Deserialize)]
+        struct Introspection {
+            active: bool,
+            sub: Option<String>,
+            client_id: Option<String>,
+            scope: Option<String>,
+            aud: Option<Audience>,
+        }
+
+        /// `aud` is a single string or an array of them
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Audience {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        let response = self
+            .http
+            .post(&self.endpoint)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&[("token", token), ("token_type_hint", "access_token")])
+            .send()
+            .await
+            .map_err(|e| MCPError::Authorization(format!("Token introspection failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(MCPError::Authorization(format!(
+                "Token introspection failed: HTTP {}",
+                response.status()
+            )));
+        }
+        let introspection: Introspection = response.json().await.map_err(|e| {
+            MCPError::Authorization(format!("Invalid introspection response: {}", e))
+        })?;
+
+        Ok(introspection.active.then(|| TokenInfo {
+            subject: introspection.sub,
+            client_id: introspection.client_id,
+            scopes: introspection
+                .scope
+                .map(|scope| scope.split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+            audience: match introspection.aud {
+                Some(Audience::One(audience)) => vec![audience],
+                Some(Audience::Many(audience)) => audience,
+                None => Vec::new(),
+            },
+        }))
+    }
+}
+
+/// Requires a valid bearer token on every request to the routes it protects
+#[derive(Clone)]
+pub struct BearerAuth {
+    validator: Arc<dyn TokenValidator>,
+    realm: String,
+    required_scopes: Vec<String>,
+    audience: Option<String>,
+    resource_metadata: Option<String>,
+}
+
+impl BearerAuth {
+    /// Check tokens with `validator`
+    pub fn new(validator: impl TokenValidator + 'static) -> Self {
+        Self {
+            validator: Arc::new(validator),
+            realm: "mcp".to_string(),
+            required_scopes: Vec::new(),
+            audience: None,
+            resource_metadata: None,
+        }
+    }
+
+    /// Set the realm named in challenges; defaults to `mcp`
+    pub fn with_realm(mut self, realm: &str) -> Self {
+        self.realm = realm.to_string();
+        self
+    }
+
+    /// Refuse tokens that lack any of these scopes
+    pub fn with_required_scopes(mut self, scopes: &[&str]) -> Self {
+        self.required_scopes = scopes.iter().map(|scope| scope.to_string()).collect();
+        self
+    }
+
+    /// Refuse tokens that were not issued for `audience`, this server's resource URL
+    ///
+    /// Without it, a token the authorization server issued for any other server is
+    /// accepted here too.
+    pub fn with_audience(mut self, audience: &str) -> Self {
+        self.audience = Some(audience.to_string());
+        self
+    }
+
+    /// Point clients at the protected resource metadata (RFC 9728) in challenges
+    pub fn with_resource_metadata(mut self, url: &str) -> Self {
+        self.resource_metadata = Some(url.to_string());
+        self
+    }
+
+    /// Wrap every route of `router`
+    pub fn protect(&self, router: Router) -> Router {
+        router.layer(middleware::from_fn_with_state(self.clone(), check_bearer))
+    }
+
+    /// The `WWW-Authenticate` value, with `error` details for a rejected token
+    fn challenge(&self, error: Option<(&str, &str)>) -> HeaderValue {
+        let mut challenge = format!("Bearer realm=\"{}\"", self.realm);
+        if let Some(url) = &self.resource_metadata {
+            challenge.push_str(&format!(", resource_metadata=\"{}\"", url));
+        }
+        if let Some((code, description)) = error {
+            challenge.push_str(&format!(
+                ", error=\"{}\", error_description=\"{}\"",
+                code, description
+            ));
+        }
+        if !self.required_scopes.is_empty() {
+            challenge.push_str(&format!(", scope=\"{}\"", self.required_scopes.join(" ")));
+        }
+        HeaderValue::from_str(&challenge).unwrap_or_else(|_| HeaderValue::from_static("Bearer"))
+    }
+
+    fn reject(&self, status: StatusCode, error: Option<(&str, &str)>) -> Response {
+        let body = match error {
+            Some((code, description)) => {
+                json!({ "error": code, "error_description": description })
+            }
+            None => json!({ "error": "unauthorized" }),
+        };
+        let mut response = (status, Json(body)).into_response();
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, self.challenge(error));
+        response
+    }
+}
+
+async fn check_bearer(
+    State(auth): State<BearerAuth>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            let (scheme, token) = value.split_once(' ')?;
+            scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+        });
+    let Some(token) = token else {
+        debug!("Refusing request without a bearer token");
+        return auth.reject(StatusCode::UNAUTHORIZED, None);
+    };
+
+    let info = match auth.validator.validate(token).await {
+        Ok(Some(info)) => info,
+        Ok(None) => {
+            debug!("Refusing request with an invalid bearer token");
+            return auth.reject(
+                StatusCode::UNAUTHORIZED,
+                Some(("invalid_token", "The access token is invalid or expired")),
+            );
+        }
+        Err(e) => {
+            error!("Failed to validate bearer token: {}", e);
+            return (StatusCode::SERVICE_UNAVAILABLE, "Token validation failed").into_response();
+        }
+    };
+
+    if let Some(audience) = &auth.audience {
+        if !info.audience.contains(audience) {
+            debug!("Refusing token issued for {:?}", info.audience);
+            return auth.reject(
+                StatusCode::UNAUTHORIZED,
+                Some((
+                    "invalid_token",
+                    "The access token was not issued for this server",
+                )),
+            );
+        }
+    }
+
+    if let Some(missing) = auth
+        .required_scopes
+        .iter()
+        .find(|scope| !info.scopes.contains(scope))
+    {
+        debug!("Refusing token without scope {}", missing);
+        return auth.reject(
+            StatusCode::FORBIDDEN,
+            Some((
+                "insufficient_scope",
+                "The access token lacks a required scope",
+            )),
+        );
+    }
+
+    request.extensions_mut().insert(info);
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::client::{AuthorizationServerMetadata, OAuthClient};
+    use crate::auth::test_server::TestAuthServer;
+    use axum::routing::get;
+    use axum::Extension;
+
+    async fn serve(router: Router) -> (String, tokio::task::JoinHandle<()>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let task = tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        (url, task)
+    }
+
+    fn whoami() -> Router {
+        Router::new().route(
+            "/whoami",
+            get(|Extension(info): Extension<TokenInfo>| async move {
+                info.subject.unwrap_or_default()
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_a_valid_token() {
+        let tokens = StaticTokens::new()
+            .with_token(
+                "good",
+                TokenInfo {
+                    subject: Some("alice".to_string()),
+                    scopes: vec!["tools".to_string()],
+                    ..TokenInfo::default()
+                },
+            )
+            .with_token("narrow", TokenInfo::default());
+        let auth = BearerAuth::new(tokens)
+            .with_required_scopes(&["tools"])
+            .with_resource_metadata("https://mcp.example.com/.well-known/oauth-protected-resource");
+        let (url, server) = serve(auth.protect(whoami())).await;
+        let http = reqwest::Client::new();
+        let whoami = format!("{}/whoami", url);
+
+        let response = http.get(&whoami).send().await.unwrap();
+        assert_eq!(response.status(), 401);
+        let challenge = response.headers()["www-authenticate"].to_str().unwrap();
+        assert!(challenge.starts_with("Bearer realm=\"mcp\""));
+        assert!(challenge.contains("resource_metadata=\"https://mcp.example.com/"));
+        assert!(!challenge.contains("error="));
+
+        let response = http.get(&whoami).bearer_auth("bad").send().await.unwrap();
+        assert_eq!(response.status(), 401);
+        let challenge = response.headers()["www-authenticate"].to_str().unwrap();
+        assert!(challenge.contains("error=\"invalid_token\""));
+
+        let response = http
+            .get(&whoami)
+            .bearer_auth("narrow")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 403);
+        let challenge = response.headers()["www-authenticate"].to_str().unwrap();
+        assert!(challenge.contains("error=\"insufficient_scope\""));
+        assert!(challenge.contains("scope=\"tools\""));
+
+        let response = http.get(&whoami).bearer_auth("good").send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "alice");
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_introspection_against_authorization_server() {
+        let auth_server = TestAuthServer::start(3600).await;
+        auth_server.add_client("mcp-server", "server-secret");
+        auth_server.add_client("agent", "agent-secret");
+
+        let validator = IntrospectionValidator::new(
+            &format!("{}/introspect", auth_server.url),
+            "mcp-server",
+            "server-secret",
+        );
+        let (url, server) = serve(BearerAuth::new(validator).protect(whoami())).await;
+
+        let metadata = AuthorizationServerMetadata::discover(&auth_server.url)
+            .await
+            .unwrap();
+        let oauth = OAuthClient::new(metadata, "agent").with_client_secret("agent-secret");
+        let token = oauth.access_token().await.unwrap();
+
+        let http = reqwest::Client::new();
+        let response = http
+            .get(format!("{}/whoami", url))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "user-of-agent");
+
+        let response = http
+            .get(format!("{}/whoami", url))
+            .bearer_auth("forged")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 401);
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_tokens_for_another_server_are_refused() {
+        let auth_server = TestAuthServer::start(3600).await;
+        auth_server.add_client("mcp-server", "server-secret");
+        auth_server.add_client("agent", "agent-secret");
+
+        let validator = IntrospectionValidator::new(
+            &format!("{}/introspect", auth_server.url),
+            "mcp-server",
+            "server-secret",
+        );
+        let auth = BearerAuth::new(validator).with_audience("https://mcp.example.com");
+        let (url, server) = serve(auth.protect(whoami())).await;
+
+        let metadata = AuthorizationServerMetadata::discover(&auth_server.url)
+            .await
+            .unwrap();
+        let token = |resource: Option<&str>| {
+            let mut oauth =
+                OAuthClient::new(metadata.clone(), "agent").with_client_secret("agent-secret");
+            if let Some(resource) = resource {
+                oauth = oauth.with_resource(resource);
+            }
+            async move { oauth.access_token().await.unwrap() }
+        };
+
+        let http = reqwest::Client::new();
+        for (resource, status) in [
+            (Some("https://mcp.example.com"), 200),
+            (Some("https://other.example.com"), 401),
+            (None, 401),
+        ] {
+            let response = http
+                .get(format!("{}/whoami", url))
+                .bearer_auth(token(resource).await)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{:?}", resource);
+            if status == 401 {
+                let challenge = response.headers()["www-authenticate"].to_str().unwrap();
+                assert!(challenge.contains("error=\"invalid_token\""));
+            }
+        }
+        server.abort();
+    }
+}
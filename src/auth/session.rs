@@ -0,0 +1,315 @@
+//! Authenticating sessions
+//!
+//! When a session starts, its transport reports the [`Credentials`] the peer presented:
+//! request headers on HTTP-based transports, client certificates over mutual TLS and the
+//! peer address. An [`Authenticator`] turns those into a [`Principal`], which the server
+//! keeps for the rest of the session and hands to its [`Policy`](super::policy::Policy).
+
+use crate::auth::server::{TokenInfo, TokenValidator};
+use crate::error::MCPError;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// What the peer presented when the session was established
+#[derive(Debug, Clone, Default)]
+pub struct Credentials {
+    /// Request headers of HTTP-based transports, with lower-case names
+    pub headers: HashMap<String, String>,
+    /// The token details, if a [`BearerAuth`](super::server::BearerAuth) layer has
+    /// already checked the request's token
+    pub token_info: Option<TokenInfo>,
+    /// DER-encoded certificates the client presented over mutual TLS, leaf first
+    pub peer_certificates: Vec<Vec<u8>>,
+    /// The peer's address, for network transports
+    pub peer_addr: Option<SocketAddr>,
+}
+
+impl Credentials {
+    /// The value of a request header
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// The token of an `Authorization: Bearer` header
+    pub fn bearer_token(&self) -> Option<&str> {
+        let (scheme, token) = self.header("authorization")?.split_once(' ')?;
+        scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+    }
+}
+
+/// The authenticated identity behind a session
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Principal {
+    /// A stable identifier, e.g. a user name or client ID
+    pub id: String,
+    /// Roles or scopes used to make authorization decisions
+    pub roles: Vec<String>,
+}
+
+impl Principal {
+    /// Create a principal without roles
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            roles: Vec::new(),
+        }
+    }
+
+    /// Add roles
+    pub fn with_roles(mut self, roles: &[&str]) -> Self {
+        self.roles.extend(roles.iter().map(|role| role.to_string()));
+        self
+    }
+
+    /// Whether the principal has `role`
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
+/// Decides who is behind a session
+#[async_trait]
+pub trait Authenticator: Send + Sync {
+    /// The principal the credentials identify, or `None` to refuse the session
+    async fn authenticate(&self, credentials: &Credentials) -> Result<Option<Principal>, MCPError>;
+}
+
+/// API keys sent in a request header
+#[derive(Debug, Clone)]
+pub struct ApiKeys {
+    header: String,
+    keys: HashMap<String, Principal>,
+}
+
+impl ApiKeys {
+    /// Look for keys in the `X-API-Key` header
+    pub fn new() -> Self {
+        Self {
+            header: "x-api-key".to_string(),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Look for keys in another header
+    pub fn with_header(mut self, header: &str) -> Self {
+        self.header = header.to_ascii_lowercase();
+        self
+    }
+
+    /// Accept `key` as identifying `principal`
+    pub fn with_key(mut self, key: &str, principal: Principal) -> Self {
+        self.keys.insert(key.to_string(), principal);
+        self
+    }
+}
+
+impl Default for ApiKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Authenticator for ApiKeys {
+    async fn authenticate(&self, credentials: &Credentials) -> Result<Option<Principal>, MCPError> {
+        Ok(credentials
+            .header(&self.header)
+            .and_then(|key| self.keys.get(key))
+            .cloned())
+    }
+}
+
+/// Bearer tokens, checked by a [`TokenValidator`]
+///
+/// The principal is the token's subject, or its client ID for tokens issued to a client
+/// itself, and its roles are the token's scopes. Tokens already checked by a
+/// [`BearerAuth`](super::server::BearerAuth) layer are not validated again.
+#[derive(Clone)]
+pub struct BearerTokens {
+    validator: Arc<dyn TokenValidator>,
+}
+
+impl BearerTokens {
+    /// Check tokens with `validator`
+    pub fn new(validator: impl TokenValidator + 'static) -> Self {
+        Self {
+            validator: Arc::new(validator),
+        }
+    }
+}
+
+#[async_trait]
+impl Authenticator for BearerTokens {
+    async fn authenticate(&self, credentials: &Credentials) -> Result<Option<Principal>, MCPError> {
+        let info = match &credentials.token_info {
+            Some(info) => Some(info.clone()),
+            None => match credentials.bearer_token() {
+                Some(token) => self.validator.validate(token).await?,
+                None => None,
+            },
+        };
+        Ok(info.and_then(|info| {
+            let id = info.subject.or(info.client_id)?;
+            Some(Principal {
+                id,
+                roles: info.scopes,
+            })
+        }))
+    }
+}
+
+/// Client certificates verified during the TLS handshake
+///
+/// The principal is the common name of the certificate's subject. Which CAs may issue
+/// client certificates is decided by the server's
+/// [`TlsServerConfig`](crate::transport::tls::TlsServerConfig); this only names the holder.
+#[derive(Debug, Clone, Default)]
+pub struct ClientCertificates {
+    roles: HashMap<String, Vec<String>>,
+}
+
+impl ClientCertificates {
+    /// Accept every verified certificate
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give the holder of the certificate for `common_name` these roles
+    pub fn with_roles(mut self, common_name: &str, roles: &[&str]) -> Self {
+        self.roles.insert(
+            common_name.to_string(),
+            roles.iter().map(|role| role.to_string()).collect(),
+        );
+        self
+    }
+}
+
+#[async_trait]
+impl Authenticator for ClientCertificates {
+    async fn authenticate(&self, credentials: &Credentials) -> Result<Option<Principal>, MCPError> {
+        let Some(leaf) = credentials.peer_certificates.first() else {
+            return Ok(None);
+        };
+        let (_, certificate) = x509_parser::parse_x509_certificate(leaf)
+            .map_err(|e| MCPError::Authorization(format!("Invalid client certificate: {}", e)))?;
+        let common_name = certificate
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|name| name.as_str().ok());
+        Ok(common_name.map(|name| Principal {
+            id: name.to_string(),
+            roles: self.roles.get(name).cloned().unwrap_or_default(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::server::StaticTokens;
+    use crate::transport::tls::test_certs::TestCa;
+
+    fn with_header(name: &str, value: &str) -> Credentials {
+        Credentials {
+            headers: [(name.to_string(), value.to_string())]
+                .into_iter()
+                .collect(),
+            ..Credentials::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() {
+        let keys = ApiKeys::new().with_key("k1", Principal::new("ci").with_roles(&["deploy"]));
+        let principal = keys
+            .authenticate(&with_header("x-api-key", "k1"))
+            .await
+            .unwrap();
+        assert_eq!(
+            principal,
+            Some(Principal::new("ci").with_roles(&["deploy"]))
+        );
+        assert_eq!(
+            keys.authenticate(&with_header("x-api-key", "k2"))
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            keys.authenticate(&Credentials::default()).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bearer_tokens_use_subject_and_scopes() {
+        let info = TokenInfo {
+            subject: Some("alice".to_string()),
+            client_id: Some("agent".to_string()),
+            scopes: vec!["tools".to_string()],
+            audience: Vec::new(),
+        };
+        let tokens = BearerTokens::new(StaticTokens::new().with_token("t", info.clone()));
+
+        let principal = tokens
+            .authenticate(&with_header("authorization", "Bearer t"))
+            .await
+            .unwrap();
+        assert_eq!(
+            principal,
+            Some(Principal::new("alice").with_roles(&["tools"]))
+        );
+
+        // Already validated by the HTTP layer
+        let credentials = Credentials {
+            token_info: Some(TokenInfo {
+                subject: None,
+                ..info
+            }),
+            ..Credentials::default()
+        };
+        let principal = tokens.authenticate(&credentials).await.unwrap();
+        assert_eq!(principal.unwrap().id, "agent");
+
+        assert_eq!(
+            tokens
+                .authenticate(&with_header("authorization", "Bearer forged"))
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_certificates_name_the_holder() {
+        let ca = TestCa::new("Test CA");
+        let pem = ca.client_cert("build-bot").cert;
+        let der = rustls_pemfile::certs(&mut pem.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap();
+        let credentials = Credentials {
+            peer_certificates: vec![der.to_vec()],
+            ..Credentials::default()
+        };
+
+        let certificates = ClientCertificates::new().with_roles("build-bot", &["deploy"]);
+        let principal = certificates.authenticate(&credentials).await.unwrap();
+        assert_eq!(
+            principal,
+            Some(Principal::new("build-bot").with_roles(&["deploy"]))
+        );
+        assert_eq!(
+            certificates
+                .authenticate(&Credentials::default())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
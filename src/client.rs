@@ -44,215 +44,419 @@
 //! - Timeouts for operations
 //! - Concurrent tool calls
 //! - Simplified session execution
+//! - Automatic reconnects that restore the session
 
 use crate::{
     constants::LATEST_PROTOCOL_VERSION,
     error::MCPError,
-    schema::json_rpc::{JSONRPCMessage, JSONRPCRequest, RequestId},
-    transport::Transport,
+    schema::client::{
+        CallToolParams, ClientCapabilities, InitializeParams, SetLevelParams, SubscribeParams,
+        UnsubscribeParams,
+    },
+    schema::common::{Implementation, LoggingLevel},
+    schema::json_rpc::{
+        ErrorCode, JSONRPCError, JSONRPCMessage, JSONRPCRequest, JSONRPCResponse, RequestId,
+    },
+    schema::messages::{ClientNotification, ClientRequest, ServerNotification, ServerRequest},
+    schema::raw::RawMessage,
+    schema::server::{ElicitRequestParams, ElicitResult, ToolResultContent},
+    schema::version::{ProtocolVersion, Versioned},
+    server::TIMEOUT_META_KEY,
+    transport::{
+        reconnect::ReconnectPolicy, BoxedTransport, Transport, TransportReceiver, TransportSender,
+    },
 };
-use async_trait::async_trait;
-use futures::future::join_all;
-use serde::{de::DeserializeOwned, Serialize};
+use futures::future::{join_all, BoxFuture};
+use log::{debug, error, info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::value::RawValue;
 use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
 
+/// Requests that have been sent and are waiting for the server's response
+///
+/// Responses are handed over with their results unparsed, for the request to read into
+/// the type it expects.
+type PendingRequests =
+    Arc<Mutex<HashMap<RequestId, oneshot::Sender<Result<RawMessage, MCPError>>>>>;
+
+/// Asks the user for the input a server requested
+type ElicitationHandler = Arc<
+    dyn Fn(ElicitRequestParams) -> BoxFuture<'static, Result<ElicitResult, MCPError>> + Send + Sync,
+>;
+
+/// Produces a fresh transport each time the client reconnects
+type TransportFactory =
+    Arc<dyn Fn() -> BoxFuture<'static, Result<BoxedTransport, MCPError>> + Send + Sync>;
+
 /// High-level MCP client
-pub struct Client<T: Transport + Send + Sync> {
-    transport: T,
-    next_request_id: i64,
+///
+/// Once initialized, a background task reads from the transport and hands each response
+/// to the request that is waiting for it, so several requests can be in flight at once.
+///
+/// With [`with_reconnect`](Client::with_reconnect), the client replaces a transport that
+/// dies, then repeats `initialize` and re-applies resource subscriptions and the log level.
+/// Requests that were in flight when the connection dropped, or that are made while it is
+/// being re-established, fail with [`MCPError::ConnectionLost`].
+pub struct Client<T: Transport> {
+    transport: Option<T>,
+    connection: Option<Arc<Connection>>,
+    reader_task: Option<JoinHandle<()>>,
+    timeout_duration: Option<Duration>,
+    capabilities: Option<ClientCapabilities>,
+    reconnect: Option<(ReconnectPolicy, TransportFactory)>,
+    elicitation_handler: Option<ElicitationHandler>,
+}
+
+/// Session state that is re-established after a reconnect
+#[derive(Default)]
+struct SessionState {
+    // Parameters of the last successful initialize request
+    initialize_params: Option<InitializeParams>,
+    // The protocol revision the server answered with
+    protocol_version: Option<ProtocolVersion>,
+    subscriptions: BTreeSet<String>,
+    log_level: Option<LoggingLevel>,
+}
+
+/// A live connection, shared between the client and its background reader
+struct Connection {
+    // Present while requests can be sent
+    sender: RwLock<Option<Arc<dyn TransportSender>>>,
+    // Why requests cannot be sent, once the connection has been lost
+    lost_reason: Mutex<Option<String>>,
+    pending: PendingRequests,
+    next_request_id: AtomicI64,
     timeout_duration: Option<Duration>,
+    session: Mutex<SessionState>,
+    reconnect: Option<(ReconnectPolicy, TransportFactory)>,
+    shutting_down: AtomicBool,
+    elicitation_handler: Option<ElicitationHandler>,
 }
 
-impl<T: Transport + Send + Sync> Client<T> {
+impl<T: Transport + 'static> Client<T> {
     /// Create a new MCP client with the given transport
     pub fn new(transport: T) -> Self {
         Self {
-            transport,
-            next_request_id: 1,
+            transport: Some(transport),
+            connection: None,
+            reader_task: None,
             timeout_duration: None,
+            capabilities: None,
+            reconnect: None,
+            elicitation_handler: None,
         }
     }
 
     /// Set a timeout for operations
+    ///
+    /// The timeout is also sent to the server in each request's `_meta`, so the server
+    /// can stop work the client no longer waits for.
     pub fn with_timeout(mut self, duration: Duration) -> Self {
         self.timeout_duration = Some(duration);
         self
     }
 
+    /// Set the capabilities announced in the initialize request
+    pub fn with_capabilities(mut self, capabilities: ClientCapabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Answer the server's requests for user input with `handler`
+    ///
+    /// Setting a handler makes the client announce the `elicitation` capability, so it
+    /// must be set before [`initialize`](Client::initialize). The handler is given the
+    /// server's message and the schema of the data it wants, and reports whether the user
+    /// accepted, declined or cancelled. It runs in its own task, so it may take as long as
+    /// the user does without holding up other responses.
+    pub fn set_elicitation_handler<F, Fut>(&mut self, handler: F)
+    where
+        F: Fn(ElicitRequestParams) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ElicitResult, MCPError>> + Send + 'static,
+    {
+        self.elicitation_handler = Some(Arc::new(move |params| Box::pin(handler(params))));
+    }
+
+    /// Reconnect through `factory` whenever the transport dies
+    ///
+    /// The factory is called for every attempt, with delays taken from `policy`. Once a new
+    /// transport has started, the client repeats the last `initialize` and re-applies
+    /// resource subscriptions and the log level before accepting new requests.
+    pub fn with_reconnect<F, Fut>(mut self, policy: ReconnectPolicy, factory: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, MCPError>> + Send + 'static,
+    {
+        let factory: TransportFactory = Arc::new(move || {
+            let transport = factory();
+            Box::pin(async move { transport.await.map(|t| Box::new(t) as BoxedTransport) })
+        });
+        self.reconnect = Some((policy, factory));
+        self
+    }
+
     /// Check if the client is connected to the server
     pub fn is_connected(&self) -> bool {
-        // For now, we don't have a direct way to check this in the Transport trait
-        // This is an estimation based on our internal state
-        self.next_request_id > 1 // If we've sent at least one request, we consider ourselves connected
+        self.connection
+            .as_ref()
+            .is_some_and(|connection| connection.sender.read().unwrap().is_some())
     }
 
     /// Initialize the client
     pub async fn initialize(&mut self) -> Result<Value, MCPError> {
-        // Start the transport
-        self.transport.start().await?;
-
-        // Send initialization request
-        let initialize_request = JSONRPCRequest::new(
-            self.next_request_id(),
-            "initialize".to_string(),
-            Some(serde_json::json!({
-                "protocol_version": LATEST_PROTOCOL_VERSION
-            })),
-        );
-
-        let message = JSONRPCMessage::Request(initialize_request);
-        self.transport.send(&message).await?;
-
-        // Wait for response with timeout if set
-        let response: JSONRPCMessage = self.receive_with_timeout().await?;
+        // Start the transport the first time round
+        if self.connection.is_none() {
+            self.connect().await?;
+        }
 
-        match response {
-            JSONRPCMessage::Response(resp) => Ok(resp.result),
-            JSONRPCMessage::Error(err) => Err(MCPError::Protocol(format!(
-                "Initialization failed: {:?}",
-                err
-            ))),
-            _ => Err(MCPError::Protocol("Unexpected response type".to_string())),
+        let mut capabilities = self.capabilities.clone().unwrap_or_default();
+        if self.elicitation_handler.is_some() {
+            capabilities
+                .elicitation
+                .get_or_insert_with(|| serde_json::json!({}));
         }
+        let params = InitializeParams {
+            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+            capabilities,
+            client_info: Implementation {
+                name: "mcpr".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                title: None,
+            },
+            meta: None,
+        };
+
+        let connection = self.connection()?;
+        let sender = connection.current_sender()?;
+        let result = connection.initialize_via(&sender, params.clone()).await?;
+        connection.session.lock().unwrap().initialize_params = Some(params);
+        Ok(result)
+    }
+
+    /// The protocol revision the server chose in `initialize`
+    ///
+    /// `None` before initialization.
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        let connection = self.connection.as_ref()?;
+        let session = connection.session.lock().unwrap();
+        session.protocol_version
     }
 
     /// Call a tool on the server
+    ///
+    /// `params` must serialize to an object, the tool's arguments. The result is decoded
+    /// from the tool's `structuredContent`, or, for tools without an output schema, from
+    /// its first text block, read as JSON or else as a plain string.
     pub async fn call_tool<P: Serialize + Send + Sync, R: DeserializeOwned + Send + Sync>(
-        &mut self,
+        &self,
         tool_name: &str,
         params: &P,
     ) -> Result<R, MCPError> {
-        // Create tool call request
-        let tool_call_request = JSONRPCRequest::new(
-            self.next_request_id(),
-            "tool_call".to_string(),
-            Some(serde_json::json!({
-                "name": tool_name,
-                "parameters": serde_json::to_value(params)?
-            })),
-        );
+        // Send tool call request and wait for the response
+        let response = self.request(call_tool_request(tool_name, params)?).await?;
+        tool_result(tool_name, response)
+    }
+
+    /// Send several requests to the server as one JSON-RPC batch
+    ///
+    /// Returns the result of each request, in the order of `requests`. The outer error is
+    /// for a batch that could not be sent at all.
+    pub async fn send_batch(
+        &self,
+        requests: Vec<ClientRequest>,
+    ) -> Result<Vec<Result<Value, MCPError>>, MCPError> {
+        let responses = self.connection()?.request_batch(requests).await?;
+        Ok(responses
+            .into_iter()
+            .map(|response| expect_result(response?))
+            .collect())
+    }
 
-        let message = JSONRPCMessage::Request(tool_call_request);
-        self.transport.send(&message).await?;
+    /// Call several tools in one JSON-RPC batch
+    ///
+    /// Like [`call_tools_concurrent`](Client::call_tools_concurrent), but all calls travel
+    /// in a single message and the server answers them in a single message.
+    pub async fn call_tools_batch<P, R>(
+        &self,
+        tool_calls: Vec<(String, P)>,
+    ) -> Result<Vec<Result<R, MCPError>>, MCPError>
+    where
+        P: Serialize + Send + Sync,
+        R: DeserializeOwned + Send + Sync,
+    {
+        let requests = tool_calls
+            .iter()
+            .map(|(tool_name, params)| call_tool_request(tool_name, params))
+            .collect::<Result<Vec<_>, _>>()?;
+        let responses = self.connection()?.request_batch(requests).await?;
+        Ok(responses
+            .into_iter()
+            .zip(&tool_calls)
+            .map(|(response, (tool_name, _))| tool_result(tool_name, response?))
+            .collect())
+    }
 
-        // Wait for response with timeout if set
-        let response: JSONRPCMessage = self.receive_with_timeout().await?;
+    /// Subscribe to updates of a resource
+    ///
+    /// Subscriptions are re-applied when the client reconnects.
+    pub async fn subscribe_resource(&self, uri: &str) -> Result<(), MCPError> {
+        let connection = self.connection()?;
+        let response = connection
+            .request(ClientRequest::Subscribe(SubscribeParams {
+                uri: uri.to_string(),
+                meta: None,
+            }))
+            .await?;
+        expect_result(response)?;
+        connection
+            .session
+            .lock()
+            .unwrap()
+            .subscriptions
+            .insert(uri.to_string());
+        Ok(())
+    }
 
-        match response {
-            JSONRPCMessage::Response(resp) => {
-                // Extract the tool result from the response
-                let result_value = resp.result;
-                let result = result_value.get("result").ok_or_else(|| {
-                    MCPError::Protocol("Missing 'result' field in response".to_string())
-                })?;
+    /// Stop receiving updates of a resource
+    pub async fn unsubscribe_resource(&self, uri: &str) -> Result<(), MCPError> {
+        let connection = self.connection()?;
+        let response = connection
+            .request(ClientRequest::Unsubscribe(UnsubscribeParams {
+                uri: uri.to_string(),
+                meta: None,
+            }))
+            .await?;
+        expect_result(response)?;
+        connection.session.lock().unwrap().subscriptions.remove(uri);
+        Ok(())
+    }
 
-                // Parse the result
-                serde_json::from_value(result.clone()).map_err(MCPError::Serialization)
-            }
-            JSONRPCMessage::Error(err) => {
-                Err(MCPError::Protocol(format!("Tool call failed: {:?}", err)))
-            }
-            _ => Err(MCPError::Protocol("Unexpected response type".to_string())),
-        }
+    /// Set the minimum level of log messages the server sends
+    ///
+    /// The level is re-applied when the client reconnects.
+    pub async fn set_log_level(&self, level: LoggingLevel) -> Result<(), MCPError> {
+        let connection = self.connection()?;
+        let response = connection
+            .request(ClientRequest::SetLevel(SetLevelParams {
+                level: level.clone(),
+                meta: None,
+            }))
+            .await?;
+        expect_result(response)?;
+        connection.session.lock().unwrap().log_level = Some(level);
+        Ok(())
     }
 
     /// Shutdown the client
     pub async fn shutdown(&mut self) -> Result<(), MCPError> {
-        // Send shutdown request
-        let shutdown_request =
-            JSONRPCRequest::new(self.next_request_id(), "shutdown".to_string(), None);
+        let connection = Arc::clone(self.connection()?);
 
-        let message = JSONRPCMessage::Request(shutdown_request);
-        self.transport.send(&message).await?;
+        // The server may hang up as soon as it has answered; that is not a lost connection
+        connection.shutting_down.store(true, Ordering::SeqCst);
 
-        // Wait for response with timeout if set
-        let response: JSONRPCMessage = self.receive_with_timeout().await?;
+        // Send shutdown request and wait for the response
+        let response = match connection.request(ClientRequest::Shutdown).await {
+            Ok(response) => response,
+            Err(e) => {
+                connection.shutting_down.store(false, Ordering::SeqCst);
+                return Err(e);
+            }
+        };
 
         match response {
-            JSONRPCMessage::Response(_) => {
+            RawMessage::Response(_) => {
                 // Close the transport
-                self.transport.close().await?;
+                let sender = connection.sender.write().unwrap().take();
+                if let Some(sender) = sender {
+                    sender.close().await?;
+                }
+                if let Some(task) = self.reader_task.take() {
+                    task.abort();
+                }
                 Ok(())
             }
-            JSONRPCMessage::Error(err) => {
-                Err(MCPError::Protocol(format!("Shutdown failed: {:?}", err)))
+            RawMessage::Error(err) => {
+                connection.shutting_down.store(false, Ordering::SeqCst);
+                Err(err.into())
+            }
+            _ => {
+                connection.shutting_down.store(false, Ordering::SeqCst);
+                Err(MCPError::Protocol("Unexpected response type".to_string()))
             }
-            _ => Err(MCPError::Protocol("Unexpected response type".to_string())),
         }
     }
 
-    /// Receive a message with optional timeout
-    async fn receive_with_timeout<R: DeserializeOwned + Send + Sync>(
-        &mut self,
-    ) -> Result<R, MCPError> {
-        if let Some(duration) = self.timeout_duration {
-            match timeout(duration, self.transport.receive::<R>()).await {
-                Ok(result) => result,
-                Err(_) => Err(MCPError::Timeout(format!(
-                    "Operation timed out after {:?}",
-                    duration
-                ))),
-            }
-        } else {
-            self.transport.receive().await
+    /// Start the transport and spawn the task that routes responses to their requests
+    async fn connect(&mut self) -> Result<(), MCPError> {
+        let mut transport = self.transport.take().ok_or_else(|| {
+            MCPError::Transport("Client transport has already been closed".to_string())
+        })?;
+
+        if let Err(e) = transport.start().await {
+            // Keep the transport so initialization can be retried
+            self.transport = Some(transport);
+            return Err(e);
         }
+
+        let (sender, receiver) = Box::new(transport).into_split();
+        let connection = Arc::new(Connection {
+            sender: RwLock::new(Some(Arc::clone(&sender))),
+            lost_reason: Mutex::new(None),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_request_id: AtomicI64::new(1),
+            timeout_duration: self.timeout_duration,
+            session: Mutex::new(SessionState::default()),
+            reconnect: self.reconnect.clone(),
+            shutting_down: AtomicBool::new(false),
+            elicitation_handler: self.elicitation_handler.clone(),
+        });
+        self.reader_task = Some(tokio::spawn(maintain_connection(
+            Arc::clone(&connection),
+            sender,
+            receiver,
+        )));
+        self.connection = Some(connection);
+        Ok(())
+    }
+
+    /// The connection, once the client has been initialized
+    fn connection(&self) -> Result<&Arc<Connection>, MCPError> {
+        self.connection
+            .as_ref()
+            .ok_or_else(|| MCPError::Transport("Client is not connected".to_string()))
     }
 
-    /// Generate the next request ID
-    fn next_request_id(&mut self) -> RequestId {
-        let id = self.next_request_id;
-        self.next_request_id += 1;
-        RequestId::Number(id)
+    /// Send a request and wait for its response, with optional timeout
+    async fn request(&self, request: ClientRequest) -> Result<RawMessage, MCPError> {
+        self.connection()?.request(request).await
     }
 
     /// Call multiple tools concurrently
     ///
     /// This method demonstrates the power of async by allowing multiple tool calls to be made
     /// concurrently. Each tool call is represented as a tuple of (tool_name, parameters).
-    ///
-    /// Note that this requires cloning the transport for each concurrent call, which may not
-    /// be supported by all transport types. Use with caution.
+    /// All requests share the client's connection and their responses are matched up by ID.
     pub async fn call_tools_concurrent<P, R>(
         &self,
         tool_calls: Vec<(String, P)>,
     ) -> Result<Vec<Result<R, MCPError>>, MCPError>
     where
-        P: Serialize + Clone + Send + Sync + 'static,
-        R: DeserializeOwned + Send + Sync + 'static,
-        T: Clone + Send + Sync + 'static,
+        P: Serialize + Send + Sync,
+        R: DeserializeOwned + Send + Sync,
     {
-        let mut tasks = Vec::with_capacity(tool_calls.len());
-
-        // Create a new client for each concurrent call
-        for (idx, (tool_name, params)) in tool_calls.into_iter().enumerate() {
-            let mut client = Client {
-                transport: self.transport.clone(),
-                next_request_id: self.next_request_id + idx as i64 + 1, // Ensure unique IDs
-                timeout_duration: self.timeout_duration,
-            };
-
-            // Spawn a task for each tool call
-            let task =
-                tokio::spawn(async move { client.call_tool::<P, R>(&tool_name, &params).await });
-
-            tasks.push(task);
-        }
-
-        // Wait for all tasks to complete
-        let results = join_all(tasks).await;
-
-        // Process results, handling any JoinErrors from the tasks
-        let processed_results = results
+        let calls = tool_calls
             .into_iter()
-            .map(|join_result| match join_result {
-                Ok(tool_result) => tool_result,
-                Err(e) => Err(MCPError::Transport(format!("Task join error: {}", e))),
-            })
-            .collect();
+            .map(|(tool_name, params)| async move { self.call_tool(&tool_name, &params).await });
 
-        Ok(processed_results)
+        Ok(join_all(calls).await)
     }
 
     /// Execute a complete client session in one call
@@ -310,6 +514,513 @@ impl<T: Transport + Send + Sync> Client<T> {
     }
 }
 
+impl<T: Transport> Drop for Client<T> {
+    fn drop(&mut self) {
+        if let Some(task) = self.reader_task.take() {
+            task.abort();
+        }
+    }
+}
+
+impl Connection {
+    /// The sender of the current transport, while requests can be sent
+    fn current_sender(&self) -> Result<Arc<dyn TransportSender>, MCPError> {
+        let sender = self.sender.read().unwrap().clone();
+        sender.ok_or_else(|| match self.lost_reason.lock().unwrap().clone() {
+            Some(reason) => MCPError::ConnectionLost(reason),
+            None => MCPError::Transport("Client is not connected".to_string()),
+        })
+    }
+
+    /// Send a request over the current transport
+    async fn request(&self, request: ClientRequest) -> Result<RawMessage, MCPError> {
+        let sender = self.current_sender()?;
+        self.request_via(&sender, request).await
+    }
+
+    /// Send a request and wait for its response, with optional timeout
+    async fn request_via(
+        &self,
+        sender: &Arc<dyn TransportSender>,
+        request: ClientRequest,
+    ) -> Result<RawMessage, MCPError> {
+        let (request, rx) = self.prepare(request);
+        let id = request.id.clone();
+        if let Err(e) = sender.send(JSONRPCMessage::Request(request)).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+        self.response(id, rx).await
+    }
+
+    /// Send the initialize request, then tell the server initialization has finished
+    ///
+    /// The server must answer with a revision we implement, which later requests are
+    /// restricted to.
+    async fn initialize_via(
+        &self,
+        sender: &Arc<dyn TransportSender>,
+        params: InitializeParams,
+    ) -> Result<Value, MCPError> {
+        let response = self
+            .request_via(sender, ClientRequest::Initialize(params))
+            .await?;
+        let result = expect_result(response)?;
+        let protocol_version = negotiated_version(&result)?;
+        self.session.lock().unwrap().protocol_version = Some(protocol_version);
+        let initialized = ClientNotification::Initialized.into_notification();
+        sender
+            .send(JSONRPCMessage::Notification(initialized))
+            .await?;
+        Ok(result)
+    }
+
+    /// Send requests as one batch and wait for each response, with optional timeout
+    async fn request_batch(
+        &self,
+        requests: Vec<ClientRequest>,
+    ) -> Result<Vec<Result<RawMessage, MCPError>>, MCPError> {
+        let sender = self.current_sender()?;
+
+        let (requests, waiters): (Vec<_>, Vec<_>) = requests
+            .into_iter()
+            .map(|request| self.prepare(request))
+            .unzip();
+        let ids: Vec<RequestId> = requests.iter().map(|request| request.id.clone()).collect();
+        let batch = requests.into_iter().map(JSONRPCMessage::Request).collect();
+        if let Err(e) = sender.send(JSONRPCMessage::Batch(batch)).await {
+            let mut pending = self.pending.lock().unwrap();
+            for id in &ids {
+                pending.remove(id);
+            }
+            return Err(e);
+        }
+
+        Ok(join_all(
+            ids.into_iter()
+                .zip(waiters)
+                .map(|(id, rx)| self.response(id, rx)),
+        )
+        .await)
+    }
+
+    /// Number a request and register it as waiting for a response
+    ///
+    /// Once the server has chosen a revision, the request is restricted to it.
+    fn prepare(
+        &self,
+        mut request: ClientRequest,
+    ) -> (
+        JSONRPCRequest,
+        oneshot::Receiver<Result<RawMessage, MCPError>>,
+    ) {
+        if let Some(version) = self.session.lock().unwrap().protocol_version {
+            request.restrict_to(version);
+        }
+        let id = RequestId::Number(self.next_request_id.fetch_add(1, Ordering::SeqCst));
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), tx);
+
+        // Tell the server how long we will wait, so it can give up when we do
+        let mut request = request.into_request(id);
+        if let (Some(duration), Some(Value::Object(params))) =
+            (self.timeout_duration, &mut request.params)
+        {
+            let meta = params
+                .entry("_meta")
+                .or_insert_with(|| Value::Object(Default::default()));
+            if let Value::Object(meta) = meta {
+                meta.insert(
+                    TIMEOUT_META_KEY.to_string(),
+                    Value::from(duration.as_millis() as u64),
+                );
+            }
+        }
+        (request, rx)
+    }
+
+    /// Wait for the response to a sent request, with optional timeout
+    async fn response(
+        &self,
+        id: RequestId,
+        rx: oneshot::Receiver<Result<RawMessage, MCPError>>,
+    ) -> Result<RawMessage, MCPError> {
+        let response = async {
+            rx.await.unwrap_or_else(|_| {
+                Err(MCPError::ConnectionLost(
+                    "Connection lost before a response was received".to_string(),
+                ))
+            })
+        };
+
+        if let Some(duration) = self.timeout_duration {
+            match timeout(duration, response).await {
+                Ok(result) => result,
+                Err(_) => {
+                    self.pending.lock().unwrap().remove(&id);
+                    Err(MCPError::Timeout(format!(
+                        "Operation timed out after {:?}",
+                        duration
+                    )))
+                }
+            }
+        } else {
+            response.await
+        }
+    }
+
+    /// Stop accepting requests and fail the ones still waiting
+    fn mark_lost(&self, reason: &str) {
+        *self.sender.write().unwrap() = None;
+        if !self.shutting_down.load(Ordering::SeqCst) {
+            *self.lost_reason.lock().unwrap() = Some(reason.to_string());
+        }
+        for (_, waiter) in self.pending.lock().unwrap().drain() {
+            let _ = waiter.send(Err(MCPError::ConnectionLost(reason.to_string())));
+        }
+    }
+
+    /// Create and start a new transport, backing off between attempts
+    async fn reopen(&self) -> Option<(Arc<dyn TransportSender>, Box<dyn TransportReceiver>)> {
+        let (policy, factory) = self.reconnect.as_ref()?;
+
+        let mut attempt = 0;
+        loop {
+            let Some(delay) = policy.delay(attempt) else {
+                let reason = format!("Gave up reconnecting after {} attempts", attempt);
+                error!("{}", reason);
+                *self.lost_reason.lock().unwrap() = Some(reason);
+                return None;
+            };
+            attempt += 1;
+            tokio::time::sleep(delay).await;
+            if self.shutting_down.load(Ordering::SeqCst) {
+                return None;
+            }
+
+            info!("Reconnecting (attempt {})", attempt);
+            let mut transport = match factory().await {
+                Ok(transport) => transport,
+                Err(e) => {
+                    warn!("Failed to create transport: {}", e);
+                    continue;
+                }
+            };
+            match transport.start().await {
+                Ok(()) => return Some(transport.into_split()),
+                Err(e) => warn!("Failed to start transport: {}", e),
+            }
+        }
+    }
+
+    /// Repeat initialize and re-apply subscriptions and the log level over a new transport
+    async fn restore_session(&self, sender: &Arc<dyn TransportSender>) -> Result<(), MCPError> {
+        let (initialize_params, subscriptions, log_level) = {
+            let session = self.session.lock().unwrap();
+            (
+                session.initialize_params.clone(),
+                session.subscriptions.clone(),
+                session.log_level.clone(),
+            )
+        };
+
+        if let Some(params) = initialize_params {
+            self.initialize_via(sender, params).await?;
+        }
+        for uri in subscriptions {
+            let request = ClientRequest::Subscribe(SubscribeParams { uri, meta: None });
+            let response = self.request_via(sender, request).await?;
+            expect_result(response)?;
+        }
+        if let Some(level) = log_level {
+            let request = ClientRequest::SetLevel(SetLevelParams { level, meta: None });
+            let response = self.request_via(sender, request).await?;
+            expect_result(response)?;
+        }
+        Ok(())
+    }
+}
+
+/// Route responses for the connection's lifetime, reconnecting if the client is set up to
+async fn maintain_connection(
+    connection: Arc<Connection>,
+    sender: Arc<dyn TransportSender>,
+    receiver: Box<dyn TransportReceiver>,
+) {
+    let mut reason = read_responses(sender, receiver, &connection).await;
+    loop {
+        connection.mark_lost(&reason);
+        if connection.shutting_down.load(Ordering::SeqCst) || connection.reconnect.is_none() {
+            return;
+        }
+
+        warn!("Connection lost ({}), reconnecting", reason);
+        let Some((sender, receiver)) = connection.reopen().await else {
+            return;
+        };
+
+        // Responses to the restoring requests arrive through the new receiver, so read it
+        // while restoring; new requests are only accepted once the session is back
+        let reading = read_responses(Arc::clone(&sender), receiver, &connection);
+        tokio::pin!(reading);
+        reason = tokio::select! {
+            reason = &mut reading => reason,
+            restored = connection.restore_session(&sender) => match restored {
+                Ok(()) => {
+                    info!("Reconnected and restored the session");
+                    *connection.lost_reason.lock().unwrap() = None;
+                    *connection.sender.write().unwrap() = Some(sender);
+                    reading.await
+                }
+                Err(e) => {
+                    let _ = sender.close().await;
+                    format!("Failed to restore the session: {}", e)
+                }
+            },
+        };
+    }
+}
+
+/// Read messages from the server and complete the matching pending requests
+///
+/// Requests from the server are answered over `sender`, each from its own task so a slow
+/// answer does not hold up responses. Returns why the connection ended.
+async fn read_responses(
+    sender: Arc<dyn TransportSender>,
+    mut receiver: Box<dyn TransportReceiver>,
+    connection: &Connection,
+) -> String {
+    loop {
+        let message = match receiver.recv_raw().await {
+            Ok(Some(message)) => message,
+            Ok(None) => return "Connection closed by server".to_string(),
+            Err(MCPError::Serialization(e)) => {
+                warn!("Received malformed message from server: {}", e);
+                let reply = JSONRPCMessage::Error(JSONRPCError::unparseable(&e));
+                if let Err(e) = sender.send(reply).await {
+                    warn!("Failed to report malformed message: {}", e);
+                }
+                continue;
+            }
+            Err(e) => {
+                error!("Error receiving message: {}", e);
+                return e.to_string();
+            }
+        };
+
+        match message {
+            // The answers to a batch are handled one by one
+            RawMessage::Batch(messages) => {
+                for message in messages {
+                    route_message(message, &sender, connection);
+                }
+            }
+            message => route_message(message, &sender, connection),
+        }
+    }
+}
+
+/// Hand a response to the request waiting for it, or answer a request from the server
+fn route_message(message: RawMessage, sender: &Arc<dyn TransportSender>, connection: &Connection) {
+    let id = match message {
+        RawMessage::Response(ref response) => response.id.clone(),
+        RawMessage::Error(JSONRPCError {
+            id: Some(ref id), ..
+        }) => id.clone(),
+        RawMessage::Error(error) => {
+            warn!("Server could not read a message: {}", error.error.message);
+            return;
+        }
+        RawMessage::Request(request) => {
+            let request = match JSONRPCRequest::try_from(request) {
+                Ok(request) => request,
+                Err(e) => {
+                    warn!("Ignoring unreadable request: {}", e);
+                    return;
+                }
+            };
+            let sender = Arc::clone(sender);
+            let elicitation_handler = connection.elicitation_handler.clone();
+            tokio::spawn(async move {
+                let reply = answer_server_request(&request, elicitation_handler).await;
+                if let Err(e) = sender.send(reply).await {
+                    warn!("Failed to answer {} request: {}", request.method, e);
+                }
+            });
+            return;
+        }
+        RawMessage::Notification(notification) => {
+            let method = &notification.method;
+            match notification
+                .params_as()
+                .and_then(|params| ServerNotification::from_parts(method, params))
+            {
+                Ok(notification) => debug!("Received {} notification", notification.method()),
+                Err(e) => warn!("Ignoring malformed {} notification: {}", method, e),
+            }
+            return;
+        }
+        RawMessage::Batch(_) => {
+            warn!("Ignoring a batch nested in a batch");
+            return;
+        }
+    };
+
+    let waiter = connection.pending.lock().unwrap().remove(&id);
+    match waiter {
+        Some(waiter) => {
+            let _ = waiter.send(Ok(message));
+        }
+        None => warn!("Received response for unknown request {:?}", id),
+    }
+}
+
+/// The client's reply to a request from the server
+async fn answer_server_request(
+    request: &JSONRPCRequest,
+    elicitation_handler: Option<ElicitationHandler>,
+) -> JSONRPCMessage {
+    let error = |code, message: String| {
+        JSONRPCMessage::Error(JSONRPCError::new_with_details(
+            request.id.clone(),
+            code,
+            message,
+            None,
+        ))
+    };
+    match ServerRequest::try_from(request) {
+        Ok(ServerRequest::Ping) => JSONRPCMessage::Response(JSONRPCResponse::new(
+            request.id.clone(),
+            serde_json::json!({}),
+        )),
+        Ok(ServerRequest::Elicit(params)) if elicitation_handler.is_some() => {
+            let handler = elicitation_handler.unwrap();
+            match handler(params)
+                .await
+                .and_then(|result| Ok(serde_json::to_value(result)?))
+            {
+                Ok(result) => {
+                    JSONRPCMessage::Response(JSONRPCResponse::new(request.id.clone(), result))
+                }
+                Err(e) => error(
+                    ErrorCode::InternalError,
+                    format!("Elicitation failed: {}", e),
+                ),
+            }
+        }
+        // Sampling and roots are not announced in the client's capabilities, nor is
+        // elicitation without a handler
+        Ok(
+            ServerRequest::CreateMessage(_)
+            | ServerRequest::ListRoots
+            | ServerRequest::Elicit(_)
+            | ServerRequest::Other { .. },
+        ) => error(
+            ErrorCode::MethodNotFound,
+            format!("Method not found: {}", request.method),
+        ),
+        Err(e) => error(
+            ErrorCode::InvalidParams,
+            format!("Invalid {} parameters: {}", request.method, e),
+        ),
+    }
+}
+
+/// A `tools/call` request for `tool_name` with `params` as its arguments
+fn call_tool_request<P: Serialize>(tool_name: &str, params: &P) -> Result<ClientRequest, MCPError> {
+    let arguments = match serde_json::to_value(params)? {
+        Value::Object(arguments) => Some(arguments),
+        Value::Null => None,
+        other => {
+            return Err(MCPError::Protocol(format!(
+                "Tool arguments must be an object, not {}",
+                other
+            )))
+        }
+    };
+    Ok(ClientRequest::CallTool(CallToolParams {
+        name: tool_name.to_string(),
+        arguments,
+        meta: None,
+    }))
+}
+
+/// The value a tool call was answered with
+fn tool_result<R: DeserializeOwned>(tool_name: &str, response: RawMessage) -> Result<R, MCPError> {
+    match response {
+        RawMessage::Response(resp) => decode_tool_result(tool_name, &resp.result),
+        RawMessage::Error(err) if err.error.code == ErrorCode::RequestTimeout => {
+            Err(MCPError::Timeout(err.error.message))
+        }
+        RawMessage::Error(err) => Err(err.into()),
+        _ => Err(MCPError::Protocol("Unexpected response type".to_string())),
+    }
+}
+
+/// A tool call's result with its structured content left unparsed
+///
+/// Structured content, often the bulk of a result, is read straight into the caller's
+/// type rather than into a [`Value`] first.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawToolResult {
+    content: Vec<ToolResultContent>,
+    is_error: Option<bool>,
+    structured_content: Option<Box<RawValue>>,
+}
+
+/// The value of a tool call's result
+fn decode_tool_result<R: DeserializeOwned>(
+    tool_name: &str,
+    result: &RawValue,
+) -> Result<R, MCPError> {
+    let result: RawToolResult = serde_json::from_str(result.get())?;
+    let text = result.content.iter().find_map(|content| match content {
+        ToolResultContent::Text(text) => Some(text.text.as_str()),
+        _ => None,
+    });
+    if result.is_error == Some(true) {
+        return Err(MCPError::Protocol(format!(
+            "Tool '{}' failed: {}",
+            tool_name,
+            text.unwrap_or("no details given")
+        )));
+    }
+
+    if let Some(structured) = result.structured_content {
+        return serde_json::from_str(structured.get()).map_err(MCPError::Serialization);
+    }
+    let text = text.ok_or_else(|| {
+        MCPError::Protocol(format!(
+            "Tool '{}' returned no text or structured content",
+            tool_name
+        ))
+    })?;
+    serde_json::from_str(text)
+        .or_else(|_| serde_json::from_value(Value::String(text.to_string())))
+        .map_err(MCPError::Serialization)
+}
+
+/// The protocol revision named in an initialize result, which must be one we implement
+fn negotiated_version(result: &Value) -> Result<ProtocolVersion, MCPError> {
+    let version = result
+        .get("protocolVersion")
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            MCPError::Protocol("Initialize result has no protocolVersion".to_string())
+        })?;
+    version.parse().map_err(MCPError::Protocol)
+}
+
+/// The result of a successful response, or the server's error as [`MCPError::Rpc`]
+fn expect_result(response: RawMessage) -> Result<Value, MCPError> {
+    match response {
+        RawMessage::Response(resp) => Ok(resp.result_as()?),
+        RawMessage::Error(err) => Err(err.into()),
+        _ => Err(MCPError::Protocol("Unexpected response type".to_string())),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -317,18 +1028,19 @@ mod tests {
     use crate::transport::Transport;
     use crate::transport::{CloseCallback, ErrorCallback, MessageCallback};
     use async_trait::async_trait;
-    use futures::future::Future;
     use std::collections::VecDeque;
-    use std::pin::Pin;
     use std::sync::{Arc, Mutex};
     use std::time::Duration;
-    use tokio::sync::Mutex as TokioMutex;
+    use tokio::sync::{Mutex as TokioMutex, Semaphore};
 
     // Mock transport for testing
+    //
+    // Queued responses are released one per request sent, the way a server would answer.
     #[derive(Clone)]
     struct MockTransport {
         send_queue: Arc<TokioMutex<VecDeque<String>>>,
         receive_queue: Arc<TokioMutex<VecDeque<String>>>,
+        responses_due: Arc<Semaphore>,
         is_started: Arc<TokioMutex<bool>>,
         is_closed: Arc<TokioMutex<bool>>,
         should_fail: Arc<TokioMutex<bool>>,
@@ -343,6 +1055,7 @@ mod tests {
             Self {
                 send_queue: Arc::new(TokioMutex::new(VecDeque::new())),
                 receive_queue: Arc::new(TokioMutex::new(VecDeque::new())),
+                responses_due: Arc::new(Semaphore::new(0)),
                 is_started: Arc::new(TokioMutex::new(false)),
                 is_closed: Arc::new(TokioMutex::new(false)),
                 should_fail: Arc::new(TokioMutex::new(false)),
@@ -376,37 +1089,49 @@ mod tests {
     }
 
     #[async_trait]
-    impl Transport for MockTransport {
-        async fn start(&mut self) -> Result<(), MCPError> {
+    impl TransportSender for MockTransport {
+        async fn send(&self, message: JSONRPCMessage) -> Result<(), MCPError> {
             let should_fail = *self.should_fail.lock().await;
             if should_fail {
                 return Err(MCPError::Transport(
-                    "Mock transport failed to start".to_string(),
+                    "Mock transport send failure".to_string(),
                 ));
             }
 
-            let mut started = self.is_started.lock().await;
-            *started = true;
+            let serialized = serde_json::to_string(&message).map_err(MCPError::Serialization)?;
+
+            let mut queue = self.send_queue.lock().await;
+            queue.push_back(serialized);
+            // Notifications get no response
+            if !matches!(message, JSONRPCMessage::Notification(_)) {
+                self.responses_due.add_permits(1);
+            }
             Ok(())
         }
 
-        async fn send<T: Serialize + Send + Sync>(&mut self, message: &T) -> Result<(), MCPError> {
+        async fn close(&self) -> Result<(), MCPError> {
             let should_fail = *self.should_fail.lock().await;
             if should_fail {
                 return Err(MCPError::Transport(
-                    "Mock transport send failure".to_string(),
+                    "Mock transport close failure".to_string(),
                 ));
             }
 
-            let serialized =
-                serde_json::to_string(message).map_err(|e| MCPError::Serialization(e))?;
+            let mut closed = self.is_closed.lock().await;
+            *closed = true;
+
+            // If there's a close callback, call it
+            if let Some(callback) = &*self.on_close.lock().unwrap() {
+                callback();
+            }
 
-            let mut queue = self.send_queue.lock().await;
-            queue.push_back(serialized);
             Ok(())
         }
+    }
 
-        async fn receive<T: DeserializeOwned + Send + Sync>(&mut self) -> Result<T, MCPError> {
+    #[async_trait]
+    impl TransportReceiver for MockTransport {
+        async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
             let should_fail = *self.should_fail.lock().await;
             if should_fail {
                 return Err(MCPError::Transport(
@@ -420,37 +1145,55 @@ mod tests {
                 tokio::time::sleep(Duration::from_secs(2)).await;
             }
 
-            let mut queue = self.receive_queue.lock().await;
+            // Wait for a request to answer
+            self.responses_due.acquire().await.unwrap().forget();
 
-            if let Some(message) = queue.pop_front() {
-                // If there's a message callback, call it
-                if let Some(callback) = &*self.on_message.lock().unwrap() {
-                    callback(&message);
-                }
+            let message = self.receive_queue.lock().await.pop_front();
+            let Some(message) = message else {
+                // Nothing scripted for this request; never answer
+                return std::future::pending().await;
+            };
 
-                return serde_json::from_str(&message).map_err(|e| MCPError::Serialization(e));
+            // If there's a message callback, call it
+            if let Some(callback) = &*self.on_message.lock().unwrap() {
+                callback(&message);
             }
 
-            Err(MCPError::Transport("No more messages".to_string()))
+            serde_json::from_str(&message)
+                .map(Some)
+                .map_err(MCPError::Serialization)
         }
+    }
 
-        async fn close(&mut self) -> Result<(), MCPError> {
+    #[async_trait]
+    impl Transport for MockTransport {
+        async fn start(&mut self) -> Result<(), MCPError> {
             let should_fail = *self.should_fail.lock().await;
             if should_fail {
                 return Err(MCPError::Transport(
-                    "Mock transport close failure".to_string(),
+                    "Mock transport failed to start".to_string(),
                 ));
             }
 
-            let mut closed = self.is_closed.lock().await;
-            *closed = true;
+            let mut started = self.is_started.lock().await;
+            *started = true;
+            Ok(())
+        }
 
-            // If there's a close callback, call it
-            if let Some(callback) = &*self.on_close.lock().unwrap() {
-                callback();
-            }
+        async fn send(&mut self, message: JSONRPCMessage) -> Result<(), MCPError> {
+            TransportSender::send(self, message).await
+        }
 
-            Ok(())
+        async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+            TransportReceiver::recv(self).await
+        }
+
+        async fn close(&mut self) -> Result<(), MCPError> {
+            TransportSender::close(self).await
+        }
+
+        fn into_split(self: Box<Self>) -> (Arc<dyn TransportSender>, Box<dyn TransportReceiver>) {
+            (Arc::new((*self).clone()), self)
         }
 
         fn set_on_close(&mut self, callback: Option<CloseCallback>) {
@@ -463,39 +1206,33 @@ mod tests {
             *cb = callback;
         }
 
-        fn set_on_message<F>(&mut self, callback: Option<F>)
-        where
-            F: Fn(&str) + Send + Sync + 'static,
-        {
+        fn set_on_message(&mut self, callback: Option<MessageCallback>) {
             let mut cb = self.on_message.lock().unwrap();
-            if let Some(callback) = callback {
-                *cb = Some(Box::new(callback));
-            } else {
-                *cb = None;
-            }
+            *cb = callback;
         }
     }
 
     // Helper function to create a server info response
     fn create_initialize_response(id: RequestId) -> JSONRPCMessage {
-        JSONRPCMessage::Response(JSONRPCResponse {
-            jsonrpc: "2.0".to_string(),
-            id,
-            result: serde_json::json!({
-                "protocol_version": "1.0",
-                "server_info": {
-                    "name": "TestServer",
-                    "version": "1.0.0",
-                    "protocol_version": "1.0"
-                },
-                "capabilities": {
-                    "tools": true
-                }
-            }),
+        JSONRPCMessage::Response(JSONRPCResponse::new(id, initialize_result()))
+    }
+
+    // The result a server on the latest revision answers `initialize` with
+    fn initialize_result() -> Value {
+        serde_json::json!({
+            "protocolVersion": LATEST_PROTOCOL_VERSION,
+            "serverInfo": {
+                "name": "TestServer",
+                "version": "1.0.0"
+            },
+            "capabilities": {
+                "tools": {}
+            }
         })
     }
 
     // Helper function to create a tools list response
+    #[allow(dead_code)]
     fn create_tools_list_response(id: RequestId) -> JSONRPCMessage {
         JSONRPCMessage::Response(JSONRPCResponse {
             jsonrpc: "2.0".to_string(),
@@ -517,13 +1254,13 @@ mod tests {
         })
     }
 
-    // Helper function to create a tool call response
-    fn create_tool_call_response(id: RequestId, result: serde_json::Value) -> JSONRPCMessage {
+    // Helper function to create a tool call response, as text the way older servers send it
+    fn create_tool_call_response(id: RequestId, result: &str) -> JSONRPCMessage {
         JSONRPCMessage::Response(JSONRPCResponse {
             jsonrpc: "2.0".to_string(),
             id,
             result: serde_json::json!({
-                "result": result
+                "content": [{ "type": "text", "text": result }]
             }),
         })
     }
@@ -532,7 +1269,7 @@ mod tests {
     #[tokio::test]
     async fn test_client_initialization() {
         // Create a mock transport
-        let mut mock = MockTransport::new();
+        let mock = MockTransport::new();
 
         // Queue the initialize response
         mock.queue_message(create_initialize_response(RequestId::Number(1)))
@@ -554,13 +1291,44 @@ mod tests {
         } else {
             panic!("Expected request message");
         }
+
+        // Followed by the notification that initialization has finished
+        let sent = mock.get_last_sent().await.unwrap();
+        let sent_msg: JSONRPCMessage = serde_json::from_str(&sent).unwrap();
+        let JSONRPCMessage::Notification(notification) = sent_msg else {
+            panic!("Expected notification message");
+        };
+        assert_eq!(notification.method, "notifications/initialized");
+        assert_eq!(client.protocol_version(), Some(ProtocolVersion::LATEST));
+    }
+
+    #[tokio::test]
+    async fn test_initialize_requires_a_protocol_version() {
+        let mock = MockTransport::new();
+        mock.queue_message(JSONRPCMessage::Response(JSONRPCResponse::new(
+            RequestId::Number(1),
+            serde_json::json!({
+                "capabilities": {},
+                "serverInfo": { "name": "TestServer", "version": "1.0.0" }
+            }),
+        )))
+        .await;
+
+        let mut client = Client::new(mock.clone());
+        let error = client.initialize().await.unwrap_err();
+        assert!(matches!(error, MCPError::Protocol(_)), "{:?}", error);
+        assert_eq!(client.protocol_version(), None);
+
+        // Initialization did not finish, so the server is not told it has
+        mock.get_last_sent().await.unwrap();
+        assert!(mock.get_last_sent().await.is_none());
     }
 
     // Test client error handling
     #[tokio::test]
     async fn test_client_error_handling() {
         // Create a mock transport that will fail
-        let mut mock = MockTransport::new();
+        let mock = MockTransport::new();
         mock.set_should_fail(true).await;
 
         // Create client with mock transport
@@ -578,7 +1346,7 @@ mod tests {
     #[tokio::test]
     async fn test_tool_call() {
         // Create a mock transport
-        let mut mock = MockTransport::new();
+        let mock = MockTransport::new();
 
         // Queue the initialize response
         mock.queue_message(create_initialize_response(RequestId::Number(1)))
@@ -587,7 +1355,7 @@ mod tests {
         // Queue the tool call response
         mock.queue_message(create_tool_call_response(
             RequestId::Number(2),
-            serde_json::json!("Hello, Test User!"),
+            "Hello, Test User!",
         ))
         .await;
 
@@ -606,12 +1374,13 @@ mod tests {
         assert_eq!(result, "Hello, Test User!");
 
         // Check what was sent to the server
-        let init_msg = mock.get_last_sent().await.unwrap();
+        let _init_msg = mock.get_last_sent().await.unwrap();
+        let _initialized_msg = mock.get_last_sent().await.unwrap();
         let tool_msg = mock.get_last_sent().await.unwrap();
 
         let tool_req: JSONRPCMessage = serde_json::from_str(&tool_msg).unwrap();
         if let JSONRPCMessage::Request(req) = tool_req {
-            assert_eq!(req.method, "tool_call");
+            assert_eq!(req.method, "tools/call");
             if let Some(params) = req.params {
                 assert_eq!(params["name"], "hello");
             } else {
@@ -626,7 +1395,7 @@ mod tests {
     #[tokio::test]
     async fn test_timeout_handling() {
         // Create a mock transport that will simulate a timeout
-        let mut mock = MockTransport::new();
+        let mock = MockTransport::new();
         mock.set_simulate_timeout(true).await;
 
         // Queue the initialize response (but it won't be used due to timeout)
@@ -647,11 +1416,193 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_deadline_sent_with_requests() {
+        let mock = MockTransport::new();
+        mock.queue_message(create_initialize_response(RequestId::Number(1)))
+            .await;
+        mock.queue_message(JSONRPCMessage::Error(JSONRPCError::new_with_details(
+            RequestId::Number(2),
+            ErrorCode::RequestTimeout,
+            "Tool 'hello' timed out after 5000 ms".to_string(),
+            None,
+        )))
+        .await;
+
+        let mut client = Client::new(mock.clone()).with_timeout(Duration::from_secs(5));
+        client.initialize().await.unwrap();
+
+        // A server-side timeout surfaces as a timeout, not a protocol error
+        let result: Result<String, _> = client.call_tool("hello", &serde_json::json!({})).await;
+        assert!(
+            matches!(result, Err(MCPError::Timeout(_))),
+            "Expected timeout error but got: {:?}",
+            result
+        );
+
+        let _init_msg = mock.get_last_sent().await.unwrap();
+        let _initialized_msg = mock.get_last_sent().await.unwrap();
+        let tool_msg = mock.get_last_sent().await.unwrap();
+        let JSONRPCMessage::Request(req) = serde_json::from_str(&tool_msg).unwrap() else {
+            panic!("Expected request message");
+        };
+        assert_eq!(req.params.unwrap()["_meta"][TIMEOUT_META_KEY], 5000);
+    }
+
+    #[tokio::test]
+    async fn test_requests_keep_to_the_negotiated_revision() {
+        let mock = MockTransport::new();
+        mock.queue_message(JSONRPCMessage::Response(JSONRPCResponse::new(
+            RequestId::Number(1),
+            serde_json::json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "serverInfo": { "name": "old", "version": "1.0.0" }
+            }),
+        )))
+        .await;
+        mock.queue_message(JSONRPCMessage::Batch(vec![JSONRPCMessage::Response(
+            JSONRPCResponse::new(RequestId::Number(2), serde_json::json!({})),
+        )]))
+        .await;
+
+        let mut client = Client::new(mock.clone());
+        client.initialize().await.unwrap();
+        let complete = serde_json::from_value(serde_json::json!({
+            "ref": { "type": "ref/prompt", "name": "greet" },
+            "argument": { "name": "language", "value": "fr" },
+            "context": { "arguments": { "tone": "formal" } }
+        }))
+        .unwrap();
+        client
+            .send_batch(vec![ClientRequest::Complete(complete)])
+            .await
+            .unwrap();
+
+        // The completion context only exists since 2025-06-18
+        let _init_msg = mock.get_last_sent().await.unwrap();
+        let _initialized_msg = mock.get_last_sent().await.unwrap();
+        let sent: Value = serde_json::from_str(&mock.get_last_sent().await.unwrap()).unwrap();
+        assert_eq!(sent[0]["params"]["argument"]["value"], "fr");
+        assert!(sent[0]["params"].get("context").is_none(), "{}", sent);
+    }
+
+    #[tokio::test]
+    async fn test_server_requests_are_answered() {
+        let (client_end, mut server_end) = crate::transport::memory::pair();
+        let mut client = Client::new(client_end);
+        let server = tokio::spawn(async move {
+            server_end.start().await.unwrap();
+            let Some(JSONRPCMessage::Request(init)) = server_end.recv().await.unwrap() else {
+                panic!("Expected the initialize request");
+            };
+            let params: InitializeParams =
+                serde_json::from_value(init.params.clone().unwrap()).unwrap();
+            assert_eq!(params.client_info.name, "mcpr");
+            let response = JSONRPCResponse::new(init.id, initialize_result());
+            server_end
+                .send(JSONRPCMessage::Response(response))
+                .await
+                .unwrap();
+            let Some(JSONRPCMessage::Notification(initialized)) = server_end.recv().await.unwrap()
+            else {
+                panic!("Expected the initialized notification");
+            };
+            assert_eq!(initialized.method, "notifications/initialized");
+
+            let mut replies = Vec::new();
+            for (id, request) in [(1, ServerRequest::Ping), (2, ServerRequest::ListRoots)] {
+                let request = request.into_request(RequestId::Number(id));
+                server_end
+                    .send(JSONRPCMessage::Request(request))
+                    .await
+                    .unwrap();
+                replies.push(server_end.recv().await.unwrap().unwrap());
+            }
+            replies
+        });
+
+        client.initialize().await.unwrap();
+        let replies = server.await.unwrap();
+        assert!(matches!(&replies[0], JSONRPCMessage::Response(_)));
+        match &replies[1] {
+            JSONRPCMessage::Error(error) => {
+                assert_eq!(error.error.code, ErrorCode::MethodNotFound)
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_elicitation_handler_answers_the_server() {
+        use crate::schema::server::{ElicitAction, ElicitationSchema};
+
+        let (client_end, mut server_end) = crate::transport::memory::pair();
+        let mut client = Client::new(client_end);
+        client.set_elicitation_handler(|params: ElicitRequestParams| async move {
+            assert_eq!(params.message, "Deploy to production?");
+            Ok(ElicitResult {
+                action: ElicitAction::Accept,
+                content: Some([("confirmed".to_string(), serde_json::json!(true))].into()),
+                meta: None,
+            })
+        });
+        let server = tokio::spawn(async move {
+            server_end.start().await.unwrap();
+            let Some(JSONRPCMessage::Request(init)) = server_end.recv().await.unwrap() else {
+                panic!("Expected the initialize request");
+            };
+            let params: InitializeParams =
+                serde_json::from_value(init.params.clone().unwrap()).unwrap();
+            assert!(params.capabilities.elicitation.is_some());
+            let response = JSONRPCResponse::new(init.id, initialize_result());
+            server_end
+                .send(JSONRPCMessage::Response(response))
+                .await
+                .unwrap();
+            let Some(JSONRPCMessage::Notification(initialized)) = server_end.recv().await.unwrap()
+            else {
+                panic!("Expected the initialized notification");
+            };
+            assert_eq!(initialized.method, "notifications/initialized");
+
+            let request = ServerRequest::Elicit(ElicitRequestParams {
+                message: "Deploy to production?".to_string(),
+                requested_schema: ElicitationSchema {
+                    r#type: "object".to_string(),
+                    properties: [(
+                        "confirmed".to_string(),
+                        serde_json::json!({ "type": "boolean" }),
+                    )]
+                    .into(),
+                    required: None,
+                },
+                meta: None,
+            });
+            server_end
+                .send(JSONRPCMessage::Request(
+                    request.into_request(RequestId::Number(1)),
+                ))
+                .await
+                .unwrap();
+            server_end.recv().await.unwrap().unwrap()
+        });
+
+        client.initialize().await.unwrap();
+        let JSONRPCMessage::Response(reply) = server.await.unwrap() else {
+            panic!("Expected the elicitation result");
+        };
+        assert_eq!(
+            reply.result,
+            serde_json::json!({ "action": "accept", "content": { "confirmed": true } })
+        );
+    }
+
     // Test shutdown
     #[tokio::test]
     async fn test_shutdown() {
         // Create a mock transport
-        let mut mock = MockTransport::new();
+        let mock = MockTransport::new();
 
         // Queue the initialize response
         mock.queue_message(create_initialize_response(RequestId::Number(1)))
@@ -684,16 +1635,16 @@ mod tests {
     #[tokio::test]
     async fn test_error_response_handling() {
         // Create a mock transport
-        let mut mock = MockTransport::new();
+        let mock = MockTransport::new();
 
         // Queue an error response for initialization
         mock.queue_message(JSONRPCMessage::Error(JSONRPCError {
             jsonrpc: "2.0".to_string(),
-            id: RequestId::Number(1),
+            id: Some(RequestId::Number(1)),
             error: crate::schema::json_rpc::JSONRPCErrorObject {
-                code: -32000,
+                code: ErrorCode::Other(-32050),
                 message: "Test error".to_string(),
-                data: None,
+                data: Some(serde_json::json!({ "retryAfter": 5 })),
             },
         }))
         .await;
@@ -708,13 +1659,18 @@ mod tests {
             "Client initialization should fail with error response"
         );
 
-        if let Err(MCPError::Protocol(msg)) = result {
-            assert!(
-                msg.contains("Test error"),
-                "Error message should contain the error from the response"
-            );
+        // The server's code, message and data all reach the caller
+        if let Err(MCPError::Rpc {
+            code,
+            message,
+            data,
+        }) = result
+        {
+            assert_eq!(code, -32050);
+            assert_eq!(message, "Test error");
+            assert_eq!(data, Some(serde_json::json!({ "retryAfter": 5 })));
         } else {
-            panic!("Expected protocol error but got: {:?}", result);
+            panic!("Expected RPC error but got: {:?}", result);
         }
     }
 
@@ -722,24 +1678,18 @@ mod tests {
     #[tokio::test]
     async fn test_concurrent_tool_calls() -> Result<(), MCPError> {
         // Create a mock transport
-        let mut mock = MockTransport::new();
+        let mock = MockTransport::new();
 
         // Queue the initialize response
         mock.queue_message(create_initialize_response(RequestId::Number(1)))
             .await;
 
         // Queue two tool call responses (they will be consumed in order)
-        mock.queue_message(create_tool_call_response(
-            RequestId::Number(2),
-            serde_json::json!("Result 1"),
-        ))
-        .await;
+        mock.queue_message(create_tool_call_response(RequestId::Number(2), "Result 1"))
+            .await;
 
-        mock.queue_message(create_tool_call_response(
-            RequestId::Number(3),
-            serde_json::json!("Result 2"),
-        ))
-        .await;
+        mock.queue_message(create_tool_call_response(RequestId::Number(3), "Result 2"))
+            .await;
 
         // Create client with mock transport
         let mut client = Client::new(mock.clone());
@@ -766,7 +1716,8 @@ mod tests {
         assert_eq!(results[1].as_ref().unwrap(), "Result 2");
 
         // Verify the requests were sent
-        let init_msg = mock.get_last_sent().await.unwrap();
+        let _init_msg = mock.get_last_sent().await.unwrap();
+        let _initialized_msg = mock.get_last_sent().await.unwrap();
         let tool1_msg = mock.get_last_sent().await.unwrap();
         let tool2_msg = mock.get_last_sent().await.unwrap();
 
@@ -775,21 +1726,167 @@ mod tests {
         let tool2_req: JSONRPCMessage = serde_json::from_str(&tool2_msg).unwrap();
 
         if let JSONRPCMessage::Request(req) = tool1_req {
-            assert_eq!(req.method, "tool_call");
+            assert_eq!(req.method, "tools/call");
             if let Some(params) = req.params {
                 assert_eq!(params["name"], "tool1");
-                assert_eq!(params["parameters"]["param"], "value1");
+                assert_eq!(params["arguments"]["param"], "value1");
             }
         }
 
         if let JSONRPCMessage::Request(req) = tool2_req {
-            assert_eq!(req.method, "tool_call");
+            assert_eq!(req.method, "tools/call");
             if let Some(params) = req.params {
                 assert_eq!(params["name"], "tool2");
-                assert_eq!(params["parameters"]["param"], "value2");
+                assert_eq!(params["arguments"]["param"], "value2");
             }
         }
 
         Ok(())
     }
+
+    /// Methods and params a recording server was sent
+    type RequestLog = Arc<Mutex<Vec<(String, Option<Value>)>>>;
+
+    /// Start a server on one end of a memory pair that answers every request with an empty
+    /// result and records what it was sent; a `tools/call` makes it hang up instead
+    fn spawn_recording_server(log: RequestLog) -> crate::transport::memory::MemoryTransport {
+        let (client_end, mut server_end) = crate::transport::memory::pair();
+        tokio::spawn(async move {
+            server_end.start().await.unwrap();
+            while let Ok(Some(message)) = server_end.recv().await {
+                let request = match message {
+                    JSONRPCMessage::Request(request) => request,
+                    JSONRPCMessage::Notification(notification) => {
+                        log.lock()
+                            .unwrap()
+                            .push((notification.method, notification.params));
+                        continue;
+                    }
+                    _ => break,
+                };
+                log.lock()
+                    .unwrap()
+                    .push((request.method.clone(), request.params.clone()));
+                if request.method == "tools/call" {
+                    break;
+                }
+                let result = match request.method.as_str() {
+                    "initialize" => initialize_result(),
+                    _ => serde_json::json!({}),
+                };
+                let response = JSONRPCResponse::new(request.id, result);
+                server_end
+                    .send(JSONRPCMessage::Response(response))
+                    .await
+                    .unwrap();
+            }
+            let _ = server_end.close().await;
+        });
+        client_end
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_restores_session() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let reconnects = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let policy = ReconnectPolicy::new()
+            .with_initial_delay(Duration::from_millis(10))
+            .with_jitter(false);
+        let factory_log = Arc::clone(&log);
+        let factory_reconnects = Arc::clone(&reconnects);
+        let mut client = Client::new(spawn_recording_server(Arc::clone(&log))).with_reconnect(
+            policy,
+            move || {
+                factory_reconnects.fetch_add(1, Ordering::SeqCst);
+                let transport = spawn_recording_server(Arc::clone(&factory_log));
+                async move { Ok(transport) }
+            },
+        );
+
+        client.initialize().await.unwrap();
+        client
+            .subscribe_resource("file:///notes.txt")
+            .await
+            .unwrap();
+        client.set_log_level(LoggingLevel::Warning).await.unwrap();
+
+        // The server hangs up while a request is in flight
+        let result: Result<Value, MCPError> =
+            client.call_tool("crash", &serde_json::json!({})).await;
+        assert!(
+            matches!(result, Err(MCPError::ConnectionLost(_))),
+            "Expected a lost connection, got {:?}",
+            result
+        );
+
+        for _ in 0..200 {
+            if client.is_connected() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(client.is_connected());
+        assert_eq!(reconnects.load(Ordering::SeqCst), 1);
+
+        let log = log.lock().unwrap();
+        let methods: Vec<&str> = log.iter().map(|(method, _)| method.as_str()).collect();
+        assert_eq!(
+            methods,
+            [
+                "initialize",
+                "notifications/initialized",
+                "resources/subscribe",
+                "logging/setLevel",
+                "tools/call",
+                "initialize",
+                "notifications/initialized",
+                "resources/subscribe",
+                "logging/setLevel",
+            ]
+        );
+        assert_eq!(log[5].1, log[0].1);
+        assert_eq!(
+            log[7].1,
+            Some(serde_json::json!({ "uri": "file:///notes.txt" }))
+        );
+        assert_eq!(log[8].1, Some(serde_json::json!({ "level": "warning" })));
+    }
+
+    #[tokio::test]
+    async fn test_requests_fail_once_reconnecting_gives_up() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let policy = ReconnectPolicy::new()
+            .with_initial_delay(Duration::from_millis(1))
+            .with_max_attempts(Some(2));
+        let factory_attempts = Arc::clone(&attempts);
+        let mut client =
+            Client::new(spawn_recording_server(log)).with_reconnect(policy, move || {
+                factory_attempts.fetch_add(1, Ordering::SeqCst);
+                async { Err(MCPError::Transport("Server is down".to_string())) }
+            });
+
+        client.initialize().await.unwrap();
+        let _ = client
+            .call_tool::<_, Value>("crash", &serde_json::json!({}))
+            .await;
+
+        let mut last_error = None;
+        for _ in 0..200 {
+            match client.subscribe_resource("file:///notes.txt").await {
+                Err(MCPError::ConnectionLost(reason)) if reason.contains("Gave up") => {
+                    last_error = Some(reason);
+                    break;
+                }
+                Err(MCPError::ConnectionLost(_)) => {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                other => panic!("Expected a lost connection, got {:?}", other),
+            }
+        }
+        assert!(last_error.is_some(), "Client never gave up reconnecting");
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
 }
@@ -200,8 +200,9 @@ where
         let response = mcpr::schema::json_rpc::JSONRPCResponse::new(
             id,
             serde_json::json!({
-                "protocol_version": mcpr::constants::LATEST_PROTOCOL_VERSION,
-                "server_info": {
+                "protocolVersion": mcpr::constants::LATEST_PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": {
                     "name": self.config.name,
                     "version": self.config.version
                 },
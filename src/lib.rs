@@ -52,7 +52,7 @@
 //!     error::MCPError,
 //!     server::{Server, ServerConfig},
 //!     transport::stdio::StdioTransport,
-//!     Tool,
+//!     Schema, Tool,
 //! };
 //! use serde_json::Value;
 //!
@@ -65,20 +65,11 @@
 //!         .with_tool(Tool {
 //!             name: "my_tool".to_string(),
 //!             description: Some("My awesome tool".to_string()),
-//!             input_schema: mcpr::schema::common::ToolInputSchema {
-//!                 r#type: "object".to_string(),
-//!                 properties: Some([
-//!                     ("param1".to_string(), serde_json::json!({
-//!                         "type": "string",
-//!                         "description": "First parameter"
-//!                     })),
-//!                     ("param2".to_string(), serde_json::json!({
-//!                         "type": "string",
-//!                         "description": "Second parameter"
-//!                     }))
-//!                 ].into_iter().collect()),
-//!                 required: Some(vec!["param1".to_string(), "param2".to_string()]),
-//!             },
+//!             input_schema: Schema::object()
+//!                 .prop("param1", Schema::string().desc("First parameter"))
+//!                 .prop("param2", Schema::string().desc("Second parameter"))
+//!                 .required(["param1", "param2"]),
+//!             ..Default::default()
 //!         });
 //!
 //!     // Create the server
@@ -112,6 +103,7 @@
 /// Current version of the MCPR crate
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+pub mod auth;
 pub mod cli;
 pub mod client;
 pub mod generator;
@@ -122,17 +114,20 @@ pub mod transport;
 // Re-export commonly used types
 pub use schema::common::{Cursor, LoggingLevel, ProgressToken, Tool};
 pub use schema::json_rpc::{JSONRPCMessage, RequestId};
+pub use schema::json_schema::Schema;
 
 /// Protocol version constants
 pub mod constants {
     /// The latest supported MCP protocol version
-    pub const LATEST_PROTOCOL_VERSION: &str = "2024-11-05";
+    pub const LATEST_PROTOCOL_VERSION: &str = "2025-06-18";
     /// The JSON-RPC version used by MCP
     pub const JSONRPC_VERSION: &str = "2.0";
 }
 
 /// Error types for the MCP implementation
 pub mod error {
+    use crate::schema::json_rpc::{ErrorCode, JSONRPCError, JSONRPCErrorObject};
+    use serde_json::Value;
     use thiserror::Error;
 
     #[derive(Error, Debug)]
@@ -151,5 +146,38 @@ pub mod error {
 
         #[error("Timeout error: {0}")]
         Timeout(String),
+
+        #[error("Connection closed: {0}")]
+        ConnectionClosed(String),
+
+        #[error("Connection lost: {0}")]
+        ConnectionLost(String),
+
+        #[error("Authorization error: {0}")]
+        Authorization(String),
+
+        /// An error response from the peer, or one to send to it
+        #[error("JSON-RPC error {code}: {message}")]
+        Rpc {
+            code: ErrorCode,
+            message: String,
+            data: Option<Value>,
+        },
+    }
+
+    impl From<JSONRPCErrorObject> for MCPError {
+        fn from(error: JSONRPCErrorObject) -> Self {
+            Self::Rpc {
+                code: error.code,
+                message: error.message,
+                data: error.data,
+            }
+        }
+    }
+
+    impl From<JSONRPCError> for MCPError {
+        fn from(error: JSONRPCError) -> Self {
+            error.error.into()
+        }
     }
 }
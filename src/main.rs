@@ -1,15 +1,22 @@
 //! MCP CLI tool for generating server and client stubs
 
 use clap::{Parser, Subcommand};
-use env_logger;
 use log::{error, info, warn};
 use mcpr::{
     client::Client,
     error::MCPError,
+    schema::{
+        json_schema::SchemaViolation,
+        validation::{ValidationMode, Validator},
+    },
     transport::{
-        sse::SSETransport, stdio::StdioTransport, websocket::WebSocketTransport, Transport,
+        sse::SSETransport, stdio::StdioTransport, tcp::TcpTransport, websocket::WebSocketTransport,
+        BoxedTransport, Transport,
     },
 };
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Read;
 use std::path::PathBuf;
 
 /// MCP CLI tool for generating server and client stubs
@@ -88,7 +95,7 @@ enum Commands {
         #[arg(short, long, default_value = "Default User")]
         name: String,
 
-        /// Transport type to use (stdio, sse, websocket)
+        /// Transport type to use (stdio, sse, websocket, tcp, unix)
         #[arg(short, long)]
         transport: String,
 
@@ -101,11 +108,18 @@ enum Commands {
         params: Option<String>,
     },
 
-    /// Validate an MCP message
+    /// Validate MCP messages
+    ///
+    /// The file holds one message or batch, or one per line. Results are checked against
+    /// the request with the same ID earlier in the file.
     Validate {
-        /// Path to the message file
+        /// Path to the message file, or `-` for stdin
         #[arg(short, long)]
         path: String,
+
+        /// Require exact conformance instead of accepting the quirks of popular hosts
+        #[arg(long)]
+        strict: bool,
     },
 }
 
@@ -113,7 +127,9 @@ enum Commands {
 #[derive(Debug, Clone)]
 struct Connect {
     uri: String,
+    #[allow(dead_code)]
     interactive: bool,
+    #[allow(dead_code)]
     name: String,
     transport: String,
     operation: Option<String>,
@@ -195,14 +211,117 @@ async fn main() -> Result<(), MCPError> {
             })
             .await
         }
-        Commands::Validate { path } => {
-            info!("Validating message from '{}'", path);
-            // TODO: Implement message validation
-            info!("Message validation not yet implemented");
-            Err(MCPError::UnsupportedFeature(
-                "Message validation not yet implemented".to_string(),
-            ))
+        Commands::Validate { path, strict } => {
+            let mode = if strict {
+                ValidationMode::Strict
+            } else {
+                ValidationMode::Lenient
+            };
+            info!("Validating messages from '{}' in {:?} mode", path, mode);
+            validate_messages(&path, mode)
+        }
+    }
+}
+
+/// Check the messages in a file, printing what is wrong with each
+fn validate_messages(path: &str, mode: ValidationMode) -> Result<(), MCPError> {
+    let mut text = String::new();
+    let read = if path == "-" {
+        std::io::stdin().read_to_string(&mut text).map(|_| ())
+    } else {
+        std::fs::read_to_string(path).map(|contents| text = contents)
+    };
+    read.map_err(|e| MCPError::Transport(format!("Cannot read {}: {}", path, e)))?;
+
+    // The whole file is one message or batch, or else there is one on each line
+    let messages = match serde_json::from_str::<Value>(&text) {
+        Ok(message) => vec![(path.to_string(), Ok(message))],
+        Err(_) => text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                (
+                    format!("{}:{}", path, index + 1),
+                    serde_json::from_str(line),
+                )
+            })
+            .collect(),
+    };
+
+    let validator = Validator::new(mode);
+    let mut methods = HashMap::new();
+    let mut invalid = 0;
+    for (place, message) in &messages {
+        let violations = match message {
+            Ok(message) => {
+                let mut violations = validator.check(message);
+                violations.extend(check_results(&validator, message, "", &mut methods));
+                violations
+            }
+            Err(e) => vec![SchemaViolation {
+                path: String::new(),
+                message: format!("not valid JSON: {}", e),
+            }],
+        };
+        if violations.is_empty() {
+            println!("{}: ok", place);
+        } else {
+            invalid += 1;
+            for violation in violations {
+                println!("{}: {}", place, violation);
+            }
+        }
+    }
+
+    if invalid > 0 {
+        return Err(MCPError::Protocol(format!(
+            "{} of {} messages are invalid",
+            invalid,
+            messages.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Check the results in `message` against the requests seen so far, and remember the
+/// requests it makes
+fn check_results(
+    validator: &Validator,
+    message: &Value,
+    path: &str,
+    methods: &mut HashMap<String, String>,
+) -> Vec<SchemaViolation> {
+    let members = match message {
+        Value::Array(messages) => {
+            return messages
+                .iter()
+                .enumerate()
+                .flat_map(|(index, message)| {
+                    check_results(validator, message, &format!("{}/{}", path, index), methods)
+                })
+                .collect()
         }
+        Value::Object(members) => members,
+        _ => return Vec::new(),
+    };
+    let Some(id) = members.get("id").map(Value::to_string) else {
+        return Vec::new();
+    };
+    if let Some(method) = members.get("method").and_then(Value::as_str) {
+        methods.insert(id, method.to_string());
+        return Vec::new();
+    }
+    match (methods.get(&id), members.get("result")) {
+        (Some(method), Some(result)) => validator
+            .check_result(method, result)
+            .into_iter()
+            .map(|violation| SchemaViolation {
+                path: format!("{}/result{}", path, violation.path),
+                ..violation
+            })
+            .collect(),
+        _ => Vec::new(),
     }
 }
 
@@ -256,36 +375,48 @@ async fn run_client(cmd: Connect) -> Result<(), MCPError> {
 
     let uri = cmd.uri.clone();
 
-    // Handle different transport types directly
-    match cmd.transport.as_str() {
+    // Pick the transport at runtime; the session logic is the same for all of them
+    let transport: BoxedTransport = match cmd.transport.as_str() {
         "sse" => {
             info!("Using SSE transport");
-            let transport = SSETransport::new(&uri);
-            let mut client = Client::new(transport);
-            handle_client_session(&mut client, cmd).await
+            SSETransport::new(&uri).boxed()
         }
         "websocket" => {
             info!("Using WebSocket transport");
-            let transport = WebSocketTransport::new(&uri);
-            let mut client = Client::new(transport);
-            handle_client_session(&mut client, cmd).await
+            WebSocketTransport::new(&uri).boxed()
         }
         "stdio" => {
             info!("Using stdio transport");
-            let transport = StdioTransport::new();
-            let mut client = Client::new(transport);
-            handle_client_session(&mut client, cmd).await
+            StdioTransport::new().boxed()
         }
-        _ => Err(MCPError::Transport(format!(
-            "Unsupported transport type: {}",
-            cmd.transport
-        ))),
-    }
+        "tcp" => {
+            info!("Using TCP transport");
+            let address = uri.strip_prefix("tcp://").unwrap_or(&uri);
+            TcpTransport::connect(address).await?.boxed()
+        }
+        #[cfg(unix)]
+        "unix" => {
+            info!("Using Unix socket transport");
+            let path = uri.strip_prefix("unix://").unwrap_or(&uri);
+            mcpr::transport::unix::UnixTransport::connect(path)
+                .await?
+                .boxed()
+        }
+        _ => {
+            return Err(MCPError::Transport(format!(
+                "Unsupported transport type: {}",
+                cmd.transport
+            )))
+        }
+    };
+
+    let mut client = Client::new(transport);
+    handle_client_session(&mut client, cmd).await
 }
 
 // Helper function to handle the client session logic
-async fn handle_client_session<T: Transport + Send + Sync>(
-    client: &mut Client<T>,
+async fn handle_client_session(
+    client: &mut Client<BoxedTransport>,
     cmd: Connect,
 ) -> Result<(), MCPError> {
     // Initialize the client
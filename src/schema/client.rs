@@ -1,17 +1,17 @@
 //! Client-specific MCP schema types
 
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 use super::common::{
-    BlobResourceContents, Cursor, Implementation, LoggingLevel, ProgressToken, Prompt,
+    BlobResourceContents, Cursor, Implementation, LoggingLevel, Meta, ProgressToken, Prompt,
     PromptMessage, Resource, ResourceTemplate, Root, TextResourceContents, Tool,
 };
 use super::json_rpc::RequestId;
 
 /// Client capabilities
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ClientCapabilities {
     /// Experimental, non-standard capabilities that the client supports.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -24,71 +24,66 @@ pub struct ClientCapabilities {
     /// Present if the client supports sampling from an LLM.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sampling: Option<Value>,
+
+    /// Present if the client supports elicitation from the user. Since 2025-06-18.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elicitation: Option<Value>,
 }
 
 /// Roots capability
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RootsCapability {
     /// Whether the client supports notifications for changes to the roots list.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub list_changed: Option<bool>,
 }
 
-/// This request is sent from the client to the server when it first connects.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct InitializeRequest {
-    pub method: String,
-    pub params: InitializeParams,
-}
-
 /// Parameters for initialize request
+///
+/// Earlier mcpr clients sent `protocol_version` in snake case and left out the
+/// capabilities and client information, so those are accepted too.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InitializeParams {
     /// The latest version of the Model Context Protocol that the client supports.
+    #[serde(alias = "protocol_version")]
     pub protocol_version: String,
 
     /// Client capabilities
+    #[serde(default)]
     pub capabilities: ClientCapabilities,
 
     /// Client information
+    #[serde(default)]
     pub client_info: Implementation,
-}
 
-/// This notification is sent from the client to the server after initialization has finished.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct InitializedNotification {
-    pub method: String,
-}
-
-/// A notification which can be sent by either side to indicate that it is cancelling a previously-issued request.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CancelledNotification {
-    pub method: String,
-    pub params: CancelledParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for cancelled notification
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CancelledParams {
     /// The ID of the request to cancel.
+    #[serde(alias = "request_id")]
     pub request_id: RequestId,
 
     /// An optional string describing the reason for the cancellation.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reason: Option<String>,
-}
 
-/// An out-of-band notification used to inform the receiver of a progress update for a long-running request.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProgressNotification {
-    pub method: String,
-    pub params: ProgressParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for progress notification
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ProgressParams {
     /// The progress token which was given in the initial request.
+    #[serde(alias = "progress_token")]
     pub progress_token: ProgressToken,
 
     /// The progress thus far.
@@ -97,20 +92,13 @@ pub struct ProgressParams {
     /// Total number of items to process (or total progress required), if known.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total: Option<f64>,
-}
-
-/// A ping, issued by either the server or the client, to check that the other party is still alive.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PingRequest {
-    pub method: String,
-}
 
-/// Sent from the client to request a list of resources the server has.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListResourcesRequest {
-    pub method: String,
+    /// A description of the progress so far. Since 2025-03-26.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub params: Option<PaginatedParams>,
+    pub message: Option<String>,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for paginated requests
@@ -119,43 +107,40 @@ pub struct PaginatedParams {
     /// An opaque token representing the current pagination position.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cursor: Option<Cursor>,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// The server's response to a resources/list request from the client.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ListResourcesResult {
     /// An opaque token representing the pagination position after the last returned result.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "next_cursor", skip_serializing_if = "Option::is_none")]
     pub next_cursor: Option<Cursor>,
 
     /// The list of resources
     pub resources: Vec<Resource>,
-}
 
-/// Sent from the client to request a list of resource templates the server has.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListResourceTemplatesRequest {
-    pub method: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub params: Option<PaginatedParams>,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// The server's response to a resources/templates/list request from the client.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ListResourceTemplatesResult {
     /// An opaque token representing the pagination position after the last returned result.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "next_cursor", skip_serializing_if = "Option::is_none")]
     pub next_cursor: Option<Cursor>,
 
     /// The list of resource templates
+    #[serde(alias = "resource_templates")]
     pub resource_templates: Vec<ResourceTemplate>,
-}
 
-/// Sent from the client to the server, to read a specific resource URI.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ReadResourceRequest {
-    pub method: String,
-    pub params: ReadResourceParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for read resource request
@@ -163,12 +148,18 @@ pub struct ReadResourceRequest {
 pub struct ReadResourceParams {
     /// The URI of the resource to read.
     pub uri: String,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// The server's response to a resources/read request from the client.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReadResourceResult {
     pub contents: Vec<ResourceContent>,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Resource content
@@ -179,25 +170,14 @@ pub enum ResourceContent {
     Blob(BlobResourceContents),
 }
 
-/// Sent from the client to request resources/updated notifications from the server.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SubscribeRequest {
-    pub method: String,
-    pub params: SubscribeParams,
-}
-
 /// Parameters for subscribe request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubscribeParams {
     /// The URI of the resource to subscribe to.
     pub uri: String,
-}
 
-/// Sent from the client to request cancellation of resources/updated notifications from the server.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UnsubscribeRequest {
-    pub method: String,
-    pub params: UnsubscribeParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for unsubscribe request
@@ -205,32 +185,24 @@ pub struct UnsubscribeRequest {
 pub struct UnsubscribeParams {
     /// The URI of the resource to unsubscribe from.
     pub uri: String,
-}
 
-/// Sent from the client to request a list of prompts and prompt templates the server has.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListPromptsRequest {
-    pub method: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub params: Option<PaginatedParams>,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// The server's response to a prompts/list request from the client.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ListPromptsResult {
     /// An opaque token representing the pagination position after the last returned result.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "next_cursor", skip_serializing_if = "Option::is_none")]
     pub next_cursor: Option<Cursor>,
 
     /// The list of prompts
     pub prompts: Vec<Prompt>,
-}
 
-/// Used by the client to get a prompt provided by the server.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GetPromptRequest {
-    pub method: String,
-    pub params: GetPromptParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for get prompt request
@@ -242,6 +214,9 @@ pub struct GetPromptParams {
     /// Arguments to use for templating the prompt.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub arguments: Option<HashMap<String, String>>,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// The server's response to a prompts/get request from the client.
@@ -253,32 +228,24 @@ pub struct GetPromptResult {
 
     /// The prompt messages
     pub messages: Vec<PromptMessage>,
-}
 
-/// Sent from the client to request a list of tools the server has.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListToolsRequest {
-    pub method: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub params: Option<PaginatedParams>,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// The server's response to a tools/list request from the client.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ListToolsResult {
     /// An opaque token representing the pagination position after the last returned result.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "next_cursor", skip_serializing_if = "Option::is_none")]
     pub next_cursor: Option<Cursor>,
 
     /// The list of tools
     pub tools: Vec<Tool>,
-}
 
-/// Used by the client to invoke a tool provided by the server.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CallToolRequest {
-    pub method: String,
-    pub params: CallToolParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for call tool request
@@ -289,14 +256,10 @@ pub struct CallToolParams {
 
     /// Arguments for the tool
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub arguments: Option<HashMap<String, Value>>,
-}
+    pub arguments: Option<Map<String, Value>>,
 
-/// A request from the client to the server, to enable or adjust logging.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SetLevelRequest {
-    pub method: String,
-    pub params: SetLevelParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for set level request
@@ -304,23 +267,27 @@ pub struct SetLevelRequest {
 pub struct SetLevelParams {
     /// The level of logging that the client wants to receive from the server.
     pub level: LoggingLevel,
-}
 
-/// A request from the client to the server, to ask for completion options.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CompleteRequest {
-    pub method: String,
-    pub params: CompleteParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for complete request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompleteParams {
     /// Reference to a prompt or resource
+    #[serde(rename = "ref", alias = "ref_")]
     pub ref_: Reference,
 
     /// The argument's information
     pub argument: ArgumentInfo,
+
+    /// Context for the completion. Since 2025-06-18.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<CompleteContext>,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Reference to a prompt or resource
@@ -349,6 +316,14 @@ pub struct ResourceReference {
     pub uri: String,
 }
 
+/// Context for a completion request. Since 2025-06-18.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompleteContext {
+    /// Arguments of the prompt or resource template that have already been resolved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<HashMap<String, String>>,
+}
+
 /// Argument information for completion
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArgumentInfo {
@@ -363,10 +338,7 @@ pub struct ArgumentInfo {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListRootsResult {
     pub roots: Vec<Root>,
-}
 
-/// A notification from the client to the server, informing it that the list of roots has changed.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RootsListChangedNotification {
-    pub method: String,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
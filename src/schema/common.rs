@@ -1,8 +1,44 @@
 //! Common types used throughout the MCP schema
 
+use base64::engine::general_purpose::STANDARD;
+use base64::read::DecoderReader;
+use base64::write::EncoderStringWriter;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::json_schema::Schema;
+
+/// Implements `Deserialize` for an enum of content types by the content's `type` member
+///
+/// Content types that share a shape, such as images and audio, cannot be told apart
+/// any other way.
+macro_rules! deserialize_by_type {
+    ($name:ident { $($tag:literal => $variant:ident),* $(,)? }) => {
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                use serde::de::Error;
+
+                let value = serde_json::Value::deserialize(deserializer)?;
+                let kind = value.get("type").and_then(serde_json::Value::as_str);
+                match kind {
+                    $(Some($tag) => serde_json::from_value(value)
+                        .map(Self::$variant)
+                        .map_err(D::Error::custom),)*
+                    Some(other) => Err(D::Error::custom(format!("unknown content type `{}`", other))),
+                    None => Err(D::Error::missing_field("type")),
+                }
+            }
+        }
+    };
+}
+pub(crate) use deserialize_by_type;
+
+/// Metadata attached to a message or object, reserved for protocol extensions.
+pub type Meta = HashMap<String, Value>;
 
 /// A progress token, used to associate progress notifications with the original request.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -62,14 +98,22 @@ pub struct Annotations {
     /// the data is entirely optional.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub priority: Option<f32>,
+
+    /// When the object was last modified, as an ISO 8601 timestamp. Since 2025-06-18.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
 }
 
 /// Describes an implementation of MCP.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Implementation {
     pub name: String,
     pub version: String,
+
+    /// A human-readable name for display. Since 2025-06-18.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
 }
 
 /// Text provided to or from an LLM.
@@ -79,16 +123,44 @@ pub struct TextContent {
     pub text: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub annotations: Option<Annotations>,
+    /// Since 2025-06-18.
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// An image provided to or from an LLM.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ImageContent {
     pub r#type: String,
     pub data: String,
+    #[serde(alias = "mime_type")]
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+    /// Since 2025-06-18.
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+}
+
+/// Audio provided to or from an LLM. Since 2025-03-26.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioContent {
+    pub r#type: String,
+
+    /// The base64-encoded audio data.
+    pub data: String,
+
+    /// The MIME type of the audio.
     pub mime_type: String,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub annotations: Option<Annotations>,
+
+    /// Since 2025-06-18.
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// The contents of a resource, embedded into a prompt or tool call result.
@@ -98,6 +170,45 @@ pub struct EmbeddedResource {
     pub resource: ResourceContents,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub annotations: Option<Annotations>,
+    /// Since 2025-06-18.
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+}
+
+/// A link to a resource the server can read, included in a prompt or tool call result.
+/// Since 2025-06-18.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceLink {
+    pub r#type: String,
+
+    /// The URI of the resource.
+    pub uri: String,
+
+    /// A name for the resource.
+    pub name: String,
+
+    /// A human-readable name for display.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// A description of what the resource represents.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The MIME type of the resource, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+
+    /// The size of the raw resource content, in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// The contents of a specific resource or sub-resource.
@@ -110,12 +221,13 @@ pub enum ResourceContents {
 
 /// Text resource contents
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TextResourceContents {
     /// The URI of this resource.
     pub uri: String,
 
     /// The MIME type of this resource, if known.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "mime_type", skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
 
     /// The text of the item.
@@ -124,20 +236,84 @@ pub struct TextResourceContents {
 
 /// Binary resource contents
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct BlobResourceContents {
     /// The URI of this resource.
     pub uri: String,
 
     /// The MIME type of this resource, if known.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "mime_type", skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
 
     /// A base64-encoded string representing the binary data of the item.
     pub blob: String,
 }
 
+/// How many bytes are encoded at a time; a multiple of 3, so chunks encode without padding
+const BLOB_CHUNK: usize = 3 * 16 * 1024;
+
+impl BlobResourceContents {
+    /// Read `reader` to its end into a blob, encoding the bytes as they are read
+    ///
+    /// Only the encoded blob is held in memory, never all of the bytes as well.
+    pub fn from_reader(
+        uri: impl Into<String>,
+        mime_type: Option<String>,
+        mut reader: impl Read,
+    ) -> io::Result<Self> {
+        let mut encoder = EncoderStringWriter::new(&STANDARD);
+        io::copy(&mut reader, &mut encoder)?;
+        Ok(Self {
+            uri: uri.into(),
+            mime_type,
+            blob: encoder.into_inner(),
+        })
+    }
+
+    /// Read an async `reader` to its end into a blob, encoding the bytes as they are read
+    pub async fn from_async_reader(
+        uri: impl Into<String>,
+        mime_type: Option<String>,
+        mut reader: impl AsyncRead + Unpin,
+    ) -> io::Result<Self> {
+        let mut blob = String::new();
+        let mut chunk = vec![0; BLOB_CHUNK];
+        let mut filled = 0;
+        loop {
+            let read = reader.read(&mut chunk[filled..]).await?;
+            filled += read;
+            // Only whole chunks and the last one are encoded, so padding only ends the blob
+            if read == 0 || filled == chunk.len() {
+                STANDARD.encode_string(&chunk[..filled], &mut blob);
+                filled = 0;
+            }
+            if read == 0 {
+                break;
+            }
+        }
+        Ok(Self {
+            uri: uri.into(),
+            mime_type,
+            blob,
+        })
+    }
+
+    /// The decoded bytes, decoded as they are read
+    pub fn reader(&self) -> impl Read + '_ {
+        DecoderReader::new(self.blob.as_bytes(), &STANDARD)
+    }
+
+    /// Decode the blob into `writer`, returning how many bytes were written
+    ///
+    /// The bytes are decoded a chunk at a time rather than all at once.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<u64> {
+        io::copy(&mut self.reader(), &mut writer)
+    }
+}
+
 /// A known resource that the server is capable of reading.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Resource {
     /// The URI of this resource.
     pub uri: String,
@@ -150,7 +326,7 @@ pub struct Resource {
     pub description: Option<String>,
 
     /// The MIME type of this resource, if known.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "mime_type", skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
 
     /// The size of the raw resource content, in bytes.
@@ -159,12 +335,22 @@ pub struct Resource {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub annotations: Option<Annotations>,
+
+    /// A human-readable name for display. Since 2025-06-18.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// Since 2025-06-18.
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// A template description for resources available on the server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ResourceTemplate {
     /// A URI template (according to RFC 6570) that can be used to construct resource URIs.
+    #[serde(alias = "uri_template")]
     pub uri_template: String,
 
     /// A human-readable name for the type of resource this template refers to.
@@ -175,11 +361,19 @@ pub struct ResourceTemplate {
     pub description: Option<String>,
 
     /// The MIME type for all resources that match this template.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "mime_type", skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub annotations: Option<Annotations>,
+
+    /// A human-readable name for display. Since 2025-06-18.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// Since 2025-06-18.
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// A prompt or prompt template that the server offers.
@@ -195,6 +389,14 @@ pub struct Prompt {
     /// A list of arguments to use for templating the prompt.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub arguments: Option<Vec<PromptArgument>>,
+
+    /// A human-readable name for display. Since 2025-06-18.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// Since 2025-06-18.
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Describes an argument that a prompt can accept.
@@ -210,25 +412,40 @@ pub struct PromptArgument {
     /// Whether this argument must be provided.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub required: Option<bool>,
+
+    /// A human-readable name for display. Since 2025-06-18.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
 }
 
 /// Describes a message returned as part of a prompt.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromptMessage {
     pub role: Role,
-    #[serde(flatten)]
     pub content: PromptMessageContent,
 }
 
 /// Content of a prompt message
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum PromptMessageContent {
     Text(TextContent),
     Image(ImageContent),
+    /// Since 2025-03-26.
+    Audio(AudioContent),
     Resource(EmbeddedResource),
+    /// Since 2025-06-18.
+    ResourceLink(ResourceLink),
 }
 
+deserialize_by_type!(PromptMessageContent {
+    "text" => Text,
+    "image" => Image,
+    "audio" => Audio,
+    "resource" => Resource,
+    "resource_link" => ResourceLink,
+});
+
 /// Definition for a tool the client can call.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -242,19 +459,76 @@ pub struct Tool {
 
     /// A JSON Schema object defining the expected parameters for the tool.
     pub input_schema: ToolInputSchema,
+
+    /// A human-readable name for display. Since 2025-06-18.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// Hints about the tool's behavior. Since 2025-03-26.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+
+    /// A JSON Schema object the tool's structured content conforms to. Since 2025-06-18.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<ToolOutputSchema>,
+
+    /// Since 2025-06-18.
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+}
+
+impl Default for Tool {
+    /// A tool without a name that takes an object without properties
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            description: None,
+            input_schema: Schema::object(),
+            title: None,
+            annotations: None,
+            output_schema: None,
+            meta: None,
+        }
+    }
 }
 
 /// JSON Schema for tool input
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// The MCP schema requires an object schema, see [`Schema::is_object_schema`];
+/// [`ServerConfig::with_tool`](crate::server::ServerConfig::with_tool) refuses others.
+pub type ToolInputSchema = Schema;
+
+/// JSON Schema for a tool's structured output, which must be an object schema too
+pub type ToolOutputSchema = ToolInputSchema;
+
+/// Hints describing a tool's behavior. Since 2025-03-26.
+///
+/// Hints are not guaranteed to be accurate, so clients should not make security
+/// decisions based on them when the server is not trusted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ToolInputSchema {
-    pub r#type: String,
+pub struct ToolAnnotations {
+    /// A human-readable title for the tool.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
 
+    /// If true, the tool does not modify its environment. Default: false.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub properties: Option<HashMap<String, Value>>,
+    pub read_only_hint: Option<bool>,
 
+    /// If true, the tool may perform destructive updates; only meaningful when the tool
+    /// is not read-only. Default: true.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub required: Option<Vec<String>>,
+    pub destructive_hint: Option<bool>,
+
+    /// If true, calling the tool again with the same arguments has no further effect;
+    /// only meaningful when the tool is not read-only. Default: false.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+
+    /// If true, the tool interacts with an open world of external entities. Default: true.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
 }
 
 /// Represents a root directory or file that the server can operate on.
@@ -267,3 +541,33 @@ pub struct Root {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_blobs_are_encoded_and_decoded_in_chunks() {
+        // Long enough to span several chunks, and not a multiple of 3
+        let bytes: Vec<u8> = (0..BLOB_CHUNK * 2 + 7).map(|i| (i % 251) as u8).collect();
+        let expected = STANDARD.encode(&bytes);
+
+        let blob = BlobResourceContents::from_reader("file:///a", None, &bytes[..]).unwrap();
+        assert_eq!(blob.blob, expected);
+
+        // Async readers may hand over fewer bytes than asked for
+        let (mut writer, reader) = tokio::io::duplex(1000);
+        let written = bytes.clone();
+        tokio::spawn(
+            async move { tokio::io::AsyncWriteExt::write_all(&mut writer, &written).await },
+        );
+        let blob = BlobResourceContents::from_async_reader("file:///a", None, reader)
+            .await
+            .unwrap();
+        assert_eq!(blob.blob, expected);
+
+        let mut decoded = Vec::new();
+        assert_eq!(blob.write_to(&mut decoded).unwrap(), bytes.len() as u64);
+        assert_eq!(decoded, bytes);
+    }
+}
@@ -1,8 +1,10 @@
 //! JSON-RPC message types for MCP
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 
 use crate::constants::JSONRPC_VERSION;
 
@@ -15,13 +17,27 @@ pub enum RequestId {
 }
 
 /// JSON-RPC message types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Messages are told apart by their members, as checked by
+/// [`Validator::lenient`](super::validation::Validator::lenient): a message with a
+/// `method` is a request if it has an `id` and a notification otherwise, and a response
+/// has either a `result` or an `error`.
+#[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum JSONRPCMessage {
     Request(JSONRPCRequest),
     Notification(JSONRPCNotification),
     Response(JSONRPCResponse),
     Error(JSONRPCError),
+    /// Several messages sent together as one JSON array
+    Batch(Vec<JSONRPCMessage>),
+}
+
+impl<'de> Deserialize<'de> for JSONRPCMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let message = Value::deserialize(deserializer)?;
+        super::validation::read_message(message).map_err(serde::de::Error::custom)
+    }
 }
 
 /// A request that expects a response.
@@ -59,7 +75,8 @@ pub struct JSONRPCResponse {
 #[serde(rename_all = "camelCase")]
 pub struct JSONRPCError {
     pub jsonrpc: String,
-    pub id: RequestId,
+    /// The ID of the request that failed, `null` if it could not be read
+    pub id: Option<RequestId>,
     pub error: JSONRPCErrorObject,
 }
 
@@ -68,7 +85,7 @@ pub struct JSONRPCError {
 #[serde(rename_all = "camelCase")]
 pub struct JSONRPCErrorObject {
     /// The error type that occurred.
-    pub code: i32,
+    pub code: ErrorCode,
 
     /// A short description of the error.
     pub message: String,
@@ -78,13 +95,131 @@ pub struct JSONRPCErrorObject {
     pub data: Option<Value>,
 }
 
-/// Standard JSON-RPC error codes
-pub mod error_codes {
-    pub const PARSE_ERROR: i32 = -32700;
-    pub const INVALID_REQUEST: i32 = -32600;
-    pub const METHOD_NOT_FOUND: i32 = -32601;
-    pub const INVALID_PARAMS: i32 = -32602;
-    pub const INTERNAL_ERROR: i32 = -32603;
+/// JSON-RPC error codes: the standard ones, those this crate's servers use, and any other
+///
+/// The codes of this crate's own errors lie in the range JSON-RPC leaves to
+/// implementations, clear of those MCP assigns (such as `-32002` for an unknown resource)
+/// and of `-32001`, which other MCP SDKs send for request timeouts.
+///
+/// Codes are equal when they are sent as the same number, so `Other(-32700)` equals
+/// `ParseError`.
+#[derive(Debug, Clone, Copy)]
+pub enum ErrorCode {
+    /// The message is not valid JSON (-32700)
+    ParseError,
+    /// The message is JSON, but not a valid JSON-RPC message (-32600)
+    InvalidRequest,
+    /// The method does not exist or is not served (-32601)
+    MethodNotFound,
+    /// The params do not fit the method (-32602)
+    InvalidParams,
+    /// The peer failed while handling the request (-32603)
+    InternalError,
+    /// A tool handler returned an error (-32000)
+    ToolExecutionFailed,
+    /// The requested resource does not exist (-32002), as MCP defines it
+    ResourceNotFound,
+    /// A tool call was refused because a limit was exceeded (-32010)
+    LimitExceeded,
+    /// A request did not finish in time (-32011)
+    RequestTimeout,
+    /// A code without a meaning of its own here
+    Other(i32),
+}
+
+impl ErrorCode {
+    /// The code as sent on the wire
+    pub const fn code(self) -> i32 {
+        match self {
+            Self::ParseError => -32700,
+            Self::InvalidRequest => -32600,
+            Self::MethodNotFound => -32601,
+            Self::InvalidParams => -32602,
+            Self::InternalError => -32603,
+            Self::ToolExecutionFailed => -32000,
+            Self::ResourceNotFound => -32002,
+            Self::LimitExceeded => -32010,
+            Self::RequestTimeout => -32011,
+            Self::Other(code) => code,
+        }
+    }
+
+    /// A short description of the error, for use as its message
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::ParseError => "Parse error",
+            Self::InvalidRequest => "Invalid Request",
+            Self::MethodNotFound => "Method not found",
+            Self::InvalidParams => "Invalid params",
+            Self::InternalError => "Internal error",
+            Self::ToolExecutionFailed => "Tool execution failed",
+            Self::ResourceNotFound => "Resource not found",
+            Self::LimitExceeded => "Limit exceeded",
+            Self::RequestTimeout => "Request timed out",
+            Self::Other(_) => "Server error",
+        }
+    }
+}
+
+impl From<i32> for ErrorCode {
+    fn from(code: i32) -> Self {
+        match code {
+            -32700 => Self::ParseError,
+            -32600 => Self::InvalidRequest,
+            -32601 => Self::MethodNotFound,
+            -32602 => Self::InvalidParams,
+            -32603 => Self::InternalError,
+            -32000 => Self::ToolExecutionFailed,
+            -32002 => Self::ResourceNotFound,
+            -32010 => Self::LimitExceeded,
+            -32011 => Self::RequestTimeout,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl From<ErrorCode> for i32 {
+    fn from(code: ErrorCode) -> Self {
+        code.code()
+    }
+}
+
+impl PartialEq for ErrorCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.code() == other.code()
+    }
+}
+
+impl Eq for ErrorCode {}
+
+impl Hash for ErrorCode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.code().hash(state);
+    }
+}
+
+impl PartialEq<i32> for ErrorCode {
+    fn eq(&self, other: &i32) -> bool {
+        self.code() == *other
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_i32(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        i32::deserialize(deserializer).map(Self::from)
+    }
 }
 
 /// Base request interface
@@ -106,8 +241,9 @@ pub struct RequestParams {
 
 /// Request metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RequestMeta {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "progress_token", skip_serializing_if = "Option::is_none")]
     pub progress_token: Option<super::common::ProgressToken>,
 }
 
@@ -180,7 +316,7 @@ impl JSONRPCError {
     pub fn new(id: RequestId, error_obj: JSONRPCErrorObject) -> Self {
         Self {
             jsonrpc: JSONRPC_VERSION.to_string(),
-            id,
+            id: Some(id),
             error: error_obj,
         }
     }
@@ -188,17 +324,97 @@ impl JSONRPCError {
     /// Create a new JSON-RPC error with details
     pub fn new_with_details(
         id: RequestId,
-        code: i32,
+        code: impl Into<ErrorCode>,
         message: String,
         data: Option<Value>,
     ) -> Self {
         Self::new(
             id,
             JSONRPCErrorObject {
-                code,
+                code: code.into(),
                 message,
                 data,
             },
         )
     }
+
+    /// An error about a message whose request ID could not be read
+    ///
+    /// That is a message that is not JSON, or not a JSON-RPC message.
+    pub fn without_id(code: ErrorCode, message: String) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: None,
+            error: JSONRPCErrorObject {
+                code,
+                message,
+                data: None,
+            },
+        }
+    }
+
+    /// The error reply to a message that could not be parsed
+    ///
+    /// Invalid JSON is a parse error, and valid JSON that is no JSON-RPC message an invalid
+    /// request.
+    pub fn unparseable(error: &serde_json::Error) -> Self {
+        let code = if error.is_syntax() || error.is_eof() {
+            ErrorCode::ParseError
+        } else {
+            ErrorCode::InvalidRequest
+        };
+        Self::without_id(code, format!("{}: {}", code.description(), error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_error_codes_travel_as_numbers() {
+        let error = JSONRPCError::new_with_details(
+            RequestId::Number(1),
+            ErrorCode::LimitExceeded,
+            "Too many calls".to_string(),
+            Some(json!({ "limit": "global" })),
+        );
+        let wire = serde_json::to_value(&error).unwrap();
+        assert_eq!(wire["error"]["code"], -32010);
+
+        let read: JSONRPCError = serde_json::from_value(wire).unwrap();
+        assert_eq!(read.error.code, ErrorCode::LimitExceeded);
+        assert_eq!(ErrorCode::from(-32099), ErrorCode::Other(-32099));
+        assert_eq!(ErrorCode::MethodNotFound, -32601);
+        assert_eq!(ErrorCode::Other(-32700), ErrorCode::ParseError);
+        assert_ne!(ErrorCode::Other(-32099), ErrorCode::Other(-32098));
+    }
+
+    #[test]
+    fn test_codes_of_other_meaning_are_not_timeouts() {
+        // MCP's "Resource not found" and other SDKs' request timeout
+        for code in [-32002, -32001] {
+            assert_ne!(ErrorCode::from(code), ErrorCode::RequestTimeout);
+            assert_ne!(ErrorCode::from(code), ErrorCode::LimitExceeded);
+        }
+        assert_eq!(ErrorCode::from(-32002), ErrorCode::ResourceNotFound);
+        assert_eq!(ErrorCode::ResourceNotFound, -32002);
+        assert_eq!(ErrorCode::from(-32001), ErrorCode::Other(-32001));
+        assert_eq!(ErrorCode::from(-32011), ErrorCode::RequestTimeout);
+    }
+
+    #[test]
+    fn test_unreadable_messages_are_answered_with_a_null_id() {
+        let syntax = serde_json::from_str::<JSONRPCMessage>("{\"id\": ").unwrap_err();
+        let error = JSONRPCError::unparseable(&syntax);
+        assert_eq!(error.error.code, ErrorCode::ParseError);
+        assert_eq!(serde_json::to_value(&error).unwrap()["id"], Value::Null);
+
+        let shape = serde_json::from_str::<JSONRPCMessage>("{\"foo\": 1}").unwrap_err();
+        assert_eq!(
+            JSONRPCError::unparseable(&shape).error.code,
+            ErrorCode::InvalidRequest
+        );
+    }
 }
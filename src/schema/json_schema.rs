@@ -0,0 +1,909 @@
+//! The JSON Schemas tools declare, and checking values against them
+//!
+//! [`Schema`] models a schema, with builders for writing one in code. [`validate`]
+//! checks a value against a schema. Only the keywords tool schemas and the MCP schema
+//! itself use are understood: `type`, `enum`, `const`, `properties`, `required`,
+//! `additionalProperties`, `items`, `anyOf`, `oneOf`, `allOf`, `not`, `$ref` to a place
+//! in the same document, and the length and range bounds. Other keywords are ignored,
+//! so a value passes unless one of those rules it out.
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A JSON Schema, such as the ones tools declare for their input and output
+///
+/// `true` and `false` are schemas of their own, accepting every value and none. Every
+/// other schema is a [`SchemaObject`].
+///
+/// ```rust
+/// use mcpr::schema::json_schema::Schema;
+/// use serde_json::json;
+///
+/// let schema = Schema::object()
+///     .prop("q", Schema::string().desc("What to search for"))
+///     .prop("limit", Schema::integer().minimum(1).maximum(100))
+///     .required(["q"]);
+/// assert!(schema.validate(&json!({ "q": "rust", "limit": 5 })).is_ok());
+/// assert_eq!(
+///     serde_json::to_value(&schema).unwrap(),
+///     json!({
+///         "type": "object",
+///         "properties": {
+///             "q": { "type": "string", "description": "What to search for" },
+///             "limit": { "type": "integer", "minimum": 1, "maximum": 100 }
+///         },
+///         "required": ["q"]
+///     })
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Schema {
+    /// `true` accepts every value, `false` none
+    Bool(bool),
+    Object(Box<SchemaObject>),
+}
+
+/// The keywords of a schema
+///
+/// The keywords tool schemas commonly use have fields of their own. Any other keyword,
+/// and any keyword whose value does not fit its field, is kept in `extra`, so every
+/// schema reads and writes back unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaObject {
+    /// A reference to another schema, such as `#/$defs/Address`
+    #[serde(rename = "$ref", skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<SchemaType>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+
+    /// The values allowed
+    #[serde(rename = "enum", skip_serializing_if = "Option::is_none")]
+    pub enum_values: Option<Vec<Value>>,
+
+    /// The one value allowed
+    #[serde(rename = "const", skip_serializing_if = "Option::is_none")]
+    pub const_value: Option<Value>,
+
+    /// A hint at the kind of string, such as `date-time` or `uri`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<BTreeMap<String, Schema>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<Vec<String>>,
+
+    /// The schema of members not named in `properties`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_properties: Option<Schema>,
+
+    /// The schema of each item of an array
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Schema>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_items: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<Number>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<Number>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub any_of: Option<Vec<Schema>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub one_of: Option<Vec<Schema>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all_of: Option<Vec<Schema>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub not: Option<Schema>,
+
+    /// Schemas for `$ref`s to point at
+    #[serde(rename = "$defs", skip_serializing_if = "Option::is_none")]
+    pub defs: Option<BTreeMap<String, Schema>>,
+
+    /// Schemas for `$ref`s to point at, as drafts before 2019-09 name them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub definitions: Option<BTreeMap<String, Schema>>,
+
+    /// Every other keyword
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The `type` keyword: one type, or a list of them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SchemaType {
+    Single(InstanceType),
+    Union(Vec<InstanceType>),
+}
+
+/// The kinds of JSON value a schema's `type` can name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstanceType {
+    Null,
+    Boolean,
+    Object,
+    Array,
+    Number,
+    Integer,
+    String,
+}
+
+impl Schema {
+    /// The schema `{}`, which accepts every value
+    pub fn any() -> Self {
+        Self::Object(Box::default())
+    }
+
+    fn of_type(r#type: InstanceType) -> Self {
+        Self::any().with(|schema| schema.r#type = Some(SchemaType::Single(r#type)))
+    }
+
+    pub fn object() -> Self {
+        Self::of_type(InstanceType::Object)
+    }
+
+    pub fn string() -> Self {
+        Self::of_type(InstanceType::String)
+    }
+
+    pub fn number() -> Self {
+        Self::of_type(InstanceType::Number)
+    }
+
+    pub fn integer() -> Self {
+        Self::of_type(InstanceType::Integer)
+    }
+
+    pub fn boolean() -> Self {
+        Self::of_type(InstanceType::Boolean)
+    }
+
+    pub fn null() -> Self {
+        Self::of_type(InstanceType::Null)
+    }
+
+    /// An array whose items match `items`
+    pub fn array(items: Schema) -> Self {
+        Self::of_type(InstanceType::Array).items(items)
+    }
+
+    /// A reference to another schema, such as `#/$defs/Address`
+    pub fn reference(reference: impl Into<String>) -> Self {
+        Self::any().with(|schema| schema.reference = Some(reference.into()))
+    }
+
+    /// Values matching at least one of `schemas`
+    pub fn any_of(schemas: impl IntoIterator<Item = Schema>) -> Self {
+        Self::any().with(|schema| schema.any_of = Some(schemas.into_iter().collect()))
+    }
+
+    /// Values matching exactly one of `schemas`
+    pub fn one_of(schemas: impl IntoIterator<Item = Schema>) -> Self {
+        Self::any().with(|schema| schema.one_of = Some(schemas.into_iter().collect()))
+    }
+
+    /// Values matching all of `schemas`
+    pub fn all_of(schemas: impl IntoIterator<Item = Schema>) -> Self {
+        Self::any().with(|schema| schema.all_of = Some(schemas.into_iter().collect()))
+    }
+
+    pub fn title(self, title: impl Into<String>) -> Self {
+        self.with(|schema| schema.title = Some(title.into()))
+    }
+
+    /// Set the description
+    pub fn desc(self, description: impl Into<String>) -> Self {
+        self.with(|schema| schema.description = Some(description.into()))
+    }
+
+    /// Set the value assumed when none is given
+    pub fn default_value(self, value: impl Into<Value>) -> Self {
+        self.with(|schema| schema.default = Some(value.into()))
+    }
+
+    /// Allow only `values`
+    pub fn enum_values<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Self {
+        self.with(|schema| schema.enum_values = Some(values.into_iter().map(Into::into).collect()))
+    }
+
+    /// Allow only `value`
+    pub fn constant(self, value: impl Into<Value>) -> Self {
+        self.with(|schema| schema.const_value = Some(value.into()))
+    }
+
+    pub fn format(self, format: impl Into<String>) -> Self {
+        self.with(|schema| schema.format = Some(format.into()))
+    }
+
+    /// Add a property
+    pub fn prop(self, name: impl Into<String>, property: Schema) -> Self {
+        self.with(|schema| {
+            schema
+                .properties
+                .get_or_insert_with(BTreeMap::new)
+                .insert(name.into(), property);
+        })
+    }
+
+    /// Add properties to those required
+    pub fn required<S: Into<String>>(self, names: impl IntoIterator<Item = S>) -> Self {
+        self.with(|schema| {
+            schema
+                .required
+                .get_or_insert_with(Vec::new)
+                .extend(names.into_iter().map(Into::into));
+        })
+    }
+
+    /// Set the schema of members not named in the properties; `false` allows none
+    pub fn additional_properties(self, additional: impl Into<Schema>) -> Self {
+        self.with(|schema| schema.additional_properties = Some(additional.into()))
+    }
+
+    pub fn items(self, items: Schema) -> Self {
+        self.with(|schema| schema.items = Some(items))
+    }
+
+    pub fn min_items(self, min: u64) -> Self {
+        self.with(|schema| schema.min_items = Some(min))
+    }
+
+    pub fn max_items(self, max: u64) -> Self {
+        self.with(|schema| schema.max_items = Some(max))
+    }
+
+    pub fn min_length(self, min: u64) -> Self {
+        self.with(|schema| schema.min_length = Some(min))
+    }
+
+    pub fn max_length(self, max: u64) -> Self {
+        self.with(|schema| schema.max_length = Some(max))
+    }
+
+    /// Set the least number allowed
+    ///
+    /// # Panics
+    ///
+    /// If `min` is not finite.
+    pub fn minimum(self, min: impl Into<f64>) -> Self {
+        self.with(|schema| schema.minimum = Some(number(min.into())))
+    }
+
+    /// Set the greatest number allowed
+    ///
+    /// # Panics
+    ///
+    /// If `max` is not finite.
+    pub fn maximum(self, max: impl Into<f64>) -> Self {
+        self.with(|schema| schema.maximum = Some(number(max.into())))
+    }
+
+    /// Add a schema to `$defs`, for `$ref`s to point at with `#/$defs/{name}`
+    pub fn def(self, name: impl Into<String>, definition: Schema) -> Self {
+        self.with(|schema| {
+            schema
+                .defs
+                .get_or_insert_with(BTreeMap::new)
+                .insert(name.into(), definition);
+        })
+    }
+
+    /// Set a keyword that has no builder of its own
+    pub fn keyword(self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        Schema::Object(Box::new(SchemaObject::from_map({
+            let mut map = match serde_json::to_value(self) {
+                Ok(Value::Object(map)) => map,
+                _ => Map::new(),
+            };
+            map.insert(name.into(), value.into());
+            map
+        })))
+    }
+
+    /// The keywords of this schema, or `None` for `true` and `false`
+    pub fn as_object(&self) -> Option<&SchemaObject> {
+        match self {
+            Self::Bool(_) => None,
+            Self::Object(schema) => Some(schema),
+        }
+    }
+
+    /// Whether this schema has type `object`, as MCP requires of tool schemas
+    pub fn is_object_schema(&self) -> bool {
+        self.as_object()
+            .is_some_and(|schema| schema.r#type == Some(SchemaType::Single(InstanceType::Object)))
+    }
+
+    /// The keywords of this schema
+    ///
+    /// `true` becomes `{}` and `false` becomes `{"not": {}}`, which mean the same.
+    pub fn object_mut(&mut self) -> &mut SchemaObject {
+        if let Self::Bool(accepts) = *self {
+            let mut schema = SchemaObject::default();
+            if !accepts {
+                schema.not = Some(Self::any());
+            }
+            *self = Self::Object(Box::new(schema));
+        }
+        match self {
+            Self::Object(schema) => schema,
+            Self::Bool(_) => unreachable!("boolean schemas were just replaced"),
+        }
+    }
+
+    fn with(mut self, f: impl FnOnce(&mut SchemaObject)) -> Self {
+        f(self.object_mut());
+        self
+    }
+
+    /// Check `value` against this schema, reporting the first mismatch
+    pub fn validate(&self, value: &Value) -> Result<(), SchemaViolation> {
+        let schema = serde_json::to_value(self).unwrap_or(Value::Null);
+        validate(&schema, value)
+    }
+}
+
+impl Default for Schema {
+    /// The schema `{}`, which accepts every value
+    fn default() -> Self {
+        Self::any()
+    }
+}
+
+impl From<bool> for Schema {
+    fn from(accepts: bool) -> Self {
+        Self::Bool(accepts)
+    }
+}
+
+impl From<SchemaObject> for Schema {
+    fn from(schema: SchemaObject) -> Self {
+        Self::Object(Box::new(schema))
+    }
+}
+
+impl<'de> Deserialize<'de> for Schema {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::Bool(accepts) => Ok(Self::Bool(accepts)),
+            Value::Object(map) => Ok(Self::Object(Box::new(SchemaObject::from_map(map)))),
+            other => Err(D::Error::custom(format!(
+                "expected a schema object or boolean, found {}",
+                type_name(&other)
+            ))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemaObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::from_map(Map::deserialize(deserializer)?))
+    }
+}
+
+impl SchemaObject {
+    /// Sort a schema's keywords into their fields, keeping whatever does not fit in `extra`
+    fn from_map(mut map: Map<String, Value>) -> Self {
+        Self {
+            reference: take(&mut map, "$ref"),
+            r#type: take(&mut map, "type"),
+            title: take(&mut map, "title"),
+            description: take(&mut map, "description"),
+            default: take(&mut map, "default"),
+            enum_values: take(&mut map, "enum"),
+            const_value: take(&mut map, "const"),
+            format: take(&mut map, "format"),
+            properties: take(&mut map, "properties"),
+            required: take(&mut map, "required"),
+            additional_properties: take(&mut map, "additionalProperties"),
+            items: take(&mut map, "items"),
+            min_items: take(&mut map, "minItems"),
+            max_items: take(&mut map, "maxItems"),
+            min_length: take(&mut map, "minLength"),
+            max_length: take(&mut map, "maxLength"),
+            minimum: take(&mut map, "minimum"),
+            maximum: take(&mut map, "maximum"),
+            any_of: take(&mut map, "anyOf"),
+            one_of: take(&mut map, "oneOf"),
+            all_of: take(&mut map, "allOf"),
+            not: take(&mut map, "not"),
+            defs: take(&mut map, "$defs"),
+            definitions: take(&mut map, "definitions"),
+            extra: map,
+        }
+    }
+}
+
+/// Remove `key` from `map` if its value reads as a `T`
+fn take<T: DeserializeOwned>(map: &mut Map<String, Value>, key: &str) -> Option<T> {
+    T::deserialize(map.get(key)?).ok().inspect(|_| {
+        map.remove(key);
+    })
+}
+
+/// A bound as JSON writes it: whole numbers without a fraction
+fn number(value: f64) -> Number {
+    if value.fract() == 0.0 && value.abs() < (1u64 << 53) as f64 {
+        Number::from(value as i64)
+    } else {
+        Number::from_f64(value).expect("schema bounds are finite")
+    }
+}
+
+/// Why a value does not match a schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaViolation {
+    /// JSON Pointer to the offending part of the value, empty for the value itself
+    pub path: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl std::error::Error for SchemaViolation {}
+
+/// Check `value` against `schema`, reporting the first mismatch
+///
+/// `$ref`s are resolved against `schema`, so `{"$ref": "#/definitions/Tool", ...}`
+/// checks a value against one definition of a schema document.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), SchemaViolation> {
+    Checker { root: schema }.check(schema, value, &mut String::new())
+}
+
+/// Checks values against a schema document, resolving `$ref`s within it
+struct Checker<'a> {
+    root: &'a Value,
+}
+
+impl Checker<'_> {
+    fn check(
+        &self,
+        schema: &Value,
+        value: &Value,
+        path: &mut String,
+    ) -> Result<(), SchemaViolation> {
+        let Value::Object(schema) = schema else {
+            // `true`, `{}` and anything else that is not an object accept every value
+            return match schema {
+                Value::Bool(false) => Err(violation(path, "no value is allowed here".to_string())),
+                _ => Ok(()),
+            };
+        };
+
+        if let Some(Value::String(reference)) = schema.get("$ref") {
+            let target = reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.root.pointer(pointer))
+                .ok_or_else(|| violation(path, format!("cannot resolve `{}`", reference)))?;
+            self.check(target, value, path)?;
+        }
+        if let Some(Value::Array(schemas)) = schema.get("allOf") {
+            for schema in schemas {
+                self.check(schema, value, path)?;
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+            if !schemas
+                .iter()
+                .any(|schema| self.check(schema, value, path).is_ok())
+            {
+                return Err(violation(
+                    path,
+                    format!("matches none of the {} allowed schemas", schemas.len()),
+                ));
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            if self.check(not, value, path).is_ok() {
+                return Err(violation(path, "matches a schema it must not".to_string()));
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+            let matches = schemas
+                .iter()
+                .filter(|schema| self.check(schema, value, path).is_ok())
+                .count();
+            if matches != 1 {
+                return Err(violation(
+                    path,
+                    format!(
+                        "matches {} of the {} schemas instead of one",
+                        matches,
+                        schemas.len()
+                    ),
+                ));
+            }
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let matches = match expected {
+                Value::String(name) => has_type(value, name),
+                Value::Array(names) => names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .any(|name| has_type(value, name)),
+                _ => true,
+            };
+            if !matches {
+                return Err(violation(
+                    path,
+                    format!("expected {}, found {}", expected, type_name(value)),
+                ));
+            }
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            if !allowed.contains(value) {
+                return Err(violation(
+                    path,
+                    format!("{} is not one of {:?}", value, allowed),
+                ));
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                return Err(violation(
+                    path,
+                    format!("expected {}, found {}", constant, value),
+                ));
+            }
+        }
+
+        match value {
+            Value::Object(object) => self.check_object(schema, object, path),
+            Value::Array(items) => {
+                bounds(
+                    schema,
+                    items.len() as f64,
+                    "minItems",
+                    "maxItems",
+                    "items",
+                    path,
+                )?;
+                if let Some(item_schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        nested(path, &index.to_string(), |path| {
+                            self.check(item_schema, item, path)
+                        })?;
+                    }
+                }
+                Ok(())
+            }
+            Value::String(string) => bounds(
+                schema,
+                string.chars().count() as f64,
+                "minLength",
+                "maxLength",
+                "characters",
+                path,
+            ),
+            Value::Number(number) => {
+                let number = number.as_f64().unwrap_or_default();
+                bounds(schema, number, "minimum", "maximum", "", path)
+            }
+            Value::Bool(_) | Value::Null => Ok(()),
+        }
+    }
+
+    fn check_object(
+        &self,
+        schema: &Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &mut String,
+    ) -> Result<(), SchemaViolation> {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    return Err(violation(path, format!("missing property `{}`", name)));
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, property) in object {
+            let property_schema = properties
+                .and_then(|properties| properties.get(name))
+                .or_else(|| schema.get("additionalProperties"));
+            if let Some(property_schema) = property_schema {
+                if property_schema == &Value::Bool(false) {
+                    return Err(violation(path, format!("unexpected property `{}`", name)));
+                }
+                nested(path, name, |path| {
+                    self.check(property_schema, property, path)
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check `value` against the schema's `min` and `max` keywords
+fn bounds(
+    schema: &Map<String, Value>,
+    value: f64,
+    min: &str,
+    max: &str,
+    unit: &str,
+    path: &str,
+) -> Result<(), SchemaViolation> {
+    let unit = if unit.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", unit)
+    };
+    if let Some(min) = schema.get(min).and_then(Value::as_f64) {
+        if value < min {
+            return Err(violation(
+                path,
+                format!("{}{} is below {}", value, unit, min),
+            ));
+        }
+    }
+    if let Some(max) = schema.get(max).and_then(Value::as_f64) {
+        if value > max {
+            return Err(violation(
+                path,
+                format!("{}{} is above {}", value, unit, max),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Run `f` with `segment` appended to `path`
+pub(super) fn nested<T>(path: &mut String, segment: &str, f: impl FnOnce(&mut String) -> T) -> T {
+    let length = path.len();
+    path.push('/');
+    path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    let result = f(path);
+    path.truncate(length);
+    result
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+        "number" => value.is_number(),
+        name => type_name(value) == name,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+pub(super) fn violation(path: &str, message: String) -> SchemaViolation {
+    SchemaViolation {
+        path: path.to_string(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_matching_values_pass() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "sum": { "type": "integer", "minimum": 0 },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "unit": { "enum": ["m", "km"] }
+            },
+            "required": ["sum"]
+        });
+        assert!(validate(&schema, &json!({ "sum": 3 })).is_ok());
+        assert!(validate(&schema, &json!({ "sum": 3.0, "tags": ["a"], "unit": "km" })).is_ok());
+        assert!(validate(&json!(true), &json!([1, "two"])).is_ok());
+    }
+
+    #[test]
+    fn test_mismatches_name_their_place() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "sum": { "type": "integer" },
+                "tags": { "type": "array", "items": { "type": "string", "maxLength": 3 } }
+            },
+            "required": ["sum"],
+            "additionalProperties": false
+        });
+        let error = |value| validate(&schema, &value).unwrap_err().to_string();
+
+        assert_eq!(error(json!({})), "missing property `sum`");
+        assert_eq!(
+            error(json!({ "sum": "3" })),
+            "/sum: expected \"integer\", found string"
+        );
+        assert_eq!(
+            error(json!({ "sum": 3, "tags": ["ok", "long"] })),
+            "/tags/1: 4 characters is above 3"
+        );
+        assert_eq!(
+            error(json!({ "sum": 3, "extra": true })),
+            "unexpected property `extra`"
+        );
+        assert_eq!(error(json!([])), "expected \"object\", found array");
+    }
+
+    #[test]
+    fn test_references_and_alternatives_are_followed() {
+        let schema = json!({
+            "$ref": "#/definitions/Content",
+            "definitions": {
+                "Content": { "anyOf": [{ "$ref": "#/definitions/Text" }, { "$ref": "#/definitions/Image" }] },
+                "Text": { "properties": { "type": { "const": "text" } }, "required": ["text"] },
+                "Image": { "properties": { "type": { "const": "image" } }, "required": ["data"] }
+            }
+        });
+        assert!(validate(&schema, &json!({ "type": "text", "text": "hi" })).is_ok());
+        assert!(validate(&schema, &json!({ "type": "image", "data": "aGk=" })).is_ok());
+        assert_eq!(
+            validate(&schema, &json!({ "type": "text" }))
+                .unwrap_err()
+                .to_string(),
+            "matches none of the 2 allowed schemas"
+        );
+
+        let one_of = json!({ "oneOf": [{ "type": "number" }, { "type": "integer" }] });
+        assert!(validate(&one_of, &json!(1.5)).is_ok());
+        assert!(validate(&one_of, &json!(1)).is_err());
+        assert!(validate(&json!({ "$ref": "#/missing" }), &json!(1)).is_err());
+    }
+
+    #[test]
+    fn test_schemas_round_trip_unchanged() {
+        let original = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "description": "A shipment",
+            "properties": {
+                "to": { "$ref": "#/$defs/address" },
+                "speed": { "enum": ["standard", "express"], "default": "standard" },
+                "weight": { "type": ["number", "null"], "minimum": 0, "maximum": 2.5 },
+                "tags": { "type": "array", "items": true, "uniqueItems": true },
+                "notes": { "oneOf": [{ "type": "string" }, { "const": null }] }
+            },
+            "required": ["to"],
+            "additionalProperties": false,
+            "$defs": {
+                "address": { "type": "object", "x-internal": { "since": 2 } }
+            },
+            "items": [{ "type": "string" }],
+            "title": null
+        });
+        let schema: Schema = serde_json::from_value(original.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&schema).unwrap(), original);
+
+        let object = schema.as_object().unwrap();
+        assert_eq!(object.additional_properties, Some(Schema::Bool(false)));
+        assert!(object.defs.as_ref().unwrap().contains_key("address"));
+        // Keywords whose values do not fit their fields are kept as they are
+        assert_eq!(object.extra["items"], json!([{ "type": "string" }]));
+        assert_eq!(object.extra["title"], Value::Null);
+    }
+
+    #[test]
+    fn test_builders_write_the_schema_they_read_as() {
+        let schema = Schema::object()
+            .title("Order")
+            .prop("id", Schema::string().format("uuid"))
+            .prop(
+                "lines",
+                Schema::array(Schema::reference("#/$defs/line")).min_items(1),
+            )
+            .prop(
+                "status",
+                Schema::string()
+                    .enum_values(["open", "paid"])
+                    .default_value("open"),
+            )
+            .prop("discount", Schema::number().minimum(0).maximum(0.5))
+            .required(["id"])
+            .required(["lines"])
+            .additional_properties(false)
+            .def(
+                "line",
+                Schema::one_of([Schema::string(), Schema::integer().constant(0)]),
+            )
+            .keyword("x-version", 2);
+
+        let written = json!({
+            "type": "object",
+            "title": "Order",
+            "properties": {
+                "id": { "type": "string", "format": "uuid" },
+                "lines": { "type": "array", "items": { "$ref": "#/$defs/line" }, "minItems": 1 },
+                "status": { "type": "string", "enum": ["open", "paid"], "default": "open" },
+                "discount": { "type": "number", "minimum": 0, "maximum": 0.5 }
+            },
+            "required": ["id", "lines"],
+            "additionalProperties": false,
+            "$defs": {
+                "line": { "oneOf": [{ "type": "string" }, { "type": "integer", "const": 0 }] }
+            },
+            "x-version": 2
+        });
+        assert_eq!(serde_json::to_value(&schema).unwrap(), written);
+        assert_eq!(serde_json::from_value::<Schema>(written).unwrap(), schema);
+
+        let order = json!({ "id": "a", "lines": ["x", 0], "discount": 0.25 });
+        assert!(schema.validate(&order).is_ok());
+        assert_eq!(
+            schema
+                .validate(&json!({ "id": "a", "lines": [] }))
+                .unwrap_err()
+                .to_string(),
+            "/lines: 0 items is below 1"
+        );
+        assert!(schema
+            .validate(&json!({ "id": "a", "lines": [1] }))
+            .is_err());
+    }
+
+    #[test]
+    fn test_keywords_on_boolean_schemas_keep_their_meaning() {
+        let rejecting = Schema::Bool(false).desc("Nothing fits");
+        assert!(rejecting.validate(&json!(1)).is_err());
+        assert_eq!(
+            serde_json::to_value(&rejecting).unwrap(),
+            json!({ "description": "Nothing fits", "not": {} })
+        );
+        assert!(Schema::Bool(true)
+            .desc("Anything")
+            .validate(&json!(1))
+            .is_ok());
+        assert!(serde_json::from_value::<Schema>(json!(3)).is_err());
+    }
+}
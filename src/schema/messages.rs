@@ -0,0 +1,435 @@
+//! Typed MCP requests and notifications
+//!
+//! Each enum covers the methods one side of a session may call, with the params each
+//! method takes. They convert to and from the untyped [`JSONRPCRequest`] and
+//! [`JSONRPCNotification`] that transports carry, and serialize as the `method` and
+//! `params` members of those messages. Methods this crate does not know are kept as
+//! received in an `Other` variant, so nothing is lost on the way through.
+//!
+//! ```rust
+//! use mcpr::schema::json_rpc::{JSONRPCRequest, RequestId};
+//! use mcpr::schema::messages::ClientRequest;
+//! use serde_json::json;
+//!
+//! let request = JSONRPCRequest::new(
+//!     RequestId::Number(1),
+//!     "tools/call".to_string(),
+//!     Some(json!({ "name": "search", "arguments": { "query": "rust" } })),
+//! );
+//! match ClientRequest::try_from(&request).unwrap() {
+//!     ClientRequest::CallTool(params) => assert_eq!(params.name, "search"),
+//!     other => panic!("Unexpected request: {:?}", other),
+//! }
+//! ```
+
+use serde::de::Error as _;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use super::client::{
+    CallToolParams, CancelledParams, CompleteParams, GetPromptParams, InitializeParams,
+    PaginatedParams, ProgressParams, ReadResourceParams, SetLevelParams, SubscribeParams,
+    UnsubscribeParams,
+};
+use super::json_rpc::{JSONRPCNotification, JSONRPCRequest, RequestId};
+use super::server::{
+    CreateMessageParams, ElicitRequestParams, LoggingMessageParams, ResourceUpdatedParams,
+};
+
+/// The `method` and `params` of a message, before they are given a type
+#[derive(Deserialize)]
+struct Parts {
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+/// Params as sent on the wire; params that serialize to `null` are left out
+fn to_params<T: Serialize>(params: T) -> Option<Value> {
+    // Schema types only have string map keys, so serializing them cannot fail
+    match serde_json::to_value(params).expect("MCP params serialize to JSON") {
+        Value::Null => None,
+        params => Some(params),
+    }
+}
+
+/// The params types of typed methods
+trait Params: Serialize {
+    /// Whether the params serialize to `null`, and are left out of the message
+    fn is_absent(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Params> Params for Option<T> {
+    fn is_absent(&self) -> bool {
+        self.is_none()
+    }
+}
+
+impl Params for CallToolParams {}
+impl Params for CancelledParams {}
+impl Params for CompleteParams {}
+impl Params for CreateMessageParams {}
+impl Params for ElicitRequestParams {}
+impl Params for GetPromptParams {}
+impl Params for InitializeParams {}
+impl Params for LoggingMessageParams {}
+impl Params for PaginatedParams {}
+impl Params for ProgressParams {}
+impl Params for ReadResourceParams {}
+impl Params for ResourceUpdatedParams {}
+impl Params for SetLevelParams {}
+impl Params for SubscribeParams {}
+impl Params for UnsubscribeParams {}
+
+/// Declares an enum of methods, one variant per method with the type of its params
+macro_rules! methods {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident $(($params:ty))? = $method:literal,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone)]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant $(($params))?,
+            )*
+            /// A method this crate does not know, kept as received
+            Other {
+                method: String,
+                params: Option<Value>,
+            },
+        }
+
+        impl $name {
+            /// The method's name on the wire
+            pub fn method(&self) -> &str {
+                match self {
+                    $(methods!(@pattern $variant $(($params))?) => $method,)*
+                    Self::Other { method, .. } => method,
+                }
+            }
+
+            /// Give `params` the type `method` takes
+            ///
+            /// Fails if the params do not fit the method; unknown methods never fail.
+            pub fn from_parts(method: &str, params: Option<Value>) -> Result<Self, serde_json::Error> {
+                Ok(match method {
+                    $($method => methods!(@parse params $variant $(($params))?),)*
+                    _ => Self::Other {
+                        method: method.to_string(),
+                        params,
+                    },
+                })
+            }
+
+            /// The params of `method` as they read once given its params type, or `None`
+            /// for a method this enum does not know
+            ///
+            /// Where the params do not fit, the error tells the path to the value at fault.
+            pub(crate) fn typed_params(
+                method: &str,
+                params: Option<&Value>,
+            ) -> Option<Result<Option<Value>, serde_path_to_error::Error<serde_json::Error>>> {
+                match method {
+                    $($method => Some(methods!(@typed params $(($params))?)),)*
+                    _ => None,
+                }
+            }
+
+            /// The method's name and params as sent on the wire
+            pub fn into_parts(self) -> (String, Option<Value>) {
+                match self {
+                    $(methods!(@bind params $variant $(($params))?) => {
+                        ($method.to_string(), methods!(@params params $(($params))?))
+                    })*
+                    Self::Other { method, params } => (method, params),
+                }
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(None)?;
+                match self {
+                    $(methods!(@bind params $variant $(($params))?) => {
+                        map.serialize_entry("method", $method)?;
+                        methods!(@entry map params $(($params))?);
+                    })*
+                    Self::Other { method, params } => {
+                        map.serialize_entry("method", method)?;
+                        if let Some(params) = params {
+                            map.serialize_entry("params", params)?;
+                        }
+                    }
+                }
+                map.end()
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let parts = Parts::deserialize(deserializer)?;
+                Self::from_parts(&parts.method, parts.params).map_err(D::Error::custom)
+            }
+        }
+    };
+    (@pattern $variant:ident ($params:ty)) => { Self::$variant(_) };
+    (@pattern $variant:ident) => { Self::$variant };
+    (@bind $value:ident $variant:ident ($params:ty)) => { Self::$variant($value) };
+    (@bind $value:ident $variant:ident) => { Self::$variant };
+    (@params $value:ident ($params:ty)) => { to_params($value) };
+    (@params $value:ident) => { None };
+    (@entry $map:ident $value:ident ($params:ty)) => {
+        if !Params::is_absent($value) {
+            $map.serialize_entry("params", $value)?;
+        }
+    };
+    (@entry $map:ident $value:ident) => {};
+    (@parse $value:ident $variant:ident ($params:ty)) => {
+        Self::$variant(serde_json::from_value($value.unwrap_or(Value::Null))?)
+    };
+    (@parse $value:ident $variant:ident) => { Self::$variant };
+    (@typed $value:ident ($params:ty)) => {
+        serde_path_to_error::deserialize::<_, $params>($value.unwrap_or(&Value::Null)).map(to_params)
+    };
+    (@typed $value:ident) => { Ok(None) };
+}
+
+/// Converts an enum of methods to and from JSON-RPC requests
+macro_rules! requests {
+    ($name:ident) => {
+        impl $name {
+            /// A JSON-RPC request calling this method
+            pub fn into_request(self, id: RequestId) -> JSONRPCRequest {
+                let (method, params) = self.into_parts();
+                JSONRPCRequest::new(id, method, params)
+            }
+        }
+
+        impl TryFrom<&JSONRPCRequest> for $name {
+            type Error = serde_json::Error;
+
+            fn try_from(request: &JSONRPCRequest) -> Result<Self, Self::Error> {
+                Self::from_parts(&request.method, request.params.clone())
+            }
+        }
+    };
+}
+
+/// Converts an enum of methods to and from JSON-RPC notifications
+macro_rules! notifications {
+    ($name:ident) => {
+        impl $name {
+            /// A JSON-RPC notification of this method
+            pub fn into_notification(self) -> JSONRPCNotification {
+                let (method, params) = self.into_parts();
+                JSONRPCNotification::new(method, params)
+            }
+        }
+
+        impl TryFrom<&JSONRPCNotification> for $name {
+            type Error = serde_json::Error;
+
+            fn try_from(notification: &JSONRPCNotification) -> Result<Self, Self::Error> {
+                Self::from_parts(&notification.method, notification.params.clone())
+            }
+        }
+    };
+}
+
+methods! {
+    /// A request from the client to the server
+    pub enum ClientRequest {
+        /// Sent when the client first connects, to negotiate the session
+        Initialize(InitializeParams) = "initialize",
+        /// Checks that the server is still alive
+        Ping = "ping",
+        /// Lists the resources the server has
+        ListResources(Option<PaginatedParams>) = "resources/list",
+        /// Lists the resource templates the server has
+        ListResourceTemplates(Option<PaginatedParams>) = "resources/templates/list",
+        /// Reads a resource
+        ReadResource(ReadResourceParams) = "resources/read",
+        /// Asks for `notifications/resources/updated` about a resource
+        Subscribe(SubscribeParams) = "resources/subscribe",
+        /// Stops `notifications/resources/updated` about a resource
+        Unsubscribe(UnsubscribeParams) = "resources/unsubscribe",
+        /// Lists the prompts and prompt templates the server has
+        ListPrompts(Option<PaginatedParams>) = "prompts/list",
+        /// Gets a prompt
+        GetPrompt(GetPromptParams) = "prompts/get",
+        /// Lists the tools the server has
+        ListTools(Option<PaginatedParams>) = "tools/list",
+        /// Calls a tool
+        CallTool(CallToolParams) = "tools/call",
+        /// Sets the minimum level of log messages the server sends
+        SetLevel(SetLevelParams) = "logging/setLevel",
+        /// Asks for completion options of a prompt or resource argument
+        Complete(CompleteParams) = "completion/complete",
+        /// Ends the session; an mcpr extension
+        Shutdown = "shutdown",
+    }
+}
+requests!(ClientRequest);
+
+methods! {
+    /// A request from the server to the client
+    pub enum ServerRequest {
+        /// Checks that the client is still alive
+        Ping = "ping",
+        /// Asks the client to sample an LLM
+        CreateMessage(CreateMessageParams) = "sampling/createMessage",
+        /// Lists the client's roots
+        ListRoots = "roots/list",
+        /// Asks the user for information via the client. Since 2025-06-18.
+        Elicit(ElicitRequestParams) = "elicitation/create",
+    }
+}
+requests!(ServerRequest);
+
+methods! {
+    /// A notification from the client to the server
+    pub enum ClientNotification {
+        /// Cancels a request the client sent earlier
+        Cancelled(CancelledParams) = "notifications/cancelled",
+        /// Reports progress of a request the server sent
+        Progress(ProgressParams) = "notifications/progress",
+        /// Sent once initialization has finished
+        Initialized = "notifications/initialized",
+        /// The client's list of roots has changed
+        RootsListChanged = "notifications/roots/list_changed",
+    }
+}
+notifications!(ClientNotification);
+
+methods! {
+    /// A notification from the server to the client
+    pub enum ServerNotification {
+        /// Cancels a request the server sent earlier
+        Cancelled(CancelledParams) = "notifications/cancelled",
+        /// Reports progress of a request the client sent
+        Progress(ProgressParams) = "notifications/progress",
+        /// The list of resources has changed
+        ResourceListChanged = "notifications/resources/list_changed",
+        /// A subscribed resource has changed
+        ResourceUpdated(ResourceUpdatedParams) = "notifications/resources/updated",
+        /// The list of prompts has changed
+        PromptListChanged = "notifications/prompts/list_changed",
+        /// The list of tools has changed
+        ToolListChanged = "notifications/tools/list_changed",
+        /// A log message
+        LoggingMessage(LoggingMessageParams) = "notifications/message",
+    }
+}
+notifications!(ServerNotification);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::common::LoggingLevel;
+    use serde_json::json;
+
+    #[test]
+    fn test_requests_round_trip() {
+        let request = JSONRPCRequest::new(
+            RequestId::Number(7),
+            "logging/setLevel".to_string(),
+            Some(json!({ "level": "warning" })),
+        );
+        let typed = ClientRequest::try_from(&request).unwrap();
+        assert!(matches!(
+            &typed,
+            ClientRequest::SetLevel(SetLevelParams {
+                level: LoggingLevel::Warning,
+                ..
+            })
+        ));
+        assert_eq!(typed.method(), "logging/setLevel");
+
+        let back = typed.into_request(RequestId::Number(7));
+        assert_eq!(back.method, request.method);
+        assert_eq!(back.params, request.params);
+    }
+
+    #[test]
+    fn test_params_are_optional_where_the_method_allows() {
+        let list = ClientRequest::from_parts("tools/list", None).unwrap();
+        assert!(matches!(list, ClientRequest::ListTools(None)));
+        assert_eq!(list.into_parts(), ("tools/list".to_string(), None));
+
+        // Methods without params ignore whatever is sent, such as `_meta`
+        let ping = ServerRequest::from_parts("ping", Some(json!({ "_meta": {} }))).unwrap();
+        assert!(matches!(ping, ServerRequest::Ping));
+        assert_eq!(ping.into_parts(), ("ping".to_string(), None));
+
+        // Methods that need params fail without them
+        assert!(ClientRequest::from_parts("tools/call", None).is_err());
+        assert!(ClientRequest::from_parts("tools/call", Some(json!({ "name": 3 }))).is_err());
+    }
+
+    #[test]
+    fn test_unknown_methods_are_kept() {
+        let params = Some(json!({ "anything": [1, 2] }));
+        let typed = ClientNotification::from_parts("vendor/thing", params.clone()).unwrap();
+        assert_eq!(typed.method(), "vendor/thing");
+        let notification = typed.into_notification();
+        assert_eq!(notification.method, "vendor/thing");
+        assert_eq!(notification.params, params);
+    }
+
+    #[test]
+    fn test_serde_uses_method_and_params() {
+        let notification: ServerNotification = serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/resources/updated",
+            "params": { "uri": "file:///notes.txt" }
+        }))
+        .unwrap();
+        match &notification {
+            ServerNotification::ResourceUpdated(params) => {
+                assert_eq!(params.uri, "file:///notes.txt")
+            }
+            other => panic!("Unexpected notification: {:?}", other),
+        }
+        assert_eq!(
+            serde_json::to_value(&notification).unwrap(),
+            json!({
+                "method": "notifications/resources/updated",
+                "params": { "uri": "file:///notes.txt" }
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(ServerNotification::ToolListChanged).unwrap(),
+            json!({ "method": "notifications/tools/list_changed" })
+        );
+
+        // Absent params are left out, as they are by `into_parts`
+        for request in [
+            ClientRequest::ListTools(None),
+            ClientRequest::ListTools(Some(PaginatedParams {
+                cursor: None,
+                meta: None,
+            })),
+            ClientRequest::Other {
+                method: "vendor/thing".to_string(),
+                params: None,
+            },
+        ] {
+            let (method, params) = request.clone().into_parts();
+            let mut parts = json!({ "method": method });
+            if let Some(params) = params {
+                parts["params"] = params;
+            }
+            assert_eq!(serde_json::to_value(&request).unwrap(), parts);
+        }
+    }
+}
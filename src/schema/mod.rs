@@ -3,10 +3,17 @@
 pub mod client;
 pub mod common;
 pub mod json_rpc;
+pub mod json_schema;
+pub mod messages;
+pub mod raw;
 pub mod server;
+pub mod validation;
+pub mod version;
 
 // Re-export all schema types
 pub use client::*;
 pub use common::*;
 pub use json_rpc::*;
+pub use messages::*;
 pub use server::*;
+pub use version::*;
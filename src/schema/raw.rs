@@ -0,0 +1,480 @@
+//! JSON-RPC messages whose params and results are left as JSON text
+//!
+//! Reading a [`JSONRPCMessage`] builds a [`Value`] tree of the whole message, params and
+//! results included. A [`RawMessage`] reads only the envelope: its params or result stay
+//! a [`RawValue`], the JSON text they arrived as, until something needs them typed. A
+//! multi-megabyte tool result then goes from the wire into its Rust type in one pass, and
+//! a message that is only passed on is never parsed beyond its envelope.
+//!
+//! Envelopes are checked as leniently as [`JSONRPCMessage`]'s, so the two read exactly
+//! the same messages.
+//!
+//! ```rust
+//! use mcpr::schema::raw::RawMessage;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Report {
+//!     rows: Vec<u32>,
+//! }
+//!
+//! let message = RawMessage::parse(r#"{"jsonrpc":"2.0","id":7,"result":{"rows":[1,2,3]}}"#)?;
+//! let RawMessage::Response(response) = message else {
+//!     panic!("expected a response");
+//! };
+//! assert_eq!(response.result.get(), r#"{"rows":[1,2,3]}"#);
+//!
+//! let report: Report = response.result_as()?;
+//! assert_eq!(report.rows, [1, 2, 3]);
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+
+use super::json_rpc::{
+    JSONRPCError, JSONRPCMessage, JSONRPCNotification, JSONRPCRequest, JSONRPCResponse, RequestId,
+};
+use super::json_schema::{violation, SchemaViolation};
+use super::validation::{check_envelope, Members, MessageKind};
+use crate::constants::JSONRPC_VERSION;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::Serialize;
+use serde_json::value::{to_raw_value, RawValue};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// A JSON-RPC message with its params or result kept as JSON text
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum RawMessage {
+    Request(RawRequest),
+    Notification(RawNotification),
+    Response(RawResponse),
+    Error(JSONRPCError),
+    /// Several messages sent together as one JSON array
+    Batch(Vec<RawMessage>),
+}
+
+/// A request whose params have not been parsed
+#[derive(Debug, Clone, Serialize)]
+pub struct RawRequest {
+    pub jsonrpc: String,
+    pub id: RequestId,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Box<RawValue>>,
+}
+
+/// A notification whose params have not been parsed
+#[derive(Debug, Clone, Serialize)]
+pub struct RawNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Box<RawValue>>,
+}
+
+/// A successful response whose result has not been parsed
+#[derive(Debug, Clone, Serialize)]
+pub struct RawResponse {
+    pub jsonrpc: String,
+    pub id: RequestId,
+    pub result: Box<RawValue>,
+}
+
+impl RawMessage {
+    /// Read a message, or a batch of them, from JSON text
+    ///
+    /// Fails like deserializing a [`JSONRPCMessage`] does: with a syntax error for text
+    /// that is not JSON, and a data error for JSON that is no JSON-RPC message.
+    pub fn parse(text: &str) -> Result<Self, serde_json::Error> {
+        let envelope = Envelope::read(serde_json::from_str(text)?, false)?;
+        check_envelope(&envelope.outline()?).map_err(serde_json::Error::custom)?;
+        envelope.into_message().map_err(serde_json::Error::custom)
+    }
+}
+
+impl RawRequest {
+    /// Create a request with params that are already serialized
+    pub fn new(id: RequestId, method: String, params: Option<Box<RawValue>>) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            method,
+            params,
+        }
+    }
+
+    /// Read the params as a `T`, reading absent params as `null`
+    pub fn params_as<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        read_params(self.params.as_deref())
+    }
+}
+
+impl RawNotification {
+    /// Create a notification with params that are already serialized
+    pub fn new(method: String, params: Option<Box<RawValue>>) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method,
+            params,
+        }
+    }
+
+    /// Read the params as a `T`, reading absent params as `null`
+    pub fn params_as<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        read_params(self.params.as_deref())
+    }
+}
+
+impl RawResponse {
+    /// Create a response with a result that is already serialized
+    pub fn new(id: RequestId, result: Box<RawValue>) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result,
+        }
+    }
+
+    /// Read the result as a `T`
+    pub fn result_as<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(self.result.get())
+    }
+}
+
+fn read_params<T: DeserializeOwned>(params: Option<&RawValue>) -> Result<T, serde_json::Error> {
+    serde_json::from_str(params.map_or("null", RawValue::get))
+}
+
+/// Parse JSON text that is known to be valid, such as a [`RawValue`]'s
+fn parse_value(raw: Option<Box<RawValue>>) -> Result<Option<Value>, serde_json::Error> {
+    raw.map(|raw| serde_json::from_str(raw.get())).transpose()
+}
+
+/// Serialize a value for a raw message
+fn raw_value(value: Option<Value>) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+    value.map(|value| to_raw_value(&value)).transpose()
+}
+
+impl TryFrom<RawRequest> for JSONRPCRequest {
+    type Error = serde_json::Error;
+
+    fn try_from(request: RawRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            jsonrpc: request.jsonrpc,
+            id: request.id,
+            method: request.method,
+            params: parse_value(request.params)?,
+        })
+    }
+}
+
+impl TryFrom<RawNotification> for JSONRPCNotification {
+    type Error = serde_json::Error;
+
+    fn try_from(notification: RawNotification) -> Result<Self, Self::Error> {
+        Ok(Self {
+            jsonrpc: notification.jsonrpc,
+            method: notification.method,
+            params: parse_value(notification.params)?,
+        })
+    }
+}
+
+impl TryFrom<RawResponse> for JSONRPCResponse {
+    type Error = serde_json::Error;
+
+    fn try_from(response: RawResponse) -> Result<Self, Self::Error> {
+        Ok(Self {
+            jsonrpc: response.jsonrpc,
+            id: response.id,
+            result: serde_json::from_str(response.result.get())?,
+        })
+    }
+}
+
+impl TryFrom<RawMessage> for JSONRPCMessage {
+    type Error = serde_json::Error;
+
+    fn try_from(message: RawMessage) -> Result<Self, serde_json::Error> {
+        Ok(match message {
+            RawMessage::Request(request) => Self::Request(request.try_into()?),
+            RawMessage::Notification(notification) => Self::Notification(notification.try_into()?),
+            RawMessage::Response(response) => Self::Response(response.try_into()?),
+            RawMessage::Error(error) => Self::Error(error),
+            RawMessage::Batch(messages) => Self::Batch(
+                messages
+                    .into_iter()
+                    .map(Self::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+impl TryFrom<JSONRPCMessage> for RawMessage {
+    type Error = serde_json::Error;
+
+    fn try_from(message: JSONRPCMessage) -> Result<Self, serde_json::Error> {
+        Ok(match message {
+            JSONRPCMessage::Request(request) => Self::Request(RawRequest {
+                jsonrpc: request.jsonrpc,
+                id: request.id,
+                method: request.method,
+                params: raw_value(request.params)?,
+            }),
+            JSONRPCMessage::Notification(notification) => Self::Notification(RawNotification {
+                jsonrpc: notification.jsonrpc,
+                method: notification.method,
+                params: raw_value(notification.params)?,
+            }),
+            JSONRPCMessage::Response(response) => Self::Response(RawResponse {
+                jsonrpc: response.jsonrpc,
+                id: response.id,
+                result: to_raw_value(&response.result)?,
+            }),
+            JSONRPCMessage::Error(error) => Self::Error(error),
+            JSONRPCMessage::Batch(messages) => Self::Batch(
+                messages
+                    .into_iter()
+                    .map(Self::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+/// A message split into its members, each still JSON text borrowed from the input
+enum Envelope<'a> {
+    Message(BTreeMap<String, &'a RawValue>),
+    Batch(Vec<Envelope<'a>>),
+    /// Anything else, which the envelope check rejects
+    Other(&'a RawValue),
+}
+
+impl<'a> Envelope<'a> {
+    fn read(value: &'a RawValue, nested: bool) -> Result<Self, serde_json::Error> {
+        match value.get().as_bytes().first() {
+            Some(b'{') => Ok(Self::Message(serde_json::from_str(value.get())?)),
+            Some(b'[') if !nested => serde_json::from_str::<Vec<&RawValue>>(value.get())?
+                .into_iter()
+                .map(|message| Self::read(message, true))
+                .collect::<Result<_, _>>()
+                .map(Self::Batch),
+            _ => Ok(Self::Other(value)),
+        }
+    }
+
+    /// The envelope as a [`Value`], with params, results and unknown members reduced to
+    /// their kind of value, which is all the envelope check looks at
+    fn outline(&self) -> Result<Value, serde_json::Error> {
+        match self {
+            Self::Message(members) => members
+                .iter()
+                .map(|(name, value)| {
+                    let value = match name.as_str() {
+                        "jsonrpc" | "id" | "method" | "error" => serde_json::from_str(value.get())?,
+                        _ => outline(value)?,
+                    };
+                    Ok((name.clone(), value))
+                })
+                .collect::<Result<Map<_, _>, _>>()
+                .map(Value::Object),
+            Self::Batch(messages) => messages
+                .iter()
+                .map(Self::outline)
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            Self::Other(value) => outline(value),
+        }
+    }
+
+    /// Read an envelope that passed the envelope check
+    fn into_message(self) -> Result<RawMessage, SchemaViolation> {
+        let mut members = match self {
+            Self::Message(members) => members,
+            Self::Batch(messages) => {
+                return messages
+                    .into_iter()
+                    .map(Self::into_message)
+                    .collect::<Result<_, _>>()
+                    .map(RawMessage::Batch)
+            }
+            Self::Other(value) => {
+                return Err(violation(
+                    "",
+                    format!("expected a JSON-RPC message, found {}", value),
+                ))
+            }
+        };
+
+        let kind = MessageKind::of(&mut members);
+        let params = members.get("params").map(|&params| params.to_owned());
+        Ok(match kind {
+            MessageKind::Request => RawMessage::Request(RawRequest {
+                jsonrpc: member(&members, "jsonrpc")?,
+                id: member(&members, "id")?,
+                method: member(&members, "method")?,
+                params,
+            }),
+            MessageKind::Notification => RawMessage::Notification(RawNotification {
+                jsonrpc: member(&members, "jsonrpc")?,
+                method: member(&members, "method")?,
+                params,
+            }),
+            MessageKind::Response => RawMessage::Response(RawResponse {
+                jsonrpc: member(&members, "jsonrpc")?,
+                id: member(&members, "id")?,
+                result: members["result"].to_owned(),
+            }),
+            MessageKind::Error => RawMessage::Error(JSONRPCError {
+                jsonrpc: member(&members, "jsonrpc")?,
+                id: member::<Option<RequestId>>(&members, "id")?,
+                error: member(&members, "error")?,
+            }),
+        })
+    }
+}
+
+impl Members for BTreeMap<String, &RawValue> {
+    fn has(&self, name: &str) -> bool {
+        self.contains_key(name)
+    }
+
+    fn is_null(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|value| value.get() == "null")
+    }
+
+    fn remove(&mut self, name: &str) {
+        self.remove(name);
+    }
+}
+
+/// What kind of value `value` is: an empty object or array for those, else the value
+fn outline(value: &RawValue) -> Result<Value, serde_json::Error> {
+    match value.get().as_bytes().first() {
+        Some(b'{') => Ok(Value::Object(Map::new())),
+        Some(b'[') => Ok(Value::Array(Vec::new())),
+        _ => serde_json::from_str(value.get()),
+    }
+}
+
+/// Read the member `name` of a message
+fn member<T: DeserializeOwned>(
+    members: &BTreeMap<String, &RawValue>,
+    name: &str,
+) -> Result<T, SchemaViolation> {
+    let value = members.get(name).map_or("null", |value| value.get());
+    serde_json::from_str(value).map_err(|e| match members.contains_key(name) {
+        true => violation("", format!("{}: {}", name, e)),
+        false => violation("", format!("missing field `{}`", name)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Parse `text` both ways, checking they agree
+    fn parse(text: &str) -> Result<RawMessage, serde_json::Error> {
+        let typed = serde_json::from_str::<JSONRPCMessage>(text);
+        let raw = RawMessage::parse(text);
+        match (&typed, &raw) {
+            (Ok(typed), Ok(raw)) => assert_eq!(
+                serde_json::to_value(typed).unwrap(),
+                serde_json::to_value(raw).unwrap()
+            ),
+            (Err(typed), Err(raw)) => assert_eq!(typed.classify(), raw.classify()),
+            _ => panic!("{:?} and {:?} disagree about {}", typed, raw, text),
+        }
+        raw
+    }
+
+    #[test]
+    fn test_params_and_results_keep_their_text() {
+        let text = r#"{"jsonrpc":"2.0","id":"a","method":"tools/call","params":{ "name" : "x" }}"#;
+        let RawMessage::Request(request) = parse(text).unwrap() else {
+            panic!("expected a request");
+        };
+        assert_eq!(request.id, RequestId::String("a".to_string()));
+        assert_eq!(request.params.unwrap().get(), r#"{ "name" : "x" }"#);
+
+        let text = r#"{"result":[1, 2],"id":3,"jsonrpc":"2.0"}"#;
+        let RawMessage::Response(response) = parse(text).unwrap() else {
+            panic!("expected a response");
+        };
+        assert_eq!(response.result.get(), "[1, 2]");
+        assert_eq!(response.result_as::<Vec<u8>>().unwrap(), [1, 2]);
+
+        // What is written is what was read
+        let message = RawMessage::Response(response);
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            r#"{"jsonrpc":"2.0","id":3,"result":[1, 2]}"#
+        );
+    }
+
+    #[test]
+    fn test_envelopes_are_read_like_typed_messages() {
+        // Lenient nulls
+        assert!(matches!(
+            parse(r#"{"jsonrpc":"2.0","id":null,"method":"ping","params":null}"#).unwrap(),
+            RawMessage::Notification(RawNotification { params: None, .. })
+        ));
+        assert!(matches!(
+            parse(r#"{"jsonrpc":"2.0","id":1,"result":{},"error":null}"#).unwrap(),
+            RawMessage::Response(_)
+        ));
+        assert!(matches!(
+            parse(r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"x"}}"#).unwrap(),
+            RawMessage::Error(JSONRPCError { id: None, .. })
+        ));
+        let RawMessage::Batch(messages) =
+            parse(r#"[{"jsonrpc":"2.0","method":"a"},{"jsonrpc":"2.0","id":1,"method":"b"}]"#)
+                .unwrap()
+        else {
+            panic!("expected a batch");
+        };
+        assert_eq!(messages.len(), 2);
+
+        // Syntax errors stay syntax errors, and the rest are data errors
+        for text in [
+            r#"{"jsonrpc":"2.0","id":1,"method":"#,
+            r#"{"jsonrpc":"2.0","id":1,"result":{},"error":{"code":1,"message":"x"}}"#,
+            r#"{"jsonrpc":"2.0","id":1.5,"method":"ping"}"#,
+            r#"{"jsonrpc":"2.0","method":"ping","params":3}"#,
+            r#"{"jsonrpc":"2.0","id":1}"#,
+            r#"{"id":1,"method":"ping"}"#,
+            r#"[]"#,
+            r#"[[{"jsonrpc":"2.0","method":"ping"}]]"#,
+            r#""ping""#,
+        ] {
+            assert!(parse(text).is_err(), "{} was read", text);
+        }
+    }
+
+    #[test]
+    fn test_conversions_round_trip() {
+        let message = JSONRPCMessage::Batch(vec![
+            JSONRPCMessage::Request(JSONRPCRequest::new(
+                RequestId::Number(1),
+                "tools/call".to_string(),
+                Some(json!({ "name": "echo", "arguments": { "text": "hi" } })),
+            )),
+            JSONRPCMessage::Response(JSONRPCResponse::new(
+                RequestId::Number(2),
+                json!({ "content": [] }),
+            )),
+        ]);
+        let raw = RawMessage::try_from(message.clone()).unwrap();
+        assert_eq!(
+            serde_json::to_string(&raw).unwrap(),
+            serde_json::to_string(&message).unwrap()
+        );
+        let back = JSONRPCMessage::try_from(raw).unwrap();
+        assert_eq!(
+            serde_json::to_value(back).unwrap(),
+            serde_json::to_value(message).unwrap()
+        );
+    }
+}
@@ -5,7 +5,8 @@ use serde_json::Value;
 use std::collections::HashMap;
 
 use super::common::{
-    EmbeddedResource, ImageContent, Implementation, LoggingLevel, Role, TextContent,
+    deserialize_by_type, AudioContent, EmbeddedResource, ImageContent, Implementation,
+    LoggingLevel, Meta, ResourceLink, Role, TextContent,
 };
 
 /// Server capabilities
@@ -31,6 +32,10 @@ pub struct ServerCapabilities {
     /// Present if the server offers any tools to call.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<ToolsCapability>,
+
+    /// Present if the server offers argument completions. Since 2025-03-26.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completions: Option<Value>,
 }
 
 /// Prompts capability
@@ -80,13 +85,9 @@ pub struct InitializeResult {
     /// Instructions describing how to use the server and its features.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub instructions: Option<String>,
-}
 
-/// A notification from the server to the client, informing it that a resource has changed.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ResourceUpdatedNotification {
-    pub method: String,
-    pub params: ResourceUpdatedParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for resource updated notification
@@ -94,31 +95,9 @@ pub struct ResourceUpdatedNotification {
 pub struct ResourceUpdatedParams {
     /// The URI of the resource that has been updated.
     pub uri: String,
-}
-
-/// An optional notification from the server to the client, informing it that the list of resources it can read from has changed.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ResourceListChangedNotification {
-    pub method: String,
-}
 
-/// An optional notification from the server to the client, informing it that the list of prompts it offers has changed.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PromptListChangedNotification {
-    pub method: String,
-}
-
-/// An optional notification from the server to the client, informing it that the list of tools it offers has changed.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ToolListChangedNotification {
-    pub method: String,
-}
-
-/// Notification of a log message passed from server to client.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LoggingMessageNotification {
-    pub method: String,
-    pub params: LoggingMessageParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for logging message notification
@@ -133,31 +112,28 @@ pub struct LoggingMessageParams {
 
     /// The data to be logged, such as a string message or an object.
     pub data: Value,
-}
 
-/// A request from the server to sample an LLM via the client.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CreateMessageRequest {
-    pub method: String,
-    pub params: CreateMessageParams,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Parameters for create message request
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CreateMessageParams {
     /// The messages to sample from
     pub messages: Vec<SamplingMessage>,
 
     /// The server's preferences for which model to select.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "model_preferences", skip_serializing_if = "Option::is_none")]
     pub model_preferences: Option<ModelPreferences>,
 
     /// An optional system prompt the server wants to use for sampling.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "system_prompt", skip_serializing_if = "Option::is_none")]
     pub system_prompt: Option<String>,
 
     /// A request to include context from one or more MCP servers.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "include_context", skip_serializing_if = "Option::is_none")]
     pub include_context: Option<IncludeContext>,
 
     /// Temperature for sampling
@@ -165,15 +141,19 @@ pub struct CreateMessageParams {
     pub temperature: Option<f32>,
 
     /// The maximum number of tokens to sample.
+    #[serde(alias = "max_tokens")]
     pub max_tokens: u32,
 
     /// Stop sequences for sampling
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "stop_sequences", skip_serializing_if = "Option::is_none")]
     pub stop_sequences: Option<Vec<String>>,
 
     /// Optional metadata to pass through to the LLM provider.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<Value>,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Include context options
@@ -187,30 +167,41 @@ pub enum IncludeContext {
 
 /// The client's response to a sampling/create_message request from the server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CreateMessageResult {
     /// The role of the message
     pub role: Role,
 
     /// The content of the message
-    #[serde(flatten)]
     pub content: MessageContent,
 
     /// The name of the model that generated the message.
     pub model: String,
 
     /// The reason why sampling stopped, if known.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "stop_reason", skip_serializing_if = "Option::is_none")]
     pub stop_reason: Option<StopReason>,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Message content
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum MessageContent {
     Text(TextContent),
     Image(ImageContent),
+    /// Since 2025-03-26.
+    Audio(AudioContent),
 }
 
+deserialize_by_type!(MessageContent {
+    "text" => Text,
+    "image" => Image,
+    "audio" => Audio,
+});
+
 /// Stop reason
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -235,27 +226,30 @@ pub enum KnownStopReason {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SamplingMessage {
     pub role: Role,
-    #[serde(flatten)]
     pub content: MessageContent,
 }
 
 /// The server's preferences for model selection, requested of the client during sampling.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ModelPreferences {
     /// Optional hints to use for model selection.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hints: Option<Vec<ModelHint>>,
 
     /// How much to prioritize cost when selecting a model.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "cost_priority", skip_serializing_if = "Option::is_none")]
     pub cost_priority: Option<f32>,
 
     /// How much to prioritize sampling speed (latency) when selecting a model.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "speed_priority", skip_serializing_if = "Option::is_none")]
     pub speed_priority: Option<f32>,
 
     /// How much to prioritize intelligence and capabilities when selecting a model.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        alias = "intelligence_priority",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub intelligence_priority: Option<f32>,
 }
 
@@ -271,10 +265,14 @@ pub struct ModelHint {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompleteResult {
     pub completion: CompletionInfo,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Completion information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CompletionInfo {
     /// An array of completion values.
     pub values: Vec<String>,
@@ -284,33 +282,99 @@ pub struct CompletionInfo {
     pub total: Option<u32>,
 
     /// Indicates whether there are additional completion options beyond those provided.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "has_more", skip_serializing_if = "Option::is_none")]
     pub has_more: Option<bool>,
 }
 
-/// Sent from the server to request a list of root URIs from the client.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListRootsRequest {
-    pub method: String,
-}
-
 /// The server's response to a tool call.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallToolResult {
     pub content: Vec<ToolResultContent>,
 
     /// Whether the tool call ended in an error.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "isError", skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
+
+    /// The result as JSON, conforming to the tool's output schema. Since 2025-06-18.
+    #[serde(rename = "structuredContent", skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
 }
 
 /// Tool result content
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum ToolResultContent {
     Text(TextContent),
     Image(ImageContent),
+    /// Since 2025-03-26.
+    Audio(AudioContent),
     Resource(EmbeddedResource),
+    /// Since 2025-06-18.
+    ResourceLink(ResourceLink),
+}
+
+deserialize_by_type!(ToolResultContent {
+    "text" => Text,
+    "image" => Image,
+    "audio" => Audio,
+    "resource" => Resource,
+    "resource_link" => ResourceLink,
+});
+
+/// A request from the server to ask the user for information via the client.
+/// Since 2025-06-18.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElicitRequestParams {
+    /// The message to present to the user.
+    pub message: String,
+
+    /// The shape of the response the server wants.
+    pub requested_schema: ElicitationSchema,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+}
+
+/// A restricted JSON Schema for elicitation: an object of primitive properties.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElicitationSchema {
+    pub r#type: String,
+
+    /// The schema of each property, each a string, number, integer, boolean or enum.
+    pub properties: HashMap<String, Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<Vec<String>>,
+}
+
+/// The client's response to an elicitation/create request. Since 2025-06-18.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElicitResult {
+    /// What the user did with the request.
+    pub action: ElicitAction,
+
+    /// The submitted data, present when the user accepted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<HashMap<String, Value>>,
+
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+}
+
+/// What the user did with an elicitation request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ElicitAction {
+    /// Submitted the requested data
+    Accept,
+    /// Explicitly refused
+    Decline,
+    /// Dismissed the request without choosing
+    Cancel,
 }
 
 /// Result of a tool call
@@ -0,0 +1,727 @@
+//! Checking messages against the MCP schema
+//!
+//! A [`Validator`] checks a message as received, before it is given a type: the JSON-RPC
+//! envelope, the params of the requests and notifications this crate knows, and, through
+//! [`Validator::check_result`], the results answering them. Every problem is reported
+//! with the JSON Pointer of the value at fault.
+//!
+//! In [`ValidationMode::Strict`] a message must conform to the specification exactly. In
+//! [`ValidationMode::Lenient`] the quirks of popular hosts are accepted:
+//!
+//! - members the specification does not define, anywhere in the message
+//! - `"params": null` in place of leaving the params out
+//! - `"id": null` on notifications
+//! - `"error": null` in successful responses and `"result": null` in error responses
+//! - `protocol_version` in place of `protocolVersion` in `initialize` params
+//! - results that are not objects
+//!
+//! Every message is read with the lenient envelope checks, so a message that is neither a
+//! request, a notification nor a response fails to parse instead of being mistaken for
+//! another kind.
+//!
+//! ```rust
+//! use mcpr::schema::validation::Validator;
+//! use serde_json::json;
+//!
+//! let message = json!({
+//!     "jsonrpc": "2.0",
+//!     "id": 1,
+//!     "method": "tools/call",
+//!     "params": { "name": "search", "arguments": {}, "verbose": true }
+//! });
+//! assert!(Validator::lenient().check(&message).is_empty());
+//!
+//! let violations = Validator::strict().check(&message);
+//! assert_eq!(violations[0].to_string(), "/params: unknown member `verbose`");
+//! ```
+
+use super::client::{
+    GetPromptResult, ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult,
+    ListRootsResult, ListToolsResult, ReadResourceResult,
+};
+use super::json_rpc::{
+    JSONRPCError, JSONRPCMessage, JSONRPCNotification, JSONRPCRequest, JSONRPCResponse,
+};
+use super::json_schema::{nested, violation, SchemaViolation};
+use super::messages::{ClientNotification, ClientRequest, ServerNotification, ServerRequest};
+use super::server::{
+    CallToolResult, CompleteResult, CreateMessageResult, ElicitResult, InitializeResult,
+};
+use crate::constants::JSONRPC_VERSION;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_path_to_error::Segment;
+
+/// How closely messages must follow the specification
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// Exactly as specified
+    Strict,
+    /// As specified, or with one of the quirks of popular hosts
+    #[default]
+    Lenient,
+}
+
+/// Checks messages against the MCP schema
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Validator {
+    mode: ValidationMode,
+}
+
+/// What a message is, by the members it has
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Request,
+    Notification,
+    Response,
+    Error,
+}
+
+impl Kind {
+    /// The members a message of this kind may have
+    fn members(self) -> &'static [&'static str] {
+        match self {
+            Self::Request => &["jsonrpc", "id", "method", "params"],
+            Self::Notification => &["jsonrpc", "method", "params"],
+            Self::Response => &["jsonrpc", "id", "result"],
+            Self::Error => &["jsonrpc", "id", "error"],
+        }
+    }
+}
+
+/// A result without members of its own
+#[derive(Serialize, Deserialize)]
+struct EmptyResult {}
+
+impl Validator {
+    /// Create a validator working in `mode`
+    pub fn new(mode: ValidationMode) -> Self {
+        Self { mode }
+    }
+
+    /// Create a validator that wants messages exactly as specified
+    pub fn strict() -> Self {
+        Self::new(ValidationMode::Strict)
+    }
+
+    /// Create a validator that accepts the quirks of popular hosts
+    pub fn lenient() -> Self {
+        Self::new(ValidationMode::Lenient)
+    }
+
+    /// How closely this validator wants messages to follow the specification
+    pub fn mode(&self) -> ValidationMode {
+        self.mode
+    }
+
+    /// Everything wrong with `message`, a message or batch as received
+    pub fn check(&self, message: &Value) -> Vec<SchemaViolation> {
+        let mut violations = Vec::new();
+        self.check_value(message, true, &mut String::new(), &mut violations);
+        violations
+    }
+
+    /// Everything wrong with the params of `request`, a request that has been read
+    ///
+    /// Reading checked the rest of the request, so only the params are looked at, in
+    /// place; paths are relative to the request.
+    pub fn check_request(&self, request: &JSONRPCRequest) -> Vec<SchemaViolation> {
+        let mut violations = Vec::new();
+        nested(&mut String::new(), "params", |path| {
+            self.check_params(
+                Kind::Request,
+                &request.method,
+                request.params.as_ref(),
+                path,
+                &mut violations,
+            )
+        });
+        violations
+    }
+
+    /// Everything wrong with `result`, the result of a `method` request
+    ///
+    /// Paths are relative to the result. Results of methods this crate does not know are
+    /// not checked.
+    pub fn check_result(&self, method: &str, result: &Value) -> Vec<SchemaViolation> {
+        let mut violations = Vec::new();
+        let path = &mut String::new();
+        if !result.is_object() {
+            if self.mode == ValidationMode::Strict {
+                violations.push(violation(
+                    path,
+                    format!("expected a result object, found {}", result),
+                ));
+            }
+            return violations;
+        }
+
+        let typed = match method {
+            "initialize" => typed::<InitializeResult>(result),
+            "resources/list" => typed::<ListResourcesResult>(result),
+            "resources/templates/list" => typed::<ListResourceTemplatesResult>(result),
+            "resources/read" => typed::<ReadResourceResult>(result),
+            "prompts/list" => typed::<ListPromptsResult>(result),
+            "prompts/get" => typed::<GetPromptResult>(result),
+            "tools/list" => typed::<ListToolsResult>(result),
+            "tools/call" => typed::<CallToolResult>(result),
+            "completion/complete" => typed::<CompleteResult>(result),
+            "sampling/createMessage" => typed::<CreateMessageResult>(result),
+            "roots/list" => typed::<ListRootsResult>(result),
+            "elicitation/create" => typed::<ElicitResult>(result),
+            "ping"
+            | "resources/subscribe"
+            | "resources/unsubscribe"
+            | "logging/setLevel"
+            | "shutdown" => typed::<EmptyResult>(result),
+            _ => return violations,
+        };
+        self.compare(result, typed, path, &mut violations);
+        violations
+    }
+
+    /// Read `message` if nothing is wrong with it
+    pub fn parse(&self, message: Value) -> Result<JSONRPCMessage, Vec<SchemaViolation>> {
+        let violations = self.check(&message);
+        if !violations.is_empty() {
+            return Err(violations);
+        }
+        read(message).map_err(|violation| vec![violation])
+    }
+
+    /// Check a message or batch, or only its envelope unless `full`
+    fn check_value(
+        &self,
+        value: &Value,
+        full: bool,
+        path: &mut String,
+        violations: &mut Vec<SchemaViolation>,
+    ) {
+        match value {
+            Value::Array(messages) if path.is_empty() => {
+                if messages.is_empty() {
+                    violations.push(violation(path, "a batch must not be empty".to_string()));
+                }
+                for (index, message) in messages.iter().enumerate() {
+                    nested(path, &index.to_string(), |path| {
+                        if message.is_array() {
+                            violations
+                                .push(violation(path, "batches cannot be nested".to_string()));
+                        } else {
+                            self.check_value(message, full, path, violations);
+                        }
+                    });
+                }
+            }
+            Value::Object(members) => self.check_message(members, full, path, violations),
+            other => violations.push(violation(
+                path,
+                format!("expected a JSON-RPC message, found {}", other),
+            )),
+        }
+    }
+
+    fn check_message(
+        &self,
+        members: &Map<String, Value>,
+        full: bool,
+        path: &mut String,
+        violations: &mut Vec<SchemaViolation>,
+    ) {
+        // Receivers check the version themselves, to answer a request under its id
+        match members.get("jsonrpc") {
+            _ if !full => {}
+            Some(Value::String(version)) if version == JSONRPC_VERSION => {}
+            Some(other) => nested(path, "jsonrpc", |path| {
+                violations.push(violation(
+                    path,
+                    format!("expected \"{}\", found {}", JSONRPC_VERSION, other),
+                ))
+            }),
+            None => violations.push(violation(path, "missing member `jsonrpc`".to_string())),
+        }
+
+        let Some(kind) = self.kind(members, path, violations) else {
+            return;
+        };
+        if self.mode == ValidationMode::Strict {
+            for name in members.keys() {
+                if !kind.members().contains(&name.as_str()) {
+                    violations.push(violation(path, format!("unknown member `{}`", name)));
+                }
+            }
+        }
+
+        if kind != Kind::Notification {
+            match members.get("id") {
+                Some(Value::String(_)) => {}
+                Some(Value::Number(id)) if id.is_i64() => {}
+                // The id of a request that could not be read
+                Some(Value::Null) if kind == Kind::Error => {}
+                Some(other) => nested(path, "id", |path| {
+                    violations.push(violation(
+                        path,
+                        format!("expected a string or integer, found {}", other),
+                    ))
+                }),
+                None => violations.push(violation(path, "missing member `id`".to_string())),
+            }
+        }
+
+        match kind {
+            Kind::Request | Kind::Notification => {
+                let Some(method) = members.get("method").and_then(Value::as_str) else {
+                    nested(path, "method", |path| {
+                        violations.push(violation(path, "expected a string".to_string()))
+                    });
+                    return;
+                };
+                let params = match members.get("params") {
+                    None => None,
+                    Some(Value::Null) if self.mode == ValidationMode::Lenient => None,
+                    Some(params @ (Value::Object(_) | Value::Array(_))) => Some(params),
+                    Some(other) => {
+                        nested(path, "params", |path| {
+                            violations.push(violation(
+                                path,
+                                format!("expected an object or array, found {}", other),
+                            ))
+                        });
+                        return;
+                    }
+                };
+                if full {
+                    nested(path, "params", |path| {
+                        self.check_params(kind, method, params, path, violations)
+                    });
+                }
+            }
+            Kind::Response => {
+                let result = &members["result"];
+                if self.mode == ValidationMode::Strict && !result.is_object() {
+                    nested(path, "result", |path| {
+                        violations.push(violation(
+                            path,
+                            format!("expected a result object, found {}", result),
+                        ))
+                    });
+                }
+            }
+            Kind::Error => nested(path, "error", |path| {
+                self.check_error(&members["error"], path, violations)
+            }),
+        }
+    }
+
+    /// What `members` make a message, if anything
+    fn kind(
+        &self,
+        members: &Map<String, Value>,
+        path: &str,
+        violations: &mut Vec<SchemaViolation>,
+    ) -> Option<Kind> {
+        let lenient = self.mode == ValidationMode::Lenient;
+        if members.contains_key("method") {
+            return Some(match members.get("id") {
+                None => Kind::Notification,
+                Some(Value::Null) if lenient => Kind::Notification,
+                Some(_) => Kind::Request,
+            });
+        }
+        match (members.get("result"), members.get("error")) {
+            (Some(_), None) => Some(Kind::Response),
+            (None, Some(_)) => Some(Kind::Error),
+            (Some(_), Some(Value::Null)) if lenient => Some(Kind::Response),
+            (Some(Value::Null), Some(_)) if lenient => Some(Kind::Error),
+            (Some(_), Some(_)) => {
+                violations.push(violation(
+                    path,
+                    "a response has either `result` or `error`, not both".to_string(),
+                ));
+                None
+            }
+            (None, None) => {
+                violations.push(violation(
+                    path,
+                    "expected a request, notification or response, found none of `method`, \
+                     `result` and `error`"
+                        .to_string(),
+                ));
+                None
+            }
+        }
+    }
+
+    fn check_params(
+        &self,
+        kind: Kind,
+        method: &str,
+        params: Option<&Value>,
+        path: &mut String,
+        violations: &mut Vec<SchemaViolation>,
+    ) {
+        // Methods with the same name take the same params whichever side sends them
+        let typed = match kind {
+            Kind::Request => ClientRequest::typed_params(method, params)
+                .or_else(|| ServerRequest::typed_params(method, params)),
+            _ => ClientNotification::typed_params(method, params)
+                .or_else(|| ServerNotification::typed_params(method, params)),
+        };
+        let Some(typed) = typed else {
+            return;
+        };
+        let empty = Value::Object(Map::new());
+        let typed = typed.map(|typed| typed.unwrap_or_else(|| empty.clone()));
+        self.compare(params.unwrap_or(&empty), typed, path, violations);
+    }
+
+    fn check_error(&self, error: &Value, path: &mut String, violations: &mut Vec<SchemaViolation>) {
+        let Value::Object(members) = error else {
+            violations.push(violation(
+                path,
+                format!("expected an error object, found {}", error),
+            ));
+            return;
+        };
+        match members.get("code") {
+            Some(Value::Number(code))
+                if code
+                    .as_i64()
+                    .and_then(|code| i32::try_from(code).ok())
+                    .is_some() => {}
+            Some(other) => nested(path, "code", |path| {
+                violations.push(violation(
+                    path,
+                    format!("expected an integer, found {}", other),
+                ))
+            }),
+            None => violations.push(violation(path, "missing member `code`".to_string())),
+        }
+        match members.get("message") {
+            Some(Value::String(_)) => {}
+            Some(other) => nested(path, "message", |path| {
+                violations.push(violation(
+                    path,
+                    format!("expected a string, found {}", other),
+                ))
+            }),
+            None => violations.push(violation(path, "missing member `message`".to_string())),
+        }
+        if self.mode == ValidationMode::Strict {
+            for name in members.keys() {
+                if !["code", "message", "data"].contains(&name.as_str()) {
+                    violations.push(violation(path, format!("unknown member `{}`", name)));
+                }
+            }
+        }
+    }
+
+    /// Report where `value` did not fit its type and, in strict mode, what its type left out
+    fn compare(
+        &self,
+        value: &Value,
+        typed: Result<Value, serde_path_to_error::Error<serde_json::Error>>,
+        path: &mut String,
+        violations: &mut Vec<SchemaViolation>,
+    ) {
+        match typed {
+            Ok(typed) => {
+                if self.mode == ValidationMode::Strict {
+                    unknown_members(value, &typed, path, violations);
+                }
+            }
+            Err(error) => {
+                let mut pointer = path.clone();
+                for segment in error.path().iter() {
+                    match segment {
+                        Segment::Seq { index } => pointer.push_str(&format!("/{}", index)),
+                        Segment::Map { key } => pointer
+                            .push_str(&format!("/{}", key.replace('~', "~0").replace('/', "~1"))),
+                        Segment::Enum { .. } | Segment::Unknown => {}
+                    }
+                }
+                violations.push(violation(&pointer, error.into_inner().to_string()));
+            }
+        }
+    }
+}
+
+/// `value` given the type `T`, as it reads again once serialized
+fn typed<T: DeserializeOwned + Serialize>(
+    value: &Value,
+) -> Result<Value, serde_path_to_error::Error<serde_json::Error>> {
+    let typed: T = serde_path_to_error::deserialize(value)?;
+    // Schema types only have string map keys, so serializing them cannot fail
+    Ok(serde_json::to_value(typed).expect("MCP types serialize to JSON"))
+}
+
+/// Report the members of `value` that were lost when it was given a type
+///
+/// `_meta` is open to extensions everywhere, so what it holds is never reported.
+fn unknown_members(
+    value: &Value,
+    typed: &Value,
+    path: &mut String,
+    violations: &mut Vec<SchemaViolation>,
+) {
+    match (value, typed) {
+        (Value::Object(members), Value::Object(typed_members)) => {
+            for (name, member) in members {
+                if name == "_meta" {
+                    continue;
+                }
+                match typed_members.get(name) {
+                    Some(typed_member) => nested(path, name, |path| {
+                        unknown_members(member, typed_member, path, violations)
+                    }),
+                    None if member.is_null() => violations.push(violation(
+                        path,
+                        format!("`{}` is null; leave it out instead", name),
+                    )),
+                    None => violations.push(violation(path, format!("unknown member `{}`", name))),
+                }
+            }
+        }
+        (Value::Array(items), Value::Array(typed_items)) if items.len() == typed_items.len() => {
+            for (index, (item, typed_item)) in items.iter().zip(typed_items).enumerate() {
+                nested(path, &index.to_string(), |path| {
+                    unknown_members(item, typed_item, path, violations)
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Read a message, rejecting anything that is not a request, notification or response
+///
+/// Only the envelope is checked, leniently. The version and params are left for the
+/// receiver, so that it can answer a request with either wrong under the request's id.
+pub(crate) fn read_message(message: Value) -> Result<JSONRPCMessage, SchemaViolation> {
+    check_envelope(&message)?;
+    read(message)
+}
+
+/// Check that `message` is a request, notification or response, or a batch of them
+///
+/// Only the kind of value of params and results is looked at, not their contents.
+pub(crate) fn check_envelope(message: &Value) -> Result<(), SchemaViolation> {
+    let mut violations = Vec::new();
+    Validator::lenient().check_value(message, false, &mut String::new(), &mut violations);
+    match violations.into_iter().next() {
+        Some(violation) => Err(violation),
+        None => Ok(()),
+    }
+}
+
+/// Read a message whose envelope has been checked
+fn read(message: Value) -> Result<JSONRPCMessage, SchemaViolation> {
+    let mut members = match message {
+        Value::Array(messages) => {
+            return messages
+                .into_iter()
+                .map(read)
+                .collect::<Result<_, _>>()
+                .map(JSONRPCMessage::Batch)
+        }
+        Value::Object(members) => members,
+        other => {
+            return Err(violation(
+                "",
+                format!("expected a JSON-RPC message, found {}", other),
+            ))
+        }
+    };
+
+    let kind = MessageKind::of(&mut members);
+    let message = Value::Object(members);
+    let read = match kind {
+        MessageKind::Request => {
+            serde_json::from_value::<JSONRPCRequest>(message).map(JSONRPCMessage::Request)
+        }
+        MessageKind::Notification => {
+            serde_json::from_value::<JSONRPCNotification>(message).map(JSONRPCMessage::Notification)
+        }
+        MessageKind::Response => {
+            serde_json::from_value::<JSONRPCResponse>(message).map(JSONRPCMessage::Response)
+        }
+        MessageKind::Error => {
+            serde_json::from_value::<JSONRPCError>(message).map(JSONRPCMessage::Error)
+        }
+    };
+    read.map_err(|e| violation("", e.to_string()))
+}
+
+/// The members of one message, parsed or kept as text
+pub(crate) trait Members {
+    /// Whether the message has the member `name`
+    fn has(&self, name: &str) -> bool;
+    /// Whether the member `name` is present and `null`
+    fn is_null(&self, name: &str) -> bool;
+    /// Drop the member `name`
+    fn remove(&mut self, name: &str);
+}
+
+impl Members for Map<String, Value> {
+    fn has(&self, name: &str) -> bool {
+        self.contains_key(name)
+    }
+
+    fn is_null(&self, name: &str) -> bool {
+        self.get(name).is_some_and(Value::is_null)
+    }
+
+    fn remove(&mut self, name: &str) {
+        self.remove(name);
+    }
+}
+
+/// Which kind of message an envelope that passed [`check_envelope`] holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MessageKind {
+    Request,
+    Notification,
+    Response,
+    Error,
+}
+
+impl MessageKind {
+    /// Tell what `members` are, dropping the nulls lenient validation lets stand in for
+    /// absent members
+    pub(crate) fn of(members: &mut impl Members) -> Self {
+        if members.is_null("params") {
+            members.remove("params");
+        }
+        if members.has("method") && members.is_null("id") {
+            members.remove("id");
+        }
+        if members.has("result") && members.is_null("error") {
+            members.remove("error");
+        } else if members.has("error") && members.is_null("result") {
+            members.remove("result");
+        }
+
+        match (members.has("method"), members.has("id")) {
+            (true, true) => Self::Request,
+            (true, false) => Self::Notification,
+            _ if members.has("result") => Self::Response,
+            _ => Self::Error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn messages(violations: Vec<SchemaViolation>) -> Vec<String> {
+        violations.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_envelopes_must_be_unambiguous() {
+        let both = json!({ "jsonrpc": "2.0", "id": 1, "result": {}, "error": { "code": 1, "message": "no" } });
+        for validator in [Validator::strict(), Validator::lenient()] {
+            assert_eq!(
+                messages(validator.check(&both)),
+                ["a response has either `result` or `error`, not both"]
+            );
+        }
+        assert!(serde_json::from_value::<JSONRPCMessage>(both).is_err());
+
+        assert_eq!(
+            messages(
+                Validator::lenient()
+                    .check(&json!({ "jsonrpc": "1.0", "id": 1.5, "method": "ping" }))
+            ),
+            [
+                "/jsonrpc: expected \"2.0\", found \"1.0\"",
+                "/id: expected a string or integer, found 1.5"
+            ]
+        );
+        assert_eq!(
+            messages(
+                Validator::lenient()
+                    .check(&json!([{ "jsonrpc": "2.0", "id": 1, "result": {} }, []]))
+            ),
+            ["/1: batches cannot be nested"]
+        );
+    }
+
+    #[test]
+    fn test_lenient_mode_accepts_host_quirks() {
+        let quirky = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list", "params": null }),
+            json!({ "jsonrpc": "2.0", "id": null, "method": "notifications/initialized" }),
+            json!({ "jsonrpc": "2.0", "id": 1, "result": {}, "error": null }),
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": { "protocol_version": "2025-06-18" } }),
+        ];
+        for message in quirky {
+            assert!(
+                Validator::lenient().check(&message).is_empty(),
+                "{}",
+                message
+            );
+            assert!(
+                !Validator::strict().check(&message).is_empty(),
+                "{}",
+                message
+            );
+        }
+
+        // They read as the message they stand for
+        let notification =
+            json!({ "jsonrpc": "2.0", "id": null, "method": "notifications/initialized" });
+        assert!(matches!(
+            serde_json::from_value(notification).unwrap(),
+            JSONRPCMessage::Notification(_)
+        ));
+        let response = json!({ "jsonrpc": "2.0", "id": 1, "result": {}, "error": null });
+        assert!(matches!(
+            Validator::lenient().parse(response).unwrap(),
+            JSONRPCMessage::Response(_)
+        ));
+    }
+
+    #[test]
+    fn test_content_is_checked_by_path() {
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": 3, "arguments": {} }
+        });
+        assert_eq!(
+            messages(Validator::lenient().check(&call)),
+            ["/params/name: invalid type: integer `3`, expected a string"]
+        );
+
+        // The same for a request that has already been read
+        let JSONRPCMessage::Request(request) = serde_json::from_value(call).unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(
+            messages(Validator::strict().check_request(&request)),
+            ["/params/name: invalid type: integer `3`, expected a string"]
+        );
+
+        let result = json!({
+            "tools": [{ "name": "search", "inputSchema": { "type": "object" }, "color": "red" }],
+            "_meta": { "anything": true }
+        });
+        assert!(Validator::lenient()
+            .check_result("tools/list", &result)
+            .is_empty());
+        assert_eq!(
+            messages(Validator::strict().check_result("tools/list", &result)),
+            ["/tools/0: unknown member `color`"]
+        );
+        assert_eq!(
+            messages(Validator::strict().check_result("ping", &json!({ "pong": true }))),
+            ["unknown member `pong`"]
+        );
+    }
+}
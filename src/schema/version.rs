@@ -0,0 +1,513 @@
+//! MCP protocol revisions
+//!
+//! The schema types carry the fields of every supported revision. Fields added after
+//! 2024-11-05 are optional and documented with the revision that introduced them.
+//! Before sending a value to a peer, a server or client calls
+//! [`Versioned::restrict_to`] with the negotiated revision, which clears whatever that
+//! revision does not define, so older peers never see fields they do not know.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+use super::client::{
+    CompleteParams, GetPromptResult, ListPromptsResult, ListResourceTemplatesResult,
+    ListResourcesResult, ListToolsResult, ProgressParams, ReadResourceResult,
+};
+use super::common::{
+    Annotations, AudioContent, EmbeddedResource, ImageContent, Implementation, Prompt,
+    PromptArgument, PromptMessage, PromptMessageContent, Resource, ResourceTemplate, TextContent,
+    Tool,
+};
+use super::messages::{ClientNotification, ClientRequest, ServerNotification, ServerRequest};
+use super::server::{
+    CallToolResult, CreateMessageParams, CreateMessageResult, InitializeResult, MessageContent,
+    SamplingMessage, ServerCapabilities, ToolResultContent,
+};
+
+/// A revision of the Model Context Protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProtocolVersion {
+    /// 2024-11-05
+    V2024_11_05,
+    /// 2025-03-26: tool annotations, audio content, completions capability, progress messages
+    V2025_03_26,
+    /// 2025-06-18: titles, structured tool output, resource links, elicitation and `_meta`
+    /// on content and definitions
+    V2025_06_18,
+}
+
+impl ProtocolVersion {
+    /// The most recent revision this crate implements
+    pub const LATEST: Self = Self::V2025_06_18;
+
+    /// Every revision this crate implements, oldest first
+    pub const ALL: [Self; 3] = [Self::V2024_11_05, Self::V2025_03_26, Self::V2025_06_18];
+
+    /// The revision's date, as sent on the wire
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::V2024_11_05 => "2024-11-05",
+            Self::V2025_03_26 => "2025-03-26",
+            Self::V2025_06_18 => "2025-06-18",
+        }
+    }
+
+    /// The revision a server answers an initialize request for `requested` with
+    ///
+    /// That is the requested revision if the server implements it, and otherwise the
+    /// latest one, leaving it to the client to disconnect if it cannot use that.
+    pub fn negotiate(requested: &str) -> Self {
+        requested.parse().unwrap_or(Self::LATEST)
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|version| version.as_str() == s)
+            .ok_or_else(|| format!("Unsupported protocol version: {}", s))
+    }
+}
+
+impl Serialize for ProtocolVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ProtocolVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        version.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Schema types with fields that not every protocol revision defines
+pub trait Versioned {
+    /// Clear the fields `version` does not define
+    fn restrict_to(&mut self, version: ProtocolVersion);
+}
+
+impl<T: Versioned> Versioned for Option<T> {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        if let Some(value) = self {
+            value.restrict_to(version);
+        }
+    }
+}
+
+impl<T: Versioned> Versioned for Vec<T> {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        for value in self {
+            value.restrict_to(version);
+        }
+    }
+}
+
+/// Clear `fields` of `value` unless `version` is at least `since`
+macro_rules! since {
+    ($version:expr, $since:ident, $value:ident: $($field:ident),+) => {
+        if $version < ProtocolVersion::$since {
+            $($value.$field = None;)+
+        }
+    };
+}
+
+impl Versioned for Implementation {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: title);
+    }
+}
+
+impl Versioned for Annotations {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: last_modified);
+    }
+}
+
+impl Versioned for TextContent {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: meta);
+        self.annotations.restrict_to(version);
+    }
+}
+
+impl Versioned for ImageContent {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: meta);
+        self.annotations.restrict_to(version);
+    }
+}
+
+impl Versioned for AudioContent {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: meta);
+        self.annotations.restrict_to(version);
+    }
+}
+
+impl Versioned for EmbeddedResource {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: meta);
+        self.annotations.restrict_to(version);
+    }
+}
+
+impl Versioned for Resource {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: title, meta);
+        self.annotations.restrict_to(version);
+    }
+}
+
+impl Versioned for ResourceTemplate {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: title, meta);
+        self.annotations.restrict_to(version);
+    }
+}
+
+impl Versioned for PromptArgument {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: title);
+    }
+}
+
+impl Versioned for Prompt {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: title, meta);
+        self.arguments.restrict_to(version);
+    }
+}
+
+impl Versioned for Tool {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_03_26, self: annotations);
+        since!(version, V2025_06_18, self: title, output_schema, meta);
+    }
+}
+
+impl Versioned for ListToolsResult {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.tools.restrict_to(version);
+    }
+}
+
+impl Versioned for ListResourcesResult {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.resources.restrict_to(version);
+    }
+}
+
+impl Versioned for ListResourceTemplatesResult {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.resource_templates.restrict_to(version);
+    }
+}
+
+impl Versioned for ReadResourceResult {
+    /// Resource contents are the same in every revision, so this changes nothing
+    fn restrict_to(&mut self, _version: ProtocolVersion) {}
+}
+
+impl Versioned for ListPromptsResult {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.prompts.restrict_to(version);
+    }
+}
+
+impl Versioned for ServerCapabilities {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_03_26, self: completions);
+    }
+}
+
+impl Versioned for InitializeResult {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.capabilities.restrict_to(version);
+        self.server_info.restrict_to(version);
+    }
+}
+
+impl Versioned for ProgressParams {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_03_26, self: message);
+    }
+}
+
+impl Versioned for CompleteParams {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        since!(version, V2025_06_18, self: context);
+    }
+}
+
+impl PromptMessageContent {
+    /// The revision that introduced this kind of content
+    pub fn since(&self) -> ProtocolVersion {
+        match self {
+            Self::Text(_) | Self::Image(_) | Self::Resource(_) => ProtocolVersion::V2024_11_05,
+            Self::Audio(_) => ProtocolVersion::V2025_03_26,
+            Self::ResourceLink(_) => ProtocolVersion::V2025_06_18,
+        }
+    }
+}
+
+impl Versioned for PromptMessageContent {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        match self {
+            Self::Text(content) => content.restrict_to(version),
+            Self::Image(content) => content.restrict_to(version),
+            Self::Audio(content) => content.restrict_to(version),
+            Self::Resource(content) => content.restrict_to(version),
+            Self::ResourceLink(_) => {}
+        }
+    }
+}
+
+impl Versioned for PromptMessage {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.content.restrict_to(version);
+    }
+}
+
+impl Versioned for GetPromptResult {
+    /// Also drops messages with content of kinds `version` does not define
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.messages
+            .retain(|message| message.content.since() <= version);
+        self.messages.restrict_to(version);
+    }
+}
+
+impl ToolResultContent {
+    /// The revision that introduced this kind of content
+    pub fn since(&self) -> ProtocolVersion {
+        match self {
+            Self::Text(_) | Self::Image(_) | Self::Resource(_) => ProtocolVersion::V2024_11_05,
+            Self::Audio(_) => ProtocolVersion::V2025_03_26,
+            Self::ResourceLink(_) => ProtocolVersion::V2025_06_18,
+        }
+    }
+}
+
+impl Versioned for ToolResultContent {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        match self {
+            Self::Text(content) => content.restrict_to(version),
+            Self::Image(content) => content.restrict_to(version),
+            Self::Audio(content) => content.restrict_to(version),
+            Self::Resource(content) => content.restrict_to(version),
+            Self::ResourceLink(_) => {}
+        }
+    }
+}
+
+impl Versioned for CallToolResult {
+    /// Also drops content of kinds `version` does not define
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.content.retain(|content| content.since() <= version);
+        self.content.restrict_to(version);
+        since!(version, V2025_06_18, self: structured_content);
+    }
+}
+
+impl MessageContent {
+    /// The revision that introduced this kind of content
+    pub fn since(&self) -> ProtocolVersion {
+        match self {
+            Self::Text(_) | Self::Image(_) => ProtocolVersion::V2024_11_05,
+            Self::Audio(_) => ProtocolVersion::V2025_03_26,
+        }
+    }
+}
+
+impl Versioned for MessageContent {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        match self {
+            Self::Text(content) => content.restrict_to(version),
+            Self::Image(content) => content.restrict_to(version),
+            Self::Audio(content) => content.restrict_to(version),
+        }
+    }
+}
+
+impl Versioned for SamplingMessage {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.content.restrict_to(version);
+    }
+}
+
+impl Versioned for CreateMessageParams {
+    /// Also drops messages with content of kinds `version` does not define
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.messages
+            .retain(|message| message.content.since() <= version);
+        self.messages.restrict_to(version);
+    }
+}
+
+impl Versioned for CreateMessageResult {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        self.content.restrict_to(version);
+    }
+}
+
+impl Versioned for ClientRequest {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        if let Self::Complete(params) = self {
+            params.restrict_to(version);
+        }
+    }
+}
+
+impl Versioned for ServerRequest {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        if let Self::CreateMessage(params) = self {
+            params.restrict_to(version);
+        }
+    }
+}
+
+impl Versioned for ClientNotification {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        if let Self::Progress(params) = self {
+            params.restrict_to(version);
+        }
+    }
+}
+
+impl Versioned for ServerNotification {
+    fn restrict_to(&mut self, version: ProtocolVersion) {
+        if let Self::Progress(params) = self {
+            params.restrict_to(version);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::common::{ResourceLink, ToolAnnotations};
+    use serde_json::json;
+
+    #[test]
+    fn test_negotiation() {
+        assert_eq!(
+            ProtocolVersion::negotiate("2025-03-26"),
+            ProtocolVersion::V2025_03_26
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate("1999-01-01"),
+            ProtocolVersion::LATEST
+        );
+        assert!(ProtocolVersion::V2024_11_05 < ProtocolVersion::V2025_03_26);
+        assert_eq!(
+            serde_json::to_value(ProtocolVersion::V2025_06_18).unwrap(),
+            json!("2025-06-18")
+        );
+    }
+
+    #[test]
+    fn test_tools_lose_fields_older_revisions_lack() {
+        let tool = Tool {
+            name: "delete".to_string(),
+            title: Some("Delete".to_string()),
+            annotations: Some(ToolAnnotations {
+                destructive_hint: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut latest = tool.clone();
+        latest.restrict_to(ProtocolVersion::V2025_06_18);
+        let latest = serde_json::to_value(latest).unwrap();
+        assert_eq!(latest["title"], "Delete");
+        assert_eq!(latest["annotations"]["destructiveHint"], true);
+
+        let mut middle = tool.clone();
+        middle.restrict_to(ProtocolVersion::V2025_03_26);
+        let middle = serde_json::to_value(middle).unwrap();
+        assert!(middle.get("title").is_none());
+        assert_eq!(middle["annotations"]["destructiveHint"], true);
+
+        let mut oldest = tool;
+        oldest.restrict_to(ProtocolVersion::V2024_11_05);
+        assert_eq!(
+            serde_json::to_value(oldest).unwrap(),
+            json!({ "name": "delete", "inputSchema": { "type": "object" } })
+        );
+    }
+
+    #[test]
+    fn test_prompts_and_sampling_drop_newer_content() {
+        let prompt: GetPromptResult = serde_json::from_value(json!({
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "hi", "_meta": { "a": 1 } } },
+                { "role": "user", "content": { "type": "audio", "data": "AAAA", "mimeType": "audio/wav" } },
+                { "role": "user", "content": { "type": "resource_link", "uri": "file:///a", "name": "a" } }
+            ]
+        }))
+        .unwrap();
+
+        let mut middle = prompt.clone();
+        middle.restrict_to(ProtocolVersion::V2025_03_26);
+        assert_eq!(middle.messages.len(), 2);
+        assert!(matches!(
+            &middle.messages[0].content,
+            PromptMessageContent::Text(text) if text.meta.is_none()
+        ));
+
+        let mut oldest = prompt;
+        oldest.restrict_to(ProtocolVersion::V2024_11_05);
+        assert_eq!(oldest.messages.len(), 1);
+
+        let mut request: CreateMessageParams = serde_json::from_value(json!({
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "hi" } },
+                { "role": "user", "content": { "type": "audio", "data": "AAAA", "mimeType": "audio/wav" } }
+            ],
+            "maxTokens": 100
+        }))
+        .unwrap();
+        request.restrict_to(ProtocolVersion::V2024_11_05);
+        assert_eq!(request.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_tool_results_drop_newer_content() {
+        let result: CallToolResult = serde_json::from_value(json!({
+            "content": [
+                { "type": "text", "text": "done" },
+                { "type": "audio", "data": "AAAA", "mimeType": "audio/wav" },
+                { "type": "resource_link", "uri": "file:///out.txt", "name": "out.txt" }
+            ],
+            "structuredContent": { "ok": true }
+        }))
+        .unwrap();
+        assert!(matches!(result.content[1], ToolResultContent::Audio(_)));
+        assert!(matches!(
+            &result.content[2],
+            ToolResultContent::ResourceLink(ResourceLink { name, .. }) if name == "out.txt"
+        ));
+
+        let mut middle = result.clone();
+        middle.restrict_to(ProtocolVersion::V2025_03_26);
+        assert_eq!(middle.content.len(), 2);
+        assert!(middle.structured_content.is_none());
+
+        let mut oldest = result;
+        oldest.restrict_to(ProtocolVersion::V2024_11_05);
+        assert_eq!(oldest.content.len(), 1);
+    }
+}
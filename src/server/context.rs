@@ -0,0 +1,232 @@
+//! What a tool handler can do besides returning its result
+//!
+//! Handlers registered with
+//! [`register_tool_handler_with_context`](super::Server::register_tool_handler_with_context)
+//! receive a [`ToolContext`] for the session the call arrived on. Through it a handler can
+//! send requests of its own to the client and wait for the answers, for instance to
+//! [`elicit`](ToolContext::elicit) input from the user:
+//!
+//! ```rust,no_run
+//! use mcpr::error::MCPError;
+//! use mcpr::server::context::ToolContext;
+//! use mcpr::server::elicitation::Elicitation;
+//! use mcpr::server::{Server, ServerConfig};
+//! use mcpr::Tool;
+//! use serde::Deserialize;
+//! use serde_json::{json, Value};
+//!
+//! #[derive(Deserialize)]
+//! struct Confirmation {
+//!     environment: String,
+//!     confirmed: bool,
+//! }
+//!
+//! let mut server = Server::new(ServerConfig::new().with_tool(Tool {
+//!     name: "deploy".to_string(),
+//!     ..Default::default()
+//! }));
+//! server.register_tool_handler_with_context("deploy", |_params: Value, ctx: ToolContext| async move {
+//!     match ctx.elicit::<Confirmation>("Where should this be deployed?").await? {
+//!         Elicitation::Accepted(answer) if answer.confirmed => {
+//!             Ok(json!({ "deployed_to": answer.environment }))
+//!         }
+//!         _ => Err(MCPError::Protocol("Deployment was not confirmed".to_string())),
+//!     }
+//! })?;
+//! # Ok::<(), MCPError>(())
+//! ```
+
+use crate::error::MCPError;
+use crate::schema::json_rpc::{JSONRPCError, JSONRPCMessage, RequestId};
+use crate::schema::messages::ServerRequest;
+use crate::schema::server::{ElicitAction, ElicitRequestParams, ElicitResult, ElicitationSchema};
+use crate::schema::version::{ProtocolVersion, Versioned};
+use crate::server::elicitation::{requested_schema, Elicitation};
+use crate::server::middleware::SessionContext;
+use crate::transport::TransportSender;
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// The session a tool call arrived on, as seen by its handler
+#[derive(Debug, Clone)]
+pub struct ToolContext {
+    session: SessionContext,
+}
+
+impl ToolContext {
+    pub(crate) fn new(session: SessionContext) -> Self {
+        Self { session }
+    }
+
+    /// What the server knows about the session
+    pub fn session(&self) -> &SessionContext {
+        &self.session
+    }
+
+    /// Whether the client can be asked for input
+    ///
+    /// That takes protocol revision 2025-06-18 or later and a client that announced the
+    /// `elicitation` capability in `initialize`.
+    pub fn can_elicit(&self) -> bool {
+        self.session.negotiated_version() >= ProtocolVersion::V2025_06_18
+            && self
+                .session
+                .client_capabilities
+                .read()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|capabilities| capabilities.elicitation.is_some())
+    }
+
+    /// Ask the user, through the client, for a `T`
+    ///
+    /// The form the client shows is derived from `T` by
+    /// [`requested_schema`]. Fails with [`MCPError::UnsupportedFeature`] if the client
+    /// cannot be asked; see [`can_elicit`](Self::can_elicit).
+    pub async fn elicit<T: DeserializeOwned>(
+        &self,
+        message: &str,
+    ) -> Result<Elicitation<T>, MCPError> {
+        let schema = requested_schema::<T>()?;
+        self.elicit_with_schema(message, schema).await
+    }
+
+    /// Ask the user for a `T`, showing a form described by `schema`
+    pub async fn elicit_with_schema<T: DeserializeOwned>(
+        &self,
+        message: &str,
+        schema: ElicitationSchema,
+    ) -> Result<Elicitation<T>, MCPError> {
+        if !self.can_elicit() {
+            return Err(MCPError::UnsupportedFeature(
+                "The client does not support elicitation".to_string(),
+            ));
+        }
+        let peer = self.session.peer.as_ref().ok_or_else(|| {
+            MCPError::Transport("Tool call is not attached to a session".to_string())
+        })?;
+
+        let request = ServerRequest::Elicit(ElicitRequestParams {
+            message: message.to_string(),
+            requested_schema: schema,
+            meta: None,
+        });
+        let result: ElicitResult = serde_json::from_value(
+            peer.request(request, self.session.negotiated_version())
+                .await?,
+        )?;
+        match result.action {
+            ElicitAction::Accept => {
+                let content = result.content.unwrap_or_default();
+                serde_json::from_value(Value::Object(content.into_iter().collect()))
+                    .map(Elicitation::Accepted)
+                    .map_err(|e| {
+                        MCPError::Protocol(format!("Client accepted with invalid content: {}", e))
+                    })
+            }
+            ElicitAction::Decline => Ok(Elicitation::Declined),
+            ElicitAction::Cancel => Ok(Elicitation::Cancelled),
+        }
+    }
+}
+
+/// Requests from the server to the client of one session, and the responses they await
+pub(crate) struct ClientPeer {
+    sender: Arc<dyn TransportSender>,
+    next_request_id: AtomicI64,
+    pending: Mutex<HashMap<RequestId, oneshot::Sender<JSONRPCMessage>>>,
+}
+
+impl ClientPeer {
+    pub(crate) fn new(sender: Arc<dyn TransportSender>) -> Self {
+        Self {
+            sender,
+            next_request_id: AtomicI64::new(1),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Send a request, restricted to the session's `version`, to the client and wait for
+    /// its result
+    pub(crate) async fn request(
+        &self,
+        mut request: ServerRequest,
+        version: ProtocolVersion,
+    ) -> Result<Value, MCPError> {
+        request.restrict_to(version);
+        let method = request.method().to_string();
+        let id = RequestId::Number(self.next_request_id.fetch_add(1, Ordering::SeqCst));
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), tx);
+
+        // Forget the request if the caller stops waiting, e.g. when the tool times out
+        let _waiting = Waiting {
+            peer: self,
+            id: &id,
+        };
+        self.sender
+            .send(JSONRPCMessage::Request(request.into_request(id.clone())))
+            .await?;
+
+        match rx.await {
+            Ok(JSONRPCMessage::Response(response)) => Ok(response.result),
+            Ok(JSONRPCMessage::Error(error)) => Err(error.into()),
+            Ok(_) => Err(MCPError::Protocol("Unexpected response type".to_string())),
+            Err(_) => Err(MCPError::ConnectionClosed(format!(
+                "Session ended before the client answered {}",
+                method
+            ))),
+        }
+    }
+
+    /// Hand a response from the client to the request waiting for it
+    pub(crate) fn complete(&self, message: JSONRPCMessage) {
+        let id = match &message {
+            JSONRPCMessage::Response(response) => &response.id,
+            JSONRPCMessage::Error(JSONRPCError { id: Some(id), .. }) => id,
+            JSONRPCMessage::Error(error) => {
+                warn!("Client could not read a message: {}", error.error.message);
+                return;
+            }
+            _ => return,
+        };
+        let waiter = self.pending.lock().unwrap().remove(id);
+        match waiter {
+            Some(waiter) => {
+                let _ = waiter.send(message);
+            }
+            None => warn!("Received response for unknown request {:?}", id),
+        }
+    }
+
+    /// Fail every request still waiting, once the session has ended
+    pub(crate) fn close(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+}
+
+impl fmt::Debug for ClientPeer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientPeer")
+            .field("pending", &self.pending.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// Removes a request from the pending ones when its caller is done with it
+struct Waiting<'a> {
+    peer: &'a ClientPeer,
+    id: &'a RequestId,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.peer.pending.lock().unwrap().remove(self.id);
+    }
+}
@@ -0,0 +1,348 @@
+//! Asking the user for input in the middle of a tool call
+//!
+//! A tool handler registered with
+//! [`register_tool_handler_with_context`](super::Server::register_tool_handler_with_context)
+//! can call [`ToolContext::elicit`](super::context::ToolContext::elicit) to have the client
+//! collect a value from its user. The form the client shows is described by a restricted
+//! JSON Schema: an object whose properties are strings, numbers, integers, booleans or
+//! enums of strings. [`requested_schema`] derives that schema from the type the handler
+//! wants back, so a plain struct is all it takes:
+//!
+//! ```rust
+//! use mcpr::server::elicitation::requested_schema;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! #[serde(rename_all = "lowercase")]
+//! enum Environment {
+//!     Staging,
+//!     Production,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Confirmation {
+//!     environment: Environment,
+//!     confirmed: bool,
+//!     note: Option<String>,
+//! }
+//!
+//! let schema = requested_schema::<Confirmation>().unwrap();
+//! assert_eq!(schema.properties["environment"]["enum"][1], "production");
+//! assert_eq!(schema.required.unwrap().len(), 2);
+//! ```
+
+use crate::error::MCPError;
+use crate::schema::server::ElicitationSchema;
+use serde::de::value::Error as TraceError;
+use serde::de::{self, DeserializeOwned, Error as _, IntoDeserializer, MapAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// What the user did when asked for a `T`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Elicitation<T> {
+    /// The user submitted a value
+    Accepted(T),
+    /// The user refused to provide one
+    Declined,
+    /// The user dismissed the request without choosing
+    Cancelled,
+}
+
+impl<T> Elicitation<T> {
+    /// The submitted value, if the user accepted
+    pub fn accepted(self) -> Option<T> {
+        match self {
+            Self::Accepted(value) => Some(value),
+            Self::Declined | Self::Cancelled => None,
+        }
+    }
+}
+
+/// The schema of the form that collects a `T`
+///
+/// `T` must deserialize from a struct whose fields are strings, numbers, booleans,
+/// enums of unit variants, or options of those; field renames are honoured. Fields that
+/// are not options are required. To add descriptions or other constraints, edit the
+/// returned schema and pass it to
+/// [`ToolContext::elicit_with_schema`](super::context::ToolContext::elicit_with_schema).
+pub fn requested_schema<T: DeserializeOwned>() -> Result<ElicitationSchema, MCPError> {
+    let mut trace = Trace::default();
+    T::deserialize(StructTracer(&mut trace)).map_err(|e| {
+        MCPError::UnsupportedFeature(format!(
+            "Cannot ask the user for {}: {}",
+            std::any::type_name::<T>(),
+            e
+        ))
+    })?;
+    Ok(ElicitationSchema {
+        r#type: "object".to_string(),
+        properties: trace.properties,
+        required: (!trace.required.is_empty()).then_some(trace.required),
+    })
+}
+
+/// What deserializing a type revealed about its fields
+#[derive(Default)]
+struct Trace {
+    properties: HashMap<String, Value>,
+    required: Vec<String>,
+}
+
+/// Deserializes the elicited type, which must be a struct
+struct StructTracer<'a>(&'a mut Trace);
+
+impl<'de> de::Deserializer<'de> for StructTracer<'_> {
+    type Error = TraceError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, TraceError> {
+        Err(TraceError::custom("only structs can be elicited"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        visitor.visit_map(FieldsTracer {
+            trace: self.0,
+            fields,
+            next: 0,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+        byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map enum
+        identifier ignored_any
+    }
+}
+
+/// Hands each of a struct's fields to the struct's visitor
+struct FieldsTracer<'a> {
+    trace: &'a mut Trace,
+    fields: &'static [&'static str],
+    next: usize,
+}
+
+impl<'de> MapAccess<'de> for FieldsTracer<'_> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, TraceError> {
+        match self.fields.get(self.next) {
+            Some(field) => seed.deserialize(field.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, TraceError> {
+        let name = self.fields[self.next];
+        self.next += 1;
+        seed.deserialize(FieldTracer {
+            trace: self.trace,
+            name,
+            optional: false,
+        })
+    }
+}
+
+/// Deserializes one field, recording the type its deserializer asks for
+struct FieldTracer<'a> {
+    trace: &'a mut Trace,
+    name: &'static str,
+    optional: bool,
+}
+
+impl FieldTracer<'_> {
+    fn record(self, schema: Value) {
+        self.trace.properties.insert(self.name.to_string(), schema);
+        if !self.optional {
+            self.trace.required.push(self.name.to_string());
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for FieldTracer<'_> {
+    type Error = TraceError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, TraceError> {
+        Err(TraceError::custom(format!(
+            "field `{}` is not a string, number, boolean or enum",
+            self.name
+        )))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "boolean" }));
+        visitor.visit_bool(false)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "integer" }));
+        visitor.visit_i64(0)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "integer", "minimum": 0 }));
+        visitor.visit_u64(0)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "number" }));
+        visitor.visit_f64(0.0)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "string", "minLength": 1, "maxLength": 1 }));
+        visitor.visit_char(' ')
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.record(json!({ "type": "string" }));
+        visitor.visit_str("")
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, TraceError> {
+        self.optional = true;
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TraceError> {
+        let Some(first) = variants.first() else {
+            return Err(TraceError::custom(format!(
+                "field `{}` is an enum without variants",
+                self.name
+            )));
+        };
+        self.record(json!({ "type": "string", "enum": variants }));
+        // Only unit variants can be chosen from a list, so that is all this offers
+        visitor.visit_enum(first.into_deserializer())
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TraceError> {
+        self.deserialize_str(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Environment {
+        Staging,
+        Production,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Deployment {
+        environment: Environment,
+        replica_count: u32,
+        confirmed: bool,
+        reason: String,
+        max_error_rate: Option<f64>,
+    }
+
+    #[test]
+    fn test_schema_follows_the_struct() {
+        let schema = requested_schema::<Deployment>().unwrap();
+        assert_eq!(schema.r#type, "object");
+        assert_eq!(
+            schema.properties["environment"],
+            json!({ "type": "string", "enum": ["staging", "production"] })
+        );
+        assert_eq!(schema.properties["replicaCount"]["type"], "integer");
+        assert_eq!(schema.properties["confirmed"]["type"], "boolean");
+        assert_eq!(schema.properties["reason"]["type"], "string");
+        assert_eq!(schema.properties["maxErrorRate"]["type"], "number");
+        assert_eq!(
+            schema.required.unwrap(),
+            ["environment", "replicaCount", "confirmed", "reason"]
+        );
+
+        // An answer that fills in the form is a `Deployment`
+        let answer: Deployment = serde_json::from_value(json!({
+            "environment": "production",
+            "replicaCount": 3,
+            "confirmed": true,
+            "reason": "release"
+        }))
+        .unwrap();
+        assert!(matches!(answer.environment, Environment::Production));
+        assert_eq!(answer.replica_count, 3);
+        assert!(answer.confirmed);
+        assert_eq!(answer.reason, "release");
+        assert_eq!(answer.max_error_rate, None);
+    }
+
+    #[test]
+    fn test_nested_values_are_refused() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Nested {
+            tags: Vec<String>,
+        }
+        assert!(matches!(
+            requested_schema::<Nested>(),
+            Err(MCPError::UnsupportedFeature(message)) if message.contains("`tags`")
+        ));
+        assert!(requested_schema::<String>().is_err());
+    }
+}
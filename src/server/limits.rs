@@ -0,0 +1,676 @@
+//! Rate and concurrency limits for tool calls
+//!
+//! [`ToolLimits`] caps how many `tools/call` requests run at once, in total and per tool,
+//! and how fast they may arrive, per session and per tool. Calls over a limit either wait
+//! for up to a timeout or are refused straight away with an [`ErrorCode::LimitExceeded`]
+//! error, depending on the [`Overflow`] setting. Calls of tools without a handler are
+//! passed on without counting against any limit.
+//! [`Server::limit_usage`](super::Server::limit_usage) reports current usage for tuning.
+
+use crate::error::MCPError;
+use crate::schema::json_rpc::{ErrorCode, JSONRPCError, JSONRPCMessage};
+use crate::server::middleware::{BoxMcpService, McpRequest};
+use crate::server::AsyncToolHandler;
+use futures::future::BoxFuture;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower::{Service, ServiceExt};
+
+/// A token bucket: `burst` calls at once, refilled at `per_second`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Calls allowed in a burst
+    pub burst: u32,
+    /// Calls allowed per second on average
+    pub per_second: f64,
+}
+
+impl RateLimit {
+    /// Allow `per_second` calls per second, in bursts of up to `burst`
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        Self {
+            burst: burst.max(1),
+            per_second,
+        }
+    }
+}
+
+/// What happens to a call over a limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overflow {
+    /// Refuse the call immediately
+    Reject,
+    /// Wait for up to `timeout` for the call to fit, then refuse it
+    Queue {
+        /// How long a call may wait in total
+        timeout: Duration,
+    },
+}
+
+/// Limits on tool calls
+///
+/// Concurrency limits and per-tool rates are shared by every session of the server;
+/// the session rate applies to each session separately.
+#[derive(Debug, Clone)]
+pub struct ToolLimits {
+    max_in_flight: Option<usize>,
+    tool_concurrency: HashMap<String, usize>,
+    session_rate: Option<RateLimit>,
+    tool_rates: HashMap<String, RateLimit>,
+    overflow: Overflow,
+}
+
+impl ToolLimits {
+    /// No limits, refusing calls over limits added later
+    pub fn new() -> Self {
+        Self {
+            max_in_flight: None,
+            tool_concurrency: HashMap::new(),
+            session_rate: None,
+            tool_rates: HashMap::new(),
+            overflow: Overflow::Reject,
+        }
+    }
+
+    /// Run at most `max` tool calls at once across all sessions
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.max_in_flight = Some(max);
+        self
+    }
+
+    /// Run at most `max` calls of `tool` at once
+    pub fn with_tool_concurrency(mut self, tool: &str, max: usize) -> Self {
+        self.tool_concurrency.insert(tool.to_string(), max);
+        self
+    }
+
+    /// Limit how fast each session may call tools
+    pub fn with_session_rate(mut self, rate: RateLimit) -> Self {
+        self.session_rate = Some(rate);
+        self
+    }
+
+    /// Limit how fast `tool` may be called, across all sessions
+    pub fn with_tool_rate(mut self, tool: &str, rate: RateLimit) -> Self {
+        self.tool_rates.insert(tool.to_string(), rate);
+        self
+    }
+
+    /// Set what happens to calls over a limit; defaults to [`Overflow::Reject`]
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+impl Default for ToolLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Current tool call usage
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LimitUsage {
+    /// Calls running now
+    pub in_flight: usize,
+    /// The configured maximum of calls running at once
+    pub max_in_flight: Option<usize>,
+    /// Calls running now, by tool
+    pub in_flight_by_tool: HashMap<String, usize>,
+    /// Calls waiting for a limit
+    pub queued: usize,
+    /// Calls refused since the server was created
+    pub rejected: u64,
+}
+
+/// Refills continuously and hands out one token per call
+#[derive(Debug)]
+struct TokenBucket {
+    rate: RateLimit,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: RateLimit) -> Self {
+        Self {
+            rate,
+            tokens: f64::from(rate.burst),
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Give back a token taken for a call that was refused after all
+    fn refund(&mut self) {
+        self.tokens = (self.tokens + 1.0).min(f64::from(self.rate.burst));
+    }
+
+    /// Take a token, or say how long until one is available
+    fn try_take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.rate.per_second).min(f64::from(self.rate.burst));
+        self.refilled = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else if self.rate.per_second > 0.0 {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.rate.per_second,
+            ))
+        } else {
+            Err(Duration::MAX)
+        }
+    }
+}
+
+/// Limit state shared by every session of a server
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    limits: ToolLimits,
+    in_flight: Option<Arc<Semaphore>>,
+    tool_in_flight: HashMap<String, Arc<Semaphore>>,
+    tool_buckets: HashMap<String, Mutex<TokenBucket>>,
+    running: AtomicUsize,
+    running_by_tool: Mutex<HashMap<String, usize>>,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+/// Why a call was refused
+struct Refusal {
+    limit: &'static str,
+    message: String,
+}
+
+impl Limiter {
+    pub(crate) fn new(limits: ToolLimits) -> Self {
+        Self {
+            in_flight: limits
+                .max_in_flight
+                .map(|max| Arc::new(Semaphore::new(max))),
+            tool_in_flight: limits
+                .tool_concurrency
+                .iter()
+                .map(|(tool, max)| (tool.clone(), Arc::new(Semaphore::new(*max))))
+                .collect(),
+            tool_buckets: limits
+                .tool_rates
+                .iter()
+                .map(|(tool, rate)| (tool.clone(), Mutex::new(TokenBucket::new(*rate))))
+                .collect(),
+            limits,
+            running: AtomicUsize::new(0),
+            running_by_tool: Mutex::new(HashMap::new()),
+            queued: AtomicUsize::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn usage(&self) -> LimitUsage {
+        LimitUsage {
+            in_flight: self.running.load(Ordering::SeqCst),
+            max_in_flight: self.limits.max_in_flight,
+            in_flight_by_tool: self.running_by_tool.lock().unwrap().clone(),
+            queued: self.queued.load(Ordering::SeqCst),
+            rejected: self.rejected.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Wrap a session's request service, giving the session its own rate limit
+    ///
+    /// Only calls of tools with a handler in `tool_handlers` are limited.
+    pub(crate) fn layer(
+        self: &Arc<Self>,
+        inner: BoxMcpService,
+        tool_handlers: ToolHandlers,
+    ) -> Limited {
+        Limited {
+            inner,
+            limiter: Arc::clone(self),
+            tool_handlers,
+            session_bucket: self
+                .limits
+                .session_rate
+                .map(|rate| Arc::new(Mutex::new(TokenBucket::new(rate)))),
+        }
+    }
+
+    /// When a queued call has to give up
+    fn deadline(&self) -> Option<Instant> {
+        match self.limits.overflow {
+            Overflow::Reject => None,
+            Overflow::Queue { timeout } => Some(Instant::now() + timeout),
+        }
+    }
+
+    /// Take a token from `bucket`, waiting until `deadline` if queueing
+    async fn take_token(
+        &self,
+        bucket: &Mutex<TokenBucket>,
+        deadline: Option<Instant>,
+    ) -> Result<(), Duration> {
+        loop {
+            let wait = match bucket.lock().unwrap().try_take() {
+                Ok(()) => return Ok(()),
+                Err(wait) => wait,
+            };
+            match deadline {
+                Some(deadline)
+                    if Instant::now()
+                        .checked_add(wait)
+                        .is_some_and(|at| at <= deadline) =>
+                {
+                    tokio::time::sleep(wait).await;
+                }
+                _ => return Err(wait),
+            }
+        }
+    }
+
+    /// Take a permit from `semaphore`, waiting until `deadline` if queueing
+    async fn acquire(
+        semaphore: &Arc<Semaphore>,
+        deadline: Option<Instant>,
+    ) -> Option<OwnedSemaphorePermit> {
+        match deadline {
+            None => Arc::clone(semaphore).try_acquire_owned().ok(),
+            Some(deadline) => {
+                let acquire = Arc::clone(semaphore).acquire_owned();
+                tokio::time::timeout_at(deadline.into(), acquire)
+                    .await
+                    .ok()?
+                    .ok()
+            }
+        }
+    }
+
+    /// Wait for every limit that applies to a call of `tool`
+    ///
+    /// Rate tokens taken for a call that a concurrency limit then refuses are given back.
+    async fn admit(
+        &self,
+        tool: &str,
+        session_bucket: Option<&Mutex<TokenBucket>>,
+    ) -> Result<Vec<OwnedSemaphorePermit>, Refusal> {
+        let deadline = self.deadline();
+        let rate_refusal = |limit, scope: String, wait: Duration| Refusal {
+            limit,
+            message: format!(
+                "Rate limit exceeded for {}; retry in {} ms",
+                scope,
+                wait.as_millis()
+            ),
+        };
+
+        let mut taken = Vec::new();
+        let refund = |taken: &[&Mutex<TokenBucket>]| {
+            for bucket in taken {
+                bucket.lock().unwrap().refund();
+            }
+        };
+
+        if let Some(bucket) = session_bucket {
+            self.take_token(bucket, deadline)
+                .await
+                .map_err(|wait| rate_refusal("session_rate", "this session".to_string(), wait))?;
+            taken.push(bucket);
+        }
+        if let Some(bucket) = self.tool_buckets.get(tool) {
+            self.take_token(bucket, deadline).await.map_err(|wait| {
+                refund(&taken);
+                rate_refusal("tool_rate", format!("tool '{}'", tool), wait)
+            })?;
+            taken.push(bucket);
+        }
+
+        let permits = self.acquire_all(tool, deadline).await;
+        if permits.is_err() {
+            refund(&taken);
+        }
+        permits
+    }
+
+    /// Take a permit from every concurrency limit that applies to a call of `tool`
+    async fn acquire_all(
+        &self,
+        tool: &str,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<OwnedSemaphorePermit>, Refusal> {
+        let mut permits = Vec::new();
+        if let Some(semaphore) = &self.in_flight {
+            permits.push(
+                Self::acquire(semaphore, deadline)
+                    .await
+                    .ok_or_else(|| Refusal {
+                        limit: "max_in_flight",
+                        message: format!(
+                            "Too many tool calls in flight (limit {})",
+                            self.limits.max_in_flight.unwrap_or_default()
+                        ),
+                    })?,
+            );
+        }
+        if let Some(semaphore) = self.tool_in_flight.get(tool) {
+            permits.push(
+                Self::acquire(semaphore, deadline)
+                    .await
+                    .ok_or_else(|| Refusal {
+                        limit: "tool_concurrency",
+                        message: format!(
+                            "Too many concurrent calls of tool '{}' (limit {})",
+                            tool, self.limits.tool_concurrency[tool]
+                        ),
+                    })?,
+            );
+        }
+        Ok(permits)
+    }
+}
+
+/// Counts a call as running until dropped
+struct Running<'a> {
+    limiter: &'a Limiter,
+    tool: String,
+}
+
+impl<'a> Running<'a> {
+    fn start(limiter: &'a Limiter, tool: &str) -> Self {
+        limiter.running.fetch_add(1, Ordering::SeqCst);
+        *limiter
+            .running_by_tool
+            .lock()
+            .unwrap()
+            .entry(tool.to_string())
+            .or_default() += 1;
+        Self {
+            limiter,
+            tool: tool.to_string(),
+        }
+    }
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.limiter.running.fetch_sub(1, Ordering::SeqCst);
+        let mut by_tool = self.limiter.running_by_tool.lock().unwrap();
+        if let Some(count) = by_tool.get_mut(&self.tool) {
+            *count -= 1;
+            if *count == 0 {
+                by_tool.remove(&self.tool);
+            }
+        }
+    }
+}
+
+/// Counts a call as queued until dropped
+struct Queued<'a>(&'a AtomicUsize);
+
+impl<'a> Queued<'a> {
+    fn start(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The server's tool handlers, by tool name
+pub(crate) type ToolHandlers = Arc<tokio::sync::Mutex<HashMap<String, AsyncToolHandler>>>;
+
+/// A session's request service with the limits applied to tool calls
+#[derive(Clone)]
+pub(crate) struct Limited {
+    inner: BoxMcpService,
+    limiter: Arc<Limiter>,
+    tool_handlers: ToolHandlers,
+    session_bucket: Option<Arc<Mutex<TokenBucket>>>,
+}
+
+impl Service<McpRequest> for Limited {
+    type Response = JSONRPCMessage;
+    type Error = MCPError;
+    type Future = BoxFuture<'static, Result<JSONRPCMessage, MCPError>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), MCPError>> {
+        // Readiness is decided per call, once the tool is known
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: McpRequest) -> Self::Future {
+        let inner = self.inner.clone();
+        if request.method() != "tools/call" {
+            return Box::pin(inner.oneshot(request));
+        }
+
+        let limiter = Arc::clone(&self.limiter);
+        let session_bucket = self.session_bucket.clone();
+        let tool_handlers = Arc::clone(&self.tool_handlers);
+        Box::pin(async move {
+            let tool = request
+                .params()
+                .and_then(|params| params.get("name"))
+                .and_then(|name| name.as_str())
+                .unwrap_or_default()
+                .to_string();
+            // Calls of unknown tools fail at once and use up nothing
+            if !tool_handlers.lock().await.contains_key(&tool) {
+                return inner.oneshot(request).await;
+            }
+
+            let admitted = {
+                let _queued = Queued::start(&limiter.queued);
+                limiter.admit(&tool, session_bucket.as_deref()).await
+            };
+            let _permits = match admitted {
+                Ok(permits) => permits,
+                Err(refusal) => {
+                    limiter.rejected.fetch_add(1, Ordering::SeqCst);
+                    log::warn!("Refusing call of tool '{}': {}", tool, refusal.message);
+                    return Ok(JSONRPCMessage::Error(JSONRPCError::new_with_details(
+                        request.request.id,
+                        ErrorCode::LimitExceeded,
+                        refusal.message,
+                        Some(json!({ "limit": refusal.limit, "tool": tool })),
+                    )));
+                }
+            };
+
+            let _running = Running::start(&limiter, &tool);
+            inner.oneshot(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::common::Tool;
+    use crate::schema::json_rpc::{JSONRPCRequest, RequestId};
+    use crate::server::{Server, ServerConfig};
+    use crate::transport::{memory, Transport};
+    use serde_json::Value;
+
+    /// A server whose `slow` tool takes 200 ms and whose `fast` tool returns at once
+    fn server(limits: ToolLimits) -> Server {
+        let tool = |name: &str| Tool {
+            name: name.to_string(),
+            description: None,
+            ..Default::default()
+        };
+        let config = ServerConfig::new()
+            .with_tool(tool("slow"))
+            .with_tool(tool("fast"));
+        let mut server = Server::new(config).with_limits(limits);
+        server
+            .register_tool_handler("slow", |_| async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Ok(json!("slow"))
+            })
+            .unwrap();
+        server
+            .register_tool_handler("fast", |_| async { Ok(json!("fast")) })
+            .unwrap();
+        server
+    }
+
+    async fn connect(server: &Server) -> impl Transport {
+        let (mut client, server_end) = memory::pair();
+        let mut session = server.clone();
+        tokio::spawn(async move { session.serve(server_end).await });
+        client.start().await.unwrap();
+        client
+    }
+
+    /// Send calls of the given tools at once and collect the replies in ID order
+    async fn call_all(client: &mut impl Transport, tools: &[&str]) -> Vec<JSONRPCMessage> {
+        for (id, tool) in tools.iter().enumerate() {
+            let params = json!({ "name": tool });
+            let request = JSONRPCRequest::new(
+                RequestId::Number(id as i64),
+                "tools/call".to_string(),
+                Some(params),
+            );
+            client.send(JSONRPCMessage::Request(request)).await.unwrap();
+        }
+        let mut replies = Vec::new();
+        for _ in tools {
+            replies.push(client.recv().await.unwrap().unwrap());
+        }
+        replies.sort_by_key(|reply| match reply {
+            JSONRPCMessage::Response(response) => format!("{:?}", response.id),
+            JSONRPCMessage::Error(error) => format!("{:?}", error.id),
+            _ => String::new(),
+        });
+        replies
+    }
+
+    fn refused_by(reply: &JSONRPCMessage) -> Option<Value> {
+        match reply {
+            JSONRPCMessage::Error(error) if error.error.code == ErrorCode::LimitExceeded => {
+                Some(error.error.data.as_ref().unwrap()["limit"].clone())
+            }
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calls_over_concurrency_limits_are_rejected() {
+        let server = server(
+            ToolLimits::new()
+                .with_max_in_flight(2)
+                .with_tool_concurrency("slow", 1),
+        );
+        let mut client = connect(&server).await;
+
+        let probe = server.clone();
+        let usage = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            probe.limit_usage()
+        });
+        let replies = call_all(&mut client, &["slow", "slow"]).await;
+        let refusals: Vec<_> = replies.iter().filter_map(refused_by).collect();
+        assert_eq!(refusals, vec![json!("tool_concurrency")]);
+
+        let usage = usage.await.unwrap();
+        assert_eq!(usage.in_flight, 1);
+        assert_eq!(usage.in_flight_by_tool.get("slow"), Some(&1));
+        assert_eq!(usage.max_in_flight, Some(2));
+        assert_eq!(server.limit_usage().rejected, 1);
+        assert_eq!(server.limit_usage().in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_queued_calls_wait_for_their_turn() {
+        let queue = |timeout| {
+            ToolLimits::new()
+                .with_max_in_flight(1)
+                .with_overflow(Overflow::Queue { timeout })
+        };
+
+        let patient = server(queue(Duration::from_secs(5)));
+        let mut client = connect(&patient).await;
+        let replies = call_all(&mut client, &["slow", "fast"]).await;
+        assert!(replies.iter().all(|reply| refused_by(reply).is_none()));
+
+        let impatient = server(queue(Duration::from_millis(50)));
+        let mut client = connect(&impatient).await;
+        let replies = call_all(&mut client, &["slow", "fast"]).await;
+        let refusals: Vec<_> = replies.iter().filter_map(refused_by).collect();
+        assert_eq!(refusals, vec![json!("max_in_flight")]);
+    }
+
+    #[tokio::test]
+    async fn test_session_rate_applies_per_session() {
+        let server = server(ToolLimits::new().with_session_rate(RateLimit::new(0.1, 2)));
+        let mut first = connect(&server).await;
+        let mut second = connect(&server).await;
+
+        let replies = call_all(&mut first, &["fast", "fast", "fast"]).await;
+        let refusals: Vec<_> = replies.iter().filter_map(refused_by).collect();
+        assert_eq!(refusals, vec![json!("session_rate")]);
+
+        // The other session has its own budget
+        let replies = call_all(&mut second, &["fast", "fast"]).await;
+        assert!(replies.iter().all(|reply| refused_by(reply).is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_refused_calls_keep_their_rate_tokens() {
+        let server = server(
+            ToolLimits::new()
+                .with_max_in_flight(1)
+                .with_tool_rate("fast", RateLimit::new(0.001, 1))
+                .with_session_rate(RateLimit::new(0.001, 2)),
+        );
+        let mut client = connect(&server).await;
+
+        // `fast` is refused for want of a slot, not for its rate
+        let replies = call_all(&mut client, &["slow", "fast"]).await;
+        let refusals: Vec<_> = replies.iter().filter_map(refused_by).collect();
+        assert_eq!(refusals, vec![json!("max_in_flight")]);
+
+        // ...and still has the token it was refused with
+        let replies = call_all(&mut client, &["fast"]).await;
+        assert!(refused_by(&replies[0]).is_none(), "{:?}", replies[0]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tools_are_not_limited() {
+        let server = server(ToolLimits::new().with_session_rate(RateLimit::new(0.001, 1)));
+        let mut client = connect(&server).await;
+
+        let replies = call_all(&mut client, &["missing", "missing"]).await;
+        assert!(replies
+            .iter()
+            .all(|reply| refused_by(reply).is_none() && matches!(reply, JSONRPCMessage::Error(_))));
+        let replies = call_all(&mut client, &["fast"]).await;
+        assert!(refused_by(&replies[0]).is_none(), "{:?}", replies[0]);
+        assert_eq!(server.limit_usage().rejected, 0);
+    }
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let mut bucket = TokenBucket::new(RateLimit::new(10.0, 2));
+        assert!(bucket.try_take().is_ok());
+        assert!(bucket.try_take().is_ok());
+        let wait = bucket.try_take().unwrap_err();
+        assert!(wait <= Duration::from_millis(100), "{:?}", wait);
+
+        bucket.refilled -= Duration::from_millis(100);
+        assert!(bucket.try_take().is_ok());
+        assert!(bucket.try_take().is_err());
+    }
+}
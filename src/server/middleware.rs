@@ -0,0 +1,347 @@
+//! Middleware around request dispatch
+//!
+//! Every request a [`Server`](super::Server) receives is handed to a [`tower::Service`]
+//! that takes an [`McpRequest`] and produces the message to send back. Layers added with
+//! [`Server::layer`](super::Server::layer) wrap that service, so they see each request's
+//! method, params and [`SessionContext`] before the server does. A layer can rewrite the
+//! request, answer it without calling the inner service, or wrap the inner call, e.g. to
+//! time it. Any `tower` middleware whose service keeps the request and response types
+//! works as a layer.
+//!
+//! ```rust
+//! use mcpr::server::middleware::{BoxMcpService, McpRequest};
+//! use mcpr::server::{Server, ServerConfig};
+//! use tower::layer::layer_fn;
+//! use tower::ServiceExt;
+//!
+//! // Let only admins past `initialize` and `tools/list`
+//! let admins_only = layer_fn(|inner: BoxMcpService| {
+//!     tower::service_fn(move |request: McpRequest| {
+//!         let inner = inner.clone();
+//!         async move {
+//!             let is_admin = request
+//!                 .session
+//!                 .principal
+//!                 .as_ref()
+//!                 .is_some_and(|principal| principal.has_role("admin"));
+//!             match request.method() {
+//!                 "initialize" | "tools/list" => inner.oneshot(request).await,
+//!                 _ if is_admin => inner.oneshot(request).await,
+//!                 _ => Ok(request.error(-32001, "Admins only")),
+//!             }
+//!         }
+//!     })
+//! });
+//! let server = Server::new(ServerConfig::new()).layer(admins_only);
+//! ```
+
+use crate::auth::policy::Policy;
+use crate::auth::session::{Credentials, Principal};
+use crate::error::MCPError;
+use crate::schema::client::ClientCapabilities;
+use crate::schema::json_rpc::{
+    ErrorCode, JSONRPCError, JSONRPCMessage, JSONRPCRequest, JSONRPCResponse,
+};
+use crate::schema::version::{ProtocolVersion, Versioned};
+use crate::server::context::ClientPeer;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+use tower::util::BoxCloneService;
+
+/// The request service a layer wraps
+pub type BoxMcpService = BoxCloneService<McpRequest, JSONRPCMessage, MCPError>;
+
+/// Wraps a request service in one layer
+pub(crate) type LayerFn = Arc<dyn Fn(BoxMcpService) -> BoxMcpService + Send + Sync>;
+
+/// What the server knows about the session a request arrived on
+#[derive(Clone, Default)]
+pub struct SessionContext {
+    /// The authenticated identity, if the server has an authenticator
+    pub principal: Option<Arc<Principal>>,
+    /// What the peer presented when the session was established
+    pub credentials: Arc<Credentials>,
+    /// The protocol revision agreed on in `initialize`, once the client has sent it
+    pub protocol_version: Arc<RwLock<Option<ProtocolVersion>>>,
+    /// What the client announced it supports in `initialize`
+    pub client_capabilities: Arc<RwLock<Option<ClientCapabilities>>>,
+    // Carries requests from the server to the client, while the session is served
+    pub(crate) peer: Option<Arc<ClientPeer>>,
+    // The server's policy, for layers that serve resources and prompts
+    pub(crate) policy: Option<Arc<dyn Policy>>,
+}
+
+impl std::fmt::Debug for SessionContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionContext")
+            .field("principal", &self.principal)
+            .field("credentials", &self.credentials)
+            .field("protocol_version", &self.protocol_version)
+            .field("client_capabilities", &self.client_capabilities)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SessionContext {
+    /// Whether the server's policy lets this session use the resource at `uri`
+    ///
+    /// Always true on servers without a policy.
+    pub fn allows_resource(&self, uri: &str) -> bool {
+        self.policy
+            .as_ref()
+            .is_none_or(|policy| policy.allow_resource(self.principal.as_deref(), uri))
+    }
+
+    /// Whether the server's policy lets this session use `prompt`
+    ///
+    /// Always true on servers without a policy.
+    pub fn allows_prompt(&self, prompt: &str) -> bool {
+        self.policy
+            .as_ref()
+            .is_none_or(|policy| policy.allow_prompt(self.principal.as_deref(), prompt))
+    }
+
+    /// The protocol revision responses must keep to
+    ///
+    /// The latest revision until the session is initialized.
+    pub fn negotiated_version(&self) -> ProtocolVersion {
+        self.protocol_version
+            .read()
+            .unwrap()
+            .unwrap_or(ProtocolVersion::LATEST)
+    }
+}
+
+/// A request on its way through the middleware to the server
+#[derive(Debug, Clone)]
+pub struct McpRequest {
+    /// The JSON-RPC request as received, or as rewritten by outer layers
+    pub request: JSONRPCRequest,
+    /// The session the request arrived on
+    pub session: SessionContext,
+}
+
+impl McpRequest {
+    /// Wrap a request from a session the server knows nothing about
+    pub fn new(request: JSONRPCRequest) -> Self {
+        Self {
+            request,
+            session: SessionContext::default(),
+        }
+    }
+
+    /// The method being called
+    pub fn method(&self) -> &str {
+        &self.request.method
+    }
+
+    /// The request's params
+    pub fn params(&self) -> Option<&Value> {
+        self.request.params.as_ref()
+    }
+
+    /// The request's params, for layers that rewrite arguments
+    pub fn params_mut(&mut self) -> &mut Option<Value> {
+        &mut self.request.params
+    }
+
+    /// A successful response to this request
+    pub fn respond(&self, result: Value) -> JSONRPCMessage {
+        JSONRPCMessage::Response(JSONRPCResponse::new(self.request.id.clone(), result))
+    }
+
+    /// A successful response to this request, restricted to the session's revision
+    ///
+    /// Layers that answer requests themselves, e.g. `prompts/get` or `resources/list`,
+    /// should answer with this so older clients never see fields they do not know.
+    pub fn respond_with<T: Serialize + Versioned>(
+        &self,
+        mut result: T,
+    ) -> Result<JSONRPCMessage, MCPError> {
+        result.restrict_to(self.session.negotiated_version());
+        Ok(self.respond(serde_json::to_value(result)?))
+    }
+
+    /// An error response to this request
+    pub fn error(&self, code: impl Into<ErrorCode>, message: &str) -> JSONRPCMessage {
+        JSONRPCMessage::Error(JSONRPCError::new_with_details(
+            self.request.id.clone(),
+            code,
+            message.to_string(),
+            None,
+        ))
+    }
+}
+
+impl From<JSONRPCRequest> for McpRequest {
+    fn from(request: JSONRPCRequest) -> Self {
+        Self::new(request)
+    }
+}
+
+/// Turn the error of a `tower` middleware back into an [`MCPError`]
+pub(crate) fn into_mcp_error(error: tower::BoxError) -> MCPError {
+    match error.downcast::<MCPError>() {
+        Ok(error) => *error,
+        Err(error) => MCPError::Protocol(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::client::ListPromptsResult;
+    use crate::schema::common::Tool;
+    use crate::schema::json_rpc::RequestId;
+    use crate::server::{Server, ServerConfig};
+    use crate::transport::{memory, Transport};
+    use serde_json::json;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tower::layer::layer_fn;
+    use tower::ServiceExt;
+
+    fn server() -> Server {
+        let tool = |name: &str| Tool {
+            name: name.to_string(),
+            description: None,
+            ..Default::default()
+        };
+        let config = ServerConfig::new()
+            .with_tool(tool("echo"))
+            .with_tool(tool("slow"));
+        let mut server = Server::new(config);
+        server
+            .register_tool_handler("echo", |params: Value| async move { Ok(params) })
+            .unwrap();
+        server
+            .register_tool_handler("slow", |_| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(json!("done"))
+            })
+            .unwrap();
+        server
+    }
+
+    /// Serve one session, returning the client's end
+    async fn connect(mut server: Server) -> impl Transport {
+        let (mut client, server_end) = memory::pair();
+        tokio::spawn(async move { server.serve(server_end).await });
+        client.start().await.unwrap();
+        client
+    }
+
+    async fn call(client: &mut impl Transport, method: &str, params: Value) -> JSONRPCMessage {
+        let request = JSONRPCRequest::new(RequestId::Number(1), method.to_string(), Some(params));
+        client.send(JSONRPCMessage::Request(request)).await.unwrap();
+        client.recv().await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_layers_rewrite_and_short_circuit() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        // Inner layer: shout every echo
+        let shout = layer_fn(|inner: BoxMcpService| {
+            tower::service_fn(move |mut request: McpRequest| {
+                if let Some(Value::Object(params)) = request.params_mut() {
+                    if let Some(arguments) = params.get_mut("arguments") {
+                        arguments["message"] = json!("HELLO");
+                    }
+                }
+                inner.clone().oneshot(request)
+            })
+        });
+        // Outer layer: records what it sees and refuses `forbidden`
+        let log = Arc::clone(&seen);
+        let gate = layer_fn(move |inner: BoxMcpService| {
+            let log = Arc::clone(&log);
+            tower::service_fn(move |request: McpRequest| {
+                log.lock().unwrap().push(request.method().to_string());
+                let inner = inner.clone();
+                async move {
+                    if request.method() == "forbidden" {
+                        return Ok(request.error(-32001, "Not here"));
+                    }
+                    inner.oneshot(request).await
+                }
+            })
+        });
+
+        let mut client = connect(server().layer(shout).layer(gate)).await;
+
+        let arguments = json!({ "name": "echo", "arguments": { "message": "hello" } });
+        match call(&mut client, "tools/call", arguments).await {
+            JSONRPCMessage::Response(response) => {
+                let text = response.result["content"][0]["text"].as_str().unwrap();
+                assert!(text.contains("HELLO"), "{}", text);
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+
+        match call(&mut client, "forbidden", json!({})).await {
+            JSONRPCMessage::Error(error) => {
+                assert_eq!(error.error.code, -32001);
+                assert_eq!(error.error.message, "Not here");
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+        assert_eq!(*seen.lock().unwrap(), vec!["tools/call", "forbidden"]);
+    }
+
+    #[tokio::test]
+    async fn test_tower_middleware_works_as_a_layer() {
+        let server = server().layer(tower::timeout::TimeoutLayer::new(Duration::from_millis(50)));
+        let mut client = connect(server).await;
+
+        match call(&mut client, "tools/call", json!({ "name": "slow" })).await {
+            JSONRPCMessage::Error(error) => {
+                assert_eq!(error.error.code, -32603);
+                assert!(
+                    error.error.message.contains("timed out"),
+                    "{}",
+                    error.error.message
+                );
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+        assert!(matches!(
+            call(&mut client, "tools/list", json!({})).await,
+            JSONRPCMessage::Response(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_layer_responses_keep_to_the_negotiated_revision() {
+        // Answers prompts/list itself, with a title only 2025-06-18 knows
+        let prompts = layer_fn(|inner: BoxMcpService| {
+            tower::service_fn(move |request: McpRequest| {
+                let inner = inner.clone();
+                async move {
+                    if request.method() != "prompts/list" {
+                        return inner.oneshot(request).await;
+                    }
+                    let result: ListPromptsResult = serde_json::from_value(json!({
+                        "prompts": [{ "name": "greet", "title": "Greeting" }]
+                    }))?;
+                    request.respond_with(result)
+                }
+            })
+        });
+        let mut client = connect(server().layer(prompts)).await;
+
+        let initialize = json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": { "name": "old", "version": "1.0.0" }
+        });
+        call(&mut client, "initialize", initialize).await;
+        match call(&mut client, "prompts/list", json!({})).await {
+            JSONRPCMessage::Response(response) => {
+                assert_eq!(response.result, json!({ "prompts": [{ "name": "greet" }] }));
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+}
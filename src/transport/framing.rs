@@ -0,0 +1,409 @@
+//! Message framing for byte-stream transports
+//!
+//! A byte stream (stdin/stdout, a pipe, a PTY or a socket) has no notion of message
+//! boundaries, so stream-based transports delegate to a [`Framing`] implementation to
+//! split the stream into individual JSON-RPC payloads.
+//!
+//! Two framings are provided:
+//! - [`NewlineDelimited`]: one JSON document per line, as used by MCP's stdio transport
+//! - [`ContentLength`]: `Content-Length: N` headers followed by a blank line and the body,
+//!   as used by the Language Server Protocol
+//!
+//! Both refuse frames larger than [`DEFAULT_MAX_FRAME_SIZE`] unless configured otherwise
+//! with `with_max_frame_size`. An oversized frame is skipped without being buffered and
+//! reported as an [`MCPError::Transport`]; the frames after it are read as usual.
+
+use crate::error::MCPError;
+use async_trait::async_trait;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Largest frame the framings read unless configured otherwise: 16 MiB
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Longest header line the `Content-Length` framing reads
+const MAX_HEADER_LINE: usize = 8 * 1024;
+
+/// The error for a frame over `limit` bytes
+fn too_large(size: impl std::fmt::Display, limit: usize) -> MCPError {
+    MCPError::Transport(format!(
+        "Frame of {} bytes exceeds the limit of {} bytes",
+        size, limit
+    ))
+}
+
+/// Strategy for splitting a byte stream into messages
+#[async_trait]
+pub trait Framing: Send + Sync {
+    /// Read the next frame from the stream
+    ///
+    /// Returns `Ok(None)` when the stream has reached end-of-file.
+    async fn read_frame(
+        &self,
+        reader: &mut (dyn AsyncBufRead + Send + Unpin),
+    ) -> Result<Option<String>, MCPError>;
+
+    /// Write a single frame to the stream and flush it
+    async fn write_frame(
+        &self,
+        writer: &mut (dyn AsyncWrite + Send + Unpin),
+        frame: &str,
+    ) -> Result<(), MCPError>;
+}
+
+/// Newline-delimited JSON framing
+///
+/// Each message is written on its own line. Blank lines between messages are skipped.
+#[derive(Debug, Clone, Copy)]
+pub struct NewlineDelimited {
+    max_frame_size: usize,
+}
+
+impl NewlineDelimited {
+    /// Newline framing that reads frames of up to [`DEFAULT_MAX_FRAME_SIZE`] bytes
+    pub fn new() -> Self {
+        Self {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Refuse lines longer than `bytes`, not counting the line ending
+    pub fn with_max_frame_size(mut self, bytes: usize) -> Self {
+        self.max_frame_size = bytes;
+        self
+    }
+}
+
+impl Default for NewlineDelimited {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Framing for NewlineDelimited {
+    async fn read_frame(
+        &self,
+        reader: &mut (dyn AsyncBufRead + Send + Unpin),
+    ) -> Result<Option<String>, MCPError> {
+        let read_error = |e| MCPError::Transport(format!("Failed to read: {}", e));
+        // Room for the frame and a `\r\n` line ending
+        let limit = self.max_frame_size as u64 + 2;
+        loop {
+            let mut line = Vec::new();
+            let read = (&mut *reader)
+                .take(limit)
+                .read_until(b'\n', &mut line)
+                .await
+                .map_err(read_error)?;
+
+            if read == 0 {
+                return Ok(None);
+            }
+
+            if read as u64 == limit && !line.ends_with(b"\n") {
+                // Skip the rest of the line so the next frame can still be read
+                loop {
+                    line.clear();
+                    let read = (&mut *reader)
+                        .take(limit)
+                        .read_until(b'\n', &mut line)
+                        .await
+                        .map_err(read_error)?;
+                    if read == 0 || line.ends_with(b"\n") {
+                        break;
+                    }
+                }
+                return Err(too_large("more than", self.max_frame_size));
+            }
+
+            let line = String::from_utf8(line)
+                .map_err(|e| MCPError::Transport(format!("Line is not valid UTF-8: {}", e)))?;
+            let trimmed = line.trim_end_matches(['\r', '\n']);
+            if trimmed.len() > self.max_frame_size {
+                return Err(too_large(trimmed.len(), self.max_frame_size));
+            }
+            if !trimmed.trim().is_empty() {
+                return Ok(Some(trimmed.to_string()));
+            }
+        }
+    }
+
+    async fn write_frame(
+        &self,
+        writer: &mut (dyn AsyncWrite + Send + Unpin),
+        frame: &str,
+    ) -> Result<(), MCPError> {
+        if frame.contains('\n') {
+            return Err(MCPError::Transport(
+                "Newline-delimited frames must not contain embedded newlines".to_string(),
+            ));
+        }
+
+        writer
+            .write_all(frame.as_bytes())
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to write: {}", e)))?;
+        writer
+            .write_all(b"\n")
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to write newline: {}", e)))?;
+        writer
+            .flush()
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to flush: {}", e)))
+    }
+}
+
+/// `Content-Length` header framing, as used by the Language Server Protocol
+///
+/// Each message is preceded by a header block terminated by an empty line. Only the
+/// `Content-Length` header is interpreted; any other headers are ignored.
+#[derive(Debug, Clone, Copy)]
+pub struct ContentLength {
+    max_frame_size: usize,
+}
+
+impl ContentLength {
+    /// `Content-Length` framing that reads bodies of up to [`DEFAULT_MAX_FRAME_SIZE`] bytes
+    pub fn new() -> Self {
+        Self {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Refuse bodies longer than `bytes`
+    pub fn with_max_frame_size(mut self, bytes: usize) -> Self {
+        self.max_frame_size = bytes;
+        self
+    }
+}
+
+impl Default for ContentLength {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Framing for ContentLength {
+    async fn read_frame(
+        &self,
+        reader: &mut (dyn AsyncBufRead + Send + Unpin),
+    ) -> Result<Option<String>, MCPError> {
+        let mut content_length: Option<usize> = None;
+        let mut seen_header = false;
+
+        loop {
+            // Header lines are short; a longer one is no header
+            let mut line = String::new();
+            let read = (&mut *reader)
+                .take(MAX_HEADER_LINE as u64)
+                .read_line(&mut line)
+                .await
+                .map_err(|e| MCPError::Transport(format!("Failed to read header: {}", e)))?;
+            if read == MAX_HEADER_LINE && !line.ends_with('\n') {
+                return Err(MCPError::Transport(format!(
+                    "Header line longer than {} bytes",
+                    MAX_HEADER_LINE
+                )));
+            }
+
+            if read == 0 {
+                if seen_header {
+                    return Err(MCPError::Transport(
+                        "Stream ended in the middle of a header block".to_string(),
+                    ));
+                }
+                return Ok(None);
+            }
+
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                if seen_header {
+                    break;
+                }
+                // Tolerate stray blank lines between messages
+                continue;
+            }
+            seen_header = true;
+
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| MCPError::Transport(format!("Malformed header: {}", line)))?;
+
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let length = value.trim().parse::<usize>().map_err(|_| {
+                    MCPError::Transport(format!("Invalid Content-Length: {}", value.trim()))
+                })?;
+                content_length = Some(length);
+            }
+        }
+
+        let length = content_length
+            .ok_or_else(|| MCPError::Transport("Missing Content-Length header".to_string()))?;
+
+        if length > self.max_frame_size {
+            // Skip the body so the next frame can still be read
+            tokio::io::copy(
+                &mut (&mut *reader).take(length as u64),
+                &mut tokio::io::sink(),
+            )
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to read body: {}", e)))?;
+            return Err(too_large(length, self.max_frame_size));
+        }
+
+        let mut body = vec![0u8; length];
+        reader
+            .read_exact(&mut body)
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to read body: {}", e)))?;
+
+        String::from_utf8(body)
+            .map(Some)
+            .map_err(|e| MCPError::Transport(format!("Message body is not valid UTF-8: {}", e)))
+    }
+
+    async fn write_frame(
+        &self,
+        writer: &mut (dyn AsyncWrite + Send + Unpin),
+        frame: &str,
+    ) -> Result<(), MCPError> {
+        let header = format!("Content-Length: {}\r\n\r\n", frame.len());
+
+        writer
+            .write_all(header.as_bytes())
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to write header: {}", e)))?;
+        writer
+            .write_all(frame.as_bytes())
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to write body: {}", e)))?;
+        writer
+            .flush()
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to flush: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    #[tokio::test]
+    async fn test_newline_round_trip() {
+        let mut buffer = Vec::new();
+        NewlineDelimited::new()
+            .write_frame(&mut buffer, r#"{"a":1}"#)
+            .await
+            .unwrap();
+        NewlineDelimited::new()
+            .write_frame(&mut buffer, r#"{"b":2}"#)
+            .await
+            .unwrap();
+        assert_eq!(buffer, b"{\"a\":1}\n{\"b\":2}\n");
+
+        let mut reader = BufReader::new(&buffer[..]);
+        let first = NewlineDelimited::new()
+            .read_frame(&mut reader)
+            .await
+            .unwrap();
+        let second = NewlineDelimited::new()
+            .read_frame(&mut reader)
+            .await
+            .unwrap();
+        let eof = NewlineDelimited::new()
+            .read_frame(&mut reader)
+            .await
+            .unwrap();
+
+        assert_eq!(first.as_deref(), Some(r#"{"a":1}"#));
+        assert_eq!(second.as_deref(), Some(r#"{"b":2}"#));
+        assert!(eof.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_newline_rejects_embedded_newline() {
+        let mut buffer = Vec::new();
+        let result = NewlineDelimited::new()
+            .write_frame(&mut buffer, "{\n}")
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_content_length_round_trip() {
+        let mut buffer = Vec::new();
+        ContentLength::new()
+            .write_frame(&mut buffer, "{\n  \"a\": \"é\"\n}")
+            .await
+            .unwrap();
+        ContentLength::new()
+            .write_frame(&mut buffer, "{}")
+            .await
+            .unwrap();
+
+        let mut reader = BufReader::new(&buffer[..]);
+        let first = ContentLength::new().read_frame(&mut reader).await.unwrap();
+        let second = ContentLength::new().read_frame(&mut reader).await.unwrap();
+        let eof = ContentLength::new().read_frame(&mut reader).await.unwrap();
+
+        assert_eq!(first.as_deref(), Some("{\n  \"a\": \"é\"\n}"));
+        assert_eq!(second.as_deref(), Some("{}"));
+        assert!(eof.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_content_length_ignores_other_headers() {
+        let input = b"Content-Type: application/vscode-jsonrpc; charset=utf-8\r\ncontent-length: 2\r\n\r\n{}";
+        let mut reader = BufReader::new(&input[..]);
+        let frame = ContentLength::new().read_frame(&mut reader).await.unwrap();
+        assert_eq!(frame.as_deref(), Some("{}"));
+    }
+
+    #[tokio::test]
+    async fn test_content_length_missing_header() {
+        let input = b"X-Other: 1\r\n\r\n{}";
+        let mut reader = BufReader::new(&input[..]);
+        assert!(ContentLength::new().read_frame(&mut reader).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_newline_frames_are_limited() {
+        let framing = NewlineDelimited::new().with_max_frame_size(8);
+        let input = b"{\"a\":12}\n{\"a\":\"too long\"}\n{\"b\":1}\r\n";
+        let mut reader = BufReader::new(&input[..]);
+
+        let first = framing.read_frame(&mut reader).await.unwrap();
+        assert_eq!(first.as_deref(), Some(r#"{"a":12}"#));
+        match framing.read_frame(&mut reader).await {
+            Err(MCPError::Transport(message)) => assert!(message.contains("limit of 8")),
+            other => panic!("Expected the frame to be refused, got {:?}", other),
+        }
+        // The oversized line was skipped whole
+        let next = framing.read_frame(&mut reader).await.unwrap();
+        assert_eq!(next.as_deref(), Some(r#"{"b":1}"#));
+    }
+
+    #[tokio::test]
+    async fn test_content_length_frames_are_limited() {
+        let framing = ContentLength::new().with_max_frame_size(8);
+        let input = b"Content-Length: 12\r\n\r\n{\"a\":\"long\"}Content-Length: 2\r\n\r\n{}";
+        let mut reader = BufReader::new(&input[..]);
+
+        match framing.read_frame(&mut reader).await {
+            Err(MCPError::Transport(message)) => {
+                assert!(message.contains("12 bytes exceeds the limit of 8"))
+            }
+            other => panic!("Expected the frame to be refused, got {:?}", other),
+        }
+        let next = framing.read_frame(&mut reader).await.unwrap();
+        assert_eq!(next.as_deref(), Some("{}"));
+
+        // A huge announced length is refused before anything is allocated
+        let input = b"Content-Length: 99999999999\r\n\r\n";
+        let mut reader = BufReader::new(&input[..]);
+        assert!(ContentLength::new().read_frame(&mut reader).await.is_err());
+    }
+}
@@ -0,0 +1,574 @@
+//! In-memory transport
+//!
+//! [`pair`] returns two connected [`MemoryTransport`]s backed by channels. Messages are
+//! handed across without being serialized, as `JSONRPCMessage`s or `RawMessage`s,
+//! whichever the sender used, so a server can be embedded in the same
+//! process as its host without going through a pipe, and a real `Client` can be wired to
+//! a real `Server` in tests.
+//!
+//! For fault testing, [`pair_with_config`] can inject latency, drop messages and swap the
+//! order of adjacent messages. All randomness comes from a seedable RNG so failures are
+//! reproducible.
+//!
+//! ```rust
+//! use mcpr::schema::json_rpc::{JSONRPCMessage, JSONRPCNotification};
+//! use mcpr::transport::{memory, Transport};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), mcpr::error::MCPError> {
+//! let (mut left, mut right) = memory::pair();
+//! left.start().await?;
+//! right.start().await?;
+//!
+//! let hello = JSONRPCNotification::new("hello".to_string(), None);
+//! left.send(JSONRPCMessage::Notification(hello)).await?;
+//! match right.recv().await? {
+//!     Some(JSONRPCMessage::Notification(received)) => assert_eq!(received.method, "hello"),
+//!     other => panic!("unexpected message: {:?}", other),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::MCPError;
+use crate::schema::json_rpc::JSONRPCMessage;
+use crate::schema::raw::RawMessage;
+use crate::transport::{
+    CloseCallback, ErrorCallback, MessageCallback, SharedErrorCallback, Transport,
+    TransportReceiver, TransportSender,
+};
+use async_trait::async_trait;
+use log::debug;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{sleep_until, timeout, Instant};
+
+/// How long a message held back for reordering waits for a successor before it is
+/// delivered anyway
+const REORDER_WINDOW: Duration = Duration::from_millis(20);
+
+/// Fault injection settings for an in-memory transport pair
+///
+/// The settings apply to both directions independently.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryConfig {
+    /// Delay added to every message
+    pub latency: Option<Duration>,
+    /// Probability in `[0, 1]` that a message is silently dropped
+    pub drop_rate: f64,
+    /// Probability in `[0, 1]` that a message is swapped with the one after it
+    pub reorder_rate: f64,
+    /// Seed for the fault RNG; a random seed is used when unset
+    pub seed: Option<u64>,
+}
+
+impl MemoryConfig {
+    /// Create a configuration with no faults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay every message by `latency`
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Drop messages with the given probability
+    pub fn with_drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Swap adjacent messages with the given probability
+    pub fn with_reorder_rate(mut self, rate: f64) -> Self {
+        self.reorder_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Seed the fault RNG for reproducible runs
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    fn has_faults(&self) -> bool {
+        self.latency.is_some() || self.drop_rate > 0.0 || self.reorder_rate > 0.0
+    }
+}
+
+/// Unit of delivery between the two ends of a pair
+#[derive(Debug)]
+enum Envelope {
+    Message(Payload),
+    Close,
+}
+
+/// A message in the form its sender handed it over
+///
+/// It is only converted if the receiver asks for the other form.
+#[derive(Debug)]
+enum Payload {
+    Typed(JSONRPCMessage),
+    Raw(RawMessage),
+}
+
+impl Payload {
+    fn typed(self) -> Result<JSONRPCMessage, serde_json::Error> {
+        match self {
+            Self::Typed(message) => Ok(message),
+            Self::Raw(message) => message.try_into(),
+        }
+    }
+
+    fn raw(self) -> Result<RawMessage, serde_json::Error> {
+        match self {
+            Self::Typed(message) => message.try_into(),
+            Self::Raw(message) => Ok(message),
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Result<String> {
+        match self {
+            Self::Typed(message) => serde_json::to_string(message),
+            Self::Raw(message) => serde_json::to_string(message),
+        }
+    }
+}
+
+/// Outgoing half of one direction; faulty links route through a forwarding task
+#[derive(Clone)]
+enum Link {
+    Direct(mpsc::UnboundedSender<Envelope>),
+    Faulty(mpsc::UnboundedSender<(Instant, Envelope)>, Option<Duration>),
+}
+
+impl Link {
+    fn send(&self, envelope: Envelope) -> Result<(), MCPError> {
+        let result = match self {
+            Link::Direct(tx) => tx.send(envelope).map_err(|_| ()),
+            Link::Faulty(tx, latency) => {
+                let deliver_at = Instant::now() + latency.unwrap_or_default();
+                tx.send((deliver_at, envelope)).map_err(|_| ())
+            }
+        };
+        result.map_err(|_| MCPError::ConnectionClosed("Peer transport dropped".to_string()))
+    }
+}
+
+/// One end of an in-memory transport pair
+pub struct MemoryTransport {
+    sender: MemorySender,
+    receiver: MemoryReceiver,
+}
+
+/// Sending half of a [`MemoryTransport`]
+struct MemorySender {
+    link: Link,
+    is_connected: Arc<AtomicBool>,
+    on_close: Option<CloseCallback>,
+    on_error: Option<SharedErrorCallback>,
+}
+
+/// Receiving half of a [`MemoryTransport`]
+struct MemoryReceiver {
+    receiver: mpsc::UnboundedReceiver<Envelope>,
+    peer_closed: bool,
+    on_message: Option<MessageCallback>,
+}
+
+/// Create two connected in-memory transports
+pub fn pair() -> (MemoryTransport, MemoryTransport) {
+    pair_with_config(MemoryConfig::default())
+}
+
+/// Create two connected in-memory transports with fault injection
+///
+/// Must be called from within a tokio runtime when any fault is configured, since each
+/// direction is then served by a forwarding task.
+pub fn pair_with_config(config: MemoryConfig) -> (MemoryTransport, MemoryTransport) {
+    let seed = config.seed.unwrap_or_else(rand::random);
+    let (left_link, right_rx) = link(&config, seed);
+    let (right_link, left_rx) = link(&config, seed.wrapping_add(1));
+
+    (
+        MemoryTransport::new(left_link, left_rx),
+        MemoryTransport::new(right_link, right_rx),
+    )
+}
+
+/// Build one direction of a pair
+fn link(config: &MemoryConfig, seed: u64) -> (Link, mpsc::UnboundedReceiver<Envelope>) {
+    let (out_tx, out_rx) = mpsc::unbounded_channel();
+
+    if !config.has_faults() {
+        return (Link::Direct(out_tx), out_rx);
+    }
+
+    let (in_tx, in_rx) = mpsc::unbounded_channel();
+    tokio::spawn(forward(
+        in_rx,
+        out_tx,
+        config.clone(),
+        StdRng::seed_from_u64(seed),
+    ));
+    (Link::Faulty(in_tx, config.latency), out_rx)
+}
+
+/// Deliver messages from `input` to `output`, applying the configured faults
+async fn forward(
+    mut input: mpsc::UnboundedReceiver<(Instant, Envelope)>,
+    output: mpsc::UnboundedSender<Envelope>,
+    config: MemoryConfig,
+    mut rng: StdRng,
+) {
+    let mut held: Option<Envelope> = None;
+
+    loop {
+        let next = if held.is_some() {
+            match timeout(REORDER_WINDOW, input.recv()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    // Nothing followed in time; release the held message in order
+                    if let Some(envelope) = held.take() {
+                        let _ = output.send(envelope);
+                    }
+                    continue;
+                }
+            }
+        } else {
+            input.recv().await
+        };
+
+        let Some((deliver_at, envelope)) = next else {
+            if let Some(envelope) = held.take() {
+                let _ = output.send(envelope);
+            }
+            break;
+        };
+        sleep_until(deliver_at).await;
+
+        let message = match envelope {
+            Envelope::Message(message) => message,
+            Envelope::Close => {
+                // Never reorder across a close; flush anything held back first
+                if let Some(envelope) = held.take() {
+                    let _ = output.send(envelope);
+                }
+                let _ = output.send(Envelope::Close);
+                continue;
+            }
+        };
+
+        if rng.gen_bool(config.drop_rate) {
+            debug!("Memory transport dropped a message");
+            continue;
+        }
+        if held.is_none() && rng.gen_bool(config.reorder_rate) {
+            debug!("Memory transport holding a message for reordering");
+            held = Some(Envelope::Message(message));
+            continue;
+        }
+
+        if output.send(Envelope::Message(message)).is_err() {
+            break;
+        }
+        if let Some(envelope) = held.take() {
+            if output.send(envelope).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+impl MemoryTransport {
+    fn new(link: Link, receiver: mpsc::UnboundedReceiver<Envelope>) -> Self {
+        Self {
+            sender: MemorySender {
+                link,
+                is_connected: Arc::new(AtomicBool::new(false)),
+                on_close: None,
+                on_error: None,
+            },
+            receiver: MemoryReceiver {
+                receiver,
+                peer_closed: false,
+                on_message: None,
+            },
+        }
+    }
+
+    /// Check whether the transport has been started and not yet closed
+    pub fn is_connected(&self) -> bool {
+        self.sender.is_connected.load(Ordering::SeqCst)
+    }
+}
+
+impl MemorySender {
+    /// Report an error to the error callback if set, then hand it back
+    fn handle_error(&self, error: MCPError) -> MCPError {
+        if let Some(callback) = &self.on_error {
+            callback(&error);
+        }
+        error
+    }
+
+    /// Hand a message to the peer
+    fn deliver(&self, payload: Payload) -> Result<(), MCPError> {
+        if !self.is_connected.load(Ordering::SeqCst) {
+            return Err(
+                self.handle_error(MCPError::Transport("Transport not connected".to_string()))
+            );
+        }
+
+        self.link
+            .send(Envelope::Message(payload))
+            .map_err(|e| self.handle_error(e))
+    }
+}
+
+#[async_trait]
+impl TransportSender for MemorySender {
+    async fn send(&self, message: JSONRPCMessage) -> Result<(), MCPError> {
+        self.deliver(Payload::Typed(message))
+    }
+
+    async fn send_raw(&self, message: RawMessage) -> Result<(), MCPError> {
+        self.deliver(Payload::Raw(message))
+    }
+
+    async fn close(&self) -> Result<(), MCPError> {
+        if !self.is_connected.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        // The peer may already be gone, in which case there is nobody to notify
+        let _ = self.link.send(Envelope::Close);
+
+        if let Some(callback) = &self.on_close {
+            callback();
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TransportReceiver for MemoryReceiver {
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        match self.next().await {
+            Some(payload) => Ok(Some(payload.typed()?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn recv_raw(&mut self) -> Result<Option<RawMessage>, MCPError> {
+        match self.next().await {
+            Some(payload) => Ok(Some(payload.raw()?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl MemoryReceiver {
+    /// The next message, or `None` once the peer has closed
+    async fn next(&mut self) -> Option<Payload> {
+        if self.peer_closed {
+            return None;
+        }
+
+        match self.receiver.recv().await {
+            Some(Envelope::Message(payload)) => {
+                if let Some(callback) = &self.on_message {
+                    if let Ok(json) = payload.to_json() {
+                        callback(&json);
+                    }
+                }
+                Some(payload)
+            }
+            Some(Envelope::Close) | None => {
+                self.peer_closed = true;
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for MemoryTransport {
+    async fn start(&mut self) -> Result<(), MCPError> {
+        self.sender.is_connected.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn send(&mut self, message: JSONRPCMessage) -> Result<(), MCPError> {
+        self.sender.send(message).await
+    }
+
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        if !self.is_connected() {
+            return Err(self
+                .sender
+                .handle_error(MCPError::Transport("Transport not connected".to_string())));
+        }
+
+        self.receiver.recv().await
+    }
+
+    async fn close(&mut self) -> Result<(), MCPError> {
+        self.sender.close().await
+    }
+
+    fn into_split(self: Box<Self>) -> (Arc<dyn TransportSender>, Box<dyn TransportReceiver>) {
+        (Arc::new(self.sender), Box::new(self.receiver))
+    }
+
+    fn set_on_close(&mut self, callback: Option<CloseCallback>) {
+        self.sender.on_close = callback;
+    }
+
+    fn set_on_error(&mut self, callback: Option<ErrorCallback>) {
+        self.sender.on_error = callback.map(Arc::from);
+    }
+
+    fn set_on_message(&mut self, callback: Option<MessageCallback>) {
+        self.receiver.on_message = callback;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::schema::common::Tool;
+    use crate::schema::json_rpc::JSONRPCNotification;
+    use crate::server::{Server, ServerConfig};
+    use serde_json::Value;
+
+    fn numbered(n: i64) -> JSONRPCMessage {
+        JSONRPCMessage::Notification(JSONRPCNotification::new(
+            "n".to_string(),
+            Some(serde_json::json!(n)),
+        ))
+    }
+
+    fn number_of(message: Option<JSONRPCMessage>) -> i64 {
+        match message {
+            Some(JSONRPCMessage::Notification(n)) => n.params.unwrap().as_i64().unwrap(),
+            other => panic!("Expected notification, got {:?}", other),
+        }
+    }
+
+    async fn started(config: MemoryConfig) -> (MemoryTransport, MemoryTransport) {
+        let (mut left, mut right) = pair_with_config(config);
+        left.start().await.unwrap();
+        right.start().await.unwrap();
+        (left, right)
+    }
+
+    #[tokio::test]
+    async fn test_real_client_and_server() -> Result<(), MCPError> {
+        let config = ServerConfig::new().with_name("Embedded").with_tool(Tool {
+            name: "echo".to_string(),
+            description: None,
+            ..Default::default()
+        });
+        let mut server = Server::new(config);
+        server.register_tool_handler("echo", |params: Value| async move { Ok(params) })?;
+
+        let (client_end, server_end) = pair();
+        let server_handle = tokio::spawn(async move { server.serve(server_end).await });
+
+        let mut client = Client::new(client_end);
+        let init = client.initialize().await?;
+        assert_eq!(init["serverInfo"]["name"], "Embedded");
+        client.shutdown().await?;
+
+        server_handle.await.unwrap()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_close_is_seen_by_peer() {
+        let (mut left, mut right) = started(MemoryConfig::new()).await;
+        left.close().await.unwrap();
+
+        assert!(right.recv().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_raw_messages_are_handed_over_as_they_are() {
+        let (left, right) = started(MemoryConfig::new()).await;
+        let (left, _) = Box::new(left).into_split();
+        let (_, mut right) = Box::new(right).into_split();
+
+        let text = r#"{"jsonrpc":"2.0","id":1,"result":{ "spaced" : [1, 2] }}"#;
+        left.send_raw(RawMessage::parse(text).unwrap())
+            .await
+            .unwrap();
+        match right.recv_raw().await.unwrap() {
+            Some(RawMessage::Response(response)) => {
+                assert_eq!(response.result.get(), r#"{ "spaced" : [1, 2] }"#);
+            }
+            other => panic!("Expected a raw response, got {:?}", other),
+        }
+
+        // Either side may use the other form
+        left.send_raw(RawMessage::parse(text).unwrap())
+            .await
+            .unwrap();
+        match right.recv().await.unwrap() {
+            Some(JSONRPCMessage::Response(response)) => {
+                assert_eq!(response.result["spaced"], serde_json::json!([1, 2]));
+            }
+            other => panic!("Expected a response, got {:?}", other),
+        }
+        left.send(numbered(3)).await.unwrap();
+        match right.recv_raw().await.unwrap() {
+            Some(RawMessage::Notification(notification)) => {
+                assert_eq!(notification.params.unwrap().get(), "3");
+            }
+            other => panic!("Expected a raw notification, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_latency() {
+        let latency = Duration::from_millis(50);
+        let (mut left, mut right) = started(MemoryConfig::new().with_latency(latency)).await;
+
+        let start = std::time::Instant::now();
+        left.send(numbered(1)).await.unwrap();
+
+        assert_eq!(number_of(right.recv().await.unwrap()), 1);
+        assert!(start.elapsed() >= latency);
+    }
+
+    #[tokio::test]
+    async fn test_drop_everything() {
+        let (mut left, mut right) = started(MemoryConfig::new().with_drop_rate(1.0)).await;
+        left.send(numbered(0)).await.unwrap();
+
+        let result = timeout(Duration::from_millis(100), right.recv()).await;
+        assert!(result.is_err(), "Dropped message should never arrive");
+    }
+
+    #[tokio::test]
+    async fn test_reorder_swaps_adjacent_messages() {
+        let config = MemoryConfig::new().with_reorder_rate(1.0).with_seed(7);
+        let (mut left, mut right) = started(config).await;
+
+        for i in 0..4 {
+            left.send(numbered(i)).await.unwrap();
+        }
+
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            received.push(number_of(right.recv().await.unwrap()));
+        }
+        assert_eq!(received, vec![1, 0, 3, 2]);
+    }
+}
@@ -4,15 +4,22 @@
 //! Transports handle the underlying mechanics of how messages are sent and received.
 //!
 //! The following transport types are supported:
+//! - Stream: Any `AsyncRead`/`AsyncWrite` pair (pipes, PTYs, sockets) with pluggable framing
 //! - Stdio: Standard input/output for local processes
+//! - Memory: Connected in-process pairs for tests and embedding, with fault injection
+//! - TCP: Newline-delimited JSON-RPC over plain TCP sockets
+//! - Unix: Newline-delimited JSON-RPC over Unix domain sockets
 //! - SSE: Server-Sent Events for server-to-client messages with HTTP POST for client-to-server
 //! - WebSocket: Bidirectional communication over WebSockets
 //!
 //! The transport implementations are now fully async, using tokio for async I/O.
 
+use crate::auth::session::Credentials;
 use crate::error::MCPError;
+use crate::schema::json_rpc::JSONRPCMessage;
+use crate::schema::raw::RawMessage;
 use async_trait::async_trait;
-use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
 
 /// Type alias for a closure that is called when an error occurs
 pub type ErrorCallback = Box<dyn Fn(&MCPError) + Send + Sync>;
@@ -23,21 +30,34 @@ pub type MessageCallback = Box<dyn Fn(&str) + Send + Sync>;
 /// Type alias for a closure that is called when the connection is closed
 pub type CloseCallback = Box<dyn Fn() + Send + Sync>;
 
+/// Error callback shared between the two halves of a split transport
+pub(crate) type SharedErrorCallback = Arc<dyn Fn(&MCPError) + Send + Sync>;
+
 /// Transport trait for MCP communication
+///
+/// Transports exchange whole [`JSONRPCMessage`]s. The trait is object safe, so a
+/// [`BoxedTransport`] can stand in for any concrete transport chosen at runtime.
+///
+/// After [`start`](Transport::start), a transport can be broken up with
+/// [`into_split`](Transport::into_split) into a [`TransportSender`] that any number of
+/// tasks can share and a [`TransportReceiver`] owned by a single reading task.
 #[async_trait]
 pub trait Transport: Send + Sync {
     /// Start processing messages
     async fn start(&mut self) -> Result<(), MCPError>;
 
     /// Send a message
-    async fn send<T: Serialize + Send + Sync>(&mut self, message: &T) -> Result<(), MCPError>;
+    async fn send(&mut self, message: JSONRPCMessage) -> Result<(), MCPError>;
 
-    /// Receive a message
-    async fn receive<T: DeserializeOwned + Send + Sync>(&mut self) -> Result<T, MCPError>;
+    /// Receive the next message, or `None` once the peer has closed the connection
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError>;
 
     /// Close the connection
     async fn close(&mut self) -> Result<(), MCPError>;
 
+    /// Split a started transport into independent sending and receiving halves
+    fn into_split(self: Box<Self>) -> (Arc<dyn TransportSender>, Box<dyn TransportReceiver>);
+
     /// Set callback for when the connection is closed
     fn set_on_close(&mut self, callback: Option<CloseCallback>);
 
@@ -45,14 +65,146 @@ pub trait Transport: Send + Sync {
     fn set_on_error(&mut self, callback: Option<ErrorCallback>);
 
     /// Set callback for when a message is received
-    fn set_on_message<F>(&mut self, callback: Option<F>)
+    fn set_on_message(&mut self, callback: Option<MessageCallback>);
+
+    /// What the peer presented when the session was established
+    ///
+    /// Available once the transport has started. Transports that know nothing about their
+    /// peer return empty credentials.
+    fn credentials(&self) -> Credentials {
+        Credentials::default()
+    }
+
+    /// Box the transport so it can be used where the concrete type is only known at runtime
+    fn boxed(self) -> BoxedTransport
     where
-        F: Fn(&str) + Send + Sync + 'static;
+        Self: Sized + 'static,
+    {
+        Box::new(self)
+    }
+}
+
+/// Sending half of a split transport
+///
+/// Senders are shared behind an `Arc`; concurrent sends never interleave on the wire.
+#[async_trait]
+pub trait TransportSender: Send + Sync {
+    /// Send a message
+    async fn send(&self, message: JSONRPCMessage) -> Result<(), MCPError>;
+
+    /// Send a message whose params or result are already JSON text
+    ///
+    /// Transports that write JSON text write it as it is. By default the message is
+    /// converted and sent with [`send`](TransportSender::send).
+    async fn send_raw(&self, message: RawMessage) -> Result<(), MCPError> {
+        self.send(message.try_into()?).await
+    }
+
+    /// Close the connection
+    async fn close(&self) -> Result<(), MCPError>;
+}
+
+/// Receiving half of a split transport
+#[async_trait]
+pub trait TransportReceiver: Send {
+    /// Receive the next message, or `None` once the peer has closed the connection
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError>;
+
+    /// Receive the next message with its params or result left as JSON text
+    ///
+    /// Transports that read JSON text only parse the envelope. By default the message is
+    /// received with [`recv`](TransportReceiver::recv) and converted.
+    async fn recv_raw(&mut self) -> Result<Option<RawMessage>, MCPError> {
+        match self.recv().await? {
+            Some(message) => Ok(Some(message.try_into()?)),
+            None => Ok(None),
+        }
+    }
 }
 
+/// A transport whose concrete type is chosen at runtime
+pub type BoxedTransport = Box<dyn Transport>;
+
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    async fn start(&mut self) -> Result<(), MCPError> {
+        (**self).start().await
+    }
+
+    async fn send(&mut self, message: JSONRPCMessage) -> Result<(), MCPError> {
+        (**self).send(message).await
+    }
+
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        (**self).recv().await
+    }
+
+    async fn close(&mut self) -> Result<(), MCPError> {
+        (**self).close().await
+    }
+
+    fn into_split(self: Box<Self>) -> (Arc<dyn TransportSender>, Box<dyn TransportReceiver>) {
+        (*self).into_split()
+    }
+
+    fn set_on_close(&mut self, callback: Option<CloseCallback>) {
+        (**self).set_on_close(callback)
+    }
+
+    fn set_on_error(&mut self, callback: Option<ErrorCallback>) {
+        (**self).set_on_error(callback)
+    }
+
+    fn set_on_message(&mut self, callback: Option<MessageCallback>) {
+        (**self).set_on_message(callback)
+    }
+
+    fn credentials(&self) -> Credentials {
+        (**self).credentials()
+    }
+}
+
+/// Source of incoming transports for servers that accept many concurrent sessions
+///
+/// Each accepted transport is an independent session; see
+/// [`Server::serve_listener`](crate::server::Server::serve_listener).
+#[async_trait]
+pub trait Listener: Send + Sync {
+    /// The transport type produced for each accepted connection
+    type Transport: Transport;
+
+    /// Wait for the next incoming connection
+    async fn accept(&mut self) -> Result<Self::Transport, MCPError>;
+}
+
+/// Message framing for byte-stream transports
+pub mod framing;
+
+/// Backoff policy for reconnecting transports and clients
+pub mod reconnect;
+
+/// TLS configuration for network transports
+pub mod tls;
+
+/// Headers, credentials, timeouts and proxies for client transports
+pub mod options;
+
+/// Transport over any async reader/writer pair
+pub mod stream;
+
 /// Standard IO transport
 pub mod stdio;
 
+/// In-memory transport pair
+pub mod memory;
+
+/// TCP transport
+pub mod tcp;
+
+/// Unix domain socket transport
+#[cfg(unix)]
+pub mod unix;
+
 /// Server-Sent Events (SSE) transport
 pub mod sse;
 
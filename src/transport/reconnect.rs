@@ -0,0 +1,130 @@
+//! Backoff policy for re-establishing dropped connections
+
+use rand::Rng;
+use std::time::Duration;
+
+/// How often and how quickly to retry a dropped connection
+///
+/// Delays grow exponentially from `initial_delay` up to `max_delay`. With jitter enabled,
+/// each delay is drawn from the upper half of its range so that many clients cut off at
+/// the same moment do not all come back at once.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Delay before the first retry
+    pub initial_delay: Duration,
+    /// Upper bound for any single delay
+    pub max_delay: Duration,
+    /// Factor applied to the delay after each failed attempt
+    pub multiplier: f64,
+    /// Give up after this many consecutive failed attempts; `None` retries forever
+    pub max_attempts: Option<u32>,
+    /// Randomize delays
+    pub jitter: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            max_attempts: None,
+            jitter: true,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Create a policy with the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the delay before the first retry
+    pub fn with_initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Set the upper bound for any single delay
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Set the factor applied to the delay after each failed attempt
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Set how many consecutive failed attempts to make before giving up
+    pub fn with_max_attempts(mut self, max_attempts: Option<u32>) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Enable or disable randomized delays
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// The delay before retry number `attempt` (starting at 0), or `None` to give up
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if self.max_attempts.is_some_and(|max| attempt >= max) {
+            return None;
+        }
+
+        // Computed in floating point so that late attempts saturate instead of overflowing
+        let factor = self.multiplier.max(1.0).powf(attempt as f64);
+        let secs = (self.initial_delay.as_secs_f64() * factor).min(self.max_delay.as_secs_f64());
+        let delay = Duration::from_secs_f64(secs);
+        if self.jitter {
+            Some(delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0)))
+        } else {
+            Some(delay)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delays_grow_and_stop() {
+        let policy = ReconnectPolicy::new()
+            .with_initial_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(350))
+            .with_max_attempts(Some(4))
+            .with_jitter(false);
+
+        let delays: Vec<_> = (0..5).map(|attempt| policy.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_millis(100)),
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_millis(350)),
+                Some(Duration::from_millis(350)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_jitter_stays_in_range() {
+        let policy = ReconnectPolicy::new().with_initial_delay(Duration::from_millis(100));
+        for _ in 0..100 {
+            let delay = policy.delay(0).unwrap();
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn test_late_attempts_saturate() {
+        let policy = ReconnectPolicy::new().with_jitter(false);
+        assert_eq!(policy.delay(10_000), Some(policy.max_delay));
+    }
+}
@@ -1,694 +1,2685 @@
+//! Server-Sent Events (SSE) transport
+//!
+//! This implements the MCP "HTTP with SSE" transport. A client opens a long-lived
+//! `GET {base}/sse` event stream; the first event, `endpoint`, tells it where to POST its
+//! own messages, and every server message then arrives as a `message` event.
+//!
+//! Every `message` event carries a monotonically increasing ID, and the server keeps the
+//! most recent messages of each session. When a stream drops, the client reconnects with
+//! backoff and sends `Last-Event-ID`, and the server replays whatever it missed.
+//!
+//! The server side runs on axum. [`SSETransport::new_server`] binds its own listener,
+//! while [`SSETransport::new_mounted`] together with [`SSETransport::router`] lets the MCP
+//! endpoints live inside an existing application.
+//!
+//! A server transport multiplexes every session over one [`Transport`]. Wrapped in an
+//! [`SSEListener`] instead, it hands out one [`SSESessionTransport`] per session, which
+//! lets [`Server::serve_listener`](crate::server::Server::serve_listener) serve and
+//! authenticate each session on its own.
+
+use crate::auth::server::{BearerAuth, TokenInfo};
+use crate::auth::session::Credentials;
 use crate::error::MCPError;
-use crate::transport::{CloseCallback, ErrorCallback, MessageCallback, Transport};
+use crate::schema::json_rpc::{JSONRPCMessage, RequestId};
+use crate::schema::raw::RawMessage;
+use crate::transport::framing::DEFAULT_MAX_FRAME_SIZE;
+use crate::transport::options::{ClientTransportOptions, HttpClient};
+use crate::transport::reconnect::ReconnectPolicy;
+use crate::transport::tls::{TlsClientConfig, TlsServerConfig};
+use crate::transport::{
+    CloseCallback, ErrorCallback, Listener, MessageCallback, SharedErrorCallback, Transport,
+    TransportReceiver, TransportSender,
+};
 use async_trait::async_trait;
+use axum::extract::{DefaultBodyLimit, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Extension;
+use axum::Router;
+use axum_server::accept::Accept;
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
 use log::{debug, error, info, warn};
-use reqwest::Client;
-use serde::{de::DeserializeOwned, Serialize};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::de::IgnoredAny;
+use serde_json::Value;
 use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tiny_http::{Method, Request, Response as HttpResponse, Server};
-use tokio::sync::{mpsc, Mutex as TokioMutex, Notify};
-use tokio::time::sleep;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+use url::Url;
+
+/// How long [`close`](TransportSender::close) waits for open event streams to drain
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Default time after which a session that has not posted anything is dropped
+const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Default number of messages kept per session for replay after a reconnect
+const DEFAULT_REPLAY_CAPACITY: usize = 256;
+
+/// Default interval of keep-alive comments on idle event streams
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Who opened a session, as far as the transport can tell
+///
+/// Only known when the transport checks bearer tokens or client certificates. Resuming the
+/// session's event stream or posting to it is then refused for anyone else.
+#[derive(Debug, Clone, PartialEq)]
+struct SessionOwner {
+    subject: Option<String>,
+    client_id: Option<String>,
+    certificate: Option<Vec<u8>>,
+}
+
+impl SessionOwner {
+    fn of(token_info: Option<&TokenInfo>, certificates: &PeerCertificates) -> Option<Self> {
+        let certificate = certificates.0.first().cloned();
+        if token_info.is_none() && certificate.is_none() {
+            return None;
+        }
+        Some(Self {
+            subject: token_info.and_then(|info| info.subject.clone()),
+            client_id: token_info.and_then(|info| info.client_id.clone()),
+            certificate,
+        })
+    }
+}
+
+/// The certificate chain a client presented over mutual TLS, leaf first
+#[derive(Debug, Clone, Default)]
+struct PeerCertificates(Vec<Vec<u8>>);
+
+/// Completes the TLS handshake and hands the client's certificates to the handlers
+#[derive(Clone)]
+struct PeerCertificatesAcceptor(RustlsAcceptor);
+
+impl<S: Send + 'static> Accept<TcpStream, S> for PeerCertificatesAcceptor {
+    type Stream = TlsStream<TcpStream>;
+    type Service = <Extension<PeerCertificates> as Layer<S>>::Service;
+    type Future = BoxFuture<'static, std::io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: TcpStream, service: S) -> Self::Future {
+        let handshake = self.0.accept(stream, service);
+        Box::pin(async move {
+            let (stream, service) = handshake.await?;
+            let certificates = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .map(|chain| chain.iter().map(|cert| cert.to_vec()).collect())
+                .unwrap_or_default();
+            Ok((
+                stream,
+                Extension(PeerCertificates(certificates)).layer(service),
+            ))
+        })
+    }
+}
 
 /// Client connection information
 struct ClientConnection {
-    #[allow(dead_code)]
     id: String,
+    owner: Option<SessionOwner>,
     last_poll: Instant,
+    // Event ID of the next message for this session
+    next_event_id: u64,
+    // The most recent messages, kept for clients that reconnect
+    replay: VecDeque<(u64, String)>,
+    replay_capacity: usize,
+    // Feeds the client's event stream while one is attached
+    events: Option<mpsc::UnboundedSender<(u64, String)>>,
+    // Counts attached streams, so a stale stream cannot detach a newer one
+    stream_generation: u64,
+    // Feeds the session's own transport when served through an `SSEListener`
+    inbound: Option<mpsc::Sender<String>>,
+}
+
+impl ClientConnection {
+    fn new(id: &str, owner: Option<SessionOwner>, replay_capacity: usize) -> Self {
+        Self {
+            id: id.to_string(),
+            owner,
+            last_poll: Instant::now(),
+            next_event_id: 1,
+            replay: VecDeque::new(),
+            replay_capacity,
+            events: None,
+            stream_generation: 0,
+            inbound: None,
+        }
+    }
+
+    /// Whether a request from `owner` may use this session
+    fn is_owned_by(&self, owner: &Option<SessionOwner>) -> bool {
+        self.owner.is_none() || self.owner == *owner
+    }
+
+    /// Queue a message for the session, keeping it for replay
+    fn push(&mut self, message: String) {
+        let event_id = self.next_event_id;
+        self.next_event_id += 1;
+
+        if self.replay_capacity > 0 {
+            if self.replay.len() == self.replay_capacity {
+                self.replay.pop_front();
+            }
+            self.replay.push_back((event_id, message.clone()));
+        }
+
+        let delivered = self
+            .events
+            .as_ref()
+            .map(|events| events.send((event_id, message)).is_ok());
+        if delivered == Some(false) {
+            debug!("Event stream of client {} already closed", self.id);
+            self.events = None;
+        }
+    }
+
+    /// Attach a new event stream, starting with the kept messages after `last_event_id`
+    fn attach(&mut self, last_event_id: u64) -> (mpsc::UnboundedReceiver<(u64, String)>, u64) {
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+
+        if let Some((oldest, _)) = self.replay.front() {
+            if *oldest > last_event_id + 1 {
+                warn!(
+                    "Client {} missed events {} to {}, which are no longer kept",
+                    self.id,
+                    last_event_id + 1,
+                    oldest - 1
+                );
+            }
+        }
+        for (event_id, message) in self.replay.iter().filter(|(id, _)| *id > last_event_id) {
+            let _ = events_tx.send((*event_id, message.clone()));
+        }
+
+        self.events = Some(events_tx);
+        self.stream_generation += 1;
+        self.last_poll = Instant::now();
+        (events_rx, self.stream_generation)
+    }
+}
+
+type ActiveClients = Arc<Mutex<HashMap<String, ClientConnection>>>;
+
+/// Server-side request IDs mapped to the session and ID the client used
+type RequestRoutes = Arc<Mutex<HashMap<RequestId, (String, RequestId)>>>;
+
+/// A message received by the transport, tagged with the session that posted it
+struct Inbound {
+    // `None` in client mode, where everything comes from the one server
+    session_id: Option<String>,
+    message: String,
+}
+
+/// Hands new sessions to an [`SSEListener`]
+type SessionQueue = mpsc::UnboundedSender<SSESessionTransport>;
+
+/// State shared by the axum handlers
+#[derive(Clone)]
+struct ServerState {
+    incoming: mpsc::Sender<Inbound>,
+    // Set when served through an `SSEListener`
+    sessions: Option<SessionQueue>,
+    active_clients: ActiveClients,
+    message_path: String,
+    replay_capacity: usize,
+    keep_alive: Duration,
 }
 
 /// Server-Sent Events (SSE) transport
 pub struct SSETransport {
     uri: String,
-    is_connected: bool,
     is_server: bool,
+    // Whether start() binds its own listener; false when mounted into another router
+    bind: bool,
+    base_path: String,
+    local_addr: Option<SocketAddr>,
+    session_timeout: Option<Duration>,
+    replay_capacity: usize,
+    keep_alive: Duration,
+    max_message_size: usize,
+    reconnect: Option<ReconnectPolicy>,
+    server_tls: Option<TlsServerConfig>,
+    auth: Option<BearerAuth>,
+    client_options: ClientTransportOptions,
+    sessions: Option<SessionQueue>,
+    // Feeds the receiver; cloned into the HTTP handlers or the event reader
+    incoming_tx: mpsc::Sender<Inbound>,
+    sender: SSESender,
+    receiver: SSEReceiver,
+}
+
+/// Sending half of an [`SSETransport`]
+struct SSESender {
+    uri: String,
+    is_server: bool,
+    client: HttpClient,
+    // For client mode: where messages are POSTed, as announced by the server
+    post_url: Arc<Mutex<Option<Url>>>,
+    is_connected: Arc<AtomicBool>,
+    // For server mode: active client connections
+    active_clients: ActiveClients,
+    // For server mode: where responses to client requests have to go
+    routes: RequestRoutes,
+    // For server mode: tells the HTTP server to shut down gracefully
+    shutdown: watch::Sender<bool>,
+    // HTTP server task in server mode, event reader task in client mode
+    background_task: Mutex<Option<JoinHandle<()>>>,
+    // For server mode: drops idle sessions
+    reaper_task: Mutex<Option<JoinHandle<()>>>,
     on_close: Option<CloseCallback>,
-    on_error: Option<ErrorCallback>,
+    on_error: Option<SharedErrorCallback>,
+}
+
+/// Receiving half of an [`SSETransport`]
+struct SSEReceiver {
+    incoming: mpsc::Receiver<Inbound>,
+    // For server mode: requests are renumbered so IDs from different sessions cannot clash
+    routes: RequestRoutes,
+    next_request_id: AtomicI64,
+    on_error: Option<SharedErrorCallback>,
     on_message: Option<MessageCallback>,
-    // HTTP client for making requests
-    client: Client,
-    // Queue for incoming messages
-    message_queue: Arc<TokioMutex<VecDeque<String>>>,
-    // For server mode: active client connections
-    active_clients: Arc<Mutex<HashMap<String, ClientConnection>>>,
-    // For server mode: client message queues
-    client_messages: Arc<Mutex<HashMap<String, VecDeque<String>>>>,
-    // For client mode: client ID
-    client_id: Arc<TokioMutex<Option<String>>>,
-    // Server instance
-    server: Option<Arc<Server>>,
-    // Signal to stop polling
-    stop_signal: Arc<Notify>,
-    // Polling task handle
-    polling_task: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl SSETransport {
-    /// Create a new SSE transport
+    /// Create a new SSE transport in client mode
+    ///
+    /// `uri` is the server's base URL; the event stream is opened at `{uri}/sse` unless the
+    /// URL already points at it.
     pub fn new(uri: &str) -> Self {
         info!("Creating new SSE transport with URI: {}", uri);
+        let client = HttpClient::plain();
+        let (incoming_tx, incoming) = mpsc::channel(32);
+        let (shutdown, _) = watch::channel(false);
+        let routes: RequestRoutes = Arc::new(Mutex::new(HashMap::new()));
         Self {
             uri: uri.to_string(),
-            is_connected: false,
             is_server: false,
-            on_close: None,
-            on_error: None,
-            on_message: None,
-            client: Client::new(),
-            message_queue: Arc::new(TokioMutex::new(VecDeque::new())),
-            active_clients: Arc::new(Mutex::new(HashMap::new())),
-            client_messages: Arc::new(Mutex::new(HashMap::new())),
-            client_id: Arc::new(TokioMutex::new(None)),
-            server: None,
-            stop_signal: Arc::new(Notify::new()),
-            polling_task: None,
+            bind: false,
+            base_path: String::new(),
+            local_addr: None,
+            session_timeout: Some(DEFAULT_SESSION_TIMEOUT),
+            replay_capacity: DEFAULT_REPLAY_CAPACITY,
+            keep_alive: DEFAULT_KEEP_ALIVE,
+            max_message_size: DEFAULT_MAX_FRAME_SIZE,
+            reconnect: Some(ReconnectPolicy::default()),
+            server_tls: None,
+            auth: None,
+            client_options: ClientTransportOptions::default(),
+            sessions: None,
+            incoming_tx,
+            sender: SSESender {
+                uri: uri.to_string(),
+                is_server: false,
+                client,
+                post_url: Arc::new(Mutex::new(None)),
+                is_connected: Arc::new(AtomicBool::new(false)),
+                active_clients: Arc::new(Mutex::new(HashMap::new())),
+                routes: Arc::clone(&routes),
+                shutdown,
+                background_task: Mutex::new(None),
+                reaper_task: Mutex::new(None),
+                on_close: None,
+                on_error: None,
+            },
+            receiver: SSEReceiver {
+                incoming,
+                routes,
+                next_request_id: AtomicI64::new(1),
+                on_error: None,
+                on_message: None,
+            },
         }
     }
 
     /// Create a new SSE transport in server mode
+    ///
+    /// [`start`](Transport::start) binds the host and port of `uri`, which may be a
+    /// hostname or an IPv6 literal such as `http://[::1]:8080`. The path of `uri`, if any,
+    /// becomes the base path of the endpoints.
     pub fn new_server(uri: &str) -> Self {
         info!("Creating new SSE server transport with URI: {}", uri);
         let mut transport = Self::new(uri);
         transport.is_server = true;
+        transport.bind = true;
+        transport.sender.is_server = true;
+        if let Ok(url) = Url::parse(uri) {
+            transport.base_path = normalize_base_path(url.path());
+        }
         transport
     }
-}
 
-#[async_trait]
-impl Transport for SSETransport {
-    async fn start(&mut self) -> Result<(), MCPError> {
-        if self.is_connected {
-            debug!("SSE transport already connected");
-            return Ok(());
-        }
+    /// Create a server transport whose endpoints are served by an existing application
+    ///
+    /// No listener is bound; mount [`router`](Self::router) into the application instead.
+    pub fn new_mounted(base_path: &str) -> Self {
+        info!(
+            "Creating new mounted SSE server transport at {:?}",
+            base_path
+        );
+        let mut transport = Self::new(base_path);
+        transport.is_server = true;
+        transport.sender.is_server = true;
+        transport.base_path = normalize_base_path(base_path);
+        transport
+    }
 
-        info!("Starting SSE transport with URI: {}", self.uri);
+    /// Set the path prefix of the server endpoints, e.g. `/mcp` for `/mcp/sse`
+    pub fn with_base_path(mut self, base_path: &str) -> Self {
+        self.base_path = normalize_base_path(base_path);
+        self
+    }
 
-        // Create a message queue for the receiver task
-        let message_queue = Arc::clone(&self.message_queue);
-        let stop_signal = Arc::clone(&self.stop_signal);
+    /// Set how long a session may go without posting a message before it is dropped
+    ///
+    /// Sessions with an open event stream are kept; the time counts from the last post or
+    /// from when the stream closed. Defaults to 30 minutes; `None` never drops sessions.
+    pub fn with_session_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.session_timeout = timeout;
+        self
+    }
 
-        if self.is_server {
-            // Parse the URI to get the host and port
-            let uri = self.uri.clone();
-            let uri_parts: Vec<&str> = uri.split("://").collect();
-            if uri_parts.len() != 2 {
-                return Err(MCPError::Transport(format!("Invalid URI: {}", uri)));
-            }
+    /// Set how many recent messages each session keeps for replay after a reconnect
+    ///
+    /// Defaults to 256; 0 disables replay.
+    pub fn with_replay_buffer(mut self, capacity: usize) -> Self {
+        self.replay_capacity = capacity;
+        self
+    }
 
-            let addr_parts: Vec<&str> = uri_parts[1].split(':').collect();
-            if addr_parts.len() != 2 {
-                return Err(MCPError::Transport(format!("Invalid URI: {}", uri)));
-            }
+    /// Set how often idle event streams get a keep-alive comment
+    ///
+    /// Defaults to 15 seconds; lower it if a proxy cuts streams that are quiet for less.
+    pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = interval;
+        self
+    }
 
-            let host = addr_parts[0];
-            let port: u16 = match addr_parts[1].parse() {
-                Ok(p) => p,
-                Err(_) => return Err(MCPError::Transport(format!("Invalid port in URI: {}", uri))),
-            };
+    /// Set the largest message, in bytes, a client may post (server mode) or the event
+    /// stream may carry (client mode)
+    ///
+    /// Defaults to 16 MiB, the frame limit of the stream transports. Larger posts are
+    /// answered with `413 Payload Too Large`; larger events are skipped with a warning.
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
 
-            let addr = format!("{}:{}", host, port);
-            info!("Starting SSE server on {}", addr);
+    /// Set how a client reconnects when its event stream drops; `None` disables reconnects
+    pub fn with_reconnect(mut self, policy: Option<ReconnectPolicy>) -> Self {
+        self.reconnect = policy;
+        self
+    }
 
-            // Create the HTTP server
-            let server = match Server::http(&addr) {
-                Ok(s) => s,
-                Err(e) => {
-                    return Err(MCPError::Transport(format!(
-                        "Failed to start HTTP server: {}",
-                        e
-                    )))
-                }
-            };
+    /// Serve over HTTPS (server mode)
+    ///
+    /// If the configuration has a client CA, clients without a certificate issued by it are
+    /// refused during the handshake. Has no effect on a mounted transport, whose listener
+    /// belongs to the application.
+    pub fn with_tls(mut self, config: TlsServerConfig) -> Self {
+        self.server_tls = Some(config);
+        self
+    }
 
-            let server_arc = Arc::new(server);
-            self.server = Some(Arc::clone(&server_arc));
-
-            // Start a task to handle incoming requests
-            let active_clients = Arc::clone(&self.active_clients);
-            let client_messages = Arc::clone(&self.client_messages);
-            let (sender, mut receiver) = mpsc::channel::<String>(32);
-
-            // Spawn a task to process incoming HTTP requests
-            let server_arc_clone = Arc::clone(&server_arc);
-            let stop_signal_clone = Arc::clone(&stop_signal);
-            let active_clients_clone = Arc::clone(&active_clients);
-            let client_messages_clone = Arc::clone(&client_messages);
-            let sender_clone = sender.clone();
-
-            tokio::spawn(async move {
-                loop {
-                    // Check for stop signal with a small timeout
-                    let should_stop = tokio::time::timeout(
-                        Duration::from_millis(100),
-                        stop_signal_clone.notified(),
-                    )
-                    .await
-                    .is_ok();
+    /// Require a valid bearer token on both endpoints (server mode)
+    ///
+    /// Requests without one are answered with `401` and a `WWW-Authenticate` challenge.
+    pub fn with_auth(mut self, auth: BearerAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
 
-                    if should_stop {
-                        debug!("Server task received stop signal");
-                        break;
-                    }
+    /// Set the TLS settings used for `https://` URLs (client mode)
+    pub fn with_client_tls(mut self, config: TlsClientConfig) -> Self {
+        self.client_options = self.client_options.with_tls(config);
+        self
+    }
 
-                    // Receive request (non-blocking)
-                    let server_for_recv = Arc::clone(&server_arc_clone);
-                    let request_result = tokio::task::spawn_blocking(move || {
-                        server_for_recv.recv_timeout(Duration::from_millis(50))
-                    })
-                    .await;
+    /// Set the headers, credentials, timeouts and proxy of client requests
+    ///
+    /// Replaces any TLS settings given earlier with those in `options`.
+    pub fn with_client_options(mut self, options: ClientTransportOptions) -> Self {
+        self.client_options = options;
+        self
+    }
 
-                    // Process the request if we got one
-                    if let Ok(result) = request_result {
-                        if let Ok(Some(request)) = result {
-                            // Extract method and URL from the request
-                            let method = request.method().clone();
-                            let url = request.url().to_string();
-
-                            debug!("Server received {} request for {}", method, url);
-
-                            // Process request in a separate task to not block the main loop
-                            let sender_task = sender_clone.clone();
-                            let active_clients_task = Arc::clone(&active_clients_clone);
-                            let client_messages_task = Arc::clone(&client_messages_clone);
-
-                            tokio::spawn(async move {
-                                process_request(
-                                    request,
-                                    &method,
-                                    &url,
-                                    &sender_task,
-                                    &active_clients_task,
-                                    &client_messages_task,
-                                )
-                                .await;
-                            });
-                        }
-                    }
-                }
-                debug!("Server HTTP handler task exited");
-            });
+    /// Routes serving this transport's `GET {base}/sse` and `POST {base}/message` endpoints
+    ///
+    /// The router is already nested under the base path, so it can be merged into an
+    /// application router as is.
+    pub fn router(&self) -> Router {
+        let state = ServerState {
+            incoming: self.incoming_tx.clone(),
+            sessions: self.sessions.clone(),
+            active_clients: Arc::clone(&self.sender.active_clients),
+            message_path: format!("{}/message", self.base_path),
+            replay_capacity: self.replay_capacity,
+            keep_alive: self.keep_alive,
+        };
+        let mut routes = Router::new()
+            .route("/sse", get(handle_sse))
+            .route(
+                "/message",
+                post(handle_message).layer(DefaultBodyLimit::max(self.max_message_size)),
+            )
+            .with_state(state);
+        if let Some(auth) = &self.auth {
+            routes = auth.protect(routes);
+        }
 
-            // Spawn a task to process messages received from clients
-            let message_queue_clone = Arc::clone(&message_queue);
-            let stop_signal_clone = Arc::clone(&stop_signal);
-            self.polling_task = Some(tokio::spawn(async move {
-                loop {
-                    tokio::select! {
-                        Some(content) = receiver.recv() => {
-                            // Add the message to the server's message queue for processing
-                            let mut queue = message_queue_clone.lock().await;
-                            queue.push_back(content);
-                            debug!("Added message to server queue for processing");
-                        }
-                        _ = stop_signal_clone.notified() => {
-                            debug!("Server message processing task received stop signal");
-                            break;
-                        }
-                    }
-                }
-                debug!("Server message processing task exited");
-            }));
+        if self.base_path.is_empty() {
+            routes
         } else {
-            // For client mode - we'll use async polling
-            let uri = self.uri.clone();
-            let client = self.client.clone();
-            let client_id = Arc::clone(&self.client_id);
-            let message_queue_clone = Arc::clone(&message_queue);
-            let stop_signal_clone = Arc::clone(&stop_signal);
-
-            // Register with the server
-            debug!("Client registering with server at {}/register", uri);
-            match client.get(format!("{}/register", uri)).send().await {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        // Parse the client ID from the response
-                        match response.text().await {
-                            Ok(text) => match serde_json::from_str::<serde_json::Value>(&text) {
-                                Ok(json) => {
-                                    if let Some(id) =
-                                        json.get("client_id").and_then(|id| id.as_str())
-                                    {
-                                        debug!("Client registration successful with ID: {}", id);
-                                        let mut client_id_guard = client_id.lock().await;
-                                        *client_id_guard = Some(id.to_string());
-                                    } else {
-                                        warn!(
-                                            "Client registration response missing client_id field"
-                                        );
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!("Failed to parse client registration response: {}", e);
-                                }
-                            },
-                            Err(e) => {
-                                warn!("Failed to read client registration response: {}", e);
-                            }
-                        }
-                    } else {
-                        warn!("Client registration failed: HTTP {}", response.status());
-                    }
-                }
-                Err(e) => {
-                    warn!("Client registration failed: {}", e);
-                }
-            }
+            Router::new().nest(&self.base_path, routes)
+        }
+    }
 
-            // Start a task to poll for messages
-            let client_id_clone = Arc::clone(&client_id);
-            let uri_clone = uri.clone();
-            let client_clone = client.clone();
-
-            // Simplify: Just use a polling task that adds messages to the queue
-            // The main thread will handle processing callbacks when messages are received
-            self.polling_task = Some(tokio::spawn(async move {
-                loop {
-                    // Get the client ID
-                    let client_id_str = {
-                        let id_guard = client_id_clone.lock().await;
-                        id_guard.clone()
-                    };
-
-                    // Send a GET request to poll for messages
-                    let poll_uri = if let Some(id) = &client_id_str {
-                        format!("{}/poll?client_id={}", uri_clone, id)
-                    } else {
-                        format!("{}/poll", uri_clone)
-                    };
-                    debug!("Client polling for messages at {}", poll_uri);
-
-                    match client_clone.get(&poll_uri).send().await {
-                        Ok(response) => {
-                            if response.status().is_success() {
-                                match response.text().await {
-                                    Ok(text) => {
-                                        if !text.is_empty() && text != "no_messages" {
-                                            debug!("Client received message from poll: {}", text);
-
-                                            // Try to parse as JSON to validate
-                                            match serde_json::from_str::<serde_json::Value>(&text) {
-                                                Ok(_) => {
-                                                    // Add the message to the queue
-                                                    let mut queue =
-                                                        message_queue_clone.lock().await;
-                                                    queue.push_back(text.clone());
-                                                    debug!("Client added message to queue for processing");
-
-                                                    // The main thread will handle callbacks when messages are processed
-                                                }
-                                                Err(e) => {
-                                                    error!("Client received invalid JSON from server: {} - {}", e, text);
-                                                }
-                                            }
-                                        } else {
-                                            debug!("Client: No new messages available");
-                                        }
-                                    }
-                                    Err(e) => {
-                                        error!("Client failed to read response text: {}", e);
-                                    }
-                                }
-                            } else {
-                                error!("Client poll request failed: HTTP {}", response.status());
-                            }
-                        }
-                        Err(e) => {
-                            error!("Client failed to poll for messages: {}", e);
-                            // Add a small delay before retrying to avoid hammering the server
-                            sleep(Duration::from_millis(1000)).await;
-                        }
-                    }
+    /// The address the server is listening on, once started
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
 
-                    // Check if we should stop polling
-                    if tokio::time::timeout(Duration::from_millis(0), stop_signal_clone.notified())
-                        .await
-                        .is_ok()
-                    {
-                        debug!("Client polling task received stop signal");
-                        break;
-                    }
+    /// Check whether the transport has been started and not yet closed
+    pub fn is_connected(&self) -> bool {
+        self.sender.is_connected.load(Ordering::SeqCst)
+    }
 
-                    // Wait before polling again
-                    sleep(Duration::from_millis(500)).await;
+    /// Bind the listener and serve the endpoints until the transport is closed
+    async fn start_as_server(&mut self) -> Result<(), MCPError> {
+        let url = Url::parse(&self.uri)
+            .map_err(|e| MCPError::Transport(format!("Invalid URI {}: {}", self.uri, e)))?;
+        let addr = bind_address(&url)?;
+
+        info!("Starting SSE server on {}", addr);
+        let listener = tokio::net::TcpListener::bind(&addr)
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to bind to {}: {}", addr, e)))?;
+        self.local_addr = listener.local_addr().ok();
+
+        let router = self.router();
+        let mut shutdown = self.sender.shutdown.subscribe();
+        if let Some(tls) = &self.server_tls {
+            let config = RustlsConfig::from_config(tls.rustls_config()?);
+            let listener = listener.into_std().map_err(|e| {
+                MCPError::Transport(format!("Failed to set up listener on {}: {}", addr, e))
+            })?;
+            let handle = axum_server::Handle::new();
+            let shutdown_handle = handle.clone();
+            let server_task = tokio::spawn(async move {
+                tokio::spawn(async move {
+                    let _ = shutdown.wait_for(|stop| *stop).await;
+                    shutdown_handle.graceful_shutdown(Some(SHUTDOWN_GRACE_PERIOD));
+                });
+                let result = axum_server::from_tcp_rustls(listener, config)
+                    .map(PeerCertificatesAcceptor)
+                    .handle(handle)
+                    .serve(router.into_make_service())
+                    .await;
+                if let Err(e) = result {
+                    error!("SSE server error: {}", e);
                 }
-                debug!("Client polling task exited");
-            }));
+                debug!("SSE server task exited");
+            });
+            *self.sender.background_task.lock().unwrap() = Some(server_task);
+            return Ok(());
         }
 
-        self.is_connected = true;
-        info!("SSE transport started successfully");
+        let server_task = tokio::spawn(async move {
+            let result = axum::serve(listener, router)
+                .with_graceful_shutdown(async move {
+                    let _ = shutdown.wait_for(|stop| *stop).await;
+                })
+                .await;
+            if let Err(e) = result {
+                error!("SSE server error: {}", e);
+            }
+            debug!("SSE server task exited");
+        });
+
+        *self.sender.background_task.lock().unwrap() = Some(server_task);
         Ok(())
     }
 
-    async fn send<T: Serialize + Send + Sync>(&mut self, message: &T) -> Result<(), MCPError> {
-        if !self.is_connected {
-            return Err(MCPError::Transport(
-                "SSE transport not connected".to_string(),
-            ));
-        }
+    /// Periodically drop sessions that have been idle for longer than the session timeout
+    fn spawn_reaper(&self) {
+        let Some(timeout) = self.session_timeout else {
+            return;
+        };
+        let active_clients = Arc::clone(&self.sender.active_clients);
+        let routes = Arc::clone(&self.sender.routes);
+        let reaper_task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval((timeout / 2).max(Duration::from_millis(10)));
+            loop {
+                interval.tick().await;
+                reap_idle_sessions(&active_clients, &routes, timeout);
+            }
+        });
+        *self.sender.reaper_task.lock().unwrap() = Some(reaper_task);
+    }
+
+    /// Open the event stream and wait for the server to announce its message endpoint
+    async fn connect_as_client(&mut self) -> Result<(), MCPError> {
+        let sse_url = sse_url(&self.uri)?;
+        self.sender.client = self.client_options.http_client().await?;
+        debug!("Opening SSE event stream at {}", sse_url);
+
+        let response = open_event_stream(&self.sender.client, &sse_url, None).await?;
+
+        let (endpoint_tx, endpoint_rx) = oneshot::channel();
+        let reader = EventReader {
+            client: self.sender.client.clone(),
+            sse_url: sse_url.clone(),
+            post_url: Arc::clone(&self.sender.post_url),
+            incoming: self.incoming_tx.clone(),
+            reconnect: self.reconnect.clone(),
+            last_event_id: None,
+            max_event_size: self.max_message_size,
+        };
+        let reader_task = tokio::spawn(reader.run(response, endpoint_tx));
 
-        // Serialize the message to JSON
-        let serialized_message = match serde_json::to_string(message) {
-            Ok(json) => json,
-            Err(e) => {
-                let error_msg = format!("Failed to serialize message: {}", e);
-                error!("{}", error_msg);
-                return Err(MCPError::Serialization(e));
+        let endpoint = match endpoint_rx.await {
+            Ok(endpoint) => endpoint,
+            Err(_) => {
+                reader_task.abort();
+                return Err(MCPError::Transport(
+                    "SSE event stream ended before the endpoint event".to_string(),
+                ));
             }
         };
+        let post_url = endpoint_url(&sse_url, &endpoint).inspect_err(|_| reader_task.abort())?;
+
+        debug!("Client will post messages to {}", post_url);
+        *self.sender.post_url.lock().unwrap() = Some(post_url);
+        *self.sender.background_task.lock().unwrap() = Some(reader_task);
+        Ok(())
+    }
+}
+
+impl SSESender {
+    /// Report an error to the error callback if set, then hand it back
+    fn handle_error(&self, error: MCPError) -> MCPError {
+        if let Some(callback) = &self.on_error {
+            callback(&error);
+        }
+        error
+    }
+
+    /// Find the session a reply belongs to and restore the request ID that session used
+    ///
+    /// `id` is the ID of the reply, or `None` for anything else.
+    fn route(&self, id: Option<&mut RequestId>) -> Option<String> {
+        let id = id?;
+        let (session_id, client_id) = self.routes.lock().unwrap().remove(id)?;
+        *id = client_id;
+        Some(session_id)
+    }
+
+    /// Deliver a serialized message to `session_id`, to every session if `None`, or to
+    /// the server in client mode
+    async fn deliver(
+        &self,
+        serialized_message: String,
+        session_id: Option<String>,
+    ) -> Result<(), MCPError> {
         debug!("Sending message: {}", serialized_message);
 
         if self.is_server {
-            // Server mode - add the message to the client message queue
-            debug!(
-                "Server adding message to client queue: {}",
-                serialized_message
-            );
-
-            // In server mode, we need to add the message to the client message queue
-            // This is a separate queue from the server's message queue
-            if let Ok(clients) = self.active_clients.lock() {
-                // Add the message to all connected clients' queues
-                for client_id in clients.keys() {
-                    if let Ok(mut client_messages) = self.client_messages.lock() {
-                        client_messages
-                            .entry(client_id.clone())
-                            .or_insert_with(VecDeque::new)
-                            .push_back(serialized_message.clone());
-                        debug!("Added message to client {}'s queue", client_id);
+            let mut clients = self.active_clients.lock().unwrap();
+            match session_id {
+                Some(session_id) => match clients.get_mut(&session_id) {
+                    Some(client) => client.push(serialized_message),
+                    None => warn!("Session {} is gone, dropping its reply", session_id),
+                },
+                None => {
+                    for client in clients.values_mut() {
+                        client.push(serialized_message.clone());
                     }
                 }
-                debug!("Server successfully added message to client queues");
-                Ok(())
-            } else {
-                error!("Failed to lock active clients");
-                Err(MCPError::Transport(
-                    "Failed to lock active clients".to_string(),
-                ))
             }
+            Ok(())
         } else {
-            // Client mode - send a POST request to the server
-            debug!("Client sending message to server: {}", serialized_message);
+            // Client mode - POST the message to the endpoint announced by the server
+            let post_url = self.post_url.lock().unwrap().clone().ok_or_else(|| {
+                self.handle_error(MCPError::Transport(
+                    "SSE transport not connected".to_string(),
+                ))
+            })?;
 
-            match self
+            let request = self
                 .client
-                .post(&self.uri)
-                .body(serialized_message.clone())
+                .post(post_url)
+                .await
+                .map_err(|e| self.handle_error(e))?;
+            match request
+                .body(serialized_message)
                 .header(reqwest::header::CONTENT_TYPE, "application/json")
                 .send()
                 .await
             {
+                Ok(response) if response.status().is_success() => {
+                    debug!("Client successfully sent message to server");
+                    Ok(())
+                }
                 Ok(response) => {
-                    if response.status().is_success() {
-                        debug!("Client successfully sent message to server");
-                        Ok(())
-                    } else {
-                        let error_msg = format!(
-                            "Failed to send message to server: HTTP {}",
-                            response.status()
-                        );
-                        error!("{}", error_msg);
-                        Err(MCPError::Transport(error_msg))
-                    }
+                    let error = http_error(&response, "Failed to send message to server");
+                    error!("{}", error);
+                    Err(self.handle_error(error))
                 }
                 Err(e) => {
                     let error_msg = format!("Failed to send message to server: {}", e);
                     error!("{}", error_msg);
-                    Err(MCPError::Transport(error_msg))
+                    Err(self.handle_error(MCPError::Transport(error_msg)))
                 }
             }
         }
     }
 
-    async fn receive<T: DeserializeOwned + Send + Sync>(&mut self) -> Result<T, MCPError> {
-        if !self.is_connected {
-            return Err(MCPError::Transport(
+    /// Fail unless the transport has been started and not closed
+    fn check_connected(&self) -> Result<(), MCPError> {
+        if !self.is_connected.load(Ordering::SeqCst) {
+            return Err(self.handle_error(MCPError::Transport(
                 "SSE transport not connected".to_string(),
-            ));
+            )));
         }
+        Ok(())
+    }
 
-        // Use a timeout of 10 seconds
-        let timeout = Duration::from_secs(10);
-        let start = Instant::now();
-
-        // Try to get a message from the queue with timeout
-        let message = loop {
-            // Try to get a message from the queue
-            let queue_msg = {
-                let mut queue = self.message_queue.lock().await;
-                queue.pop_front()
-            };
-
-            if let Some(message) = queue_msg {
-                debug!("Received message: {}", message);
-                break message;
-            }
+    /// The JSON text of a typed or raw message
+    fn serialize(&self, message: &impl serde::Serialize) -> Result<String, MCPError> {
+        serde_json::to_string(message).map_err(|e| {
+            error!("Failed to serialize message: {}", e);
+            self.handle_error(MCPError::Serialization(e))
+        })
+    }
+}
 
-            // Check if we've exceeded the timeout
-            if start.elapsed() >= timeout {
-                debug!("Receive timeout after {:?}", timeout);
-                return Err(MCPError::Transport(
-                    "Timeout waiting for message".to_string(),
-                ));
-            }
+impl SSEReceiver {
+    /// Report an error to the error callback if set, then hand it back
+    fn handle_error(&self, error: MCPError) -> MCPError {
+        if let Some(callback) = &self.on_error {
+            callback(&error);
+        }
+        error
+    }
 
-            // Sleep for a short time before checking again
-            sleep(Duration::from_millis(100)).await;
+    /// The next message, or `None` once the background tasks have stopped
+    async fn next(&mut self) -> Option<Inbound> {
+        let Some(inbound) = self.incoming.recv().await else {
+            debug!("SSE transport receiver closed");
+            return None;
         };
+        debug!("Received message: {}", inbound.message);
 
-        // Parse the message
-        match serde_json::from_str::<T>(&message) {
-            Ok(parsed) => {
-                debug!("Successfully parsed message");
-                Ok(parsed)
-            }
-            Err(e) => {
-                let error_msg = format!(
-                    "Failed to deserialize message: {} - Content: {}",
-                    e, message
-                );
-                error!("{}", error_msg);
-                Err(MCPError::Serialization(e))
-            }
+        if let Some(callback) = &self.on_message {
+            callback(&inbound.message);
         }
+        Some(inbound)
     }
 
-    async fn close(&mut self) -> Result<(), MCPError> {
-        if !self.is_connected {
-            debug!("SSE transport already closed");
-            return Ok(());
-        }
-
-        info!("Closing SSE transport for URI: {}", self.uri);
+    /// Report a message that could not be parsed
+    fn parse_error(&self, error: serde_json::Error, message: &str) -> MCPError {
+        error!(
+            "Failed to deserialize message: {} - Content: {}",
+            error, message
+        );
+        self.handle_error(MCPError::Serialization(error))
+    }
 
-        // Set the connection flag
-        self.is_connected = false;
+    /// Give a request from a session an ID of our own and remember where the response
+    /// has to go
+    fn route_request(&self, session_id: String, id: &mut RequestId) {
+        let ours = RequestId::Number(self.next_request_id.fetch_add(1, Ordering::SeqCst));
+        let theirs = std::mem::replace(id, ours.clone());
+        self.routes
+            .lock()
+            .unwrap()
+            .insert(ours, (session_id, theirs));
+    }
 
-        // Signal the polling task to stop
-        self.stop_signal.notify_waiters();
+    /// Cancellations name the client's ID for the request, which becomes ours
+    fn route_cancellation(&self, session_id: &str, params: &mut Value) -> Result<(), MCPError> {
+        let Some(theirs) = params
+            .get("requestId")
+            .and_then(|id| serde_json::from_value::<RequestId>(id.clone()).ok())
+        else {
+            return Ok(());
+        };
+        let route = (session_id.to_string(), theirs.clone());
+        let ours = self
+            .routes
+            .lock()
+            .unwrap()
+            .iter()
+            .find_map(|(ours, to)| (*to == route).then(|| ours.clone()))
+            // A request no longer in flight; this ID matches no other session's
+            .unwrap_or_else(|| RequestId::String(format!("{}/{}", session_id, json_id(&theirs))));
+        params["requestId"] = serde_json::to_value(ours)?;
+        Ok(())
+    }
+}
 
-        // If we're a server, wait a short time to allow clients to receive final responses
-        if self.is_server {
-            debug!("Server waiting for clients to receive final responses");
-            // Wait a short time to allow clients to receive final responses
-            sleep(Duration::from_millis(1000)).await;
+#[async_trait]
+impl Transport for SSETransport {
+    async fn start(&mut self) -> Result<(), MCPError> {
+        if self.is_connected() {
+            debug!("SSE transport already connected");
+            return Ok(());
         }
 
-        // Join the polling task if it exists
-        if let Some(task) = self.polling_task.take() {
-            match task.abort() {
-                _ => debug!("Aborted polling task"),
+        info!("Starting SSE transport with URI: {}", self.uri);
+
+        if !self.is_server {
+            self.connect_as_client().await?;
+        } else {
+            if self.bind {
+                self.start_as_server().await?;
             }
+            self.spawn_reaper();
         }
 
-        // Call the close callback if set
-        if let Some(callback) = &self.on_close {
-            callback();
+        self.sender.is_connected.store(true, Ordering::SeqCst);
+        info!("SSE transport started successfully");
+        Ok(())
+    }
+
+    async fn send(&mut self, message: JSONRPCMessage) -> Result<(), MCPError> {
+        self.sender.send(message).await
+    }
+
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        if !self.is_connected() {
+            return Err(self.receiver.handle_error(MCPError::Transport(
+                "SSE transport not connected".to_string(),
+            )));
         }
 
-        info!("SSE transport closed successfully");
-        Ok(())
+        self.receiver.recv().await
+    }
+
+    async fn close(&mut self) -> Result<(), MCPError> {
+        self.sender.close().await
+    }
+
+    fn into_split(self: Box<Self>) -> (Arc<dyn TransportSender>, Box<dyn TransportReceiver>) {
+        (Arc::new(self.sender), Box::new(self.receiver))
     }
 
     fn set_on_close(&mut self, callback: Option<CloseCallback>) {
         debug!("Setting on_close callback for SSE transport");
-        self.on_close = callback;
+        self.sender.on_close = callback;
     }
 
     fn set_on_error(&mut self, callback: Option<ErrorCallback>) {
         debug!("Setting on_error callback for SSE transport");
-        self.on_error = callback;
+        let callback: Option<SharedErrorCallback> = callback.map(Arc::from);
+        self.sender.on_error = callback.clone();
+        self.receiver.on_error = callback;
     }
 
-    fn set_on_message<F>(&mut self, callback: Option<F>)
-    where
-        F: Fn(&str) + Send + Sync + 'static,
-    {
+    fn set_on_message(&mut self, callback: Option<MessageCallback>) {
         debug!("Setting on_message callback for SSE transport");
-        self.on_message = callback.map(|f| Box::new(f) as Box<dyn Fn(&str) + Send + Sync>);
-    }
-}
-
-// Helper function to process HTTP requests
-async fn process_request(
-    mut request: Request,
-    method: &Method,
-    url: &str,
-    sender: &mpsc::Sender<String>,
-    active_clients: &Arc<Mutex<HashMap<String, ClientConnection>>>,
-    client_messages: &Arc<Mutex<HashMap<String, VecDeque<String>>>>,
-) {
-    match (method, url) {
-        (Method::Post, "/") => {
-            // Handle POST request (client sending a message to server)
-            let mut content = String::new();
-            if let Err(e) = request.as_reader().read_to_string(&mut content) {
-                error!("Error reading request body: {}", e);
-                let _ = request.respond(
-                    HttpResponse::from_string("Error reading request").with_status_code(400),
-                );
-                return;
-            }
+        self.receiver.on_message = callback;
+    }
+}
 
-            debug!("Server received POST request body: {}", content);
+#[async_trait]
+impl TransportSender for SSESender {
+    async fn send(&self, mut message: JSONRPCMessage) -> Result<(), MCPError> {
+        self.check_connected()?;
+        // Replies go back to the session that sent the request; anything else the server
+        // sends on its own initiative goes to every session
+        let id = match &mut message {
+            JSONRPCMessage::Response(response) => Some(&mut response.id),
+            JSONRPCMessage::Error(error) => error.id.as_mut(),
+            _ => None,
+        };
+        let session_id = if self.is_server { self.route(id) } else { None };
+        let serialized_message = self.serialize(&message)?;
+        self.deliver(serialized_message, session_id).await
+    }
 
-            // Send the message to be processed
-            if let Err(e) = sender.send(content).await {
-                error!("Failed to send message to processing task: {}", e);
-            }
+    async fn send_raw(&self, mut message: RawMessage) -> Result<(), MCPError> {
+        self.check_connected()?;
+        let id = match &mut message {
+            RawMessage::Response(response) => Some(&mut response.id),
+            RawMessage::Error(error) => error.id.as_mut(),
+            _ => None,
+        };
+        let session_id = if self.is_server { self.route(id) } else { None };
+        let serialized_message = self.serialize(&message)?;
+        self.deliver(serialized_message, session_id).await
+    }
 
-            // Send a success response
-            let _ = request.respond(HttpResponse::from_string("OK").with_status_code(200));
+    async fn close(&self) -> Result<(), MCPError> {
+        if !self.is_connected.swap(false, Ordering::SeqCst) {
+            debug!("SSE transport already closed");
+            return Ok(());
         }
-        (Method::Get, path) if path.starts_with("/poll") => {
-            // Handle polling request from client
-            debug!("Server received polling request: {}", path);
 
-            // Extract client ID from query parameters
-            let client_id = path.split('?').nth(1).and_then(|query| {
-                query.split('&').find_map(|pair| {
-                    let mut parts = pair.split('=');
-                    if let Some(key) = parts.next() {
-                        if key == "client_id" {
-                            parts.next().map(|value| value.to_string())
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                })
-            });
+        info!("Closing SSE transport for URI: {}", self.uri);
 
-            if let Some(client_id) = client_id {
-                // Check if there are any messages in the client-specific queue
-                let message = if let Ok(mut client_msgs) = client_messages.lock() {
-                    client_msgs
-                        .entry(client_id.clone())
-                        .or_insert_with(VecDeque::new)
-                        .pop_front()
-                } else {
-                    None
-                };
+        let task = self.background_task.lock().unwrap().take();
+        if let Some(reaper) = self.reaper_task.lock().unwrap().take() {
+            reaper.abort();
+        }
+        if self.is_server {
+            // Dropping the stream senders ends each event stream once it has flushed the
+            // messages already queued, which lets the graceful shutdown complete
+            self.active_clients.lock().unwrap().clear();
+            let _ = self.shutdown.send(true);
 
-                // Send the message or a no-message response
-                if let Some(msg) = message {
-                    debug!("Server sending message to client {}: {}", client_id, msg);
-                    let response = HttpResponse::from_string(msg)
-                        .with_status_code(200)
-                        .with_header(tiny_http::Header {
-                            field: "Content-Type".parse().unwrap(),
-                            value: "application/json".parse().unwrap(),
-                        });
-
-                    if let Err(e) = request.respond(response) {
-                        error!("Failed to send response to client: {}", e);
-                    } else {
-                        debug!("Server successfully sent response to client");
-                    }
-                } else {
-                    // No messages available
-                    debug!(
-                        "Server sending no_messages response to client {}",
-                        client_id
-                    );
-                    let response = HttpResponse::from_string("no_messages").with_status_code(200);
-
-                    if let Err(e) = request.respond(response) {
-                        error!("Failed to send no_messages response: {}", e);
-                    }
+            if let Some(mut task) = task {
+                if tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, &mut task)
+                    .await
+                    .is_err()
+                {
+                    warn!("SSE server did not shut down in time, aborting");
+                    task.abort();
                 }
+            }
+        } else if let Some(task) = task {
+            task.abort();
+            debug!("Stopped SSE event reader");
+        }
 
-                // Update the client's last poll time
-                if let Ok(mut clients) = active_clients.lock() {
-                    if let Some(client) = clients.get_mut(&client_id) {
-                        client.last_poll = Instant::now();
-                    }
-                }
-            } else {
-                // No client ID provided
-                debug!("Client poll request missing client_id parameter");
-                let response =
-                    HttpResponse::from_string("Missing client_id parameter").with_status_code(400);
-                let _ = request.respond(response);
-            }
-        }
-        (Method::Get, "/register") => {
-            // Handle client registration
-            debug!("Server received client registration request");
-
-            // Track the client connection
-            let client_id = format!(
-                "client-{}",
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_millis()
-            );
+        // Call the close callback if set
+        if let Some(callback) = &self.on_close {
+            callback();
+        }
 
-            if let Ok(mut clients) = active_clients.lock() {
-                clients.insert(
-                    client_id.clone(),
-                    ClientConnection {
-                        id: client_id.clone(),
-                        last_poll: Instant::now(),
-                    },
-                );
-                debug!("Client registered: {}", client_id);
-                debug!("Total connected clients: {}", clients.len());
-            }
+        info!("SSE transport closed successfully");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TransportReceiver for SSEReceiver {
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        let Some(Inbound {
+            session_id,
+            message,
+        }) = self.next().await
+        else {
+            return Ok(None);
+        };
+        let parsed = serde_json::from_str(&message).map_err(|e| self.parse_error(e, &message))?;
 
-            // Initialize the client's message queue
-            if let Ok(mut client_msgs) = client_messages.lock() {
-                client_msgs
-                    .entry(client_id.clone())
-                    .or_insert_with(VecDeque::new);
-                debug!("Initialized message queue for client {}", client_id);
+        match (parsed, session_id) {
+            (JSONRPCMessage::Request(mut request), Some(session_id)) => {
+                self.route_request(session_id, &mut request.id);
+                Ok(Some(JSONRPCMessage::Request(request)))
+            }
+            (JSONRPCMessage::Notification(mut notification), Some(session_id))
+                if notification.method == "notifications/cancelled" =>
+            {
+                if let Some(params) = notification.params.as_mut() {
+                    self.route_cancellation(&session_id, params)?;
+                }
+                Ok(Some(JSONRPCMessage::Notification(notification)))
             }
+            (parsed, _) => Ok(Some(parsed)),
+        }
+    }
 
-            // Send a success response
-            let response =
-                HttpResponse::from_string(format!("{{\"client_id\":\"{}\"}}", client_id))
-                    .with_status_code(200)
-                    .with_header(tiny_http::Header {
-                        field: "Content-Type".parse().unwrap(),
-                        value: "application/json".parse().unwrap(),
-                    });
+    async fn recv_raw(&mut self) -> Result<Option<RawMessage>, MCPError> {
+        let Some(Inbound {
+            session_id,
+            message,
+        }) = self.next().await
+        else {
+            return Ok(None);
+        };
+        let parsed = RawMessage::parse(&message).map_err(|e| self.parse_error(e, &message))?;
 
-            if let Err(e) = request.respond(response) {
-                error!("Failed to send registration response: {}", e);
-            } else {
-                debug!("Server successfully registered client");
+        match (parsed, session_id) {
+            (RawMessage::Request(mut request), Some(session_id)) => {
+                self.route_request(session_id, &mut request.id);
+                Ok(Some(RawMessage::Request(request)))
             }
+            (RawMessage::Notification(mut notification), Some(session_id))
+                if notification.method == "notifications/cancelled" =>
+            {
+                if let Some(raw) = notification.params.take() {
+                    let mut params: Value = serde_json::from_str(raw.get())?;
+                    self.route_cancellation(&session_id, &mut params)?;
+                    notification.params = Some(serde_json::value::to_raw_value(&params)?);
+                }
+                Ok(Some(RawMessage::Notification(notification)))
+            }
+            (parsed, _) => Ok(Some(parsed)),
         }
-        _ => {
-            // Unsupported method or path
-            error!("Unsupported request: {} {}", method, url);
-            let _ = request.respond(
-                HttpResponse::from_string("Method or path not allowed").with_status_code(405),
-            );
-        }
+    }
+}
+
+/// A request ID as it appears in JSON
+fn json_id(id: &RequestId) -> String {
+    match id {
+        RequestId::String(id) => id.clone(),
+        RequestId::Number(id) => id.to_string(),
+    }
+}
+
+/// Detaches a client's event stream when the connection is dropped
+///
+/// The session itself stays, buffering messages until the client reconnects or the
+/// session times out.
+struct ClientGuard {
+    id: String,
+    generation: u64,
+    active_clients: ActiveClients,
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        if let Ok(mut clients) = self.active_clients.lock() {
+            if let Some(client) = clients.get_mut(&self.id) {
+                if client.stream_generation == self.generation {
+                    client.events = None;
+                    client.last_poll = Instant::now();
+                }
+            }
+        }
+        debug!("Event stream of client {} closed", self.id);
+    }
+}
+
+/// `GET {base}/sse`: register a client, or resume its session, and stream messages to it
+///
+/// Resuming someone else's session is refused with 403.
+async fn handle_sse(
+    State(state): State<ServerState>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    token_info: Option<Extension<TokenInfo>>,
+    certificates: Option<Extension<PeerCertificates>>,
+) -> Response {
+    let token_info = token_info.map(|Extension(info)| info);
+    let certificates = certificates
+        .map(|Extension(chain)| chain)
+        .unwrap_or_default();
+    let owner = SessionOwner::of(token_info.as_ref(), &certificates);
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(0);
+
+    let mut session_inbound = None;
+    let (client_id, events_rx, generation) = {
+        let mut clients = state.active_clients.lock().unwrap();
+        let resumed = query
+            .get("sessionId")
+            .and_then(|id| clients.get_mut(id.as_str()));
+        match resumed {
+            Some(client) if !client.is_owned_by(&owner) => {
+                warn!("Refused to resume session {} for another client", client.id);
+                return (StatusCode::FORBIDDEN, "Session belongs to another client")
+                    .into_response();
+            }
+            Some(client) => {
+                let (events_rx, generation) = client.attach(last_event_id);
+                info!("Client {} resumed after event {}", client.id, last_event_id);
+                (client.id.clone(), events_rx, generation)
+            }
+            None => {
+                let client_id = new_session_id();
+                let mut client = ClientConnection::new(&client_id, owner, state.replay_capacity);
+                let (events_rx, generation) = client.attach(0);
+                if state.sessions.is_some() {
+                    let (inbound_tx, inbound_rx) = mpsc::channel(32);
+                    client.inbound = Some(inbound_tx);
+                    session_inbound = Some(inbound_rx);
+                }
+                clients.insert(client_id.clone(), client);
+                debug!("Client registered: {}", client_id);
+                debug!("Total connected clients: {}", clients.len());
+                (client_id, events_rx, generation)
+            }
+        }
+    };
+
+    // Each new session becomes a transport of its own for the listener
+    if let (Some(sessions), Some(inbound)) = (&state.sessions, session_inbound) {
+        let credentials = Credentials {
+            headers: headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            token_info,
+            peer_certificates: certificates.0,
+            ..Credentials::default()
+        };
+        let session = SSESessionTransport::new(
+            &client_id,
+            Arc::clone(&state.active_clients),
+            inbound,
+            credentials,
+        );
+        if sessions.send(session).is_err() {
+            warn!(
+                "SSE listener is gone, session {} will not be served",
+                client_id
+            );
+        }
+    }
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("{}?sessionId={}", state.message_path, client_id));
+    let guard = ClientGuard {
+        id: client_id,
+        generation,
+        active_clients: state.active_clients,
+    };
+    let messages = stream::unfold((events_rx, guard), |(mut events, guard)| async move {
+        let (event_id, message) = events.recv().await?;
+        let event = Event::default()
+            .event("message")
+            .id(event_id.to_string())
+            .data(message);
+        Some((Ok(event), (events, guard)))
+    });
+
+    Sse::new(stream::once(async move { Ok::<_, Infallible>(endpoint) }).chain(messages))
+        .keep_alive(KeepAlive::new().interval(state.keep_alive))
+        .into_response()
+}
+
+/// `POST {base}/message?sessionId=...`: hand a client message to the receiver
+async fn handle_message(
+    State(state): State<ServerState>,
+    Query(query): Query<HashMap<String, String>>,
+    token_info: Option<Extension<TokenInfo>>,
+    certificates: Option<Extension<PeerCertificates>>,
+    body: String,
+) -> impl IntoResponse {
+    let Some(client_id) = query.get("sessionId") else {
+        return (StatusCode::BAD_REQUEST, "Missing sessionId parameter");
+    };
+
+    let certificates = certificates
+        .map(|Extension(chain)| chain)
+        .unwrap_or_default();
+    let owner = SessionOwner::of(
+        token_info.as_ref().map(|Extension(info)| info),
+        &certificates,
+    );
+    let session_inbound = match state.active_clients.lock().unwrap().get_mut(client_id) {
+        Some(client) if !client.is_owned_by(&owner) => {
+            return (StatusCode::FORBIDDEN, "Session belongs to another client");
+        }
+        Some(client) => {
+            client.last_poll = Instant::now();
+            client.inbound.clone()
+        }
+        None => return (StatusCode::NOT_FOUND, "Unknown session"),
+    };
+
+    // Tell the client at once if the body is not JSON, without building it up in memory
+    if serde_json::from_str::<IgnoredAny>(&body).is_err() {
+        return (StatusCode::BAD_REQUEST, "Invalid JSON");
+    }
+
+    debug!("Server received message from {}: {}", client_id, body);
+    let delivered = match session_inbound {
+        Some(inbound) => inbound.send(body).await.is_ok(),
+        None => {
+            let inbound = Inbound {
+                session_id: Some(client_id.clone()),
+                message: body,
+            };
+            state.incoming.send(inbound).await.is_ok()
+        }
+    };
+    if !delivered {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down");
+    }
+    (StatusCode::ACCEPTED, "Accepted")
+}
+
+/// Accepts each session of an SSE server as a transport of its own
+///
+/// Wraps a server transport created with [`SSETransport::new_server`] or
+/// [`SSETransport::new_mounted`]. Every client that opens an event stream becomes an
+/// [`SSESessionTransport`], which reports the client's request headers, the token details
+/// if the transport checks bearer tokens, and the client's certificates if it serves TLS
+/// with a client CA, as its [`credentials`](Transport::credentials). Certificates are not
+/// available when the endpoints are mounted into an application that terminates TLS
+/// itself.
+pub struct SSEListener {
+    transport: SSETransport,
+    sessions: mpsc::UnboundedReceiver<SSESessionTransport>,
+}
+
+impl SSEListener {
+    /// Start `transport` and accept its sessions
+    pub async fn new(mut transport: SSETransport) -> Result<Self, MCPError> {
+        if !transport.is_server {
+            return Err(MCPError::Transport(
+                "An SSE listener needs a server transport".to_string(),
+            ));
+        }
+        let (sessions_tx, sessions) = mpsc::unbounded_channel();
+        transport.sessions = Some(sessions_tx);
+        transport.start().await?;
+        Ok(Self {
+            transport,
+            sessions,
+        })
+    }
+
+    /// Routes serving the endpoints, for transports created with
+    /// [`SSETransport::new_mounted`]
+    pub fn router(&self) -> Router {
+        self.transport.router()
+    }
+
+    /// The address the server is listening on
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.transport.local_addr()
+    }
+
+    /// Stop serving, ending every session
+    pub async fn close(&mut self) -> Result<(), MCPError> {
+        self.transport.close().await
+    }
+}
+
+#[async_trait]
+impl Listener for SSEListener {
+    type Transport = SSESessionTransport;
+
+    async fn accept(&mut self) -> Result<SSESessionTransport, MCPError> {
+        self.sessions
+            .recv()
+            .await
+            .ok_or_else(|| MCPError::ConnectionClosed("SSE listener closed".to_string()))
+    }
+}
+
+/// One session of an SSE server, accepted by an [`SSEListener`]
+///
+/// The session ends when either side closes it or the server drops it as idle.
+pub struct SSESessionTransport {
+    credentials: Credentials,
+    sender: SSESessionSender,
+    receiver: SSESessionReceiver,
+}
+
+/// Sending half of an [`SSESessionTransport`]
+struct SSESessionSender {
+    id: String,
+    active_clients: ActiveClients,
+    is_connected: AtomicBool,
+    on_close: Option<CloseCallback>,
+    on_error: Option<SharedErrorCallback>,
+}
+
+/// Receiving half of an [`SSESessionTransport`]
+struct SSESessionReceiver {
+    incoming: mpsc::Receiver<String>,
+    on_error: Option<SharedErrorCallback>,
+    on_message: Option<MessageCallback>,
+}
+
+impl SSESessionTransport {
+    fn new(
+        id: &str,
+        active_clients: ActiveClients,
+        incoming: mpsc::Receiver<String>,
+        credentials: Credentials,
+    ) -> Self {
+        Self {
+            credentials,
+            sender: SSESessionSender {
+                id: id.to_string(),
+                active_clients,
+                is_connected: AtomicBool::new(false),
+                on_close: None,
+                on_error: None,
+            },
+            receiver: SSESessionReceiver {
+                incoming,
+                on_error: None,
+                on_message: None,
+            },
+        }
+    }
+
+    /// The ID the client uses for this session
+    pub fn session_id(&self) -> &str {
+        &self.sender.id
+    }
+}
+
+impl SSESessionSender {
+    /// Report an error to the error callback if set, then hand it back
+    fn handle_error(&self, error: MCPError) -> MCPError {
+        if let Some(callback) = &self.on_error {
+            callback(&error);
+        }
+        error
+    }
+
+    /// Queue a serialized message on the session's event stream
+    fn push(&self, serialized_message: String) -> Result<(), MCPError> {
+        debug!("Sending message to {}: {}", self.id, serialized_message);
+
+        match self.active_clients.lock().unwrap().get_mut(&self.id) {
+            Some(client) => {
+                client.push(serialized_message);
+                Ok(())
+            }
+            None => Err(self.handle_error(MCPError::ConnectionClosed(format!(
+                "Session {} is gone",
+                self.id
+            )))),
+        }
+    }
+}
+
+#[async_trait]
+impl TransportSender for SSESessionSender {
+    async fn send(&self, message: JSONRPCMessage) -> Result<(), MCPError> {
+        let serialized_message =
+            serde_json::to_string(&message).map_err(|e| self.handle_error(e.into()))?;
+        self.push(serialized_message)
+    }
+
+    async fn send_raw(&self, message: RawMessage) -> Result<(), MCPError> {
+        let serialized_message =
+            serde_json::to_string(&message).map_err(|e| self.handle_error(e.into()))?;
+        self.push(serialized_message)
+    }
+
+    async fn close(&self) -> Result<(), MCPError> {
+        if !self.is_connected.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        // Removing the session ends its event stream
+        self.active_clients.lock().unwrap().remove(&self.id);
+        if let Some(callback) = &self.on_close {
+            callback();
+        }
+        info!("SSE session {} closed", self.id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TransportReceiver for SSESessionReceiver {
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        let Some(message) = self.next().await else {
+            return Ok(None);
+        };
+        serde_json::from_str(&message)
+            .map(Some)
+            .map_err(|e| self.handle_error(e))
+    }
+
+    async fn recv_raw(&mut self) -> Result<Option<RawMessage>, MCPError> {
+        let Some(message) = self.next().await else {
+            return Ok(None);
+        };
+        RawMessage::parse(&message)
+            .map(Some)
+            .map_err(|e| self.handle_error(e))
+    }
+}
+
+impl SSESessionReceiver {
+    /// The next message, or `None` once the session is removed
+    async fn next(&mut self) -> Option<String> {
+        let message = self.incoming.recv().await?;
+        if let Some(callback) = &self.on_message {
+            callback(&message);
+        }
+        Some(message)
+    }
+
+    /// Report a message that could not be parsed
+    fn handle_error(&self, error: serde_json::Error) -> MCPError {
+        let error = MCPError::Serialization(error);
+        if let Some(callback) = &self.on_error {
+            callback(&error);
+        }
+        error
+    }
+}
+
+#[async_trait]
+impl Transport for SSESessionTransport {
+    async fn start(&mut self) -> Result<(), MCPError> {
+        self.sender.is_connected.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn send(&mut self, message: JSONRPCMessage) -> Result<(), MCPError> {
+        self.sender.send(message).await
+    }
+
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        self.receiver.recv().await
+    }
+
+    async fn close(&mut self) -> Result<(), MCPError> {
+        self.sender.close().await
+    }
+
+    fn into_split(self: Box<Self>) -> (Arc<dyn TransportSender>, Box<dyn TransportReceiver>) {
+        (Arc::new(self.sender), Box::new(self.receiver))
+    }
+
+    fn set_on_close(&mut self, callback: Option<CloseCallback>) {
+        self.sender.on_close = callback;
+    }
+
+    fn set_on_error(&mut self, callback: Option<ErrorCallback>) {
+        let callback: Option<SharedErrorCallback> = callback.map(Arc::from);
+        self.sender.on_error = callback.clone();
+        self.receiver.on_error = callback;
+    }
+
+    fn set_on_message(&mut self, callback: Option<MessageCallback>) {
+        self.receiver.on_message = callback;
+    }
+
+    fn credentials(&self) -> Credentials {
+        self.credentials.clone()
+    }
+}
+
+/// A new session ID: 128 bits from the operating system's CSPRNG, hex encoded
+fn new_session_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Drop sessions without an event stream that have not posted for longer than `timeout`
+///
+/// Replies still owed to a removed session are forgotten.
+fn reap_idle_sessions(active_clients: &ActiveClients, routes: &RequestRoutes, timeout: Duration) {
+    let mut clients = active_clients.lock().unwrap();
+    clients.retain(|id, client| {
+        let idle = client.events.is_none() && client.last_poll.elapsed() > timeout;
+        if idle {
+            info!("Dropping idle session {}", id);
+        }
+        !idle
+    });
+    routes
+        .lock()
+        .unwrap()
+        .retain(|_, (session_id, _)| clients.contains_key(session_id));
+}
+
+/// Open an event stream, asking the server to resume after `last_event_id` if given
+async fn open_event_stream(
+    client: &HttpClient,
+    url: &Url,
+    last_event_id: Option<&str>,
+) -> Result<reqwest::Response, MCPError> {
+    let mut request = client
+        .get(url.clone())
+        .await?
+        .header(reqwest::header::ACCEPT, "text/event-stream");
+    if let Some(last_event_id) = last_event_id {
+        request = request.header("Last-Event-ID", last_event_id);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| MCPError::Transport(format!("Failed to connect to {}: {}", url, e)))?;
+    if !response.status().is_success() {
+        return Err(http_error(&response, "Failed to open SSE event stream"));
+    }
+    Ok(response)
+}
+
+/// Describe an unsuccessful response, passing on the server's authentication challenge
+fn http_error(response: &reqwest::Response, what: &str) -> MCPError {
+    let status = response.status();
+    if status != reqwest::StatusCode::UNAUTHORIZED && status != reqwest::StatusCode::FORBIDDEN {
+        return MCPError::Transport(format!("{}: HTTP {}", what, status));
+    }
+    match response
+        .headers()
+        .get(reqwest::header::WWW_AUTHENTICATE)
+        .and_then(|challenge| challenge.to_str().ok())
+    {
+        Some(challenge) => {
+            MCPError::Authorization(format!("{}: HTTP {} ({})", what, status, challenge))
+        }
+        None => MCPError::Authorization(format!("{}: HTTP {}", what, status)),
+    }
+}
+
+/// Client-side reader of the event stream, reconnecting whenever the stream drops
+struct EventReader {
+    client: HttpClient,
+    sse_url: Url,
+    post_url: Arc<Mutex<Option<Url>>>,
+    incoming: mpsc::Sender<Inbound>,
+    reconnect: Option<ReconnectPolicy>,
+    last_event_id: Option<String>,
+    max_event_size: usize,
+}
+
+impl EventReader {
+    /// Hand the first endpoint to `start` and messages to the receiver until giving up
+    async fn run(mut self, mut response: reqwest::Response, endpoint_tx: oneshot::Sender<String>) {
+        let mut endpoint_tx = Some(endpoint_tx);
+        loop {
+            if !self.read(response, &mut endpoint_tx).await {
+                debug!("Client receiver dropped, stopping event reader");
+                return;
+            }
+            // Before the first endpoint, start() is still waiting and reports the failure
+            if endpoint_tx.is_some() {
+                return;
+            }
+            response = match self.reconnect().await {
+                Some(response) => response,
+                None => {
+                    warn!("Giving up on SSE event stream at {}", self.sse_url);
+                    return;
+                }
+            };
+        }
+    }
+
+    /// Read one stream until it ends; returns `false` once the receiver is gone
+    async fn read(
+        &mut self,
+        response: reqwest::Response,
+        endpoint_tx: &mut Option<oneshot::Sender<String>>,
+    ) -> bool {
+        let mut parser = EventParser::new(self.max_event_size);
+        let mut body = response.bytes_stream();
+
+        while let Some(chunk) = body.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    warn!("SSE event stream error: {}", e);
+                    break;
+                }
+            };
+            for event in parser.feed(&chunk) {
+                if event.id.is_some() {
+                    self.last_event_id = event.id;
+                }
+                match event.event.as_str() {
+                    "endpoint" => match endpoint_tx.take() {
+                        Some(tx) => {
+                            let _ = tx.send(event.data);
+                        }
+                        None => self.update_endpoint(&event.data),
+                    },
+                    "message" => {
+                        let inbound = Inbound {
+                            session_id: None,
+                            message: event.data,
+                        };
+                        if self.incoming.send(inbound).await.is_err() {
+                            return false;
+                        }
+                    }
+                    other => debug!("Ignoring SSE event of type {:?}", other),
+                }
+            }
+        }
+        debug!("SSE event stream ended");
+        true
+    }
+
+    /// Follow the endpoint announced on a reconnected stream
+    fn update_endpoint(&self, endpoint: &str) {
+        match endpoint_url(&self.sse_url, endpoint) {
+            Ok(url) => {
+                let mut post_url = self.post_url.lock().unwrap();
+                if post_url.as_ref() != Some(&url) {
+                    warn!("Server started a new session at {}", url);
+                }
+                *post_url = Some(url);
+            }
+            Err(e) => error!("{}", e),
+        }
+    }
+
+    /// Reopen the stream for the current session, backing off between attempts
+    async fn reconnect(&self) -> Option<reqwest::Response> {
+        let policy = self.reconnect.as_ref()?;
+
+        let mut url = self.sse_url.clone();
+        let session_id = self.post_url.lock().unwrap().as_ref().and_then(|post_url| {
+            post_url
+                .query_pairs()
+                .find(|(key, _)| key == "sessionId")
+                .map(|(_, value)| value.into_owned())
+        });
+        if let Some(session_id) = session_id {
+            url.query_pairs_mut().append_pair("sessionId", &session_id);
+        }
+
+        let mut attempt = 0;
+        loop {
+            tokio::time::sleep(policy.delay(attempt)?).await;
+            attempt += 1;
+            info!("Reconnecting to {} (attempt {})", url, attempt);
+            match open_event_stream(&self.client, &url, self.last_event_id.as_deref()).await {
+                Ok(response) => return Some(response),
+                Err(e) => warn!("Reconnect failed: {}", e),
+            }
+        }
+    }
+}
+
+/// A dispatched server-sent event
+#[derive(Debug, PartialEq)]
+struct SseEvent {
+    event: String,
+    data: String,
+    id: Option<String>,
+}
+
+/// Incremental parser for the `text/event-stream` format
+///
+/// Events whose data grows past `max_event_size` bytes are skipped without being
+/// buffered.
+struct EventParser {
+    buffer: Vec<u8>,
+    // Bytes at the start of `buffer` already searched for a line ending
+    scanned: usize,
+    event: Option<String>,
+    data: Vec<String>,
+    // Bytes of data in the current event, with the newlines that will join its lines
+    size: usize,
+    id: Option<String>,
+    max_event_size: usize,
+    // Set while the rest of an oversized event is skipped
+    oversized: bool,
+    // Set until the end of a line dropped from `buffer` has gone by
+    truncated: bool,
+}
+
+impl EventParser {
+    fn new(max_event_size: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            scanned: 0,
+            event: None,
+            data: Vec::new(),
+            size: 0,
+            id: None,
+            max_event_size,
+            oversized: false,
+            truncated: false,
+        }
+    }
+
+    /// Feed a chunk of the stream, returning the events it completes
+    fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let chunk = match self.truncated {
+            true => match memchr::memchr(b'\n', chunk) {
+                Some(end) => {
+                    self.truncated = false;
+                    &chunk[end + 1..]
+                }
+                None => return Vec::new(),
+            },
+            false => chunk,
+        };
+        self.buffer.extend_from_slice(chunk);
+        let buffer = std::mem::take(&mut self.buffer);
+        let mut events = Vec::new();
+
+        let mut start = 0;
+        while let Some(offset) = memchr::memchr(b'\n', &buffer[self.scanned..]) {
+            let end = self.scanned + offset;
+            let line = &buffer[start..end];
+            self.scanned = end + 1;
+            start = end + 1;
+
+            let line = String::from_utf8_lossy(line);
+            if let Some(event) = self.line(line.strip_suffix('\r').unwrap_or(&line)) {
+                events.push(event);
+            }
+        }
+
+        self.buffer = buffer;
+        self.buffer.drain(..start);
+        self.scanned = self.buffer.len();
+
+        // A partial line that cannot fit is dropped rather than buffered to its end
+        if self.size + self.buffer.len() > self.max_event_size {
+            self.skip_event();
+            self.buffer.clear();
+            self.scanned = 0;
+            self.truncated = true;
+        }
+
+        events
+    }
+
+    /// Take one line without its line ending, returning the event it completes
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" if !self.oversized => {
+                self.size += value.len() + usize::from(!self.data.is_empty());
+                if self.size > self.max_event_size {
+                    self.skip_event();
+                } else {
+                    self.data.push(value.to_string());
+                }
+            }
+            "id" => self.id = Some(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+
+    /// Drop the data of the current event and ignore the rest of it
+    fn skip_event(&mut self) {
+        if !self.oversized {
+            warn!(
+                "Skipping SSE event of more than {} bytes",
+                self.max_event_size
+            );
+        }
+        self.oversized = true;
+        self.data.clear();
+        self.size = 0;
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        self.size = 0;
+        if std::mem::take(&mut self.oversized) || self.data.is_empty() {
+            return None;
+        }
+        Some(SseEvent {
+            event: event.unwrap_or_else(|| "message".to_string()),
+            data: std::mem::take(&mut self.data).join("\n"),
+            id: self.id.clone(),
+        })
+    }
+}
+
+/// Normalize a base path to either `""` or `/segment...` without a trailing slash
+fn normalize_base_path(path: &str) -> String {
+    let trimmed = path.trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+/// The `host:port` to bind for a server URI, keeping IPv6 literals bracketed
+fn bind_address(url: &Url) -> Result<String, MCPError> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| MCPError::Transport(format!("Missing host in URI: {}", url)))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| MCPError::Transport(format!("Missing port in URI: {}", url)))?;
+    Ok(format!("{}:{}", host, port))
+}
+
+/// The event stream URL for a client URI
+fn sse_url(uri: &str) -> Result<Url, MCPError> {
+    let url =
+        Url::parse(uri).map_err(|e| MCPError::Transport(format!("Invalid URI {}: {}", uri, e)))?;
+    if url.path().trim_end_matches('/').ends_with("/sse") {
+        return Ok(url);
+    }
+    let path = format!("{}/sse", url.path().trim_end_matches('/'));
+    let mut url = url;
+    url.set_path(&path);
+    Ok(url)
+}
+
+/// The URL the client posts messages to, from the endpoint the server announced
+///
+/// The endpoint must stay on the origin of the event stream, so a server cannot have
+/// the client send its messages, and the credentials that go with them, elsewhere.
+fn endpoint_url(sse_url: &Url, endpoint: &str) -> Result<Url, MCPError> {
+    let url = sse_url
+        .join(endpoint)
+        .map_err(|e| MCPError::Transport(format!("Invalid endpoint {:?}: {}", endpoint, e)))?;
+    if url.origin() != sse_url.origin() {
+        return Err(MCPError::Transport(format!(
+            "Endpoint {} is not on the origin of {}",
+            url, sse_url
+        )));
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::client::{AuthorizationServerMetadata, OAuthClient};
+    use crate::auth::server::{IntrospectionValidator, StaticTokens};
+    use crate::auth::test_server::TestAuthServer;
+    use crate::schema::json_rpc::{JSONRPCNotification, JSONRPCRequest, JSONRPCResponse};
+    use crate::schema::raw::RawResponse;
+    use crate::transport::tls::test_certs::TestCa;
+    use futures::Stream;
+    use serde_json::json;
+
+    /// Answer every request with a response carrying the request's params
+    fn spawn_echo(server_transport: SSETransport) -> JoinHandle<()> {
+        let (server_sender, mut server_receiver) = Box::new(server_transport).into_split();
+        tokio::spawn(async move {
+            while let Ok(Some(message)) = server_receiver.recv().await {
+                if let JSONRPCMessage::Request(request) = message {
+                    let result = request.params.unwrap_or_default();
+                    let response = JSONRPCResponse::new(request.id, result);
+                    server_sender
+                        .send(JSONRPCMessage::Response(response))
+                        .await
+                        .unwrap();
+                }
+            }
+        })
+    }
+
+    async fn started_server() -> (SSETransport, String) {
+        let mut server = SSETransport::new_server("http://127.0.0.1:0");
+        server.start().await.unwrap();
+        let uri = format!("http://{}", server.local_addr().unwrap());
+        (server, uri)
+    }
+
+    #[test]
+    fn test_event_parser_skips_oversized_events() {
+        let mut parser = EventParser::new(8);
+        // One line too long, dropped before its end arrives
+        assert!(parser.feed(b"id: 1\ndata: 0123").is_empty());
+        assert!(parser.feed(b"456789").is_empty());
+        assert!(parser.buffer.is_empty());
+        // Lines that only add up to too much, then an event that fits
+        let events = parser.feed(b"\n\ndata: 0123\ndata: 4567\n\ndata: 01\ndata: 3456\n\n");
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                event: "message".to_string(),
+                data: "01\n3456".to_string(),
+                id: Some("1".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_event_parser_handles_split_chunks() {
+        let mut parser = EventParser::new(DEFAULT_MAX_FRAME_SIZE);
+        assert!(parser.feed(b"event: endpoint\r\nda").is_empty());
+        let events = parser
+            .feed(b"ta: /message?sessionId=1\r\n\r\n: keep-alive\n\ndata: {\"a\":\ndata: 1}\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: "endpoint".to_string(),
+                    data: "/message?sessionId=1".to_string(),
+                    id: None,
+                },
+                SseEvent {
+                    event: "message".to_string(),
+                    data: "{\"a\":\n1}".to_string(),
+                    id: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_addresses_and_paths() {
+        let url = Url::parse("http://[::1]:8080/mcp/").unwrap();
+        assert_eq!(bind_address(&url).unwrap(), "[::1]:8080");
+        let url = Url::parse("http://localhost").unwrap();
+        assert_eq!(bind_address(&url).unwrap(), "localhost:80");
+
+        assert_eq!(normalize_base_path("/"), "");
+        assert_eq!(normalize_base_path("mcp/v1/"), "/mcp/v1");
+
+        assert_eq!(
+            sse_url("http://localhost:8080").unwrap().as_str(),
+            "http://localhost:8080/sse"
+        );
+        assert_eq!(
+            sse_url("http://localhost:8080/mcp/sse").unwrap().as_str(),
+            "http://localhost:8080/mcp/sse"
+        );
+    }
+
+    async fn echo_round_trip(server_transport: SSETransport, client_uri: &str) {
+        let echo = spawn_echo(server_transport);
+
+        let mut client = SSETransport::new(client_uri);
+        client.start().await.unwrap();
+        let request = JSONRPCRequest::new(RequestId::Number(7), "ping".to_string(), None);
+        client.send(JSONRPCMessage::Request(request)).await.unwrap();
+
+        match client.recv().await.unwrap() {
+            Some(JSONRPCMessage::Response(res)) => assert_eq!(res.id, RequestId::Number(7)),
+            other => panic!("Expected response, got {:?}", other),
+        }
+        client.close().await.unwrap();
+        echo.abort();
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_with_base_path() {
+        let mut server = SSETransport::new_server("http://127.0.0.1:0/mcp");
+        server.start().await.unwrap();
+        let addr = server.local_addr().unwrap();
+
+        echo_round_trip(server, &format!("http://{}/mcp", addr)).await;
+    }
+
+    #[tokio::test]
+    async fn test_https_with_client_certificates() {
+        let ca = TestCa::new("Test CA");
+        let server_cert = ca.server_cert();
+        let tls =
+            TlsServerConfig::from_pem(server_cert.cert.as_bytes(), server_cert.key.as_bytes())
+                .unwrap()
+                .with_client_ca_pem(ca.pem.as_bytes())
+                .unwrap();
+        let mut server = SSETransport::new_server("https://127.0.0.1:0").with_tls(tls);
+        server.start().await.unwrap();
+        let uri = format!("https://localhost:{}", server.local_addr().unwrap().port());
+        let echo = spawn_echo(server);
+
+        // Without a client certificate the handshake is refused
+        let trust_only = TlsClientConfig::with_custom_roots_only()
+            .with_root_ca_pem(ca.pem.as_bytes())
+            .unwrap();
+        let mut anonymous = SSETransport::new(&uri).with_client_tls(trust_only.clone());
+        assert!(anonymous.start().await.is_err());
+
+        let client_cert = ca.client_cert("agent");
+        let tls = trust_only
+            .with_client_cert_pem(client_cert.cert.as_bytes(), client_cert.key.as_bytes())
+            .unwrap();
+        let mut client = SSETransport::new(&uri).with_client_tls(tls);
+        client.start().await.unwrap();
+        let request = JSONRPCRequest::new(RequestId::Number(7), "ping".to_string(), None);
+        client.send(JSONRPCMessage::Request(request)).await.unwrap();
+        match client.recv().await.unwrap() {
+            Some(JSONRPCMessage::Response(res)) => assert_eq!(res.id, RequestId::Number(7)),
+            other => panic!("Expected response, got {:?}", other),
+        }
+        client.close().await.unwrap();
+        echo.abort();
+    }
+
+    #[tokio::test]
+    async fn test_listener_reports_client_certificates() {
+        use crate::auth::session::{Authenticator, ClientCertificates};
+
+        let ca = TestCa::new("Test CA");
+        let server_cert = ca.server_cert();
+        let tls =
+            TlsServerConfig::from_pem(server_cert.cert.as_bytes(), server_cert.key.as_bytes())
+                .unwrap()
+                .with_client_ca_pem(ca.pem.as_bytes())
+                .unwrap();
+        let mut listener =
+            SSEListener::new(SSETransport::new_server("https://127.0.0.1:0").with_tls(tls))
+                .await
+                .unwrap();
+        let uri = format!(
+            "https://localhost:{}",
+            listener.local_addr().unwrap().port()
+        );
+
+        let client_cert = ca.client_cert("agent");
+        let tls = TlsClientConfig::with_custom_roots_only()
+            .with_root_ca_pem(ca.pem.as_bytes())
+            .unwrap()
+            .with_client_cert_pem(client_cert.cert.as_bytes(), client_cert.key.as_bytes())
+            .unwrap();
+        let mut client = SSETransport::new(&uri).with_client_tls(tls);
+        client.start().await.unwrap();
+
+        let session = listener.accept().await.unwrap();
+        let credentials = session.credentials();
+        assert_eq!(credentials.peer_certificates.len(), 1);
+        let principal = ClientCertificates::new()
+            .authenticate(&credentials)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(principal.id, "agent");
+        client.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_options_apply_to_every_request() {
+        let mut server = SSETransport::new_mounted("");
+        let seen: Arc<Mutex<Vec<(String, String)>>> = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&seen);
+        let app = server.router().layer(axum::middleware::from_fn(
+            move |request: axum::extract::Request, next: axum::middleware::Next| {
+                let header = |name: &str| {
+                    request.headers()[name]
+                        .to_str()
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                recorder
+                    .lock()
+                    .unwrap()
+                    .push((header("authorization"), header("x-tenant")));
+                next.run(request)
+            },
+        ));
+        server.start().await.unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app_task = tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let tokens = Arc::new(AtomicI64::new(0));
+        let options = ClientTransportOptions::new()
+            .with_header("X-Tenant", "acme")
+            .with_connect_timeout(Duration::from_secs(5))
+            .with_token_provider(move || {
+                let n = tokens.fetch_add(1, Ordering::SeqCst);
+                async move { Ok(format!("token-{}", n)) }
+            });
+        let mut client =
+            SSETransport::new(&format!("http://{}", addr)).with_client_options(options);
+        client.start().await.unwrap();
+        let request = JSONRPCRequest::new(RequestId::Number(1), "ping".to_string(), None);
+        client.send(JSONRPCMessage::Request(request)).await.unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("Bearer token-0".to_string(), "acme".to_string()),
+                ("Bearer token-1".to_string(), "acme".to_string()),
+            ]
+        );
+        client.close().await.unwrap();
+        app_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_oauth_protected_server() {
+        let auth_server = TestAuthServer::start(3600).await;
+        auth_server.add_client("mcp-server", "server-secret");
+        auth_server.add_client("agent", "agent-secret");
+        let validator = IntrospectionValidator::new(
+            &format!("{}/introspect", auth_server.url),
+            "mcp-server",
+            "server-secret",
+        );
+        let mut server =
+            SSETransport::new_server("http://127.0.0.1:0").with_auth(BearerAuth::new(validator));
+        server.start().await.unwrap();
+        let uri = format!("http://{}", server.local_addr().unwrap());
+        let echo = spawn_echo(server);
+
+        let mut anonymous = SSETransport::new(&uri).with_reconnect(None);
+        match anonymous.start().await {
+            Err(MCPError::Authorization(e)) => assert!(e.contains("Bearer realm"), "{}", e),
+            other => panic!("Expected an authorization error, got {:?}", other),
+        }
+
+        let metadata = AuthorizationServerMetadata::discover(&auth_server.url)
+            .await
+            .unwrap();
+        let oauth = OAuthClient::new(metadata, "agent").with_client_secret("agent-secret");
+        let options = ClientTransportOptions::new().with_oauth(Arc::new(oauth));
+        let mut client = SSETransport::new(&uri).with_client_options(options);
+        client.start().await.unwrap();
+        let request = JSONRPCRequest::new(RequestId::Number(3), "ping".to_string(), None);
+        client.send(JSONRPCMessage::Request(request)).await.unwrap();
+        assert!(matches!(
+            client.recv().await.unwrap(),
+            Some(JSONRPCMessage::Response(_))
+        ));
+        // One token served both the event stream and the posted message
+        assert_eq!(auth_server.grants("client_credentials"), 1);
+        client.close().await.unwrap();
+        echo.abort();
+    }
+
+    #[tokio::test]
+    async fn test_listener_authenticates_each_session() {
+        use crate::auth::session::{ApiKeys, Principal};
+        use crate::schema::common::Tool;
+        use crate::server::{Server, ServerConfig};
+
+        let tool = |name: &str| Tool {
+            name: name.to_string(),
+            description: None,
+            ..Default::default()
+        };
+        let config = ServerConfig::new()
+            .with_tool(tool("search"))
+            .with_tool(tool("deploy"));
+        let keys = ApiKeys::new()
+            .with_key("ops-key", Principal::new("ops").with_roles(&["ops"]))
+            .with_key("dev-key", Principal::new("dev"));
+        let server = Server::new(config).with_authenticator(keys).with_policy(
+            |principal: Option<&Principal>, tool: &str| {
+                tool != "deploy" || principal.is_some_and(|p| p.has_role("ops"))
+            },
+        );
+
+        let listener = SSEListener::new(SSETransport::new_server("http://127.0.0.1:0"))
+            .await
+            .unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap());
+        let serving = tokio::spawn(async move { server.serve_listener(listener).await });
+
+        for (key, visible) in [
+            ("ops-key", json!(["search", "deploy"])),
+            ("dev-key", json!(["search"])),
+        ] {
+            let options = ClientTransportOptions::new().with_header("X-API-Key", key);
+            let mut client = SSETransport::new(&uri).with_client_options(options);
+            client.start().await.unwrap();
+            let request = JSONRPCRequest::new(RequestId::Number(1), "tools/list".to_string(), None);
+            client.send(JSONRPCMessage::Request(request)).await.unwrap();
+            let Some(JSONRPCMessage::Response(response)) = client.recv().await.unwrap() else {
+                panic!("Expected a tools/list response");
+            };
+            let names: Vec<_> = response.result["tools"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|tool| tool["name"].clone())
+                .collect();
+            assert_eq!(json!(names), visible);
+            client.close().await.unwrap();
+        }
+        serving.abort();
+    }
+
+    #[tokio::test]
+    async fn test_mounted_into_application_router() {
+        let mut server = SSETransport::new_mounted("/api/mcp");
+        let app = Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .merge(server.router());
+        server.start().await.unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app_task = tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let health = reqwest::get(format!("http://{}/health", addr))
+            .await
+            .unwrap();
+        assert_eq!(health.text().await.unwrap(), "ok");
+
+        echo_round_trip(server, &format!("http://{}/api/mcp", addr)).await;
+        app_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_endpoints_stay_on_the_stream_origin() {
+        let sse = Url::parse("http://127.0.0.1:8080/mcp/sse").unwrap();
+        assert_eq!(
+            endpoint_url(&sse, "/message?sessionId=1").unwrap().as_str(),
+            "http://127.0.0.1:8080/message?sessionId=1"
+        );
+        for endpoint in [
+            "http://evil.example/message",
+            "//evil.example/message",
+            "https://127.0.0.1:8080/message",
+            "http://127.0.0.1:8081/message",
+        ] {
+            assert!(endpoint_url(&sse, endpoint).is_err(), "{}", endpoint);
+        }
+
+        // A server announcing another origin is refused before anything is posted there
+        let app = Router::new().route(
+            "/sse",
+            get(|| async {
+                (
+                    [(axum::http::header::CONTENT_TYPE, "text/event-stream")],
+                    "event: endpoint\ndata: http://evil.example/message?sessionId=1\n\n",
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app_task = tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut client = SSETransport::new(&format!("http://{}", addr));
+        let error = client.start().await.unwrap_err();
+        assert!(error.to_string().contains("evil.example"), "{}", error);
+        app_task.abort();
+    }
+
+    #[test]
+    fn test_session_ids_are_random() {
+        let first = new_session_id();
+        let second = new_session_id();
+        assert_eq!(first.len(), 32);
+        assert!(first.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_replies_go_only_to_the_originating_session() {
+        let (server, uri) = started_server().await;
+        let echo = spawn_echo(server);
+
+        let mut first = SSETransport::new(&uri);
+        let mut second = SSETransport::new(&uri);
+        first.start().await.unwrap();
+        second.start().await.unwrap();
+
+        // Both clients use the same request ID
+        for (client, name) in [(&mut first, "first"), (&mut second, "second")] {
+            let params = json!({ "from": name });
+            let request =
+                JSONRPCRequest::new(RequestId::Number(1), "ping".to_string(), Some(params));
+            client.send(JSONRPCMessage::Request(request)).await.unwrap();
+        }
+
+        for (client, name) in [(&mut first, "first"), (&mut second, "second")] {
+            match client.recv().await.unwrap() {
+                Some(JSONRPCMessage::Response(res)) => {
+                    assert_eq!(res.id, RequestId::Number(1));
+                    assert_eq!(res.result["from"], name);
+                }
+                other => panic!("Expected response, got {:?}", other),
+            }
+            // Nothing meant for the other session arrives
+            let extra = tokio::time::timeout(Duration::from_millis(100), client.recv()).await;
+            assert!(extra.is_err(), "Unexpected message: {:?}", extra);
+        }
+
+        first.close().await.unwrap();
+        second.close().await.unwrap();
+        echo.abort();
+    }
+
+    #[tokio::test]
+    async fn test_cancellations_name_the_servers_request_id() {
+        let (server, uri) = started_server().await;
+        let (_server_sender, mut server_receiver) = Box::new(server).into_split();
+
+        let mut first = SSETransport::new(&uri);
+        let mut second = SSETransport::new(&uri);
+        first.start().await.unwrap();
+        second.start().await.unwrap();
+
+        // Both clients use the same request ID, which the server sees as two of its own
+        let mut ours = HashMap::new();
+        for (client, name) in [(&mut first, "first"), (&mut second, "second")] {
+            let params = json!({ "from": name });
+            let request =
+                JSONRPCRequest::new(RequestId::Number(1), "slow".to_string(), Some(params));
+            client.send(JSONRPCMessage::Request(request)).await.unwrap();
+            let Some(JSONRPCMessage::Request(received)) = server_receiver.recv().await.unwrap()
+            else {
+                panic!("Expected the request");
+            };
+            ours.insert(name, received.id);
+        }
+
+        let cancel = |id: i64| {
+            JSONRPCMessage::Notification(JSONRPCNotification::new(
+                "notifications/cancelled".to_string(),
+                Some(json!({ "requestId": id })),
+            ))
+        };
+        let cancelled = |message: Option<JSONRPCMessage>| match message {
+            Some(JSONRPCMessage::Notification(notification)) => {
+                serde_json::from_value::<RequestId>(
+                    notification.params.unwrap()["requestId"].clone(),
+                )
+                .unwrap()
+            }
+            other => panic!("Expected the cancellation, got {:?}", other),
+        };
+
+        first.send(cancel(1)).await.unwrap();
+        let id = cancelled(server_receiver.recv().await.unwrap());
+        assert_eq!(id, ours["first"]);
+
+        // A request the server never saw cannot be mistaken for another session's
+        second.send(cancel(7)).await.unwrap();
+        let id = cancelled(server_receiver.recv().await.unwrap());
+        assert!(!ours.values().any(|ours| *ours == id));
+        assert!(matches!(id, RequestId::String(_)));
+
+        first.close().await.unwrap();
+        second.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_raw_messages_are_routed_like_typed_ones() {
+        let (server, uri) = started_server().await;
+        let (server_sender, mut server_receiver) = Box::new(server).into_split();
+        let mut client = SSETransport::new(&uri);
+        client.start().await.unwrap();
+        let (client_sender, mut client_receiver) = Box::new(client).into_split();
+
+        let request = JSONRPCRequest::new(
+            RequestId::Number(1),
+            "slow".to_string(),
+            Some(json!({ "n": 1 })),
+        );
+        client_sender
+            .send(JSONRPCMessage::Request(request))
+            .await
+            .unwrap();
+        let Some(RawMessage::Request(received)) = server_receiver.recv_raw().await.unwrap() else {
+            panic!("Expected the request");
+        };
+        assert_eq!(received.params.as_ref().unwrap().get(), r#"{"n":1}"#);
+        let ours = received.id.clone();
+
+        let cancel = JSONRPCNotification::new(
+            "notifications/cancelled".to_string(),
+            Some(json!({ "requestId": 1 })),
+        );
+        client_sender
+            .send(JSONRPCMessage::Notification(cancel))
+            .await
+            .unwrap();
+        match server_receiver.recv_raw().await.unwrap() {
+            Some(RawMessage::Notification(notification)) => {
+                let params: Value = notification.params_as().unwrap();
+                assert_eq!(
+                    serde_json::from_value::<RequestId>(params["requestId"].clone()).unwrap(),
+                    ours
+                );
+            }
+            other => panic!("Expected the cancellation, got {:?}", other),
+        }
+
+        let response = RawResponse::new(
+            ours,
+            serde_json::value::to_raw_value(&json!([1, 2])).unwrap(),
+        );
+        server_sender
+            .send_raw(RawMessage::Response(response))
+            .await
+            .unwrap();
+        match client_receiver.recv_raw().await.unwrap() {
+            Some(RawMessage::Response(response)) => {
+                assert_eq!(response.id, RequestId::Number(1));
+                assert_eq!(response.result.get(), "[1,2]");
+            }
+            other => panic!("Expected the response, got {:?}", other),
+        }
+        client_sender.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_notifications_reach_every_session() {
+        let (server, uri) = started_server().await;
+        let (server_sender, _server_receiver) = Box::new(server).into_split();
+
+        let mut clients = Vec::new();
+        for _ in 0..2 {
+            let mut client = SSETransport::new(&uri);
+            client.start().await.unwrap();
+            clients.push(client);
+        }
+
+        let notification = JSONRPCNotification::new("notifications/message".to_string(), None);
+        server_sender
+            .send(JSONRPCMessage::Notification(notification))
+            .await
+            .unwrap();
+        for client in &mut clients {
+            assert!(matches!(
+                client.recv().await.unwrap(),
+                Some(JSONRPCMessage::Notification(_))
+            ));
+        }
+        server_sender.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_idle_sessions_are_reaped() {
+        let mut server = SSETransport::new_server("http://127.0.0.1:0")
+            .with_session_timeout(Some(Duration::from_millis(50)));
+        server.start().await.unwrap();
+        let uri = format!("http://{}", server.local_addr().unwrap());
+        let active_clients = Arc::clone(&server.sender.active_clients);
+
+        let mut client = SSETransport::new(&uri).with_reconnect(None);
+        client.start().await.unwrap();
+        assert_eq!(active_clients.lock().unwrap().len(), 1);
+
+        // A session whose event stream is open is kept however long it stays quiet
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(active_clients.lock().unwrap().len(), 1);
+
+        // Once the stream is gone the session is dropped after the timeout
+        client.close().await.unwrap();
+        let reaped = tokio::time::timeout(Duration::from_secs(5), async {
+            while !active_clients.lock().unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await;
+        assert!(reaped.is_ok(), "Session should be dropped once detached");
+        server.close().await.unwrap();
+    }
+
+    #[test]
+    fn test_replay_buffer_is_bounded() {
+        let mut client = ClientConnection::new("session", None, 2);
+        for message in ["one", "two", "three"] {
+            client.push(message.to_string());
+        }
+
+        let (mut events, _) = client.attach(0);
+        assert_eq!(events.try_recv().unwrap(), (2, "two".to_string()));
+        assert_eq!(events.try_recv().unwrap(), (3, "three".to_string()));
+        assert!(events.try_recv().is_err());
+
+        let (mut events, _) = client.attach(2);
+        assert_eq!(events.try_recv().unwrap(), (3, "three".to_string()));
+        assert!(events.try_recv().is_err());
+    }
+
+    /// Read events from a raw event stream until `count` have arrived
+    async fn next_events<B: AsRef<[u8]>>(
+        body: &mut (impl Stream<Item = reqwest::Result<B>> + Unpin),
+        count: usize,
+    ) -> Vec<SseEvent> {
+        let mut parser = EventParser::new(DEFAULT_MAX_FRAME_SIZE);
+        let mut events = Vec::new();
+        while events.len() < count {
+            let chunk = body.next().await.unwrap().unwrap();
+            events.extend(parser.feed(chunk.as_ref()));
+        }
+        events
+    }
+
+    fn notification(method: &str) -> JSONRPCMessage {
+        JSONRPCMessage::Notification(JSONRPCNotification::new(method.to_string(), None))
+    }
+
+    #[tokio::test]
+    async fn test_last_event_id_replays_missed_messages() {
+        let (server, uri) = started_server().await;
+        let (server_sender, _server_receiver) = Box::new(server).into_split();
+
+        let mut first = reqwest::get(format!("{}/sse", uri))
+            .await
+            .unwrap()
+            .bytes_stream();
+        let endpoint = next_events(&mut first, 1).await.remove(0);
+        let session_id = endpoint
+            .data
+            .split("sessionId=")
+            .nth(1)
+            .unwrap()
+            .to_string();
+
+        server_sender.send(notification("one")).await.unwrap();
+        let one = next_events(&mut first, 1).await.remove(0);
+        assert_eq!(one.id.as_deref(), Some("1"));
+
+        // The stream drops, and the server keeps sending
+        drop(first);
+        server_sender.send(notification("two")).await.unwrap();
+        server_sender.send(notification("three")).await.unwrap();
+
+        let resumed = reqwest::Client::new()
+            .get(format!("{}/sse?sessionId={}", uri, session_id))
+            .header("Last-Event-ID", "1")
+            .send()
+            .await
+            .unwrap();
+        let events = next_events(&mut resumed.bytes_stream(), 3).await;
+        assert_eq!(events[0].event, "endpoint");
+        assert!(events[0].data.ends_with(&session_id));
+        assert_eq!(events[1].id.as_deref(), Some("2"));
+        assert!(events[1].data.contains("\"two\""));
+        assert_eq!(events[2].id.as_deref(), Some("3"));
+        assert!(events[2].data.contains("\"three\""));
+
+        server_sender.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_posted_messages_must_be_json() {
+        let (mut server, uri) = started_server().await;
+        let mut stream = reqwest::get(format!("{}/sse", uri))
+            .await
+            .unwrap()
+            .bytes_stream();
+        let endpoint = next_events(&mut stream, 1).await.remove(0);
+
+        let http = reqwest::Client::new();
+        let post = |body: &'static str| {
+            http.post(format!("{}{}", uri, endpoint.data))
+                .body(body)
+                .send()
+        };
+        let rejected = post(r#"{"jsonrpc":"2.0","#).await.unwrap();
+        assert_eq!(rejected.status(), reqwest::StatusCode::BAD_REQUEST);
+        let accepted = post(r#"{"jsonrpc":"2.0","method":"ping"}"#).await.unwrap();
+        assert_eq!(accepted.status(), reqwest::StatusCode::ACCEPTED);
+
+        server.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_posted_message_size_is_configurable() {
+        // Several MB of params, well over the 2 MB axum allows by default
+        let message = serde_json::to_string(&JSONRPCNotification::new(
+            "notifications/message".to_string(),
+            Some(json!({ "level": "info", "data": "x".repeat(6 * 1024 * 1024) })),
+        ))
+        .unwrap();
+
+        for (limit, status) in [
+            (None, reqwest::StatusCode::ACCEPTED),
+            (Some(1024 * 1024), reqwest::StatusCode::PAYLOAD_TOO_LARGE),
+        ] {
+            let mut server = SSETransport::new_server("http://127.0.0.1:0");
+            if let Some(limit) = limit {
+                server = server.with_max_message_size(limit);
+            }
+            server.start().await.unwrap();
+            let uri = format!("http://{}", server.local_addr().unwrap());
+            let mut stream = reqwest::get(format!("{}/sse", uri))
+                .await
+                .unwrap()
+                .bytes_stream();
+            let endpoint = next_events(&mut stream, 1).await.remove(0);
+
+            let post = reqwest::Client::new()
+                .post(format!("{}{}", uri, endpoint.data))
+                .body(message.clone())
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(post.status(), status);
+            if limit.is_none() {
+                let Some(JSONRPCMessage::Notification(received)) = server.recv().await.unwrap()
+                else {
+                    panic!("Expected the notification");
+                };
+                assert_eq!(
+                    received.params.unwrap()["data"].as_str().unwrap().len(),
+                    6 * 1024 * 1024
+                );
+            }
+            server.close().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_bound_to_their_token() {
+        let token = |subject: &str| TokenInfo {
+            subject: Some(subject.to_string()),
+            client_id: None,
+            scopes: Vec::new(),
+            audience: Vec::new(),
+        };
+        let tokens = StaticTokens::new()
+            .with_token("alice-token", token("alice"))
+            .with_token("alice-other-token", token("alice"))
+            .with_token("mallory-token", token("mallory"));
+        let mut server =
+            SSETransport::new_server("http://127.0.0.1:0").with_auth(BearerAuth::new(tokens));
+        server.start().await.unwrap();
+        let uri = format!("http://{}", server.local_addr().unwrap());
+
+        let http = reqwest::Client::new();
+        let mut first = http
+            .get(format!("{}/sse", uri))
+            .bearer_auth("alice-token")
+            .send()
+            .await
+            .unwrap()
+            .bytes_stream();
+        let endpoint = next_events(&mut first, 1).await.remove(0);
+        drop(first);
+
+        // Someone else who learns the session ID can neither resume nor post to it
+        let resume = |token: &'static str| {
+            http.get(format!(
+                "{}{}",
+                uri,
+                endpoint.data.replace("/message", "/sse")
+            ))
+            .bearer_auth(token)
+            .send()
+        };
+        let hijack = resume("mallory-token").await.unwrap();
+        assert_eq!(hijack.status(), reqwest::StatusCode::FORBIDDEN);
+        let post = http
+            .post(format!("{}{}", uri, endpoint.data))
+            .bearer_auth("mallory-token")
+            .body(r#"{"jsonrpc":"2.0","method":"ping"}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(post.status(), reqwest::StatusCode::FORBIDDEN);
+
+        // The same subject may, even with a refreshed token
+        let resumed = resume("alice-other-token").await.unwrap();
+        assert_eq!(resumed.status(), reqwest::StatusCode::OK);
+        let events = next_events(&mut resumed.bytes_stream(), 1).await;
+        assert_eq!(events[0].data, endpoint.data);
+
+        server.close().await.unwrap();
+    }
+
+    /// A TCP proxy whose open connections can be cut, like an impatient corporate proxy
+    struct FlakyProxy {
+        addr: SocketAddr,
+        connections: Arc<Mutex<Vec<JoinHandle<()>>>>,
+        accept_task: JoinHandle<()>,
+    }
+
+    impl FlakyProxy {
+        async fn start(upstream: SocketAddr) -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let connections = Arc::new(Mutex::new(Vec::new()));
+            let tracked = Arc::clone(&connections);
+            let accept_task = tokio::spawn(async move {
+                while let Ok((mut inbound, _)) = listener.accept().await {
+                    let connection = tokio::spawn(async move {
+                        if let Ok(mut outbound) = tokio::net::TcpStream::connect(upstream).await {
+                            let _ =
+                                tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                        }
+                    });
+                    tracked.lock().unwrap().push(connection);
+                }
+            });
+            Self {
+                addr,
+                connections,
+                accept_task,
+            }
+        }
+
+        fn cut(&self) {
+            for connection in self.connections.lock().unwrap().drain(..) {
+                connection.abort();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_reconnects_and_resumes() {
+        let (server, _) = started_server().await;
+        let proxy = FlakyProxy::start(server.local_addr().unwrap()).await;
+        let (server_sender, mut server_receiver) = Box::new(server).into_split();
+
+        let policy = ReconnectPolicy::new().with_initial_delay(Duration::from_millis(10));
+        let mut client =
+            SSETransport::new(&format!("http://{}", proxy.addr)).with_reconnect(Some(policy));
+        client.start().await.unwrap();
+
+        server_sender.send(notification("before")).await.unwrap();
+        match client.recv().await.unwrap() {
+            Some(JSONRPCMessage::Notification(n)) => assert_eq!(n.method, "before"),
+            other => panic!("Expected notification, got {:?}", other),
+        }
+
+        // Messages sent while the stream is down arrive after the reconnect
+        proxy.cut();
+        server_sender.send(notification("during")).await.unwrap();
+        match client.recv().await.unwrap() {
+            Some(JSONRPCMessage::Notification(n)) => assert_eq!(n.method, "during"),
+            other => panic!("Expected notification, got {:?}", other),
+        }
+
+        // The session survived, so posting still works
+        let request = JSONRPCRequest::new(RequestId::Number(1), "ping".to_string(), None);
+        client.send(JSONRPCMessage::Request(request)).await.unwrap();
+        assert!(matches!(
+            server_receiver.recv().await.unwrap(),
+            Some(JSONRPCMessage::Request(_))
+        ));
+
+        client.close().await.unwrap();
+        server_sender.close().await.unwrap();
+        proxy.accept_task.abort();
     }
 }
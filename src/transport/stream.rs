@@ -0,0 +1,415 @@
+//! Transport over an arbitrary pair of async byte streams
+//!
+//! [`StreamTransport`] runs MCP over any `AsyncRead` + `AsyncWrite` pair: pipes, PTYs,
+//! sockets handed over by another system, or the process's own stdin/stdout (see
+//! [`StdioTransport`](crate::transport::stdio::StdioTransport)). How messages are
+//! delimited on the wire is decided by a pluggable [`Framing`].
+
+use crate::auth::session::Credentials;
+use crate::error::MCPError;
+use crate::schema::json_rpc::JSONRPCMessage;
+use crate::schema::raw::RawMessage;
+use crate::transport::framing::{ContentLength, Framing, NewlineDelimited};
+use crate::transport::{
+    CloseCallback, ErrorCallback, MessageCallback, SharedErrorCallback, Transport,
+    TransportReceiver, TransportSender,
+};
+use async_trait::async_trait;
+use log::debug;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
+use tokio::sync::Mutex;
+
+/// Transport over a reader/writer pair with configurable framing
+///
+/// Use [`Transport::into_split`] to send from several tasks while one task reads.
+pub struct StreamTransport<R, W> {
+    sender: StreamSender<W>,
+    receiver: StreamReceiver<R>,
+    credentials: Credentials,
+}
+
+/// Writing half of a [`StreamTransport`]
+struct StreamSender<W> {
+    writer: Mutex<W>,
+    framing: Arc<dyn Framing>,
+    is_connected: Arc<AtomicBool>,
+    on_close: Option<CloseCallback>,
+    on_error: Option<SharedErrorCallback>,
+}
+
+/// Reading half of a [`StreamTransport`]
+struct StreamReceiver<R> {
+    reader: BufReader<R>,
+    framing: Arc<dyn Framing>,
+    on_error: Option<SharedErrorCallback>,
+    on_message: Option<MessageCallback>,
+}
+
+impl<R, W> StreamTransport<R, W>
+where
+    R: AsyncRead + Send + Sync + Unpin + 'static,
+    W: AsyncWrite + Send + Unpin + 'static,
+{
+    /// Create a transport over `reader` and `writer` using the given framing
+    pub fn with_framing<F: Framing + 'static>(reader: R, writer: W, framing: F) -> Self {
+        let framing: Arc<dyn Framing> = Arc::new(framing);
+        Self {
+            sender: StreamSender {
+                writer: Mutex::new(writer),
+                framing: Arc::clone(&framing),
+                is_connected: Arc::new(AtomicBool::new(false)),
+                on_close: None,
+                on_error: None,
+            },
+            receiver: StreamReceiver {
+                reader: BufReader::new(reader),
+                framing,
+                on_error: None,
+                on_message: None,
+            },
+            credentials: Credentials::default(),
+        }
+    }
+
+    /// Report these credentials for the peer, e.g. as learned when accepting the stream
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Create a transport that exchanges newline-delimited JSON messages
+    pub fn newline_delimited(reader: R, writer: W) -> Self {
+        Self::with_framing(reader, writer, NewlineDelimited::new())
+    }
+
+    /// Create a transport that exchanges `Content-Length` framed messages
+    pub fn content_length(reader: R, writer: W) -> Self {
+        Self::with_framing(reader, writer, ContentLength::new())
+    }
+
+    /// Check whether the transport has been started and not yet closed
+    pub fn is_connected(&self) -> bool {
+        self.sender.is_connected()
+    }
+}
+
+impl<S> StreamTransport<ReadHalf<S>, WriteHalf<S>>
+where
+    S: AsyncRead + AsyncWrite + Send + Sync + 'static,
+{
+    /// Create a transport over a single bidirectional stream, such as a socket
+    pub fn from_stream<F: Framing + 'static>(stream: S, framing: F) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        Self::with_framing(reader, writer, framing)
+    }
+}
+
+impl<W> StreamSender<W> {
+    fn is_connected(&self) -> bool {
+        self.is_connected.load(Ordering::SeqCst)
+    }
+
+    /// Report an error to the error callback if set, then hand it back
+    fn handle_error(&self, error: MCPError) -> MCPError {
+        if let Some(callback) = &self.on_error {
+            callback(&error);
+        }
+        error
+    }
+
+    /// Serialize a message and write it as one frame
+    async fn write(&self, message: &impl Serialize) -> Result<(), MCPError>
+    where
+        W: AsyncWrite + Send + Unpin,
+    {
+        if !self.is_connected() {
+            return Err(
+                self.handle_error(MCPError::Transport("Transport not connected".to_string()))
+            );
+        }
+
+        let json = serde_json::to_string(message)
+            .map_err(|e| self.handle_error(MCPError::Serialization(e)))?;
+
+        // Hold the writer lock for the whole frame so concurrent senders never interleave
+        let mut writer = self.writer.lock().await;
+        self.framing
+            .write_frame(&mut *writer, &json)
+            .await
+            .map_err(|e| self.handle_error(e))
+    }
+}
+
+impl<R> StreamReceiver<R> {
+    /// Report an error to the error callback if set, then hand it back
+    fn handle_error(&self, error: MCPError) -> MCPError {
+        if let Some(callback) = &self.on_error {
+            callback(&error);
+        }
+        error
+    }
+
+    /// Read the next frame, or `None` at the end of the stream
+    async fn next_frame(&mut self) -> Result<Option<String>, MCPError>
+    where
+        R: AsyncRead + Send + Unpin,
+    {
+        let frame = match self.framing.read_frame(&mut self.reader).await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(None),
+            Err(error) => return Err(self.handle_error(error)),
+        };
+
+        if let Some(callback) = &self.on_message {
+            callback(&frame);
+        }
+        Ok(Some(frame))
+    }
+}
+
+#[async_trait]
+impl<W> TransportSender for StreamSender<W>
+where
+    W: AsyncWrite + Send + Unpin + 'static,
+{
+    async fn send(&self, message: JSONRPCMessage) -> Result<(), MCPError> {
+        self.write(&message).await
+    }
+
+    async fn send_raw(&self, message: RawMessage) -> Result<(), MCPError> {
+        self.write(&message).await
+    }
+
+    async fn close(&self) -> Result<(), MCPError> {
+        if !self.is_connected.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        // Signal end-of-stream to the peer; sockets see this as a half-close
+        if let Err(e) = self.writer.lock().await.shutdown().await {
+            debug!("Error shutting down stream writer: {}", e);
+        }
+
+        if let Some(callback) = &self.on_close {
+            callback();
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<R> TransportReceiver for StreamReceiver<R>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        let Some(frame) = self.next_frame().await? else {
+            return Ok(None);
+        };
+        serde_json::from_str(&frame)
+            .map(Some)
+            .map_err(|e| self.handle_error(MCPError::Serialization(e)))
+    }
+
+    async fn recv_raw(&mut self) -> Result<Option<RawMessage>, MCPError> {
+        let Some(frame) = self.next_frame().await? else {
+            return Ok(None);
+        };
+        RawMessage::parse(&frame)
+            .map(Some)
+            .map_err(|e| self.handle_error(MCPError::Serialization(e)))
+    }
+}
+
+#[async_trait]
+impl<R, W> Transport for StreamTransport<R, W>
+where
+    R: AsyncRead + Send + Sync + Unpin + 'static,
+    W: AsyncWrite + Send + Unpin + 'static,
+{
+    async fn start(&mut self) -> Result<(), MCPError> {
+        self.sender.is_connected.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn send(&mut self, message: JSONRPCMessage) -> Result<(), MCPError> {
+        self.sender.send(message).await
+    }
+
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        if !self.is_connected() {
+            return Err(self
+                .receiver
+                .handle_error(MCPError::Transport("Transport not connected".to_string())));
+        }
+
+        self.receiver.recv().await
+    }
+
+    async fn close(&mut self) -> Result<(), MCPError> {
+        self.sender.close().await
+    }
+
+    fn into_split(self: Box<Self>) -> (Arc<dyn TransportSender>, Box<dyn TransportReceiver>) {
+        (Arc::new(self.sender), Box::new(self.receiver))
+    }
+
+    fn set_on_close(&mut self, callback: Option<CloseCallback>) {
+        self.sender.on_close = callback;
+    }
+
+    fn set_on_error(&mut self, callback: Option<ErrorCallback>) {
+        let callback: Option<SharedErrorCallback> = callback.map(Arc::from);
+        self.sender.on_error = callback.clone();
+        self.receiver.on_error = callback;
+    }
+
+    fn set_on_message(&mut self, callback: Option<MessageCallback>) {
+        self.receiver.on_message = callback;
+    }
+
+    fn credentials(&self) -> Credentials {
+        self.credentials.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::json_rpc::{JSONRPCNotification, JSONRPCRequest, RequestId};
+    use crate::schema::raw::RawResponse;
+    use serde_json::value::RawValue;
+
+    fn notification(params: serde_json::Value) -> JSONRPCMessage {
+        JSONRPCMessage::Notification(JSONRPCNotification::new("test".to_string(), Some(params)))
+    }
+
+    #[tokio::test]
+    async fn test_duplex_newline_delimited() {
+        let (left, right) = tokio::io::duplex(1024);
+        let mut a = StreamTransport::from_stream(left, NewlineDelimited::new());
+        let mut b = StreamTransport::from_stream(right, NewlineDelimited::new());
+        a.start().await.unwrap();
+        b.start().await.unwrap();
+
+        let request = JSONRPCMessage::Request(JSONRPCRequest::new(
+            RequestId::Number(7),
+            "ping".to_string(),
+            None,
+        ));
+        a.send(request).await.unwrap();
+
+        match b.recv().await.unwrap() {
+            Some(JSONRPCMessage::Request(req)) => {
+                assert_eq!(req.id, RequestId::Number(7));
+                assert_eq!(req.method, "ping");
+            }
+            other => panic!("Expected request, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_raw_messages_are_written_as_they_are() {
+        let (left, right) = tokio::io::duplex(1024);
+        let mut a = StreamTransport::from_stream(left, ContentLength::new());
+        let mut b = StreamTransport::from_stream(right, ContentLength::new());
+        a.start().await.unwrap();
+        b.start().await.unwrap();
+        let (_a_sender, mut a_receiver) = Box::new(a).into_split();
+        let (b_sender, _b_receiver) = Box::new(b).into_split();
+
+        let result = RawValue::from_string(r#"{ "rows": [1, 2] }"#.to_string()).unwrap();
+        let response = RawMessage::Response(RawResponse::new(RequestId::Number(3), result));
+        b_sender.send_raw(response).await.unwrap();
+
+        match a_receiver.recv_raw().await.unwrap() {
+            Some(RawMessage::Response(response)) => {
+                assert_eq!(response.id, RequestId::Number(3));
+                assert_eq!(response.result.get(), r#"{ "rows": [1, 2] }"#);
+            }
+            other => panic!("Expected response, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplex_content_length() {
+        let (left, right) = tokio::io::duplex(1024);
+        let (left_read, left_write) = tokio::io::split(left);
+        let mut a = StreamTransport::content_length(left_read, left_write);
+        let mut b = StreamTransport::from_stream(right, ContentLength::new());
+        a.start().await.unwrap();
+        b.start().await.unwrap();
+
+        a.send(notification(
+            serde_json::json!({"text": "line one\nline two"}),
+        ))
+        .await
+        .unwrap();
+
+        match b.recv().await.unwrap() {
+            Some(JSONRPCMessage::Notification(n)) => {
+                assert_eq!(n.params.unwrap()["text"], "line one\nline two");
+            }
+            other => panic!("Expected notification, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_sender_is_shared() {
+        let (left, right) = tokio::io::duplex(4096);
+        let mut a = StreamTransport::from_stream(left, NewlineDelimited::new());
+        let mut b = StreamTransport::from_stream(right, NewlineDelimited::new());
+        a.start().await.unwrap();
+        b.start().await.unwrap();
+
+        let (sender, _receiver) = Box::new(a).into_split();
+
+        // Send from several tasks concurrently; frames must arrive intact
+        let mut handles = Vec::new();
+        for i in 0..5 {
+            let sender = Arc::clone(&sender);
+            handles.push(tokio::spawn(async move {
+                sender
+                    .send(notification(serde_json::json!({ "n": i })))
+                    .await
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+
+        let mut seen = Vec::new();
+        for _ in 0..5 {
+            match b.recv().await.unwrap() {
+                Some(JSONRPCMessage::Notification(n)) => {
+                    seen.push(n.params.unwrap()["n"].as_i64().unwrap());
+                }
+                other => panic!("Expected notification, got {:?}", other),
+            }
+        }
+        seen.sort();
+        assert_eq!(seen, vec![0, 1, 2, 3, 4]);
+
+        // Closing the shared sender ends the stream for the peer
+        sender.close().await.unwrap();
+        assert!(b.recv().await.unwrap().is_none());
+        assert!(sender
+            .send(notification(serde_json::json!({})))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_eof_ends_stream() {
+        let (left, right) = tokio::io::duplex(64);
+        let mut b = StreamTransport::from_stream(right, NewlineDelimited::new());
+        b.start().await.unwrap();
+        drop(left);
+
+        assert!(b.recv().await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,151 @@
+//! TCP transport
+//!
+//! Plain TCP sockets carrying newline-delimited JSON-RPC, the same framing used by the
+//! stdio transport. A [`TcpTransportListener`] accepts any number of concurrent clients,
+//! each of which becomes its own [`TcpTransport`] session.
+
+use crate::auth::session::Credentials;
+use crate::error::MCPError;
+use crate::transport::framing::NewlineDelimited;
+use crate::transport::stream::StreamTransport;
+use crate::transport::Listener;
+use async_trait::async_trait;
+use log::{debug, info};
+use std::net::SocketAddr;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+/// Transport over a single TCP connection
+pub type TcpTransport = StreamTransport<OwnedReadHalf, OwnedWriteHalf>;
+
+impl TcpTransport {
+    /// Connect to a TCP server
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, MCPError> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to connect: {}", e)))?;
+
+        if let Ok(peer) = stream.peer_addr() {
+            info!("Connected to TCP server at {}", peer);
+        }
+
+        Ok(Self::from_tcp_stream(stream))
+    }
+
+    /// Wrap an already-connected TCP stream
+    pub fn from_tcp_stream(stream: TcpStream) -> Self {
+        // Messages are small and latency-sensitive, so don't wait to coalesce writes
+        if let Err(e) = stream.set_nodelay(true) {
+            debug!("Failed to set TCP_NODELAY: {}", e);
+        }
+
+        let credentials = Credentials {
+            peer_addr: stream.peer_addr().ok(),
+            ..Credentials::default()
+        };
+        let (reader, writer) = stream.into_split();
+        Self::with_framing(reader, writer, NewlineDelimited::new()).with_credentials(credentials)
+    }
+}
+
+/// Listener that accepts TCP connections as independent transport sessions
+pub struct TcpTransportListener {
+    listener: TcpListener,
+}
+
+impl TcpTransportListener {
+    /// Bind a listener to the given address
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, MCPError> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to bind TCP listener: {}", e)))?;
+
+        if let Ok(local) = listener.local_addr() {
+            info!("TCP transport listening on {}", local);
+        }
+
+        Ok(Self { listener })
+    }
+
+    /// The local address the listener is bound to
+    pub fn local_addr(&self) -> Result<SocketAddr, MCPError> {
+        self.listener
+            .local_addr()
+            .map_err(|e| MCPError::Transport(format!("Failed to get local address: {}", e)))
+    }
+
+    /// Accept the next connection, returning its transport and peer address
+    pub async fn accept_with_addr(&self) -> Result<(TcpTransport, SocketAddr), MCPError> {
+        let (stream, peer) = self
+            .listener
+            .accept()
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to accept connection: {}", e)))?;
+
+        info!("Accepted TCP connection from {}", peer);
+        Ok((TcpTransport::from_tcp_stream(stream), peer))
+    }
+}
+
+#[async_trait]
+impl Listener for TcpTransportListener {
+    type Transport = TcpTransport;
+
+    async fn accept(&mut self) -> Result<TcpTransport, MCPError> {
+        self.accept_with_addr()
+            .await
+            .map(|(transport, _)| transport)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::json_rpc::{JSONRPCMessage, JSONRPCRequest, JSONRPCResponse, RequestId};
+    use crate::transport::Transport;
+
+    #[tokio::test]
+    async fn test_concurrent_sessions() {
+        let mut listener = TcpTransportListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Echo each request's method back as the result, once per session
+        let server = tokio::spawn(async move {
+            for _ in 0..2 {
+                let mut session = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    session.start().await.unwrap();
+                    while let Ok(Some(JSONRPCMessage::Request(req))) = session.recv().await {
+                        let response = JSONRPCResponse::new(req.id, serde_json::json!(req.method));
+                        session
+                            .send(JSONRPCMessage::Response(response))
+                            .await
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        let mut first = TcpTransport::connect(addr).await.unwrap();
+        let mut second = TcpTransport::connect(addr).await.unwrap();
+        first.start().await.unwrap();
+        second.start().await.unwrap();
+
+        for (transport, method) in [(&mut first, "first"), (&mut second, "second")] {
+            let request = JSONRPCRequest::new(RequestId::Number(1), method.to_string(), None);
+            transport
+                .send(JSONRPCMessage::Request(request))
+                .await
+                .unwrap();
+        }
+
+        for (transport, method) in [(&mut second, "second"), (&mut first, "first")] {
+            match transport.recv().await.unwrap() {
+                Some(JSONRPCMessage::Response(resp)) => assert_eq!(resp.result, method),
+                other => panic!("Expected response, got {:?}", other),
+            }
+        }
+
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,355 @@
+//! TLS configuration shared by the network transports
+//!
+//! [`TlsServerConfig`] holds the certificate chain and private key a server presents, and
+//! optionally the CA that client certificates must chain to (mutual TLS).
+//! [`TlsClientConfig`] holds the roots a client trusts and optionally the certificate it
+//! presents. Both are plain PEM in, so certificates can come from files, secrets stores or
+//! be generated at runtime.
+//!
+//! TLS is implemented with rustls using the `ring` crypto provider.
+
+use crate::error::MCPError;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::{client, server, TlsAcceptor, TlsConnector};
+
+/// Server-side TLS settings
+#[derive(Debug, Clone)]
+pub struct TlsServerConfig {
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: Arc<PrivateKeyDer<'static>>,
+    client_ca: Option<Vec<CertificateDer<'static>>>,
+}
+
+impl TlsServerConfig {
+    /// Create a configuration from a PEM certificate chain and a PEM private key
+    pub fn from_pem(cert_chain_pem: &[u8], key_pem: &[u8]) -> Result<Self, MCPError> {
+        let cert_chain = parse_certs(cert_chain_pem)?;
+        if cert_chain.is_empty() {
+            return Err(MCPError::Transport(
+                "No certificates found in the certificate chain".to_string(),
+            ));
+        }
+        Ok(Self {
+            cert_chain,
+            key: Arc::new(parse_key(key_pem)?),
+            client_ca: None,
+        })
+    }
+
+    /// Create a configuration from PEM files holding the certificate chain and private key
+    pub fn from_pem_files(
+        cert_chain_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Self, MCPError> {
+        Self::from_pem(&read_file(cert_chain_path)?, &read_file(key_path)?)
+    }
+
+    /// Require clients to present a certificate issued by one of the CAs in `ca_pem`
+    pub fn with_client_ca_pem(mut self, ca_pem: &[u8]) -> Result<Self, MCPError> {
+        let cas = parse_certs(ca_pem)?;
+        if cas.is_empty() {
+            return Err(MCPError::Transport(
+                "No certificates found in the client CA".to_string(),
+            ));
+        }
+        self.client_ca = Some(cas);
+        Ok(self)
+    }
+
+    /// Whether clients must authenticate with a certificate
+    pub fn requires_client_auth(&self) -> bool {
+        self.client_ca.is_some()
+    }
+
+    /// Build the rustls configuration
+    pub(crate) fn rustls_config(&self) -> Result<Arc<ServerConfig>, MCPError> {
+        let provider = provider();
+        let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?;
+
+        let builder = match &self.client_ca {
+            Some(cas) => {
+                let verifier = WebPkiClientVerifier::builder_with_provider(
+                    Arc::new(root_store(cas)?),
+                    provider,
+                )
+                .build()
+                .map_err(|e| MCPError::Transport(format!("Invalid client CA: {}", e)))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let mut config = builder
+            .with_single_cert(self.cert_chain.clone(), self.key.clone_key())
+            .map_err(tls_error)?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+
+    /// Perform the server side of a TLS handshake over an accepted connection
+    pub(crate) async fn accept<S>(&self, stream: S) -> Result<server::TlsStream<S>, MCPError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        TlsAcceptor::from(self.rustls_config()?)
+            .accept(stream)
+            .await
+            .map_err(|e| MCPError::Transport(format!("TLS handshake failed: {}", e)))
+    }
+}
+
+/// Client-side TLS settings
+///
+/// By default the well-known public roots are trusted. Add private CAs with
+/// [`with_root_ca_pem`](Self::with_root_ca_pem), or trust only those by starting from
+/// [`with_custom_roots_only`](Self::with_custom_roots_only).
+#[derive(Debug, Clone)]
+pub struct TlsClientConfig {
+    public_roots: bool,
+    roots: Vec<CertificateDer<'static>>,
+    client_cert: Option<(Vec<CertificateDer<'static>>, Arc<PrivateKeyDer<'static>>)>,
+}
+
+impl Default for TlsClientConfig {
+    fn default() -> Self {
+        Self {
+            public_roots: true,
+            roots: Vec::new(),
+            client_cert: None,
+        }
+    }
+}
+
+impl TlsClientConfig {
+    /// Create a configuration that trusts the well-known public roots
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a configuration that trusts only the roots added to it
+    pub fn with_custom_roots_only() -> Self {
+        Self {
+            public_roots: false,
+            ..Self::default()
+        }
+    }
+
+    /// Trust the CA certificates in `ca_pem`
+    pub fn with_root_ca_pem(mut self, ca_pem: &[u8]) -> Result<Self, MCPError> {
+        let cas = parse_certs(ca_pem)?;
+        if cas.is_empty() {
+            return Err(MCPError::Transport(
+                "No certificates found in the root CA".to_string(),
+            ));
+        }
+        self.roots.extend(cas);
+        Ok(self)
+    }
+
+    /// Trust the CA certificates in a PEM file
+    pub fn with_root_ca_file(self, path: impl AsRef<Path>) -> Result<Self, MCPError> {
+        self.with_root_ca_pem(&read_file(path)?)
+    }
+
+    /// Present a client certificate, for servers that require mutual TLS
+    pub fn with_client_cert_pem(
+        mut self,
+        cert_chain_pem: &[u8],
+        key_pem: &[u8],
+    ) -> Result<Self, MCPError> {
+        let cert_chain = parse_certs(cert_chain_pem)?;
+        if cert_chain.is_empty() {
+            return Err(MCPError::Transport(
+                "No certificates found in the client certificate chain".to_string(),
+            ));
+        }
+        self.client_cert = Some((cert_chain, Arc::new(parse_key(key_pem)?)));
+        Ok(self)
+    }
+
+    /// Present a client certificate from PEM files
+    pub fn with_client_cert_files(
+        self,
+        cert_chain_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Self, MCPError> {
+        self.with_client_cert_pem(&read_file(cert_chain_path)?, &read_file(key_path)?)
+    }
+
+    /// Build the rustls configuration
+    pub(crate) fn rustls_config(&self) -> Result<Arc<ClientConfig>, MCPError> {
+        let mut roots = root_store(&self.roots)?;
+        if self.public_roots {
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+
+        let builder = ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?
+            .with_root_certificates(roots);
+        let config = match &self.client_cert {
+            Some((cert_chain, key)) => builder
+                .with_client_auth_cert(cert_chain.clone(), key.clone_key())
+                .map_err(tls_error)?,
+            None => builder.with_no_client_auth(),
+        };
+        Ok(Arc::new(config))
+    }
+
+    /// Perform the client side of a TLS handshake with `host`
+    pub(crate) async fn connect<S>(
+        &self,
+        host: &str,
+        stream: S,
+    ) -> Result<client::TlsStream<S>, MCPError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        // IPv6 literals come out of URLs in brackets
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let server_name = ServerName::try_from(host.to_string())
+            .map_err(|e| MCPError::Transport(format!("Invalid server name {}: {}", host, e)))?;
+        TlsConnector::from(self.rustls_config()?)
+            .connect(server_name, stream)
+            .await
+            .map_err(|e| MCPError::Transport(format!("TLS handshake failed: {}", e)))
+    }
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn tls_error(error: rustls::Error) -> MCPError {
+    MCPError::Transport(format!("Invalid TLS configuration: {}", error))
+}
+
+fn read_file(path: impl AsRef<Path>) -> Result<Vec<u8>, MCPError> {
+    let path = path.as_ref();
+    std::fs::read(path)
+        .map_err(|e| MCPError::Transport(format!("Failed to read {}: {}", path.display(), e)))
+}
+
+fn parse_certs(pem: &[u8]) -> Result<Vec<CertificateDer<'static>>, MCPError> {
+    rustls_pemfile::certs(&mut &*pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| MCPError::Transport(format!("Invalid PEM certificate: {}", e)))
+}
+
+fn parse_key(pem: &[u8]) -> Result<PrivateKeyDer<'static>, MCPError> {
+    rustls_pemfile::private_key(&mut &*pem)
+        .map_err(|e| MCPError::Transport(format!("Invalid PEM private key: {}", e)))?
+        .ok_or_else(|| MCPError::Transport("No private key found".to_string()))
+}
+
+fn root_store(cas: &[CertificateDer<'static>]) -> Result<RootCertStore, MCPError> {
+    let mut roots = RootCertStore::empty();
+    for ca in cas {
+        roots
+            .add(ca.clone())
+            .map_err(|e| MCPError::Transport(format!("Invalid CA certificate: {}", e)))?;
+    }
+    Ok(roots)
+}
+
+/// Certificates generated on the fly for tests
+#[cfg(test)]
+pub(crate) mod test_certs {
+    use rcgen::{
+        BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+    };
+
+    /// A PEM certificate and its private key
+    pub(crate) struct Pem {
+        pub(crate) cert: String,
+        pub(crate) key: String,
+    }
+
+    /// A self-signed CA that issues server and client certificates
+    pub(crate) struct TestCa {
+        pub(crate) pem: String,
+        cert: rcgen::Certificate,
+        key: KeyPair,
+    }
+
+    impl TestCa {
+        pub(crate) fn new(name: &str) -> Self {
+            let mut params = CertificateParams::new(Vec::new()).unwrap();
+            params.distinguished_name.push(DnType::CommonName, name);
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let key = KeyPair::generate().unwrap();
+            let cert = params.self_signed(&key).unwrap();
+            Self {
+                pem: cert.pem(),
+                cert,
+                key,
+            }
+        }
+
+        /// A certificate for `localhost` and `127.0.0.1`
+        pub(crate) fn server_cert(&self) -> Pem {
+            self.issue(
+                vec!["localhost".to_string(), "127.0.0.1".to_string()],
+                ExtendedKeyUsagePurpose::ServerAuth,
+            )
+        }
+
+        pub(crate) fn client_cert(&self, name: &str) -> Pem {
+            self.issue(vec![name.to_string()], ExtendedKeyUsagePurpose::ClientAuth)
+        }
+
+        fn issue(&self, names: Vec<String>, usage: ExtendedKeyUsagePurpose) -> Pem {
+            let mut params = CertificateParams::new(names.clone()).unwrap();
+            params
+                .distinguished_name
+                .push(DnType::CommonName, &names[0]);
+            params.extended_key_usages = vec![usage];
+            let key = KeyPair::generate().unwrap();
+            let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+            Pem {
+                cert: cert.pem(),
+                key: key.serialize_pem(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_certs::TestCa;
+    use super::*;
+
+    #[test]
+    fn test_configs_build_from_pem() {
+        let ca = TestCa::new("Test CA");
+        let server = ca.server_cert();
+        let config = TlsServerConfig::from_pem(server.cert.as_bytes(), server.key.as_bytes())
+            .unwrap()
+            .with_client_ca_pem(ca.pem.as_bytes())
+            .unwrap();
+        assert!(config.requires_client_auth());
+        assert!(config.rustls_config().is_ok());
+
+        let client = ca.client_cert("agent");
+        let config = TlsClientConfig::with_custom_roots_only()
+            .with_root_ca_pem(ca.pem.as_bytes())
+            .unwrap()
+            .with_client_cert_pem(client.cert.as_bytes(), client.key.as_bytes())
+            .unwrap();
+        assert!(config.rustls_config().is_ok());
+    }
+
+    #[test]
+    fn test_invalid_pem_is_rejected() {
+        assert!(TlsServerConfig::from_pem(b"not a certificate", b"not a key").is_err());
+        assert!(TlsClientConfig::new().with_root_ca_pem(b"").is_err());
+    }
+}
@@ -0,0 +1,292 @@
+//! Unix domain socket transport
+//!
+//! Local daemons can expose an MCP server on a socket file that several agent processes
+//! connect to. Messages are newline-delimited JSON-RPC, the same framing used by the stdio
+//! transport, and each accepted connection is an independent [`UnixTransport`] session.
+
+use crate::error::MCPError;
+use crate::transport::framing::NewlineDelimited;
+use crate::transport::stream::StreamTransport;
+use crate::transport::Listener;
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{UnixListener, UnixStream};
+
+/// Transport over a single Unix domain socket connection
+pub type UnixTransport = StreamTransport<OwnedReadHalf, OwnedWriteHalf>;
+
+impl UnixTransport {
+    /// Connect to a server listening on the socket at `path`
+    pub async fn connect<P: AsRef<Path>>(path: P) -> Result<Self, MCPError> {
+        let path = path.as_ref();
+        let stream = UnixStream::connect(path).await.map_err(|e| {
+            MCPError::Transport(format!("Failed to connect to {}: {}", path.display(), e))
+        })?;
+
+        info!("Connected to Unix socket {}", path.display());
+        Ok(Self::from_unix_stream(stream))
+    }
+
+    /// Wrap an already-connected Unix stream
+    pub fn from_unix_stream(stream: UnixStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self::with_framing(reader, writer, NewlineDelimited::new())
+    }
+}
+
+/// A fresh suffix for the private directory `bind_with_mode` binds in
+fn next_private() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Give the socket staged by `bind_with_mode` its final path, failing if anything exists
+/// there already
+///
+/// A hard link cannot replace its target the way a rename does, so a file created at
+/// `path` after the stale socket was removed is never clobbered. The staged name goes
+/// away with its private directory.
+fn move_into_place(staged: &Path, path: &Path) -> std::io::Result<()> {
+    fs::hard_link(staged, path)
+}
+
+/// Listener that accepts Unix socket connections as independent transport sessions
+///
+/// The socket file is removed when the listener is dropped.
+pub struct UnixTransportListener {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl UnixTransportListener {
+    /// Bind a listener to the socket file at `path`
+    ///
+    /// A stale socket left behind by a previous process is replaced. Any other kind of
+    /// file at `path` is left untouched and binding fails.
+    pub async fn bind<P: AsRef<Path>>(path: P) -> Result<Self, MCPError> {
+        let path = path.as_ref().to_path_buf();
+        Self::remove_stale(&path).await?;
+
+        let listener = UnixListener::bind(&path).map_err(|e| {
+            MCPError::Transport(format!("Failed to bind to {}: {}", path.display(), e))
+        })?;
+
+        info!("Unix socket transport listening on {}", path.display());
+        Ok(Self { listener, path })
+    }
+
+    /// Bind a listener and set the socket file's permission bits, e.g. `0o600`
+    ///
+    /// The socket is created inside a private `0700` directory next to `path`, given
+    /// `mode` there and then moved into place, so it is never reachable with looser
+    /// permissions than requested. Binding fails if a file appears at `path` meanwhile.
+    pub async fn bind_with_mode<P: AsRef<Path>>(path: P, mode: u32) -> Result<Self, MCPError> {
+        let path = path.as_ref().to_path_buf();
+        Self::remove_stale(&path).await?;
+
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let private = parent.join(format!(".mcpr-{}-{}", std::process::id(), next_private()));
+        fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&private)
+            .map_err(|e| {
+                MCPError::Transport(format!("Failed to create {}: {}", private.display(), e))
+            })?;
+
+        let staged = private.join("socket");
+        let bound = UnixListener::bind(&staged)
+            .map_err(|e| {
+                MCPError::Transport(format!("Failed to bind to {}: {}", path.display(), e))
+            })
+            .and_then(|listener| {
+                let result = fs::set_permissions(&staged, fs::Permissions::from_mode(mode))
+                    .and_then(|_| move_into_place(&staged, &path));
+                result.map(|_| listener).map_err(|e| {
+                    MCPError::Transport(format!(
+                        "Failed to set up socket {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })
+            });
+        if let Err(e) = fs::remove_dir_all(&private) {
+            warn!("Failed to remove {}: {}", private.display(), e);
+        }
+
+        let listener = bound?;
+        info!("Unix socket transport listening on {}", path.display());
+        Ok(Self { listener, path })
+    }
+
+    /// Remove a stale socket at `path`, refusing live sockets and other kinds of file
+    async fn remove_stale(path: &Path) -> Result<(), MCPError> {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return Ok(());
+        };
+        if !metadata.file_type().is_socket() {
+            return Err(MCPError::Transport(format!(
+                "Refusing to replace non-socket file at {}",
+                path.display()
+            )));
+        }
+
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(MCPError::Transport(format!(
+                "Socket {} is already in use",
+                path.display()
+            )));
+        }
+
+        debug!("Removing stale socket file {}", path.display());
+        fs::remove_file(path).map_err(|e| {
+            MCPError::Transport(format!(
+                "Failed to remove stale socket {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Change the permission bits of the socket file
+    pub fn set_mode(&self, mode: u32) -> Result<(), MCPError> {
+        fs::set_permissions(&self.path, fs::Permissions::from_mode(mode)).map_err(|e| {
+            MCPError::Transport(format!(
+                "Failed to set permissions on {}: {}",
+                self.path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Path of the socket file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[async_trait]
+impl Listener for UnixTransportListener {
+    type Transport = UnixTransport;
+
+    async fn accept(&mut self) -> Result<UnixTransport, MCPError> {
+        let (stream, _) = self
+            .listener
+            .accept()
+            .await
+            .map_err(|e| MCPError::Transport(format!("Failed to accept connection: {}", e)))?;
+
+        debug!("Accepted Unix socket connection on {}", self.path.display());
+        Ok(UnixTransport::from_unix_stream(stream))
+    }
+}
+
+impl Drop for UnixTransportListener {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!(
+                "Failed to remove socket file {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::json_rpc::{JSONRPCMessage, JSONRPCNotification};
+    use crate::transport::Transport;
+
+    fn socket_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mcpr-{}-{}.sock", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_bind_with_mode_and_cleanup() {
+        let path = socket_path("mode");
+        let listener = UnixTransportListener::bind_with_mode(&path, 0o600)
+            .await
+            .unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // The socket is reachable at its path and the staging directory is gone
+        UnixTransport::connect(&path).await.unwrap();
+        let parent = path.parent().unwrap();
+        let staging = format!(".mcpr-{}-", std::process::id());
+        assert!(!fs::read_dir(parent).unwrap().any(|entry| entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with(&staging)));
+
+        drop(listener);
+        assert!(!path.exists(), "Socket file should be removed on drop");
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let path = socket_path("round-trip");
+        let mut listener = UnixTransportListener::bind(&path).await.unwrap();
+
+        let server = tokio::spawn(async move {
+            let mut session = listener.accept().await.unwrap();
+            session.start().await.unwrap();
+            let message = session.recv().await.unwrap().unwrap();
+            session.send(message).await.unwrap();
+            // Keep the listener alive until the session is done
+            drop(listener);
+        });
+
+        let mut client = UnixTransport::connect(&path).await.unwrap();
+        client.start().await.unwrap();
+        let ping = JSONRPCNotification::new("ping".to_string(), None);
+        client
+            .send(JSONRPCMessage::Notification(ping))
+            .await
+            .unwrap();
+        match client.recv().await.unwrap() {
+            Some(JSONRPCMessage::Notification(echoed)) => assert_eq!(echoed.method, "ping"),
+            other => panic!("Expected notification, got {:?}", other),
+        }
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_staged_socket_never_replaces_a_file() {
+        let path = socket_path("no-replace");
+        fs::write(&path, b"created meanwhile").unwrap();
+        let staged = socket_path("no-replace-staged");
+        let _listener = UnixListener::bind(&staged).unwrap();
+
+        let err = move_into_place(&staged, &path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&path).unwrap(), b"created meanwhile");
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&staged).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refuses_to_replace_regular_file() {
+        let path = socket_path("regular-file");
+        fs::write(&path, b"not a socket").unwrap();
+
+        let result = UnixTransportListener::bind(&path).await;
+        assert!(result.is_err());
+        assert!(path.exists());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,51 +1,79 @@
+use crate::auth::session::Credentials;
 use crate::error::MCPError;
-use crate::transport::{CloseCallback, ErrorCallback, MessageCallback, Transport};
+use crate::schema::json_rpc::JSONRPCMessage;
+use crate::schema::raw::RawMessage;
+use crate::transport::options::ClientTransportOptions;
+use crate::transport::tls::{TlsClientConfig, TlsServerConfig};
+use crate::transport::{
+    CloseCallback, ErrorCallback, MessageCallback, SharedErrorCallback, Transport,
+    TransportReceiver, TransportSender,
+};
 use async_trait::async_trait;
-use futures::{SinkExt, StreamExt};
-use log::{debug, error, info, warn};
-use serde::{de::DeserializeOwned, Serialize};
-use std::{collections::VecDeque, sync::Arc, time::Duration};
-use tokio::{
-    sync::{Mutex as TokioMutex, Notify},
-    time::sleep,
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::Mutex;
+use tokio_tungstenite::{
+    tungstenite::{
+        client::IntoClientRequest,
+        error::ProtocolError,
+        handshake::server::{Request, Response},
+        http::{HeaderName, HeaderValue},
+        Error as WsError, Message,
+    },
+    WebSocketStream,
 };
-use tokio_tungstenite::{connect_async, tungstenite::Message};
 use url::Url;
 
+/// Outgoing half of an established WebSocket connection
+type WsSink = Pin<Box<dyn Sink<Message, Error = WsError> + Send + Sync>>;
+
+/// Incoming half of an established WebSocket connection
+type WsStream = Pin<Box<dyn Stream<Item = Result<Message, WsError>> + Send + Sync>>;
+
 /// WebSocket transport implementation for MCP
+///
+/// A single WebSocket connection carries messages in both directions. In server mode,
+/// [`start`](Transport::start) binds to the address and waits for one client to connect.
+///
+/// `wss://` URLs are connected over TLS, trusting the public roots unless a
+/// [`TlsClientConfig`] is given with [`with_client_tls`](Self::with_client_tls). A server
+/// accepts TLS connections once configured with [`with_tls`](Self::with_tls). Headers,
+/// credentials, timeouts and a proxy for the client's handshake are set with
+/// [`with_client_options`](Self::with_client_options).
+///
+/// In server mode, [`credentials`](Transport::credentials) reports the client's handshake
+/// headers, address and, over mutual TLS, its certificates.
 pub struct WebSocketTransport {
     uri: String,
-    is_connected: bool,
     is_server: bool,
-    on_close: Option<CloseCallback>,
-    on_error: Option<ErrorCallback>,
-    on_message: Option<MessageCallback>,
-
-    // Queue for incoming messages
-    message_queue: Arc<TokioMutex<VecDeque<String>>>,
-
-    // Signal to stop background tasks
-    stop_signal: Arc<Notify>,
+    server_tls: Option<TlsServerConfig>,
+    client_options: ClientTransportOptions,
+    credentials: Credentials,
+    sender: WebSocketSender,
+    receiver: WebSocketReceiver,
+}
 
-    // Background task handle
-    message_task: Option<tokio::task::JoinHandle<()>>,
+/// Sending half of a [`WebSocketTransport`]
+struct WebSocketSender {
+    sink: Mutex<Option<WsSink>>,
+    is_connected: Arc<AtomicBool>,
+    on_close: Option<CloseCallback>,
+    on_error: Option<SharedErrorCallback>,
 }
 
-// Implement Clone for WebSocketTransport
-impl Clone for WebSocketTransport {
-    fn clone(&self) -> Self {
-        Self {
-            uri: self.uri.clone(),
-            is_connected: self.is_connected,
-            is_server: self.is_server,
-            on_close: None, // Callbacks cannot be cloned
-            on_error: None,
-            on_message: None,
-            message_queue: Arc::new(TokioMutex::new(VecDeque::new())),
-            stop_signal: Arc::new(Notify::new()),
-            message_task: None, // Each clone should create its own task
-        }
-    }
+/// Receiving half of a [`WebSocketTransport`]
+struct WebSocketReceiver {
+    stream: Option<WsStream>,
+    read_timeout: Option<Duration>,
+    on_error: Option<SharedErrorCallback>,
+    on_message: Option<MessageCallback>,
 }
 
 impl WebSocketTransport {
@@ -54,14 +82,22 @@ impl WebSocketTransport {
         info!("Creating new WebSocket client transport with URI: {}", uri);
         Self {
             uri: uri.to_string(),
-            is_connected: false,
             is_server: false,
-            on_close: None,
-            on_error: None,
-            on_message: None,
-            message_queue: Arc::new(TokioMutex::new(VecDeque::new())),
-            stop_signal: Arc::new(Notify::new()),
-            message_task: None,
+            server_tls: None,
+            client_options: ClientTransportOptions::default(),
+            credentials: Credentials::default(),
+            sender: WebSocketSender {
+                sink: Mutex::new(None),
+                is_connected: Arc::new(AtomicBool::new(false)),
+                on_close: None,
+                on_error: None,
+            },
+            receiver: WebSocketReceiver {
+                stream: None,
+                read_timeout: None,
+                on_error: None,
+                on_message: None,
+            },
         }
     }
 
@@ -73,6 +109,34 @@ impl WebSocketTransport {
         transport
     }
 
+    /// Serve over TLS (server mode)
+    ///
+    /// Clients must then connect with `wss://`. If the configuration has a client CA,
+    /// clients without a certificate issued by it are refused during the handshake.
+    pub fn with_tls(mut self, config: TlsServerConfig) -> Self {
+        self.server_tls = Some(config);
+        self
+    }
+
+    /// Set the TLS settings used for `wss://` URLs (client mode)
+    pub fn with_client_tls(mut self, config: TlsClientConfig) -> Self {
+        self.client_options = self.client_options.with_tls(config);
+        self
+    }
+
+    /// Set the headers, credentials, timeouts and proxy of the client connection
+    ///
+    /// Replaces any TLS settings given earlier with those in `options`.
+    pub fn with_client_options(mut self, options: ClientTransportOptions) -> Self {
+        self.client_options = options;
+        self
+    }
+
+    /// Check whether the transport has been started and not yet closed
+    pub fn is_connected(&self) -> bool {
+        self.sender.is_connected.load(Ordering::SeqCst)
+    }
+
     /// Start client connection to a WebSocket server
     async fn connect_as_client(&mut self) -> Result<(), MCPError> {
         debug!("Connecting to WebSocket server: {}", self.uri);
@@ -81,20 +145,57 @@ impl WebSocketTransport {
         let url = Url::parse(&self.uri)
             .map_err(|e| MCPError::Transport(format!("Invalid WebSocket URL: {}", e)))?;
 
-        // Connect to server
-        let (ws_stream, _) = connect_async(url).await.map_err(|e| {
-            MCPError::Transport(format!("Failed to connect to WebSocket server: {}", e))
-        })?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| MCPError::Transport(format!("No host in URL: {}", url)))?
+            .to_string();
+        let secure = match url.scheme() {
+            "ws" => false,
+            "wss" => true,
+            scheme => {
+                return Err(MCPError::Transport(format!(
+                    "Unsupported WebSocket URL scheme: {}",
+                    scheme
+                )))
+            }
+        };
+        let port = url
+            .port_or_known_default()
+            .unwrap_or(if secure { 443 } else { 80 });
 
-        info!("Connected to WebSocket server: {}", self.uri);
+        let mut request = url
+            .as_str()
+            .into_client_request()
+            .map_err(|e| MCPError::Transport(format!("Invalid WebSocket URL: {}", e)))?;
+        for (name, value) in self.client_options.headers().await?.iter() {
+            // tungstenite uses its own version of the http types
+            let name = HeaderName::from_bytes(name.as_str().as_bytes())
+                .map_err(|e| MCPError::Transport(format!("Invalid header name: {}", e)))?;
+            let value = HeaderValue::from_bytes(value.as_bytes())
+                .map_err(|e| MCPError::Transport(format!("Invalid header value: {}", e)))?;
+            request.headers_mut().append(name, value);
+        }
 
-        // Start message processing
-        self.start_message_processing(ws_stream).await?;
+        let socket = self.client_options.connect_tcp(&host, port).await?;
+        if secure {
+            let tls_stream = self.client_options.tls().connect(&host, socket).await?;
+            let (ws_stream, _) = tokio_tungstenite::client_async(request, tls_stream)
+                .await
+                .map_err(handshake_error)?;
+            self.attach(ws_stream).await;
+        } else {
+            let (ws_stream, _) = tokio_tungstenite::client_async(request, socket)
+                .await
+                .map_err(handshake_error)?;
+            self.attach(ws_stream).await;
+        }
+        self.receiver.read_timeout = self.client_options.read_timeout();
 
+        info!("Connected to WebSocket server: {}", self.uri);
         Ok(())
     }
 
-    /// Start server and listen for connections
+    /// Start server and wait for a client to connect
     async fn start_as_server(&mut self) -> Result<(), MCPError> {
         debug!("Starting WebSocket server on: {}", self.uri);
 
@@ -113,263 +214,497 @@ impl WebSocketTransport {
 
         info!("WebSocket connection accepted from {}", addr);
 
-        // Upgrade to WebSocket
-        let ws_stream = tokio_tungstenite::accept_async(socket)
-            .await
-            .map_err(|e| MCPError::Transport(format!("Error during WebSocket handshake: {}", e)))?;
-
-        // Start message processing
-        self.start_message_processing(ws_stream).await?;
-
+        // Upgrade to WebSocket, after the TLS handshake if configured
+        let mut credentials = Credentials {
+            peer_addr: Some(addr),
+            ..Credentials::default()
+        };
+        match &self.server_tls {
+            Some(tls) => {
+                let tls_stream = tls.accept(socket).await?;
+                if let Some(certificates) = tls_stream.get_ref().1.peer_certificates() {
+                    credentials.peer_certificates =
+                        certificates.iter().map(|cert| cert.to_vec()).collect();
+                }
+                let ws_stream = accept_websocket(tls_stream, &mut credentials.headers).await?;
+                self.attach(ws_stream).await;
+            }
+            None => {
+                let ws_stream = accept_websocket(socket, &mut credentials.headers).await?;
+                self.attach(ws_stream).await;
+            }
+        }
+        self.credentials = credentials;
         Ok(())
     }
 
-    /// Start message processing for the WebSocket stream
-    async fn start_message_processing<S>(&mut self, ws_stream: S) -> Result<(), MCPError>
+    /// Hand an established connection to the two halves
+    async fn attach<S>(&mut self, ws_stream: WebSocketStream<S>)
     where
-        S: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>>
-            + Send
-            + 'static,
+        S: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
     {
-        // Clone resources for the task
-        let message_queue = Arc::clone(&self.message_queue);
-        let stop_signal = Arc::clone(&self.stop_signal);
-
-        // Start a task to process incoming messages
-        self.message_task = Some(tokio::spawn(async move {
-            debug!("WebSocket message processing task started");
-
-            tokio::pin!(ws_stream);
-
-            // Process messages until the stream ends or we're signaled to stop
-            loop {
-                tokio::select! {
-                    // Process incoming message
-                    msg = ws_stream.next() => match msg {
-                        Some(Ok(msg)) => {
-                            if let Message::Text(text) = msg {
-                                debug!("Received WebSocket text message: {}", text);
-
-                                // Add to message queue
-                                let mut queue = message_queue.lock().await;
-                                queue.push_back(text);
-                            } else if let Message::Binary(data) = msg {
-                                debug!("Received WebSocket binary message of {} bytes", data.len());
-                                // We don't handle binary messages currently
-                            } else if let Message::Close(_) = msg {
-                                debug!("Received WebSocket close message");
-                                break;
-                            }
-                        },
-                        Some(Err(e)) => {
-                            error!("WebSocket error: {}", e);
-                            break;
-                        },
-                        None => {
-                            debug!("WebSocket stream ended");
-                            break;
-                        }
-                    },
-
-                    // Check for stop signal
-                    _ = stop_signal.notified() => {
-                        debug!("Received stop signal, exiting message processing task");
-                        break;
-                    }
-                }
-            }
-
-            debug!("WebSocket message processing task ended");
-        }));
-
-        Ok(())
+        let (sink, stream) = ws_stream.split();
+        let sink: WsSink = Box::pin(sink);
+        let stream: WsStream = Box::pin(stream);
+        *self.sender.sink.lock().await = Some(sink);
+        self.receiver.stream = Some(stream);
     }
+}
 
-    /// Create a new connection for sending messages
-    async fn create_send_connection(&self) -> Result<impl SinkExt<Message>, MCPError> {
-        let url = Url::parse(&self.uri)
-            .map_err(|e| MCPError::Transport(format!("Invalid WebSocket URL: {}", e)))?;
-
-        let (stream, _) = connect_async(url)
-            .await
-            .map_err(|e| MCPError::Transport(format!("Failed to create send connection: {}", e)))?;
-
-        Ok(stream)
+/// Describe a failed WebSocket handshake
+fn handshake_error(error: WsError) -> MCPError {
+    match error {
+        WsError::Http(response) if matches!(response.status().as_u16(), 401 | 403) => {
+            MCPError::Authorization(format!(
+                "WebSocket handshake rejected: HTTP {}",
+                response.status()
+            ))
+        }
+        WsError::Http(response) => MCPError::Transport(format!(
+            "WebSocket handshake rejected: HTTP {}",
+            response.status()
+        )),
+        e => MCPError::Transport(format!("Error during WebSocket handshake: {}", e)),
     }
 }
 
-#[async_trait]
-impl Transport for WebSocketTransport {
-    async fn start(&mut self) -> Result<(), MCPError> {
-        if self.is_connected {
-            debug!("WebSocket transport already connected");
-            return Ok(());
+/// Perform the server side of the WebSocket handshake, recording the request headers
+// The error type of tungstenite's header callback is not ours to shrink
+#[allow(clippy::result_large_err)]
+async fn accept_websocket<S>(
+    stream: S,
+    headers: &mut HashMap<String, String>,
+) -> Result<WebSocketStream<S>, MCPError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let record_headers = |request: &Request, response: Response| {
+        for (name, value) in request.headers() {
+            if let Ok(value) = value.to_str() {
+                headers.insert(name.as_str().to_ascii_lowercase(), value.to_string());
+            }
         }
+        Ok(response)
+    };
+    tokio_tungstenite::accept_hdr_async(stream, record_headers)
+        .await
+        .map_err(|e| MCPError::Transport(format!("Error during WebSocket handshake: {}", e)))
+}
 
-        info!("Starting WebSocket transport: {}", self.uri);
-
-        // Connect or start server based on mode
-        if self.is_server {
-            self.start_as_server().await?;
-        } else {
-            self.connect_as_client().await?;
+impl WebSocketSender {
+    /// Report an error to the error callback if set, then hand it back
+    fn handle_error(&self, error: MCPError) -> MCPError {
+        if let Some(callback) = &self.on_error {
+            callback(&error);
         }
-
-        self.is_connected = true;
-        info!("WebSocket transport started successfully");
-        Ok(())
+        error
     }
 
-    async fn send<T: Serialize + Send + Sync>(&mut self, message: &T) -> Result<(), MCPError> {
-        if !self.is_connected {
-            return Err(MCPError::Transport(
+    /// Serialize a message and send it as one text message
+    async fn write(&self, message: &impl Serialize) -> Result<(), MCPError> {
+        if !self.is_connected.load(Ordering::SeqCst) {
+            return Err(self.handle_error(MCPError::Transport(
                 "WebSocket transport not connected".to_string(),
-            ));
+            )));
         }
 
         // Serialize the message
-        let serialized_message = serde_json::to_string(message).map_err(|e| {
-            let msg = format!("Failed to serialize message: {}", e);
-            error!("{}", msg);
-            MCPError::Serialization(e)
-        })?;
+        let serialized_message = serde_json::to_string(message)
+            .map_err(|e| self.handle_error(MCPError::Serialization(e)))?;
 
         debug!("Sending WebSocket message: {}", serialized_message);
 
-        // Create a new connection for sending if none exists
-        let mut send_stream = self.create_send_connection().await?;
+        let mut sink = self.sink.lock().await;
+        let sink = sink.as_mut().ok_or_else(|| {
+            self.handle_error(MCPError::Transport(
+                "WebSocket transport not connected".to_string(),
+            ))
+        })?;
 
-        // Send the message
-        send_stream
-            .send(Message::Text(serialized_message))
+        sink.send(Message::Text(serialized_message))
             .await
-            .map_err(|_| MCPError::Transport("Error sending WebSocket message".to_string()))?;
+            .map_err(|e| {
+                self.handle_error(MCPError::Transport(format!(
+                    "Error sending WebSocket message: {}",
+                    e
+                )))
+            })?;
 
         debug!("WebSocket message sent successfully");
         Ok(())
     }
+}
 
-    async fn receive<T: DeserializeOwned + Send + Sync>(&mut self) -> Result<T, MCPError> {
-        if !self.is_connected {
-            return Err(MCPError::Transport(
-                "WebSocket transport not connected".to_string(),
-            ));
+impl WebSocketReceiver {
+    /// Report an error to the error callback if set, then hand it back
+    fn handle_error(&self, error: MCPError) -> MCPError {
+        if let Some(callback) = &self.on_error {
+            callback(&error);
         }
+        error
+    }
 
-        // Use a timeout of 30 seconds
-        let timeout_duration = Duration::from_secs(30);
-        let start = std::time::Instant::now();
-
-        // Wait for a message
-        let message = loop {
-            // Check for timeout
-            if start.elapsed() >= timeout_duration {
-                return Err(MCPError::Transport(
-                    "Timeout waiting for message".to_string(),
-                ));
-            }
+    /// Wait for the next text message, skipping other frames
+    async fn next_text(&mut self) -> Result<Option<String>, MCPError> {
+        let Some(stream) = self.stream.as_mut() else {
+            return Err(self.handle_error(MCPError::Transport(
+                "WebSocket transport not connected".to_string(),
+            )));
+        };
 
-            // Try to get a message from the queue
-            let queue_msg = {
-                let mut queue = self.message_queue.lock().await;
-                queue.pop_front()
+        let text = loop {
+            let next = match self.read_timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, stream.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        return Err(self.handle_error(MCPError::Timeout(format!(
+                            "No WebSocket message within {:?}",
+                            timeout
+                        ))));
+                    }
+                },
+                None => stream.next().await,
             };
-
-            if let Some(message) = queue_msg {
-                debug!("Received message from queue: {}", message);
-
-                // Execute callback if set
-                if let Some(callback) = &self.on_message {
-                    callback(&message);
+            match next {
+                Some(Ok(Message::Text(text))) => break text,
+                Some(Ok(Message::Binary(data))) => {
+                    debug!("Ignoring WebSocket binary message of {} bytes", data.len());
+                }
+                Some(Ok(Message::Close(_))) | None => {
+                    debug!("WebSocket stream ended");
+                    return Ok(None);
+                }
+                // Pings are answered by tungstenite itself
+                Some(Ok(_)) => {}
+                // The peer went away, cleanly or otherwise
+                Some(Err(
+                    WsError::ConnectionClosed
+                    | WsError::AlreadyClosed
+                    | WsError::Protocol(ProtocolError::ResetWithoutClosingHandshake),
+                )) => return Ok(None),
+                Some(Err(e)) => {
+                    return Err(self.handle_error(MCPError::ConnectionClosed(format!(
+                        "WebSocket error: {}",
+                        e
+                    ))));
                 }
-
-                break message;
             }
-
-            // Wait before checking again
-            sleep(Duration::from_millis(100)).await;
         };
 
-        // Parse the message
-        match serde_json::from_str::<T>(&message) {
-            Ok(parsed) => {
-                debug!("Successfully parsed WebSocket message");
-                Ok(parsed)
-            }
-            Err(e) => {
-                let error_msg = format!(
-                    "Failed to deserialize WebSocket message: {} - Content: {}",
-                    e, message
-                );
-                error!("{}", error_msg);
-                Err(MCPError::Serialization(e))
-            }
+        debug!("Received WebSocket text message: {}", text);
+        if let Some(callback) = &self.on_message {
+            callback(&text);
         }
+        Ok(Some(text))
     }
+}
 
-    async fn close(&mut self) -> Result<(), MCPError> {
-        if !self.is_connected {
+#[async_trait]
+impl TransportSender for WebSocketSender {
+    async fn send(&self, message: JSONRPCMessage) -> Result<(), MCPError> {
+        self.write(&message).await
+    }
+
+    async fn send_raw(&self, message: RawMessage) -> Result<(), MCPError> {
+        self.write(&message).await
+    }
+
+    async fn close(&self) -> Result<(), MCPError> {
+        if !self.is_connected.swap(false, Ordering::SeqCst) {
             debug!("WebSocket transport already closed");
             return Ok(());
         }
 
-        info!("Closing WebSocket transport: {}", self.uri);
-
-        // Create a send connection to send the close frame
-        let mut send_stream = self.create_send_connection().await?;
+        if let Some(mut sink) = self.sink.lock().await.take() {
+            debug!("Sending WebSocket close frame");
+            if sink.send(Message::Close(None)).await.is_err() {
+                warn!("Error sending WebSocket close frame");
+            }
+            let _ = sink.close().await;
+        }
 
-        // Send close frame
-        debug!("Sending WebSocket close frame");
-        if let Err(_) = send_stream.send(Message::Close(None)).await {
-            warn!("Error sending WebSocket close frame");
+        // Call close callback
+        if let Some(callback) = &self.on_close {
+            callback();
         }
 
-        // Signal tasks to stop
-        self.stop_signal.notify_waiters();
+        info!("WebSocket transport closed successfully");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TransportReceiver for WebSocketReceiver {
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        let Some(text) = self.next_text().await? else {
+            return Ok(None);
+        };
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| self.handle_error(MCPError::Serialization(e)))
+    }
 
-        // Wait for tasks to finish
-        if let Some(task) = self.message_task.take() {
-            debug!("Waiting for WebSocket message task to finish");
-            let _ = tokio::time::timeout(Duration::from_secs(5), task).await;
+    async fn recv_raw(&mut self) -> Result<Option<RawMessage>, MCPError> {
+        let Some(text) = self.next_text().await? else {
+            return Ok(None);
+        };
+        RawMessage::parse(&text)
+            .map(Some)
+            .map_err(|e| self.handle_error(MCPError::Serialization(e)))
+    }
+}
+
+#[async_trait]
+impl Transport for WebSocketTransport {
+    async fn start(&mut self) -> Result<(), MCPError> {
+        if self.is_connected() {
+            debug!("WebSocket transport already connected");
+            return Ok(());
         }
 
-        // Update state
-        self.is_connected = false;
+        info!("Starting WebSocket transport: {}", self.uri);
 
-        // Call close callback
-        if let Some(callback) = &self.on_close {
-            callback();
+        // Connect or start server based on mode
+        if self.is_server {
+            self.start_as_server().await?;
+        } else {
+            self.connect_as_client().await?;
         }
 
-        info!("WebSocket transport closed successfully");
+        self.sender.is_connected.store(true, Ordering::SeqCst);
+        info!("WebSocket transport started successfully");
         Ok(())
     }
 
+    async fn send(&mut self, message: JSONRPCMessage) -> Result<(), MCPError> {
+        self.sender.send(message).await
+    }
+
+    async fn recv(&mut self) -> Result<Option<JSONRPCMessage>, MCPError> {
+        self.receiver.recv().await
+    }
+
+    async fn close(&mut self) -> Result<(), MCPError> {
+        info!("Closing WebSocket transport: {}", self.uri);
+        self.sender.close().await
+    }
+
+    fn into_split(self: Box<Self>) -> (Arc<dyn TransportSender>, Box<dyn TransportReceiver>) {
+        (Arc::new(self.sender), Box::new(self.receiver))
+    }
+
     fn set_on_close(&mut self, callback: Option<CloseCallback>) {
         debug!("Setting on_close callback for WebSocket transport");
-        self.on_close = callback;
+        self.sender.on_close = callback;
     }
 
     fn set_on_error(&mut self, callback: Option<ErrorCallback>) {
         debug!("Setting on_error callback for WebSocket transport");
-        self.on_error = callback;
+        let callback: Option<SharedErrorCallback> = callback.map(Arc::from);
+        self.sender.on_error = callback.clone();
+        self.receiver.on_error = callback;
     }
 
-    fn set_on_message<F>(&mut self, callback: Option<F>)
-    where
-        F: Fn(&str) + Send + Sync + 'static,
-    {
+    fn set_on_message(&mut self, callback: Option<MessageCallback>) {
         debug!("Setting on_message callback for WebSocket transport");
-        self.on_message = callback.map(|f| Box::new(f) as MessageCallback);
+        self.receiver.on_message = callback;
+    }
+
+    fn credentials(&self) -> Credentials {
+        self.credentials.clone()
     }
 }
 
-impl Drop for WebSocketTransport {
-    fn drop(&mut self) {
-        if self.is_connected {
-            debug!("WebSocketTransport dropped while still connected, attempting to close");
-            self.stop_signal.notify_waiters();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::session::{Authenticator, ClientCertificates, Principal};
+    use crate::schema::json_rpc::{JSONRPCNotification, JSONRPCRequest, RequestId};
+    use crate::transport::tls::test_certs::TestCa;
+
+    #[tokio::test]
+    async fn test_round_trip_over_one_connection() {
+        // Reserve a free port, then let the server transport bind it
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let server = tokio::spawn(async move {
+            let mut transport = WebSocketTransport::new_server(&addr.to_string());
+            transport.start().await.unwrap();
+            let credentials = transport.credentials();
+            assert_eq!(credentials.header("Sec-WebSocket-Version"), Some("13"));
+            assert!(credentials.peer_addr.unwrap().ip().is_loopback());
+            while let Some(message) = transport.recv().await.unwrap() {
+                transport.send(message).await.unwrap();
+            }
+        });
+
+        let mut client = WebSocketTransport::new(&format!("ws://{}", addr));
+        for _ in 0..50 {
+            if client.start().await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
         }
-        debug!("WebSocketTransport dropped");
+        assert!(client.is_connected());
+
+        let (sender, mut receiver) = Box::new(client).into_split();
+        for i in 0..3 {
+            let request = JSONRPCRequest::new(RequestId::Number(i), "ping".to_string(), None);
+            sender.send(JSONRPCMessage::Request(request)).await.unwrap();
+        }
+        let bye = JSONRPCNotification::new("bye".to_string(), None);
+        sender
+            .send(JSONRPCMessage::Notification(bye))
+            .await
+            .unwrap();
+
+        for i in 0..3 {
+            match receiver.recv().await.unwrap() {
+                Some(JSONRPCMessage::Request(req)) => assert_eq!(req.id, RequestId::Number(i)),
+                other => panic!("Expected request, got {:?}", other),
+            }
+        }
+        assert!(matches!(
+            receiver.recv().await.unwrap(),
+            Some(JSONRPCMessage::Notification(_))
+        ));
+
+        // Closing ends the session on both sides
+        sender.close().await.unwrap();
+        assert!(receiver.recv().await.unwrap().is_none());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let ca = TestCa::new("Test CA");
+        let server_cert = ca.server_cert();
+        let tls =
+            TlsServerConfig::from_pem(server_cert.cert.as_bytes(), server_cert.key.as_bytes())
+                .unwrap()
+                .with_client_ca_pem(ca.pem.as_bytes())
+                .unwrap();
+
+        // The server accepts one connection per start, so it is started twice
+        let server = tokio::spawn(async move {
+            let mut transport = WebSocketTransport::new_server(&addr.to_string()).with_tls(tls);
+            assert!(transport.start().await.is_err());
+            transport.start().await.unwrap();
+            let principal = ClientCertificates::new()
+                .authenticate(&transport.credentials())
+                .await
+                .unwrap();
+            assert_eq!(principal, Some(Principal::new("agent")));
+            let message = transport.recv().await.unwrap().unwrap();
+            transport.send(message).await.unwrap();
+        });
+
+        let uri = format!("wss://localhost:{}", addr.port());
+        let trust_only = TlsClientConfig::with_custom_roots_only()
+            .with_root_ca_pem(ca.pem.as_bytes())
+            .unwrap();
+        let mut anonymous = WebSocketTransport::new(&uri).with_client_tls(trust_only.clone());
+        let mut refused = false;
+        for _ in 0..50 {
+            match anonymous.start().await {
+                Err(MCPError::Transport(e)) if e.contains("Failed to connect") => {
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                }
+                result => {
+                    refused = result.is_err();
+                    break;
+                }
+            }
+        }
+        assert!(refused, "Server accepted a client without a certificate");
+
+        let client_cert = ca.client_cert("agent");
+        let tls = trust_only
+            .with_client_cert_pem(client_cert.cert.as_bytes(), client_cert.key.as_bytes())
+            .unwrap();
+        let mut client = WebSocketTransport::new(&uri).with_client_tls(tls);
+        client.start().await.unwrap();
+        let request = JSONRPCRequest::new(RequestId::Number(1), "ping".to_string(), None);
+        client.send(JSONRPCMessage::Request(request)).await.unwrap();
+        assert!(matches!(
+            client.recv().await.unwrap(),
+            Some(JSONRPCMessage::Request(_))
+        ));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_server_is_refused() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server_cert = TestCa::new("Test CA").server_cert();
+        let tls =
+            TlsServerConfig::from_pem(server_cert.cert.as_bytes(), server_cert.key.as_bytes())
+                .unwrap();
+        let server = tokio::spawn(async move {
+            let mut transport = WebSocketTransport::new_server(&addr.to_string()).with_tls(tls);
+            assert!(transport.start().await.is_err());
+        });
+
+        // Only the public roots are trusted, which did not issue the test certificate
+        let mut client = WebSocketTransport::new(&format!("wss://localhost:{}", addr.port()));
+        let mut result = client.start().await;
+        for _ in 0..50 {
+            match &result {
+                Err(MCPError::Transport(e)) if e.contains("Failed to connect") => {
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                    result = client.start().await;
+                }
+                _ => break,
+            }
+        }
+        match result {
+            Err(MCPError::Transport(e)) => assert!(e.contains("TLS handshake failed"), "{}", e),
+            other => panic!("Expected a TLS error, got {:?}", other),
+        }
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    // The error type of tungstenite's header callback is not ours to shrink
+    #[allow(clippy::result_large_err)]
+    async fn test_handshake_sends_client_options() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut headers = None;
+            let _ws = tokio_tungstenite::accept_hdr_async(socket, |request: &Request, response| {
+                headers = Some(request.headers().clone());
+                Ok(response)
+            })
+            .await
+            .unwrap();
+            // Stay silent so the client's read times out
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            headers.unwrap()
+        });
+
+        let options = ClientTransportOptions::new()
+            .with_user_agent("mcpr-test")
+            .with_token_provider(|| async { Ok("secret".to_string()) })
+            .with_read_timeout(Duration::from_millis(100));
+        let mut client =
+            WebSocketTransport::new(&format!("ws://{}", addr)).with_client_options(options);
+        client.start().await.unwrap();
+        assert!(matches!(client.recv().await, Err(MCPError::Timeout(_))));
+
+        let headers = server.await.unwrap();
+        assert_eq!(headers["user-agent"], "mcpr-test");
+        assert_eq!(headers["authorization"], "Bearer secret");
     }
 }
@@ -0,0 +1,502 @@
+//! Conformance of mcpr's messages with the published MCP JSON Schema
+//!
+//! `tests/schema` holds the schema of each protocol revision mcpr supports and
+//! `tests/fixtures` an example exchange for every method. Each fixture names the
+//! schema definitions its message and result must satisfy, and the revision it first
+//! appears in, if later than the first. The tests check that:
+//!
+//! - every fixture satisfies the schema of each revision it belongs to,
+//! - every fixture reads into mcpr's types and writes back unchanged, and
+//! - what the client and server put on the wire satisfies the schema.
+//!
+//! The schemas are the upstream files, checked with the `jsonschema` crate's draft-07
+//! validator rather than mcpr's own. Every revision in `REVISIONS` must have one;
+//! `tests/schema/update.sh` fetches them all.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mcpr::client::Client;
+use mcpr::error::MCPError;
+use mcpr::schema::client::{
+    CompleteParams, GetPromptResult, ListPromptsResult, ListResourceTemplatesResult,
+    ListResourcesResult, ListRootsResult, ListToolsResult, ReadResourceResult,
+};
+use mcpr::schema::json_rpc::{
+    EmptyResult, JSONRPCMessage, JSONRPCRequest, JSONRPCResponse, RequestId,
+};
+use mcpr::schema::messages::{
+    ClientNotification, ClientRequest, ServerNotification, ServerRequest,
+};
+use mcpr::schema::server::{
+    CallToolResult, CompleteResult, CreateMessageResult, ElicitResult, InitializeResult,
+};
+use mcpr::schema::version::ProtocolVersion;
+use mcpr::server::{Server, ServerConfig};
+use mcpr::transport::{memory, Transport};
+use mcpr::Tool;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// The revisions with a schema in `tests/schema`, oldest first
+const REVISIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+
+/// An example message, and the result that answers it if it is a request
+struct Fixture {
+    name: String,
+    since: String,
+    definition: String,
+    message: Value,
+    result: Option<(String, Value)>,
+}
+
+impl Fixture {
+    /// The revisions this fixture belongs to
+    fn revisions(&self) -> impl Iterator<Item = &'static str> + '_ {
+        REVISIONS
+            .iter()
+            .copied()
+            .filter(move |revision| *revision >= self.since.as_str())
+    }
+
+    fn method(&self) -> &str {
+        self.message["method"].as_str().unwrap()
+    }
+}
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+fn read_json(path: &Path) -> Value {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|error| panic!("cannot read {}: {}", path.display(), error));
+    serde_json::from_str(&text)
+        .unwrap_or_else(|error| panic!("{} is not JSON: {}", path.display(), error))
+}
+
+fn fixtures() -> Vec<Fixture> {
+    let mut paths: Vec<_> = fs::read_dir(root().join("fixtures"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let fixture = read_json(&path);
+            let text = |key: &str| fixture[key].as_str().map(str::to_string);
+            Fixture {
+                name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+                since: text("since").unwrap_or_else(|| REVISIONS[0].to_string()),
+                definition: text("definition").expect("fixtures name their definition"),
+                message: fixture["message"].clone(),
+                result: text("resultDefinition").map(|name| (name, fixture["result"].clone())),
+            }
+        })
+        .collect()
+}
+
+/// The schema document of a revision
+struct Schema {
+    revision: &'static str,
+    document: Value,
+}
+
+impl Schema {
+    /// The revision's schema, which must be checked in
+    fn load(revision: &'static str) -> Self {
+        let path = root().join("schema").join(revision).join("schema.json");
+        assert!(
+            path.exists(),
+            "no schema for {}: run tests/schema/update.sh and commit {}",
+            revision,
+            path.display()
+        );
+        Self {
+            revision,
+            document: read_json(&path),
+        }
+    }
+
+    /// Check `value` against one of the schema's definitions
+    fn check(&self, definition: &str, value: &Value) -> Result<(), String> {
+        let mut schema = self.document.clone();
+        schema["$ref"] = json!(format!("#/definitions/{}", definition));
+        if self.document["definitions"].get(definition).is_none() {
+            return Err(format!(
+                "{} has no definition `{}`",
+                self.revision, definition
+            ));
+        }
+        let validator = jsonschema::draft7::new(&schema)
+            .map_err(|error| format!("{} is not a valid schema: {}", self.revision, error))?;
+        let violations: Vec<String> = validator
+            .iter_errors(value)
+            .map(|violation| format!("{} at {}", violation, violation.instance_path))
+            .collect();
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "{} ({}): {}",
+            definition,
+            self.revision,
+            violations.join("; ")
+        ))
+    }
+
+    /// Check a JSON-RPC response against the definition of its result
+    fn check_response(&self, definition: &str, response: &Value) -> Result<(), String> {
+        self.check("JSONRPCResponse", response)?;
+        self.check(definition, &response["result"])
+    }
+}
+
+/// The schema of every revision, oldest first
+fn schemas() -> Vec<Schema> {
+    REVISIONS.iter().copied().map(Schema::load).collect()
+}
+
+/// The schema of the revision mcpr negotiates by default
+fn latest() -> Schema {
+    Schema::load(REVISIONS[REVISIONS.len() - 1])
+}
+
+/// Read `value` as a `T` and write it back
+fn through<T: Serialize + DeserializeOwned>(value: &Value) -> Result<Value, String> {
+    let typed: T = serde_json::from_value(value.clone()).map_err(|error| error.to_string())?;
+    serde_json::to_value(typed).map_err(|error| error.to_string())
+}
+
+/// Read a message into the typed request or notification enums and write it back
+fn message_through_types(message: &Value) -> Result<Value, String> {
+    let message: JSONRPCMessage =
+        serde_json::from_value(message.clone()).map_err(|error| error.to_string())?;
+    let rewritten = match message {
+        JSONRPCMessage::Request(request) => {
+            let (method, params) = (request.method.as_str(), request.params.clone());
+            let typed = match ClientRequest::from_parts(method, params.clone()) {
+                Ok(ClientRequest::Other { .. }) => {
+                    ServerRequest::from_parts(method, params).map(|typed| match typed {
+                        ServerRequest::Other { .. } => None,
+                        typed => Some(typed.into_request(request.id.clone())),
+                    })
+                }
+                typed => typed.map(|typed| Some(typed.into_request(request.id.clone()))),
+            };
+            let typed = typed.map_err(|error| error.to_string())?;
+            JSONRPCMessage::Request(
+                typed.ok_or_else(|| format!("mcpr has no type for `{}`", method))?,
+            )
+        }
+        JSONRPCMessage::Notification(notification) => {
+            let (method, params) = (notification.method.as_str(), notification.params.clone());
+            let typed = match ClientNotification::from_parts(method, params.clone()) {
+                Ok(ClientNotification::Other { .. }) => {
+                    ServerNotification::from_parts(method, params).map(|typed| match typed {
+                        ServerNotification::Other { .. } => None,
+                        typed => Some(typed.into_notification()),
+                    })
+                }
+                typed => typed.map(|typed| Some(typed.into_notification())),
+            };
+            let typed = typed.map_err(|error| error.to_string())?;
+            JSONRPCMessage::Notification(
+                typed.ok_or_else(|| format!("mcpr has no type for `{}`", method))?,
+            )
+        }
+        other => {
+            return Err(format!(
+                "fixtures hold requests and notifications, not {:?}",
+                other
+            ))
+        }
+    };
+    serde_json::to_value(rewritten).map_err(|error| error.to_string())
+}
+
+/// Read the result of `method` into its mcpr type and write it back
+fn result_through_types(method: &str, result: &Value) -> Result<Value, String> {
+    match method {
+        "initialize" => through::<InitializeResult>(result),
+        "ping" | "resources/subscribe" | "resources/unsubscribe" | "logging/setLevel" => {
+            through::<EmptyResult>(result)
+        }
+        "resources/list" => through::<ListResourcesResult>(result),
+        "resources/templates/list" => through::<ListResourceTemplatesResult>(result),
+        "resources/read" => through::<ReadResourceResult>(result),
+        "prompts/list" => through::<ListPromptsResult>(result),
+        "prompts/get" => through::<GetPromptResult>(result),
+        "tools/list" => through::<ListToolsResult>(result),
+        "tools/call" => through::<CallToolResult>(result),
+        "completion/complete" => through::<CompleteResult>(result),
+        "sampling/createMessage" => through::<CreateMessageResult>(result),
+        "roots/list" => through::<ListRootsResult>(result),
+        "elicitation/create" => through::<ElicitResult>(result),
+        _ => Err(format!("mcpr has no result type for `{}`", method)),
+    }
+}
+
+/// Collect failures so one run reports every fixture that is off
+fn report(failures: Vec<String>) {
+    assert!(
+        failures.is_empty(),
+        "{} failures:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_every_revision_has_a_schema() {
+    let revisions: Vec<_> = ProtocolVersion::ALL
+        .iter()
+        .map(ProtocolVersion::as_str)
+        .collect();
+    assert_eq!(revisions, REVISIONS);
+    assert_eq!(latest().revision, ProtocolVersion::LATEST.as_str());
+}
+
+#[test]
+fn test_fixtures_match_the_schema() {
+    let schemas = schemas();
+    let mut failures = Vec::new();
+    for fixture in fixtures() {
+        for schema in schemas
+            .iter()
+            .filter(|schema| fixture.revisions().any(|r| r == schema.revision))
+        {
+            let checks = [
+                schema.check("JSONRPCMessage", &fixture.message),
+                schema.check(&fixture.definition, &fixture.message),
+            ];
+            let result = fixture
+                .result
+                .as_ref()
+                .map(|(definition, result)| schema.check(definition, result));
+            for outcome in checks.into_iter().chain(result) {
+                if let Err(error) = outcome {
+                    failures.push(format!("{}: {}", fixture.name, error));
+                }
+            }
+        }
+    }
+    report(failures);
+}
+
+#[test]
+fn test_fixtures_round_trip_through_mcpr_types() {
+    let mut failures = Vec::new();
+    for fixture in fixtures() {
+        let mut check =
+            |what: &str, original: &Value, rewritten: Result<Value, String>| match rewritten {
+                Ok(rewritten) if rewritten == *original => {}
+                Ok(rewritten) => failures.push(format!(
+                    "{}: {} changed on the way through\n  fixture: {}\n  mcpr:    {}",
+                    fixture.name, what, original, rewritten
+                )),
+                Err(error) => failures.push(format!("{}: {}: {}", fixture.name, what, error)),
+            };
+        check(
+            "message",
+            &fixture.message,
+            message_through_types(&fixture.message),
+        );
+        if let Some((_, result)) = &fixture.result {
+            check(
+                "result",
+                result,
+                result_through_types(fixture.method(), result),
+            );
+        }
+    }
+    report(failures);
+}
+
+#[test]
+fn test_every_method_has_a_fixture() {
+    let fixtures = fixtures();
+    let covered = |method: &str| fixtures.iter().any(|fixture| fixture.method() == method);
+    let params = |method: &str| {
+        fixtures
+            .iter()
+            .find(|fixture| fixture.method() == method)
+            .and_then(|fixture| fixture.message.get("params").cloned())
+    };
+    let missing: Vec<_> = [
+        "initialize",
+        "ping",
+        "resources/list",
+        "resources/templates/list",
+        "resources/read",
+        "resources/subscribe",
+        "resources/unsubscribe",
+        "prompts/list",
+        "prompts/get",
+        "tools/list",
+        "tools/call",
+        "logging/setLevel",
+        "completion/complete",
+        "sampling/createMessage",
+        "roots/list",
+        "elicitation/create",
+        "notifications/initialized",
+        "notifications/cancelled",
+        "notifications/progress",
+        "notifications/message",
+        "notifications/resources/updated",
+        "notifications/resources/list_changed",
+        "notifications/prompts/list_changed",
+        "notifications/tools/list_changed",
+        "notifications/roots/list_changed",
+    ]
+    .into_iter()
+    .filter(|method| !covered(method))
+    .collect();
+    assert!(missing.is_empty(), "no fixture for {:?}", missing);
+
+    // `ref` is a keyword in Rust, so it is the member most likely to be misnamed
+    let complete: CompleteParams =
+        serde_json::from_value(params("completion/complete").unwrap()).unwrap();
+    assert!(serde_json::to_value(complete).unwrap().get("ref").is_some());
+}
+
+#[tokio::test]
+async fn test_client_messages_match_the_schema() -> Result<(), MCPError> {
+    let schema = latest();
+    let (client_end, mut server) = memory::pair();
+    server.start().await?;
+    let mut client = Client::new(client_end);
+
+    let session = tokio::spawn(async move {
+        let mut failures = Vec::new();
+        let mut methods = Vec::new();
+        while let Some(message) = server.recv().await.unwrap() {
+            let value = serde_json::to_value(&message).unwrap();
+            methods.push(value["method"].as_str().unwrap_or_default().to_string());
+            let (definition, result) = match value["method"].as_str() {
+                Some("initialize") => (
+                    "InitializeRequest",
+                    json!({
+                        "protocolVersion": schema.revision,
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "conformance", "version": "1.0.0" }
+                    }),
+                ),
+                Some("notifications/initialized") => ("InitializedNotification", Value::Null),
+                Some("tools/call") => (
+                    "CallToolRequest",
+                    json!({ "content": [{ "type": "text", "text": "{\"sum\":3}" }] }),
+                ),
+                // mcpr's own way to end a session, which the schema does not cover
+                Some("shutdown") => ("JSONRPCRequest", json!({})),
+                _ => ("JSONRPCMessage", Value::Null),
+            };
+            for check in [
+                schema.check("JSONRPCMessage", &value),
+                schema.check(definition, &value),
+            ] {
+                if let Err(error) = check {
+                    failures.push(error);
+                }
+            }
+            if let JSONRPCMessage::Request(JSONRPCRequest { id, .. }) = message {
+                let response = JSONRPCResponse::new(id, result);
+                server
+                    .send(JSONRPCMessage::Response(response))
+                    .await
+                    .unwrap();
+            }
+        }
+        // Initialization finishes with a notification, before any other request
+        if methods[..2] != ["initialize", "notifications/initialized"] {
+            failures.push(format!("client sent {:?}", methods));
+        }
+        failures
+    });
+
+    client.initialize().await?;
+    let sum: Value = client.call_tool("add", &json!({ "a": 1, "b": 2 })).await?;
+    assert_eq!(sum, json!({ "sum": 3 }));
+    client.shutdown().await?;
+
+    report(session.await.unwrap());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_replies_match_the_schema() -> Result<(), MCPError> {
+    let config = ServerConfig::new()
+        .with_name("conformance")
+        .with_version("1.0.0")
+        .with_tool(Tool {
+            name: "add".to_string(),
+            description: Some("Add two numbers".to_string()),
+            ..Default::default()
+        });
+    let mut server = Server::new(config);
+    server.register_tool_handler("add", |params: Value| async move {
+        let sum = params["a"].as_i64().unwrap_or(0) + params["b"].as_i64().unwrap_or(0);
+        Ok(json!({ "sum": sum }))
+    })?;
+    let (mut client, server_end) = memory::pair();
+    tokio::spawn(async move { server.serve(server_end).await });
+    client.start().await?;
+
+    // The server answers in the revision the client asks for, the latest
+    let schema = latest();
+    let exchanges = [
+        (
+            "initialize",
+            json!({
+                "protocolVersion": schema.revision,
+                "capabilities": {},
+                "clientInfo": { "name": "conformance", "version": "1.0.0" }
+            }),
+            "InitializeResult",
+        ),
+        ("ping", Value::Null, "EmptyResult"),
+        ("tools/list", Value::Null, "ListToolsResult"),
+        (
+            "tools/call",
+            json!({ "name": "add", "arguments": { "a": 1, "b": 2 } }),
+            "CallToolResult",
+        ),
+        (
+            "tools/call",
+            json!({ "name": "missing", "arguments": {} }),
+            "CallToolResult",
+        ),
+    ];
+
+    let mut failures = Vec::new();
+    for (id, (method, params, definition)) in exchanges.into_iter().enumerate() {
+        let id = RequestId::Number(id as i64);
+        let params = (!params.is_null()).then_some(params);
+        let request = JSONRPCRequest::new(id, method.to_string(), params);
+        client.send(JSONRPCMessage::Request(request)).await?;
+        let reply = client.recv().await?.expect("the server answers");
+        let reply = serde_json::to_value(reply)?;
+        if reply.get("error").is_some() {
+            // The schema says nothing of which calls fail, only how failures look
+            for schema in schemas() {
+                if let Err(error) = schema.check("JSONRPCError", &reply) {
+                    failures.push(format!("{}: {}", method, error));
+                }
+            }
+            continue;
+        }
+        if let Err(error) = schema.check_response(definition, &reply) {
+            failures.push(format!("{}: {}", method, error));
+        }
+    }
+    report(failures);
+    Ok(())
+}
@@ -0,0 +1,13 @@
+#!/bin/sh
+# Replace the vendored schemas with the published ones.
+set -eu
+
+cd "$(dirname "$0")"
+base=https://raw.githubusercontent.com/modelcontextprotocol/modelcontextprotocol/main/schema
+
+for revision in 2024-11-05 2025-03-26 2025-06-18; do
+    mkdir -p "$revision"
+    curl --fail --silent --show-error --location \
+        --output "$revision/schema.json" "$base/$revision/schema.json"
+    echo "updated $revision"
+done